@@ -5,48 +5,208 @@ fn generate_bindings(
     sysroot: Option<&PathBuf>,
     headers: &[PathBuf],
     include_path: &Path,
-    bindings_path: &Path,
+    out_path: &Path,
+    dynamic_library_name: Option<&str>,
 ) {
+    // Create the parent directory if it doesn't exist
+    if !out_path.parent().unwrap().exists() {
+        std::fs::create_dir_all(out_path.parent().unwrap()).unwrap();
+    }
+    let mut builder = bindgen::Builder::default()
+        .clang_arg(format!("-I{}", include_path.display()))
+        .derive_default(true)
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()));
+
+    // All headers are generated into a single file, so declarations shared between them are only
+    // emitted once.
     for path in headers {
         let header_path = include_path.join(path);
         println!("cargo:rerun-if-changed={}", header_path.display());
-        // Generate binding file name is the same as the header file
-        let out_path = bindings_path.join(path).with_extension("rs");
-        // Create the parent directory if it doesn't exist
-        if !out_path.parent().unwrap().exists() {
-            std::fs::create_dir_all(out_path.parent().unwrap()).unwrap();
-        }
-        let mut builder = bindgen::Builder::default()
-            .header(include_path.join(path).display().to_string())
-            .clang_arg(format!("-I{}", include_path.display()))
-            .derive_default(true)
-            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()));
+        builder = builder.header(header_path.display().to_string());
+    }
 
-        #[cfg(not(feature = "std"))]
-        {
-            builder = builder
-                .use_core()
-                .clang_arg("-DIREE_PLATFORM_GENERIC=1")
-                .clang_arg("-Wno-char-subscripts")
-                .clang_arg("-Wno-format")
-                .clang_arg("-Wno-implicit-function-declaration")
-                .clang_arg("-Wno-unused-variable")
-                .clang_arg("-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1")
-                .clang_arg("-DFLATCC_USE_GENERIC_ALIGNED_ALLOC=1")
-        }
+    #[cfg(not(feature = "std"))]
+    {
+        builder = builder
+            .use_core()
+            .clang_arg("-DIREE_PLATFORM_GENERIC=1")
+            .clang_arg("-Wno-char-subscripts")
+            .clang_arg("-Wno-format")
+            .clang_arg("-Wno-implicit-function-declaration")
+            .clang_arg("-Wno-unused-variable")
+            .clang_arg("-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1")
+            .clang_arg("-DFLATCC_USE_GENERIC_ALIGNED_ALLOC=1")
+    }
+
+    // WASI has no platform of its own in IREE, so it is treated like bare metal.
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("wasi") {
+        builder = builder
+            .clang_arg("-DIREE_PLATFORM_GENERIC=1")
+            .clang_arg("-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1")
+    }
+
+    if let Some(sysroot) = sysroot {
+        builder = builder.clang_arg(format!("--sysroot={}", sysroot.display()));
+    }
+
+    // Functions of dynamically loaded libraries are generated as methods of a struct that loads
+    // the library. Missing symbols only fail when their function is called.
+    if let Some(dynamic_library_name) = dynamic_library_name {
+        builder = builder
+            .dynamic_library_name(dynamic_library_name)
+            .dynamic_link_require_all(false);
+    }
 
-        if let Some(sysroot) = sysroot {
-            builder = builder.clang_arg(format!("--sysroot={}", sysroot.display()));
+    builder
+        .generate()
+        .expect("Unable to generate bindings")
+        .write_to_file(out_path)
+        .expect("Couldn't write bindings!");
+}
+
+// Generates free functions that forward to the methods of the dynamically loaded library, so that
+// the bindings are called the same way whether the library is linked or loaded at runtime.
+#[cfg(feature = "compiler-dlopen")]
+fn generate_forwarders(bindings_path: &Path, out_path: &Path) {
+    let bindings = std::fs::read_to_string(bindings_path).expect("Couldn't read bindings!");
+    let file = syn::parse_file(&bindings).expect("Unable to parse bindings");
+    let mut forwarders = quote::quote! {};
+    for item in file.items {
+        let syn::Item::Impl(item_impl) = item else {
+            continue;
+        };
+        let self_ty = &item_impl.self_ty;
+        if quote::quote!(#self_ty).to_string() != "IreeCompiler" {
+            continue;
         }
+        for impl_item in item_impl.items {
+            let syn::ImplItem::Fn(method) = impl_item else {
+                continue;
+            };
+            // Constructors of the library are not forwarded.
+            if method.sig.receiver().is_none() {
+                continue;
+            }
+            let name = &method.sig.ident;
+            let inputs = method
+                .sig
+                .inputs
+                .iter()
+                .filter_map(|input| match input {
+                    syn::FnArg::Typed(input) => Some(input),
+                    syn::FnArg::Receiver(_) => None,
+                })
+                .collect::<Vec<_>>();
+            let args = inputs.iter().map(|input| &input.pat);
+            let output = &method.sig.output;
+            forwarders.extend(quote::quote! {
+                pub unsafe fn #name(#(#inputs),*) #output {
+                    library().#name(#(#args),*)
+                }
+            });
+        }
+    }
+    std::fs::write(out_path, forwarders.to_string()).expect("Couldn't write forwarders!");
+}
+
+// Returns the path to the Android NDK, which provides the toolchain and sysroot for Android
+// targets.
+#[cfg(feature = "runtime")]
+fn android_ndk_path() -> PathBuf {
+    env::var("ANDROID_NDK_HOME")
+        .or_else(|_| env::var("ANDROID_NDK_ROOT"))
+        .expect(
+            "The ANDROID_NDK_HOME environment variable must be set to the path to the Android NDK",
+        )
+        .into()
+}
 
-        builder
-            .generate()
-            .expect("Unable to generate bindings")
-            .write_to_file(&out_path)
-            .expect("Couldn't write bindings!");
+// Returns the path to the sysroot of the NDK's prebuilt LLVM toolchain for the host.
+#[cfg(feature = "runtime")]
+fn android_ndk_sysroot(ndk_path: &Path) -> PathBuf {
+    let host = env::var("HOST").unwrap();
+    let host_tag = if host.contains("linux") {
+        "linux-x86_64"
+    } else if host.contains("darwin") {
+        "darwin-x86_64"
+    } else if host.contains("windows") {
+        "windows-x86_64"
+    } else {
+        panic!("The Android NDK does not support the host {}", host);
+    };
+    ndk_path
+        .join("toolchains/llvm/prebuilt")
+        .join(host_tag)
+        .join("sysroot")
+}
+
+// Returns the name of the Xcode SDK for the iOS target, the simulator for x86_64 and `-sim`
+// targets.
+#[cfg(feature = "runtime")]
+fn ios_sdk_name() -> &'static str {
+    let target = env::var("TARGET").unwrap();
+    if target.ends_with("-sim") || target.starts_with("x86_64") {
+        "iphonesimulator"
+    } else {
+        "iphoneos"
     }
 }
 
+// Returns the path to the SDK for the iOS target, as reported by xcrun.
+#[cfg(feature = "runtime")]
+fn ios_sdk_path() -> PathBuf {
+    let output = std::process::Command::new("xcrun")
+        .args(["--sdk", ios_sdk_name(), "--show-sdk-path"])
+        .output()
+        .expect("Failed to execute xcrun, Xcode is required for iOS targets");
+    String::from_utf8(output.stdout)
+        .expect("Failed to parse SDK path")
+        .trim()
+        .into()
+}
+
+// Parameter support is not part of the unified runtime library, so its libraries are built and
+// linked separately, in dependency order. Directories are relative to the runtime sources.
+#[cfg(feature = "parameters")]
+const PARAMETER_LIBRARIES: [(&str, &str); 9] = [
+    (
+        "modules/io/parameters",
+        "iree_modules_io_parameters_parameters",
+    ),
+    ("io/formats", "iree_io_formats_parser_registry"),
+    ("io/formats/irpa", "iree_io_formats_irpa_irpa"),
+    ("io/formats/gguf", "iree_io_formats_gguf_gguf"),
+    (
+        "io/formats/safetensors",
+        "iree_io_formats_safetensors_safetensors",
+    ),
+    ("io", "iree_io_parameter_index_provider"),
+    ("io", "iree_io_parameter_provider"),
+    ("io", "iree_io_parameter_index"),
+    ("io", "iree_io_scope_map"),
+];
+
+// The inline HAL modules are not part of the unified runtime library either. They run executables
+// on the host through the executable loaders of the local HAL.
+#[cfg(feature = "hal-inline")]
+const HAL_INLINE_LIBRARIES: [(&str, &str); 3] = [
+    ("modules/hal/inline", "iree_modules_hal_inline_inline"),
+    ("modules/hal/loader", "iree_modules_hal_loader_loader"),
+    (
+        "modules/hal/utils",
+        "iree_modules_hal_utils_buffer_diagnostics",
+    ),
+];
+
+// The static library loader is not part of the unified runtime library either, and it is not
+// selected by an executable loader option. It runs executables linked into the application, for
+// targets that cannot load code at runtime.
+#[cfg(feature = "static-library")]
+const STATIC_LIBRARY_LIBRARIES: [(&str, &str); 1] = [(
+    "hal/local/loaders",
+    "iree_hal_local_loaders_static_library_loader",
+)];
+
 fn main() {
     let iree_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("iree");
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
@@ -74,40 +234,51 @@ fn main() {
             // Path to IREE compiler header sources
             &iree_path.join("compiler/bindings/c"),
             // Path to generated compiler bindings
-            &out_path.join("compiler"),
+            &out_path.join("compiler/iree/compiler/embedding_api.rs"),
+            // The compiler-dlopen feature loads the compiler library at runtime
+            cfg!(feature = "compiler-dlopen").then_some("IreeCompiler"),
+        );
+        #[cfg(feature = "compiler-dlopen")]
+        generate_forwarders(
+            &out_path.join("compiler/iree/compiler/embedding_api.rs"),
+            &out_path.join("compiler/iree/compiler/forwarders.rs"),
         );
 
-        let compiler_lib_path = if std::env::var("DOCS_RS").is_ok() {
-            // Docs.rs automatically downloads the IREE compiler from pypi
-            // and sets the LIB_IREE_COMPILER environment variable
-
-            std::process::Command::new("pip3")
-                .args(["install", "iree-compiler"])
-                .status()
-                .map_err(|e| format!("Failed to install IREE compiler: {}", e))
-                .unwrap();
-
-            // Find the IREE compiler library
-            std::str::from_utf8(
-                &std::process::Command::new(out_path.join("python3"))
-                    .args([
-                        "-c",
-                        "import iree.compiler as _; print(f'{_.__path__[0]}/_mlir_libs/')",
-                    ])
-                    .output()
-                    .expect("Failed to find IREE compiler library")
-                    .stdout,
-            )
-            .unwrap()
-            .to_string()
-        } else {
-            // The user can set the LIB_IREE_COMPILER environment variable
-            env::var("LIB_IREE_COMPILER").expect(
-				"The LIB_IREE_COMPILER environment variable must be set to the path to the IREE compiler library")
-        };
-        // The linker needs to find the IREE compiler dynamic library
-        println!("cargo:rustc-link-search={}", compiler_lib_path);
-        println!("cargo:rustc-link-lib=dylib=IREECompiler");
+        // Nothing is linked when the compiler library is loaded at runtime.
+        #[cfg(not(feature = "compiler-dlopen"))]
+        {
+            let compiler_lib_path = if std::env::var("DOCS_RS").is_ok() {
+                // Docs.rs automatically downloads the IREE compiler from pypi
+                // and sets the LIB_IREE_COMPILER environment variable
+
+                std::process::Command::new("pip3")
+                    .args(["install", "iree-compiler"])
+                    .status()
+                    .map_err(|e| format!("Failed to install IREE compiler: {}", e))
+                    .unwrap();
+
+                // Find the IREE compiler library
+                std::str::from_utf8(
+                    &std::process::Command::new(out_path.join("python3"))
+                        .args([
+                            "-c",
+                            "import iree.compiler as _; print(f'{_.__path__[0]}/_mlir_libs/')",
+                        ])
+                        .output()
+                        .expect("Failed to find IREE compiler library")
+                        .stdout,
+                )
+                .unwrap()
+                .to_string()
+            } else {
+                // The user can set the LIB_IREE_COMPILER environment variable
+                env::var("LIB_IREE_COMPILER").expect(
+    				"The LIB_IREE_COMPILER environment variable must be set to the path to the IREE compiler library")
+            };
+            // The linker needs to find the IREE compiler dynamic library
+            println!("cargo:rustc-link-search={}", compiler_lib_path);
+            println!("cargo:rustc-link-lib=dylib=IREECompiler");
+        }
     }
 
     // The runtime feature enables the IREE runtime. It configures available runtime backends based
@@ -125,14 +296,19 @@ fn main() {
             .arg("--print-sysroot")
             .output()
             .expect("Failed to execute command");
-        let sysroot: Option<PathBuf> = match sysroot_output.status.success() {
-            true => Some(
-                String::from_utf8(sysroot_output.stdout)
-                    .expect("Failed to parse sysroot")
-                    .trim()
-                    .into(),
-            ),
-            false => None,
+        // The NDK and Xcode keep their sysroots outside of the compiler's default search paths.
+        let sysroot: Option<PathBuf> = match target_os.as_str() {
+            "android" => Some(android_ndk_sysroot(&android_ndk_path())),
+            "ios" => Some(ios_sdk_path()),
+            _ => match sysroot_output.status.success() {
+                true => Some(
+                    String::from_utf8(sysroot_output.stdout)
+                        .expect("Failed to parse sysroot")
+                        .trim()
+                        .into(),
+                ),
+                false => None,
+            },
         };
         let multi_dir_output = cc::Build::new()
             .get_compiler()
@@ -149,143 +325,144 @@ fn main() {
             ),
             false => None,
         };
+        #[allow(unused_mut)]
+        let mut runtime_headers = vec![
+            PathBuf::from("iree").join("runtime").join("api.h"),
+            PathBuf::from("iree")
+                .join("vm")
+                .join("bytecode")
+                .join("module.h"),
+            PathBuf::from("iree")
+                .join("modules")
+                .join("hal")
+                .join("module.h"),
+            // The task executor and the executable loaders, to create local-task devices with
+            // custom worker topologies.
+            PathBuf::from("iree").join("task").join("api.h"),
+            PathBuf::from("iree")
+                .join("hal")
+                .join("drivers")
+                .join("local_task")
+                .join("task_device.h"),
+            PathBuf::from("iree")
+                .join("hal")
+                .join("local")
+                .join("loaders")
+                .join("registration")
+                .join("init.h"),
+        ];
+        // The registration functions of the drivers, to fill a driver registry with specific
+        // drivers. Only the drivers built into the runtime can be registered.
+        let mut drivers = vec!["local_sync", "local_task"];
+        #[cfg(feature = "cuda")]
+        drivers.push("cuda");
+        #[cfg(feature = "hip")]
+        drivers.push("hip");
+        #[cfg(feature = "vulkan")]
+        drivers.push("vulkan");
+        if cfg!(feature = "metal") || target_os == "ios" {
+            drivers.push("metal");
+        }
+        runtime_headers.extend(drivers.iter().map(|driver| {
+            PathBuf::from("iree")
+                .join("hal")
+                .join("drivers")
+                .join(driver)
+                .join("registration")
+                .join("driver_module.h")
+        }));
+        #[cfg(feature = "parameters")]
+        runtime_headers.extend([
+            PathBuf::from("iree")
+                .join("io")
+                .join("formats")
+                .join("parser_registry.h"),
+            PathBuf::from("iree")
+                .join("io")
+                .join("parameter_index_provider.h"),
+            PathBuf::from("iree")
+                .join("modules")
+                .join("io")
+                .join("parameters")
+                .join("module.h"),
+        ]);
+        #[cfg(feature = "hal-inline")]
+        runtime_headers.extend([
+            PathBuf::from("iree")
+                .join("modules")
+                .join("hal")
+                .join("inline")
+                .join("module.h"),
+            PathBuf::from("iree")
+                .join("modules")
+                .join("hal")
+                .join("loader")
+                .join("module.h"),
+        ]);
+        #[cfg(feature = "static-library")]
+        runtime_headers.extend([
+            PathBuf::from("iree")
+                .join("hal")
+                .join("local")
+                .join("loaders")
+                .join("static_library_loader.h"),
+            PathBuf::from("iree")
+                .join("hal")
+                .join("drivers")
+                .join("local_sync")
+                .join("sync_device.h"),
+        ]);
         generate_bindings(
             sysroot.as_ref(),
-            &[PathBuf::from("iree").join("runtime").join("api.h")],
+            &runtime_headers,
             &iree_path.join("runtime").join("src"),
-            &PathBuf::from(env::var("OUT_DIR").unwrap()).join("runtime"),
+            &out_path.join("runtime/iree/runtime/api.rs"),
+            None,
+        );
+        // Crates that compile C against the runtime, e.g. modules compiled to C source, find its
+        // headers in the DEP_IREE_INCLUDE environment variable of their build scripts.
+        println!(
+            "cargo:include={}",
+            iree_path.join("runtime").join("src").display()
         );
 
-        // The build process requires runtime tools: iree-flatcc-cli and generate_embed_data
-        // So there has to be a host tool build before the actual runtime build in no-std builds.
-        #[cfg(not(feature = "std"))]
-        {
-            let mut host_config = cmake::Config::new(&iree_path);
-
-            // CMake config for host tool
-            let cmake_host_defs = vec![
-                ("IREE_HAL_DRIVER_DEFAULTS", "OFF"),
-                ("IREE_BUILD_COMPILER", "OFF"),
-                ("IREE_BUILD_TESTS", "OFF"),
-                ("IREE_BUILD_SAMPLES", "OFF"),
-                ("IREE_BUILD_BINDINGS_TFLITE", "OFF"),
-                ("IREE_BUILD_BINDINGS_TFLITE_JAVA", "OFF"),
-            ];
-            cmake_host_defs.iter().for_each(|(k, v)| {
-                host_config.define(k, v);
-            });
-
-            // TODO: Change this once cmake-rs supports multiple targets
-            host_config
-                .target(&std::env::var("HOST").unwrap())
-                .build_target("iree-flatcc-cli")
-                .out_dir(&build_path.join("host"));
-            host_config.build();
-            host_config.build_target("generate_embed_data").build();
-        }
-        #[cfg(not(feature = "std"))]
-        let host_bin_dir = build_path.join("host/build/tools");
-
-        let mut config = cmake::Config::new(iree_path);
-
-        // CMake config for IREE runtime build
-        let mut cmake_defs = vec![
-            ("IREE_BUILD_COMPILER", "OFF"),
-            ("IREE_BUILD_TESTS", "OFF"),
-            ("IREE_BUILD_SAMPLES", "OFF"),
-            ("IREE_BUILD_BINDINGS_TFLITE", "OFF"),
-            ("IREE_BUILD_BINDINGS_TFLITE_JAVA", "OFF"),
-        ];
-
-        let mut cflags = vec![];
-
-        match std::env::var("OPT_LEVEL").unwrap().as_str() {
-            "z" => {
-                cflags.push("-Oz");
-                cmake_defs.push(("IREE_SIZE_OPTIMIZED", "ON"));
-            }
-            "3" => {
-                cflags.push("-O3");
-            }
-            "2" => {
-                cflags.push("-O2");
-            }
-            "1" => {
-                cflags.push("-O1");
-            }
-            "0" => {
-                cflags.push("-O0");
-            }
-            _ => {}
-        }
-
-        // If bare metal (no-std), use the following config.
-        #[cfg(not(feature = "std"))]
-        {
-            // CMake config for no-std runtime build
-            cmake_defs.extend(vec![
-                ("IREE_ENABLE_THREADING", "OFF"),
-                ("IREE_HAL_DRIVER_DEFAULTS", "OFF"),
-                ("IREE_HAL_DRIVER_LOCAL_SYNC", "ON"),
-                ("IREE_HAL_EXECUTABLE_LOADER_DEFAULTS", "OFF"),
-                ("IREE_HAL_EXECUTABLE_LOADER_EMBEDDED_ELF", "ON"),
-                ("IREE_HAL_EXECUTABLE_LOADER_VMVX_MODULE", "ON"),
-                ("IREE_HAL_EXECUTABLE_PLUGIN_DEFAULTS", "OFF"),
-                ("IREE_HAL_EXECUTABLE_PLUGIN_EMBEDDED_ELF", "ON"),
-                ("IREE_ENABLE_POSITION_INDEPENDENT_CODE", "OFF"),
-                ("IREE_HOST_BIN_DIR", host_bin_dir.to_str().unwrap()),
-                ("CMAKE_SYSTEM_NAME", "Generic"),
-            ]);
-            // C flags for no-std runtime build
-            cflags.extend(vec![
-                "-specs=nosys.specs",
-                "-DIREE_PLATFORM_GENERIC=1",
-                "-DIREE_FILE_IO_ENABLE=0",
-                "-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1",
-                "-DIREE_TIME_NOW_FN=\"{return 0; }\"",
-                "-D'IREE_WAIT_UNTIL_FN(n)=false'",
-                "-DFLATCC_USE_GENERIC_ALIGNED_ALLOC",
-                "-DIREE_STATUS_FEATURES=0",
-                "-fdata-sections",
-                "-ffunction-sections",
-                "-Wno-char-subscripts",
-                "-Wno-format",
-                "-Wno-error=unused-variable",
-                "-Wl,--gc-sections",
-            ]);
-        }
-
-        cmake_defs.iter().for_each(|(k, v)| {
-            config.define(k, v);
-        });
-
-        cflags.iter().for_each(|v| {
-            config.cflag(v);
-            config.cxxflag(v);
-        });
-
-        config
-            .build_target("iree_runtime_unified")
-            .out_dir(&build_path);
-
-        // Build IREE runtime
-        config.build();
+        // The user can set the IREE_RUNTIME_LIB environment variable to an existing runtime build,
+        // either a directory holding the static libraries or a CMake build tree.
+        println!("cargo:rerun-if-env-changed=IREE_RUNTIME_LIB");
+        let runtime_lib_dir = env::var("IREE_RUNTIME_LIB").ok().map(PathBuf::from);
 
         // The IREE runtime is compiled as static library, and it requires iree_runtime_unified,
         // flatcc_parsing, and platform-specific libraries. When cross-compiling, lld is
         // recommended.
-        println!(
-            "cargo:rustc-link-search={}",
-            build_path.join("build/runtime/src/iree/runtime").display()
-        );
-        println!(
-            "cargo:rustc-link-search={}",
-            build_path
-                .join("build/build_tools/third_party/flatcc")
-                .display()
-        );
+        let library_dirs = match runtime_lib_dir {
+            Some(dir) if dir.join("runtime/src/iree/runtime").exists() => {
+                build_tree_library_dirs(&dir)
+            }
+            Some(dir) if dir.exists() => vec![dir],
+            Some(dir) => panic!(
+                "The runtime library directory {} does not exist",
+                dir.display()
+            ),
+            None => build_runtime(&iree_path, &build_path, &target_os),
+        };
+        for dir in library_dirs.iter() {
+            println!("cargo:rustc-link-search={}", dir.display());
+        }
+        let target_is_msvc = std::env::var("CARGO_CFG_TARGET_ENV").unwrap() == "msvc";
 
         // Print order is important.
+        #[cfg(feature = "parameters")]
+        for (_, library) in PARAMETER_LIBRARIES.iter() {
+            println!("cargo:rustc-link-lib={}", library);
+        }
+        #[cfg(feature = "hal-inline")]
+        for (_, library) in HAL_INLINE_LIBRARIES.iter() {
+            println!("cargo:rustc-link-lib={}", library);
+        }
+        #[cfg(feature = "static-library")]
+        for (_, library) in STATIC_LIBRARY_LIBRARIES.iter() {
+            println!("cargo:rustc-link-lib={}", library);
+        }
         println!("cargo:rustc-link-lib=iree_runtime_unified");
         println!("cargo:rustc-link-lib=flatcc_parsing");
 
@@ -309,9 +486,307 @@ fn main() {
                 println!("cargo:rustc-link-lib=c");
                 println!("cargo:rustc-link-lib=m");
             }
+            // MSVC links its C++ runtime on its own. WaitOnAddress, used by the runtime's
+            // synchronization primitives, lives in the synchronization library.
+            "windows" if target_is_msvc => {
+                println!("cargo:rustc-link-lib=synchronization");
+            }
+
+            "windows" => {
+                println!("cargo:rustc-link-lib=stdc++");
+                println!("cargo:rustc-link-lib=synchronization");
+            }
+
+            // Emscripten links its C++ runtime on its own.
+            "emscripten" => {}
+
+            "wasi" => {
+                println!("cargo:rustc-link-lib=c++");
+                println!("cargo:rustc-link-lib=c++abi");
+            }
+
+            // The runtime logs through the Android log library.
+            "android" => {
+                println!("cargo:rustc-link-lib=c++_static");
+                println!("cargo:rustc-link-lib=c++abi");
+                println!("cargo:rustc-link-lib=log");
+                println!("cargo:rustc-link-lib=dl");
+            }
+
+            "ios" => {
+                println!("cargo:rustc-link-lib=c++");
+                println!("cargo:rustc-link-lib=framework=Foundation");
+                println!("cargo:rustc-link-lib=framework=Metal");
+            }
+
             _ => {
-                panic!("Only Linux, macOS, and no-std targets are supported");
+                panic!("Only Linux, macOS, Windows, Android, iOS, WebAssembly, and no-std targets are supported");
             }
         }
     }
 }
+
+// Builds the IREE runtime from source with CMake, configured for the target, and returns the
+// directories that hold the built libraries.
+#[cfg(feature = "runtime")]
+fn build_runtime(iree_path: &Path, build_path: &Path, target_os: &str) -> Vec<PathBuf> {
+    // WebAssembly targets run without threads or a file system, like bare metal targets.
+    let target_is_wasm = matches!(target_os, "emscripten" | "wasi");
+
+    // Mobile targets are cross-compiled with the toolchains of their SDKs.
+    let target_is_mobile = matches!(target_os, "android" | "ios");
+
+    // The build process requires runtime tools: iree-flatcc-cli and generate_embed_data
+    // So there has to be a host tool build before the actual runtime build in no-std,
+    // WebAssembly and mobile builds.
+    if cfg!(not(feature = "std")) || target_is_wasm || target_is_mobile {
+        let mut host_config = cmake::Config::new(iree_path);
+
+        // CMake config for host tool
+        let cmake_host_defs = vec![
+            ("IREE_HAL_DRIVER_DEFAULTS", "OFF"),
+            ("IREE_BUILD_COMPILER", "OFF"),
+            ("IREE_BUILD_TESTS", "OFF"),
+            ("IREE_BUILD_SAMPLES", "OFF"),
+            ("IREE_BUILD_BINDINGS_TFLITE", "OFF"),
+            ("IREE_BUILD_BINDINGS_TFLITE_JAVA", "OFF"),
+        ];
+        cmake_host_defs.iter().for_each(|(k, v)| {
+            host_config.define(k, v);
+        });
+
+        // TODO: Change this once cmake-rs supports multiple targets
+        host_config
+            .target(&std::env::var("HOST").unwrap())
+            .build_target("iree-flatcc-cli")
+            .out_dir(&build_path.join("host"));
+        host_config.build();
+        host_config.build_target("generate_embed_data").build();
+    }
+    let host_bin_dir = build_path.join("host/build/tools");
+
+    let mut config = cmake::Config::new(iree_path);
+
+    // CMake config for IREE runtime build
+    let mut cmake_defs = vec![
+        ("IREE_BUILD_COMPILER", "OFF"),
+        ("IREE_BUILD_TESTS", "OFF"),
+        ("IREE_BUILD_SAMPLES", "OFF"),
+        ("IREE_BUILD_BINDINGS_TFLITE", "OFF"),
+        ("IREE_BUILD_BINDINGS_TFLITE_JAVA", "OFF"),
+    ];
+
+    // GPU drivers load their vendor libraries at runtime, so they need no link flags.
+    #[cfg(feature = "cuda")]
+    cmake_defs.push(("IREE_HAL_DRIVER_CUDA", "ON"));
+    #[cfg(feature = "hip")]
+    cmake_defs.push(("IREE_HAL_DRIVER_HIP", "ON"));
+    #[cfg(feature = "vulkan")]
+    cmake_defs.push(("IREE_HAL_DRIVER_VULKAN", "ON"));
+    #[cfg(feature = "metal")]
+    cmake_defs.push(("IREE_HAL_DRIVER_METAL", "ON"));
+
+    // Tracing instruments the runtime with Tracy zones, which a Tracy server can capture.
+    #[cfg(feature = "profiling")]
+    cmake_defs.extend(vec![
+        ("IREE_ENABLE_RUNTIME_TRACING", "ON"),
+        ("IREE_TRACING_PROVIDER", "tracy"),
+    ]);
+
+    // MSVC does not understand GCC style flags, and its multi-config generators would move
+    // the libraries into per-configuration directories. Ninja keeps the layout of the other
+    // targets, so the link search paths below hold.
+    let target_is_msvc = std::env::var("CARGO_CFG_TARGET_ENV").unwrap() == "msvc";
+    if target_is_msvc {
+        config.generator("Ninja");
+    }
+
+    let mut cflags = vec![];
+
+    match std::env::var("OPT_LEVEL").unwrap().as_str() {
+        _ if target_is_msvc => {}
+        "z" => {
+            cflags.push("-Oz");
+            cmake_defs.push(("IREE_SIZE_OPTIMIZED", "ON"));
+        }
+        "3" => {
+            cflags.push("-O3");
+        }
+        "2" => {
+            cflags.push("-O2");
+        }
+        "1" => {
+            cflags.push("-O1");
+        }
+        "0" => {
+            cflags.push("-O0");
+        }
+        _ => {}
+    }
+
+    // If bare metal (no-std), use the following config.
+    #[cfg(not(feature = "std"))]
+    {
+        // CMake config for no-std runtime build
+        cmake_defs.extend(vec![
+            ("IREE_ENABLE_THREADING", "OFF"),
+            ("IREE_HAL_DRIVER_DEFAULTS", "OFF"),
+            ("IREE_HAL_DRIVER_LOCAL_SYNC", "ON"),
+            ("IREE_HAL_EXECUTABLE_LOADER_DEFAULTS", "OFF"),
+            ("IREE_HAL_EXECUTABLE_LOADER_EMBEDDED_ELF", "ON"),
+            ("IREE_HAL_EXECUTABLE_LOADER_VMVX_MODULE", "ON"),
+            ("IREE_HAL_EXECUTABLE_PLUGIN_DEFAULTS", "OFF"),
+            ("IREE_HAL_EXECUTABLE_PLUGIN_EMBEDDED_ELF", "ON"),
+            ("IREE_ENABLE_POSITION_INDEPENDENT_CODE", "OFF"),
+            ("IREE_HOST_BIN_DIR", host_bin_dir.to_str().unwrap()),
+            ("CMAKE_SYSTEM_NAME", "Generic"),
+        ]);
+        // C flags for no-std runtime build
+        cflags.extend(vec![
+            "-specs=nosys.specs",
+            "-DIREE_PLATFORM_GENERIC=1",
+            "-DIREE_FILE_IO_ENABLE=0",
+            "-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1",
+            "-DIREE_TIME_NOW_FN=\"{return 0; }\"",
+            "-D'IREE_WAIT_UNTIL_FN(n)=false'",
+            "-DFLATCC_USE_GENERIC_ALIGNED_ALLOC",
+            "-DIREE_STATUS_FEATURES=0",
+            "-fdata-sections",
+            "-ffunction-sections",
+            "-Wno-char-subscripts",
+            "-Wno-format",
+            "-Wno-error=unused-variable",
+            "-Wl,--gc-sections",
+        ]);
+    }
+
+    // WebAssembly builds only support the local-sync driver. Emscripten and the WASI SDK
+    // provide CMake toolchain files that select their compilers and sysroots.
+    let toolchain_file = match target_os {
+        "emscripten" => Some(
+            PathBuf::from(env::var("EMSDK").expect(
+                "The EMSDK environment variable must be set to the path to the Emscripten SDK",
+            ))
+            .join("upstream/emscripten/cmake/Modules/Platform/Emscripten.cmake"),
+        ),
+        "wasi" => Some(
+            PathBuf::from(env::var("WASI_SDK_PATH").expect(
+                "The WASI_SDK_PATH environment variable must be set to the path to the WASI SDK",
+            ))
+            .join("share/cmake/wasi-sdk.cmake"),
+        ),
+        "android" => Some(android_ndk_path().join("build/cmake/android.toolchain.cmake")),
+        _ => None,
+    };
+    if target_is_wasm {
+        cmake_defs.extend(vec![
+            ("IREE_ENABLE_THREADING", "OFF"),
+            ("IREE_HAL_DRIVER_DEFAULTS", "OFF"),
+            ("IREE_HAL_DRIVER_LOCAL_SYNC", "ON"),
+            ("IREE_HAL_EXECUTABLE_LOADER_DEFAULTS", "OFF"),
+            ("IREE_HAL_EXECUTABLE_LOADER_VMVX_MODULE", "ON"),
+            ("IREE_HAL_EXECUTABLE_PLUGIN_DEFAULTS", "OFF"),
+            ("IREE_ENABLE_CPUINFO", "OFF"),
+            ("IREE_HOST_BIN_DIR", host_bin_dir.to_str().unwrap()),
+        ]);
+        cflags.push("-DIREE_FILE_IO_ENABLE=0");
+    }
+    if target_os == "wasi" {
+        cflags.extend(vec![
+            "-DIREE_PLATFORM_GENERIC=1",
+            "-DIREE_SYNCHRONIZATION_DISABLE_UNSAFE=1",
+            "-DIREE_TIME_NOW_FN=\"{return 0; }\"",
+            "-D'IREE_WAIT_UNTIL_FN(n)=false'",
+        ]);
+    }
+    // The NDK toolchain file selects the ABI and the minimum API level of the build.
+    let target_arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let android_abi = match target_arch.as_str() {
+        "aarch64" => "arm64-v8a",
+        "arm" => "armeabi-v7a",
+        "x86_64" => "x86_64",
+        "x86" => "x86",
+        arch => arch,
+    };
+    let android_platform =
+        env::var("ANDROID_PLATFORM").unwrap_or_else(|_| String::from("android-29"));
+    if target_os == "android" {
+        cmake_defs.extend(vec![
+            ("ANDROID_ABI", android_abi),
+            ("ANDROID_PLATFORM", android_platform.as_str()),
+            ("IREE_HOST_BIN_DIR", host_bin_dir.to_str().unwrap()),
+        ]);
+    }
+    // iOS builds always include the Metal driver, the only GPU API of the platform.
+    let ios_architecture = match target_arch.as_str() {
+        "x86_64" => "x86_64",
+        _ => "arm64",
+    };
+    if target_os == "ios" {
+        cmake_defs.extend(vec![
+            ("CMAKE_SYSTEM_NAME", "iOS"),
+            ("CMAKE_OSX_ARCHITECTURES", ios_architecture),
+            ("CMAKE_OSX_SYSROOT", ios_sdk_name()),
+            ("IREE_HAL_DRIVER_METAL", "ON"),
+            ("IREE_HOST_BIN_DIR", host_bin_dir.to_str().unwrap()),
+        ]);
+    }
+    if let Some(toolchain_file) = &toolchain_file {
+        config.define("CMAKE_TOOLCHAIN_FILE", toolchain_file);
+    }
+
+    cmake_defs.iter().for_each(|(k, v)| {
+        config.define(k, v);
+    });
+
+    cflags.iter().for_each(|v| {
+        config.cflag(v);
+        config.cxxflag(v);
+    });
+
+    config
+        .build_target("iree_runtime_unified")
+        .out_dir(build_path);
+
+    // Build IREE runtime
+    config.build();
+
+    // Libraries that are not part of the unified runtime library are built separately.
+    #[cfg(feature = "parameters")]
+    for (_, library) in PARAMETER_LIBRARIES.iter() {
+        config.build_target(library).build();
+    }
+    #[cfg(feature = "hal-inline")]
+    for (_, library) in HAL_INLINE_LIBRARIES.iter() {
+        config.build_target(library).build();
+    }
+    #[cfg(feature = "static-library")]
+    for (_, library) in STATIC_LIBRARY_LIBRARIES.iter() {
+        config.build_target(library).build();
+    }
+
+    build_tree_library_dirs(&build_path.join("build"))
+}
+
+// Returns the directories of a CMake build tree of IREE that hold the runtime libraries.
+#[cfg(feature = "runtime")]
+fn build_tree_library_dirs(build_tree: &Path) -> Vec<PathBuf> {
+    #[allow(unused_mut)]
+    let mut library_dirs = vec![
+        build_tree.join("runtime/src/iree/runtime"),
+        build_tree.join("build_tools/third_party/flatcc"),
+    ];
+    #[cfg(feature = "parameters")]
+    for (dir, _) in PARAMETER_LIBRARIES.iter() {
+        library_dirs.push(build_tree.join("runtime/src/iree").join(dir));
+    }
+    #[cfg(feature = "hal-inline")]
+    for (dir, _) in HAL_INLINE_LIBRARIES.iter() {
+        library_dirs.push(build_tree.join("runtime/src/iree").join(dir));
+    }
+    #[cfg(feature = "static-library")]
+    for (dir, _) in STATIC_LIBRARY_LIBRARIES.iter() {
+        library_dirs.push(build_tree.join("runtime/src/iree").join(dir));
+    }
+    library_dirs
+}
@@ -6,3 +6,37 @@ include!(concat!(
     env!("OUT_DIR"),
     "/compiler/iree/compiler/embedding_api.rs"
 ));
+
+#[cfg(feature = "compiler-dlopen")]
+include!(concat!(
+    env!("OUT_DIR"),
+    "/compiler/iree/compiler/forwarders.rs"
+));
+
+#[cfg(feature = "compiler-dlopen")]
+static LIBRARY: std::sync::OnceLock<IreeCompiler> = std::sync::OnceLock::new();
+
+/// Loads the IREE compiler library at the given path, e.g. `libIREECompiler.so`. Once a library
+/// is loaded, it stays loaded for the lifetime of the program and later calls do nothing.
+#[cfg(feature = "compiler-dlopen")]
+pub fn load_library<P: AsRef<std::ffi::OsStr>>(path: P) -> Result<(), libloading::Error> {
+    if LIBRARY.get().is_none() {
+        let library = unsafe { IreeCompiler::new(path) }?;
+        let _ = LIBRARY.set(library);
+    }
+    Ok(())
+}
+
+/// Returns true if the IREE compiler library is loaded.
+#[cfg(feature = "compiler-dlopen")]
+pub fn is_library_loaded() -> bool {
+    LIBRARY.get().is_some()
+}
+
+/// Returns the loaded IREE compiler library. Panics if it is not loaded.
+#[cfg(feature = "compiler-dlopen")]
+pub fn library() -> &'static IreeCompiler {
+    LIBRARY
+        .get()
+        .expect("The IREE compiler library is not loaded")
+}
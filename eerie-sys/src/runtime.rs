@@ -6,3 +6,12 @@ use core::debug_assert;
 use core::prelude::rust_2021::*;
 
 include!(concat!(env!("OUT_DIR"), "/runtime/iree/runtime/api.rs"));
+
+// Declared by iree/base/tracing/tracy.h and the Tracy C API, which are only part of the runtime
+// when it is built with tracing.
+#[cfg(feature = "profiling")]
+extern "C" {
+    pub fn iree_tracing_tracy_initialize();
+    pub fn iree_tracing_tracy_deinitialize();
+    pub fn ___tracy_emit_frame_mark(name: *const core::ffi::c_char);
+}
@@ -0,0 +1,89 @@
+//! Compares invoking a function in a loop with `Function::call`, which builds new lists for every
+//! invocation, against `Call::invoke_batch`, which reuses the lists of a single call.
+#[cfg(all(feature = "compiler", feature = "runtime"))]
+use eerie::runtime::{
+    self,
+    hal::{BufferView, EncodingType},
+};
+
+#[cfg(all(feature = "compiler", feature = "runtime"))]
+const BATCH_SIZE: usize = 1000;
+
+#[cfg(all(feature = "compiler", feature = "runtime"))]
+fn compile_mlir(data: &[u8]) -> Vec<u8> {
+    use eerie::compiler;
+    let compiler = compiler::Compiler::new().unwrap();
+    let mut compiler_session = compiler.create_session();
+    compiler_session
+        .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
+        .unwrap();
+    let source = compiler_session.create_source_from_buf(data).unwrap();
+    let mut invocation = compiler_session.create_invocation();
+    let mut output = compiler::MemBufferOutput::new(&compiler).unwrap();
+    invocation
+        .parse_source(source)
+        .unwrap()
+        .pipeline(compiler::Pipeline::Std)
+        .unwrap()
+        .output_vm_byte_code(&mut output)
+        .unwrap();
+    Vec::from(output.map_memory().unwrap())
+}
+
+#[cfg(all(feature = "compiler", feature = "runtime"))]
+fn main() {
+    env_logger::init();
+    let vmfb = compile_mlir(include_bytes!("../tests/mul.mlir"));
+
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+    let function = session.lookup_function("arithmetic.simple_mul").unwrap();
+    let inputs = (0..BATCH_SIZE)
+        .map(|i| {
+            BufferView::<f32>::new(
+                &session,
+                &[100],
+                EncodingType::DenseRowMajor,
+                &[i as f32; 100],
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let start = std::time::Instant::now();
+    for input in &inputs {
+        let (_,): (BufferView<f32>,) = function.call((input, input)).unwrap();
+    }
+    println!(
+        "Function::call: {} invocations in {} us",
+        BATCH_SIZE,
+        start.elapsed().as_micros()
+    );
+
+    let start = std::time::Instant::now();
+    let mut call = runtime::api::Call::new(&session, &function).unwrap();
+    let outputs: Vec<(BufferView<f32>,)> = call
+        .invoke_batch(inputs.iter().map(|input| (input, input)))
+        .unwrap();
+    println!(
+        "Call::invoke_batch: {} invocations in {} us",
+        outputs.len(),
+        start.elapsed().as_micros()
+    );
+}
+
+#[cfg(not(all(feature = "compiler", feature = "runtime")))]
+fn main() {}
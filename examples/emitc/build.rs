@@ -0,0 +1,19 @@
+use std::{env, path::PathBuf};
+
+use eerie::compiler::{CModuleBuild, CompilerOptions, LlvmCpuTarget, TargetBackend};
+
+// Compiles `tests/mul.mlir` into a C module and a static executable library, and links both into
+// the binary, so that neither bytecode nor executables are loaded at runtime.
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let source =
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("../../tests/mul.mlir");
+    let executables = out_dir.join("simple_mul_executables.o");
+    let options = CompilerOptions::new()
+        .target_backend(TargetBackend::LlvmCpu)
+        .llvm_cpu_target(LlvmCpuTarget::host().static_library_output(&executables));
+    CModuleBuild::new(options)
+        .compile(&source, "simple_mul_module")
+        .unwrap();
+    println!("cargo:rustc-link-arg={}", executables.display());
+}
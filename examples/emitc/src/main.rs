@@ -0,0 +1,42 @@
+//! Runs `tests/mul.mlir` compiled to C source, with the executables linked into the binary and
+//! served by the static library loader. No bytecode is interpreted and no code is loaded at
+//! runtime.
+use eerie::runtime::{
+    api::{Instance, InstanceOptions, Session, SessionOptions},
+    error::RuntimeError,
+    hal::{BufferView, DriverRegistry, EncodingType, StaticLibraryLoader},
+    vm,
+};
+use eerie_sys::runtime as sys;
+
+extern "C" {
+    // Declared in the generated `simple_mul_module.h`, after the name of the MLIR module.
+    fn arithmetic_create(
+        instance: *mut sys::iree_vm_instance_t,
+        allocator: sys::iree_allocator_t,
+        out_module: *mut *mut sys::iree_vm_module_t,
+    ) -> sys::iree_status_t;
+
+    // Declared in the generated `simple_mul_executables.h`.
+    fn simple_mul_dispatch_0_library_query(
+        max_version: sys::iree_hal_executable_library_version_t,
+        environment: *const sys::iree_hal_executable_environment_v0_t,
+    ) -> *const *const sys::iree_hal_executable_library_header_t;
+}
+
+fn main() -> Result<(), RuntimeError> {
+    // No driver is registered, the only device is the one of the static library loader.
+    let instance = Instance::new(&InstanceOptions::new(&mut DriverRegistry::new()))?;
+    let loader = StaticLibraryLoader::new(&instance, &[Some(simple_mul_dispatch_0_library_query)])?;
+    let device = instance.create_static_library_device(&loader)?;
+    let session = Session::create_with_device(&instance, &SessionOptions::default(), &device)?;
+    let module = unsafe { vm::Module::from_c_module(&instance, arithmetic_create) }?;
+    session.append_module(&module)?;
+    let function = session.lookup_function("arithmetic.simple_mul")?;
+
+    let input = BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[3.0; 100])?;
+    let (output,): (BufferView<f32>,) = function.call((&input, &input))?;
+    assert_eq!(output.to_vec()?, vec![9.0; 100]);
+    println!("Output: {:?}", &output.to_vec()?[..4]);
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Serves `tests/mul.mlir` with an `AsyncModel` and sends it concurrent requests from tokio tasks,
+//! like the handlers of an HTTP server would.
+use std::sync::Arc;
+
+use eerie::{
+    compiler,
+    runtime::api::DeviceSpec,
+    serve::{AsyncModel, Backpressure, ServeOptions},
+    Tensor,
+};
+
+const REQUESTS: usize = 64;
+
+fn compile_mlir(data: &[u8]) -> Vec<u8> {
+    let compiler = compiler::Compiler::new().unwrap();
+    let mut compiler_session = compiler.create_session();
+    compiler_session
+        .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
+        .unwrap();
+    let source = compiler_session.create_source_from_buf(data).unwrap();
+    let mut invocation = compiler_session.create_invocation();
+    let mut output = compiler::MemBufferOutput::new(&compiler).unwrap();
+    invocation
+        .parse_source(source)
+        .unwrap()
+        .pipeline(compiler::Pipeline::Std)
+        .unwrap()
+        .output_vm_byte_code(&mut output)
+        .unwrap();
+    Vec::from(output.map_memory().unwrap())
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let vmfb = compile_mlir(include_bytes!("../tests/mul.mlir"));
+    let model = Arc::new(
+        AsyncModel::from_bytes(
+            &vmfb,
+            DeviceSpec::Driver("local-task"),
+            ServeOptions::new("simple_mul")
+                .workers(2)
+                .queue_capacity(8)
+                .backpressure(Backpressure::Wait),
+        )
+        .unwrap(),
+    );
+
+    let start = std::time::Instant::now();
+    let handles = (0..REQUESTS)
+        .map(|i| {
+            let model = model.clone();
+            tokio::spawn(async move {
+                let input = Tensor::new(&[100], &[i as f32; 100]).unwrap();
+                let outputs = model.infer(vec![input.clone(), input]).await.unwrap();
+                outputs[0].as_slice::<f32>().unwrap()[0]
+            })
+        })
+        .collect::<Vec<_>>();
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.await.unwrap(), (i * i) as f32);
+    }
+    println!(
+        "{} requests on {} workers in {} us",
+        REQUESTS,
+        model.workers(),
+        start.elapsed().as_micros()
+    );
+}
@@ -0,0 +1,20 @@
+use std::{env, fs, path::PathBuf};
+
+// Puts the memory layout where the linker script of cortex-m-rt finds it, and links the
+// executables of the static library example.
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::copy("memory.x", out_dir.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out_dir.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
+    if env::var("CARGO_FEATURE_STATIC_LIBRARY").is_ok() {
+        let object =
+            PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("simple_mul_static.o");
+        println!(
+            "cargo:rustc-link-arg-bin=static_library={}",
+            object.display()
+        );
+        println!("cargo:rerun-if-changed={}", object.display());
+    }
+}
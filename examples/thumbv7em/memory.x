@@ -0,0 +1,6 @@
+/* Memory layout of an STM32F407, adjust it to the target chip. */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 1024K
+  RAM : ORIGIN = 0x20000000, LENGTH = 128K
+}
@@ -0,0 +1,89 @@
+//! Runs `simple_mul.mlir` compiled to a static library for the Cortex-M4F, with the executables
+//! linked into the binary and served by the static library loader.
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+
+use cortex_m_rt::entry;
+use eerie::runtime::{
+    api::{Instance, InstanceOptions, Session, SessionOptions},
+    error::RuntimeError,
+    hal::{BufferView, DriverRegistry, EncodingType, StaticLibraryLoader},
+    vm::{List, ListStorage, Ref, StaticList, ToRef},
+};
+use eerie_sys::runtime as sys;
+use embedded_alloc::Heap;
+use panic_halt as _;
+
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+
+const HEAP_SIZE: usize = 16 * 1024;
+
+// Compiled with `--iree-llvmcpu-link-static`, see the README. The module only references the
+// executables, which are linked from `simple_mul_static.o`.
+static MODULE: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/simple_mul_static.vmfb"
+));
+
+extern "C" {
+    // Declared in the generated `simple_mul_static.h`.
+    fn simple_mul_dispatch_0_library_query(
+        max_version: sys::iree_hal_executable_library_version_t,
+        environment: *const sys::iree_hal_executable_environment_v0_t,
+    ) -> *const *const sys::iree_hal_executable_library_header_t;
+}
+
+fn run(output: &mut [f32; 4]) -> Result<(), RuntimeError> {
+    let instance = Instance::new(&InstanceOptions::new(&mut DriverRegistry::new()))?;
+    let loader = StaticLibraryLoader::new(&instance, &[Some(simple_mul_dispatch_0_library_query)])?;
+    let device = instance.create_static_library_device(&loader)?;
+    let session = Session::create_with_device(&instance, &SessionOptions::default(), &device)?;
+    unsafe { session.append_static_module(MODULE) }?;
+    let function = session.lookup_function("module.simple_mul")?;
+
+    let mut input_storage = ListStorage::<256>::new();
+    let mut output_storage = ListStorage::<128>::new();
+    let inputs =
+        StaticList::<Ref<BufferView<f32>>>::new(input_storage.as_byte_span(), 2, &instance)?;
+    let outputs =
+        StaticList::<Ref<BufferView<f32>>>::new(output_storage.as_byte_span(), 1, &instance)?;
+
+    let lhs = BufferView::new(
+        &session,
+        &[4],
+        EncodingType::DenseRowMajor,
+        &[1.0, 2.0, 3.0, 4.0],
+    )?;
+    let rhs = BufferView::new(
+        &session,
+        &[4],
+        EncodingType::DenseRowMajor,
+        &[2.0, 2.0, 2.0, 2.0],
+    )?;
+    inputs.push_ref(&lhs.to_ref(&instance)?)?;
+    inputs.push_ref(&rhs.to_ref(&instance)?)?;
+    function.invoke(&inputs, &outputs)?;
+
+    let result = outputs.get_ref(0)?.to_buffer_view(&session);
+    result.copy_to_host(output)
+}
+
+#[entry]
+fn main() -> ! {
+    {
+        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+        unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
+    }
+
+    let mut output = [0.0f32; 4];
+    match run(&mut output) {
+        Ok(()) => assert_eq!(output, [2.0, 4.0, 6.0, 8.0]),
+        Err(_) => cortex_m::asm::bkpt(),
+    }
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
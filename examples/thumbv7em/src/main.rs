@@ -0,0 +1,99 @@
+//! Runs `simple_mul.mlir` on a Cortex-M4F with the `local-sync` driver.
+//!
+//! Lists live in fixed storage, the module is read from flash and errors carry static
+//! messages, so the Rust side does not allocate to run the model or to report an error. The IREE
+//! runtime itself allocates from the Newlib heap.
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+
+use cortex_m_rt::entry;
+use eerie::runtime::{
+    api::{Instance, InstanceOptions, Session, SessionOptions},
+    error::RuntimeError,
+    hal::{BufferView, DriverRegistry, EncodingType},
+    vm::{List, ListStorage, Ref, StaticList, ToRef},
+};
+use embedded_alloc::Heap;
+use panic_halt as _;
+
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+
+const HEAP_SIZE: usize = 16 * 1024;
+
+// Compiled with `iree-compile --iree-hal-target-backends=vmvx`, see the README.
+static MODULE: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/simple_mul.vmfb"));
+
+// Holds the last error, formatted without allocating, to inspect it with a debugger.
+static mut ERROR_MESSAGE: [u8; 256] = [0; 256];
+
+fn run(output: &mut [f32; 4]) -> Result<(), RuntimeError> {
+    let instance = Instance::new(
+        &InstanceOptions::new(&mut DriverRegistry::new()).use_all_available_drivers(),
+    )?;
+    let device = instance.try_create_default_device("local-sync")?;
+    let session = Session::create_with_device(&instance, &SessionOptions::default(), &device)?;
+    unsafe { session.append_static_module(MODULE) }?;
+    let function = session.lookup_function("module.simple_mul")?;
+
+    let mut input_storage = ListStorage::<256>::new();
+    let mut output_storage = ListStorage::<128>::new();
+    let inputs =
+        StaticList::<Ref<BufferView<f32>>>::new(input_storage.as_byte_span(), 2, &instance)?;
+    let outputs =
+        StaticList::<Ref<BufferView<f32>>>::new(output_storage.as_byte_span(), 1, &instance)?;
+
+    let lhs = BufferView::new(
+        &session,
+        &[4],
+        EncodingType::DenseRowMajor,
+        &[1.0, 2.0, 3.0, 4.0],
+    )?;
+    let rhs = BufferView::new(
+        &session,
+        &[4],
+        EncodingType::DenseRowMajor,
+        &[2.0, 2.0, 2.0, 2.0],
+    )?;
+    inputs.push_ref(&lhs.to_ref(&instance)?)?;
+    inputs.push_ref(&rhs.to_ref(&instance)?)?;
+    function.invoke(&inputs, &outputs)?;
+
+    let result = outputs.get_ref(0)?.to_buffer_view(&session);
+    result.copy_to_host(output)
+}
+
+#[entry]
+fn main() -> ! {
+    {
+        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+        unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
+    }
+
+    let mut output = [0.0f32; 4];
+    match run(&mut output) {
+        Ok(()) => assert_eq!(output, [2.0, 4.0, 6.0, 8.0]),
+        Err(RuntimeError::StatusError(err)) => unsafe {
+            let _ = err.format_into(&mut *core::ptr::addr_of_mut!(ERROR_MESSAGE));
+            cortex_m::asm::bkpt();
+        },
+        Err(
+            RuntimeError::InstanceMismatch(message)
+            | RuntimeError::SignatureMismatch(message)
+            | RuntimeError::ShapeMismatch(message)
+            | RuntimeError::InvalidModule(message)
+            | RuntimeError::InvalidArchive(message),
+        ) => unsafe {
+            let buffer = &mut *core::ptr::addr_of_mut!(ERROR_MESSAGE);
+            let length = message.len().min(buffer.len());
+            buffer[..length].copy_from_slice(&message.as_bytes()[..length]);
+            cortex_m::asm::bkpt();
+        },
+        Err(_) => cortex_m::asm::bkpt(),
+    }
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
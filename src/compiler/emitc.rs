@@ -0,0 +1,117 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use super::{compile_mlir_to_c_module, Compiler, CompilerError, CompilerOptions};
+
+/// The files generated by [`CModuleBuild::compile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CModule {
+    /// The header emitted by the compiler, declaring `<module name>_create`.
+    pub header: PathBuf,
+    /// The C file that compiles the implementation of the header.
+    pub source: PathBuf,
+}
+
+/// Compiles an MLIR module to C source and builds it into a static library with `cc`, from a
+/// build script. This is how modules run without the bytecode interpreter, e.g. in fully static
+/// deployments that cannot load code at runtime.
+///
+/// The library is linked into the crate, and the module is created at runtime with
+/// `vm::Module::from_c_module` from `<module name>_create`, declared as an `extern "C"`
+/// function. The executables of the module are compiled by the backends of the options as usual,
+/// so they are usually linked statically as well with `LlvmCpuTarget::static_library_output` and
+/// served by `hal::StaticLibraryLoader`.
+///
+/// The C source includes the runtime headers. Their directory is taken from the
+/// `DEP_IREE_INCLUDE` environment variable, which Cargo sets for build scripts of crates that
+/// depend on `eerie-sys` directly, or set with [`CModuleBuild::include_dir`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CModuleBuild {
+    options: CompilerOptions,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, Option<String>)>,
+    out_dir: Option<PathBuf>,
+}
+
+impl CModuleBuild {
+    /// Creates a build that compiles modules with the given options.
+    pub fn new(options: CompilerOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a directory to the include path of the C compiler, e.g. the runtime sources of an
+    /// IREE checkout.
+    pub fn include_dir(mut self, dir: &Path) -> Self {
+        self.include_dirs.push(dir.to_path_buf());
+        self
+    }
+
+    /// Defines a preprocessor macro for the C compiler, e.g. the configuration the runtime was
+    /// built with on bare-metal targets.
+    pub fn define(mut self, name: &str, value: Option<&str>) -> Self {
+        self.defines
+            .push((name.to_string(), value.map(str::to_string)));
+        self
+    }
+
+    /// Sets the directory the generated files and the library are written to. Defaults to
+    /// `OUT_DIR`.
+    pub fn out_dir(mut self, dir: &Path) -> Self {
+        self.out_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Compiles the MLIR file at the given path to `<name>.h`, and builds it into the static
+    /// library `lib<name>.a`, which is linked into the crate. Prints the `cargo:` directives to
+    /// rebuild when the file changes.
+    pub fn compile(&self, source: &Path, name: &str) -> Result<CModule, CompilerError> {
+        println!("cargo:rerun-if-changed={}", source.display());
+        println!("cargo:rerun-if-env-changed=DEP_IREE_INCLUDE");
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => PathBuf::from(env::var("OUT_DIR").map_err(|_| {
+                CompilerError::CBuildError(
+                    "OUT_DIR is not set, set an output directory outside of build scripts"
+                        .to_string(),
+                )
+            })?),
+        };
+        let data = std::fs::read(source)
+            .map_err(|_| CompilerError::FileNotFound(source.display().to_string()))?;
+        let c_source = compile_mlir_to_c_module(Compiler::global()?, data, &self.options)?;
+
+        let module = CModule {
+            header: out_dir.join(format!("{}.h", name)),
+            source: out_dir.join(format!("{}.c", name)),
+        };
+        std::fs::write(&module.header, c_source)?;
+        std::fs::write(
+            &module.source,
+            format!("#define EMITC_IMPLEMENTATION\n#include \"{}.h\"\n", name),
+        )?;
+
+        let mut build = cc::Build::new();
+        build
+            .file(&module.source)
+            .include(&out_dir)
+            .out_dir(&out_dir);
+        if let Some(dir) = env::var_os("DEP_IREE_INCLUDE") {
+            build.include(dir);
+        }
+        for dir in &self.include_dirs {
+            build.include(dir);
+        }
+        for (name, value) in &self.defines {
+            build.define(name, value.as_deref());
+        }
+        build
+            .try_compile(name)
+            .map_err(|err| CompilerError::CBuildError(err.to_string()))?;
+        Ok(module)
+    }
+}
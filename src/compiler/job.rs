@@ -0,0 +1,111 @@
+use super::{Compiler, CompilerError, CompilerOptions, Diagnostic, MemBufferOutput};
+use log::debug;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+/// Progress of a [`CompileJob`].
+#[derive(Debug, Clone)]
+pub enum CompileEvent {
+    /// A phase of the standard pipeline finished, see [`super::COMPILE_PHASES`].
+    PhaseFinished(&'static str),
+    /// The compiler emitted a diagnostic.
+    Diagnostic(Diagnostic),
+}
+
+/// A compilation of an MLIR module to a VM bytecode module (vmfb) running on a background
+/// thread, so that GUIs and servers stay responsive during long compiles.
+///
+/// The standard pipeline runs one phase at a time. Progress and diagnostics are streamed as
+/// [`CompileEvent`]s after each phase, and a cancelled job stops before its next phase.
+pub struct CompileJob {
+    cancelled: Arc<AtomicBool>,
+    events: mpsc::Receiver<CompileEvent>,
+    handle: thread::JoinHandle<Result<Vec<u8>, CompilerError>>,
+}
+
+impl CompileJob {
+    /// Starts compiling the given source, in textual or bytecode form, with the given options.
+    /// The compiler must outlive the job, e.g. by being stored in a static.
+    pub fn spawn(
+        compiler: &'static Compiler,
+        source: Vec<u8>,
+        options: CompilerOptions,
+    ) -> Result<Self, CompilerError> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, events) = mpsc::channel();
+        let handle = {
+            let cancelled = cancelled.clone();
+            thread::Builder::new()
+                .name("iree-compile".to_string())
+                .spawn(move || compile(compiler, &source, &options, &cancelled, &sender))?
+        };
+        Ok(Self {
+            cancelled,
+            events,
+            handle,
+        })
+    }
+
+    /// Requests the job to stop. The phase that is running is completed first, and the job then
+    /// returns [`CompilerError::Cancelled`].
+    pub fn cancel(&self) {
+        debug!("Cancelling compile job");
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the job has finished, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Returns the events emitted since the last call, without blocking.
+    pub fn events(&self) -> impl Iterator<Item = CompileEvent> + '_ {
+        self.events.try_iter()
+    }
+
+    /// Waits for the job to finish and returns the compiled module.
+    pub fn join(self) -> Result<Vec<u8>, CompilerError> {
+        self.handle.join().expect("The compile job panicked")
+    }
+
+    /// Waits for the job to finish and returns the compiled module, with the events that were
+    /// not yet read with [`CompileJob::events`].
+    pub fn join_with_events(self) -> (Result<Vec<u8>, CompilerError>, Vec<CompileEvent>) {
+        let result = self.handle.join().expect("The compile job panicked");
+        // The sender is dropped with the thread, so this reads every remaining event.
+        (result, self.events.into_iter().collect())
+    }
+}
+
+fn compile(
+    compiler: &Compiler,
+    source: &[u8],
+    options: &CompilerOptions,
+    cancelled: &AtomicBool,
+    sender: &mpsc::Sender<CompileEvent>,
+) -> Result<Vec<u8>, CompilerError> {
+    let mut session = compiler.create_session();
+    session.set_options(options)?;
+    let source = session.create_source_from_buf(source)?;
+    let mut invocation = session.create_invocation();
+    invocation.parse_source(source)?;
+    invocation.run_phases(|invocation, phase| {
+        // The receiver may be dropped, in which case events are discarded.
+        for diagnostic in invocation.diagnostic_queue.as_ref().get_ref().take() {
+            let _ = sender.send(CompileEvent::Diagnostic(diagnostic));
+        }
+        let _ = sender.send(CompileEvent::PhaseFinished(phase));
+        match cancelled.load(Ordering::Relaxed) {
+            true => Err(CompilerError::Cancelled),
+            false => Ok(()),
+        }
+    })?;
+    let mut output = MemBufferOutput::new(compiler)?;
+    invocation.output_vm_byte_code(&mut output)?;
+    Ok(output.map_memory()?.to_vec())
+}
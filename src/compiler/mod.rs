@@ -11,6 +11,15 @@ use std::{
 };
 use thiserror::Error;
 
+#[cfg(feature = "emitc")]
+mod emitc;
+mod job;
+mod options;
+#[cfg(feature = "emitc")]
+pub use emitc::{CModule, CModuleBuild};
+pub use job::{CompileEvent, CompileJob};
+pub use options::{CompilerOptions, InputType, LlvmCpuTarget, OptimizationLevel, TargetBackend};
+
 /// Errors from the IREE compiler
 pub struct Error {
     message: String,
@@ -45,15 +54,96 @@ impl std::error::Error for Error {}
 /// previously exposed functionality. A major version bump implies an API
 /// break and no forward or backward compatibility is assumed across major
 /// versions.
+///
+/// With the `compiler-dlopen` feature, this loads the compiler library like [`Compiler::new`], and
+/// panics if it cannot be loaded.
 pub fn get_api_version() -> (u16, u16) {
+    #[cfg(feature = "compiler-dlopen")]
+    load_default_library().expect("Failed to load the IREE compiler library");
     let version_bytes = unsafe { sys::ireeCompilerGetAPIVersion() } as u32;
     let major = (version_bytes >> 16) as u16;
     let minor = (version_bytes & 0xFFFF) as u16;
     (major, minor)
 }
 
+/// The version of the IREE compiler, the counterpart of the module versions the runtime provides,
+/// see `runtime::version`. Modules should be compiled by the compiler release matching the IREE
+/// sources of the runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerVersion {
+    /// Major and minor version of the compiler API, see [`get_api_version`].
+    pub api_version: (u16, u16),
+    /// Build revision of the compiler, see [`Compiler::get_revision`].
+    pub revision: String,
+}
+
+/// Compiles an MLIR module, in textual or bytecode form, to a VM bytecode module (vmfb) with the
+/// standard pipeline. This runs the session, source, invocation and output steps in one go.
+/// Errors carry the diagnostics emitted by the compiler.
+pub fn compile_mlir_to_vmfb(
+    compiler: &Compiler,
+    source: impl AsRef<[u8]>,
+    options: &CompilerOptions,
+) -> Result<Vec<u8>, CompilerError> {
+    let mut session = compiler.create_session();
+    session.set_options(options)?;
+    let source = session.create_source_from_buf(source.as_ref())?;
+    let mut invocation = session.create_invocation();
+    invocation.parse_source(source)?.pipeline(Pipeline::Std)?;
+    let mut output = MemBufferOutput::new(compiler)?;
+    invocation.output_vm_byte_code(&mut output)?;
+    Ok(output.map_memory()?.to_vec())
+}
+
+/// Compiles an MLIR module like [`compile_mlir_to_vmfb`], but emits the VM module as C source
+/// instead of bytecode. The source is a header that holds the implementation of the module when
+/// `EMITC_IMPLEMENTATION` is defined, and declares `<module name>_create`, which creates the
+/// module for `vm::Module::from_c_module` of the runtime. See `CModuleBuild` to build it from a
+/// build script, with the `emitc` feature.
+pub fn compile_mlir_to_c_module(
+    compiler: &Compiler,
+    source: impl AsRef<[u8]>,
+    options: &CompilerOptions,
+) -> Result<String, CompilerError> {
+    let mut session = compiler.create_session();
+    session.set_options(options)?;
+    let source = session.create_source_from_buf(source.as_ref())?;
+    let mut invocation = session.create_invocation();
+    invocation.parse_source(source)?.pipeline(Pipeline::Std)?;
+    let mut output = MemBufferOutput::new(compiler)?;
+    invocation.output_vm_c_source(&mut output)?;
+    Ok(std::str::from_utf8(output.map_memory()?)?.to_string())
+}
+
+/// Loads the IREE compiler library at the given path at runtime. This is only needed to load it
+/// from a custom location before creating the [`Compiler`]. Once a library is loaded, later calls
+/// do nothing.
+#[cfg(feature = "compiler-dlopen")]
+pub fn load_library(path: &Path) -> Result<(), CompilerError> {
+    sys::load_library(path).map_err(|e| CompilerError::LibraryLoadError(e.to_string()))
+}
+
+// Loads the compiler library from the directory in the LIB_IREE_COMPILER environment variable, or
+// from the library search path of the system, unless a library is loaded already.
+#[cfg(feature = "compiler-dlopen")]
+fn load_default_library() -> Result<(), CompilerError> {
+    if sys::is_library_loaded() {
+        return Ok(());
+    }
+    let file_name = format!(
+        "{}IREECompiler{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    );
+    match std::env::var_os("LIB_IREE_COMPILER") {
+        Some(dir) => load_library(&Path::new(&dir).join(file_name)),
+        None => load_library(Path::new(&file_name)),
+    }
+}
+
 static IS_INITIALIZED: OnceLock<()> = OnceLock::new();
-static GLOBAL_CL_IS_SET: OnceLock<()> = OnceLock::new();
+static GLOBAL_CL_IS_SET: Mutex<bool> = Mutex::new(false);
+static GLOBAL_COMPILER: OnceLock<Compiler> = OnceLock::new();
 
 /// The IREE compiler. The compiler is globally initialized when it is created. It can not be
 /// instantiated more than once.
@@ -62,7 +152,12 @@ pub struct Compiler {}
 impl Compiler {
     /// Create a new IREE compiler.
     /// This should only be called once through the lifetime of the program.
+    /// With the `compiler-dlopen` feature, the compiler library is loaded first, from
+    /// `LIB_IREE_COMPILER` at runtime or the library search path, unless [`load_library`] loaded
+    /// it already.
     pub fn new() -> Result<Self, CompilerError> {
+        #[cfg(feature = "compiler-dlopen")]
+        load_default_library()?;
         match IS_INITIALIZED.set(()) {
             Ok(_) => {
                 unsafe {
@@ -75,6 +170,25 @@ impl Compiler {
         }
     }
 
+    /// Returns the compiler shared by the whole program, creating it on first use. Unlike
+    /// [`Compiler::new`], this can be called any number of times and from any thread, so that
+    /// independent parts of a program, or tests, can compile without owning the compiler. The
+    /// shared compiler is never shut down. Returns `CompilerError::AlreadyInitialized` if an owned
+    /// compiler was created with [`Compiler::new`].
+    pub fn global() -> Result<&'static Self, CompilerError> {
+        // Serializes the initialization, so that the compiler is created only once.
+        static GLOBAL_COMPILER_INIT: Mutex<()> = Mutex::new(());
+        if let Some(compiler) = GLOBAL_COMPILER.get() {
+            return Ok(compiler);
+        }
+        let _guard = GLOBAL_COMPILER_INIT.lock().unwrap();
+        if let Some(compiler) = GLOBAL_COMPILER.get() {
+            return Ok(compiler);
+        }
+        let compiler = Self::new()?;
+        Ok(GLOBAL_COMPILER.get_or_init(|| compiler))
+    }
+
     /// Gets the build revision of the IREE compiler. In official releases, this
     /// will be a string with the build tag. In development builds, it may be an
     /// empty string. The returned is valid for as long as the compiler is
@@ -85,33 +199,43 @@ impl Compiler {
         Ok(rev_str.to_string())
     }
 
+    /// Gets the API version and build revision of the IREE compiler.
+    pub fn version(&self) -> Result<CompilerVersion, CompilerError> {
+        Ok(CompilerVersion {
+            api_version: get_api_version(),
+            revision: self.get_revision()?,
+        })
+    }
+
     /// Initializes the command line environment from an explicit argc/argv
     /// The function returns GlobalCLAlreadySet if called more than once.
     /// This uses dark magic to setup the usual array of expected signal handlers.
     /// This API is not yet considered version-stable. If using out of tree, please
     /// contact the developers.
-    pub fn setup_global_cl(&mut self, argv: Vec<String>) -> Result<&mut Self, CompilerError> {
-        match GLOBAL_CL_IS_SET.set(()) {
-            Ok(_) => {
-                let c_str_vec = argv
-                    .iter()
-                    .map(|arg| std::ffi::CString::new(arg.as_str()))
-                    .collect::<Result<Vec<_>, _>>()?;
-                let mut ptr_array = c_str_vec.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
-                let banner = std::ffi::CString::new("IREE Compiler")?;
-                unsafe {
-                    sys::ireeCompilerSetupGlobalCL(
-                        argv.len() as i32,
-                        ptr_array.as_mut_ptr(),
-                        banner.as_ptr(),
-                        false,
-                    )
-                }
-                debug!("Global CL setup");
-                Ok(self)
-            }
-            Err(_) => Err(CompilerError::GlobalCLAlreadySet),
+    /// Concurrent calls are serialized, so that only the first one sets up the environment and
+    /// the others return once it is done.
+    pub fn setup_global_cl(&self, argv: Vec<String>) -> Result<&Self, CompilerError> {
+        let mut global_cl_is_set = GLOBAL_CL_IS_SET.lock().unwrap();
+        if *global_cl_is_set {
+            return Err(CompilerError::GlobalCLAlreadySet);
         }
+        let c_str_vec = argv
+            .iter()
+            .map(|arg| std::ffi::CString::new(arg.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut ptr_array = c_str_vec.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+        let banner = std::ffi::CString::new("IREE Compiler")?;
+        unsafe {
+            sys::ireeCompilerSetupGlobalCL(
+                argv.len() as i32,
+                ptr_array.as_mut_ptr(),
+                banner.as_ptr(),
+                false,
+            )
+        }
+        debug!("Global CL setup");
+        *global_cl_is_set = true;
+        Ok(self)
     }
 
     extern "C" fn capture_registered_hal_target_backend_callback(
@@ -160,7 +284,9 @@ impl Compiler {
         debug!("Backend name: {}", backend_name.to_str().unwrap());
     }
 
-    /// Enumerates the registered plugins.
+    /// Enumerates the registered plugins. Out-of-tree plugins, e.g. ones providing custom target
+    /// backends, are registered when they are built into the compiler library, which can be
+    /// loaded from a custom path with [`load_library`] under the `compiler-dlopen` feature.
     pub fn get_plugins(&self) -> Vec<String> {
         let mut plugins = Mutex::new(Vec::new());
         debug!("Enumerating plugins");
@@ -189,6 +315,39 @@ impl Drop for Compiler {
     }
 }
 
+// Returns an identifier for temporary files that is unique within the system.
+fn temp_file_id() -> String {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    )
+}
+
+// Imports an ONNX protobuf to MLIR in the torch dialect with `iree-import-onnx`, and returns the
+// path of the temporary MLIR file.
+fn import_onnx_model(path: &Path) -> Result<std::path::PathBuf, CompilerError> {
+    let mlir_path = std::env::temp_dir().join(format!("eerie-{}.mlir", temp_file_id()));
+    debug!("Importing ONNX model {:?}", path);
+    let output = std::process::Command::new("iree-import-onnx")
+        .arg(path)
+        .arg("-o")
+        .arg(&mlir_path)
+        .output()
+        .map_err(|e| {
+            CompilerError::ImportError(format!("failed to run iree-import-onnx: {}", e))
+        })?;
+    if !output.status.success() {
+        return Err(CompilerError::ImportError(format!(
+            "iree-import-onnx failed to import {:?}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(mlir_path)
+}
+
 /// A session represents a scope where one or more runs can be executed.
 /// Internally, it consists of an MLIRContext and a private set of session
 /// options. If the CL environment was initialized, session options will be
@@ -236,6 +395,41 @@ impl<'a> Session<'a> {
         }
     }
 
+    /// Selects the input pipeline for sources of the given dialect. Returns
+    /// `CompilerError::UnsupportedInputType` if the compiler was built without the plugin
+    /// providing it, instead of failing when the source is compiled.
+    pub fn set_input_type(&mut self, input_type: InputType) -> Result<&mut Self, CompilerError> {
+        if let Some(plugin) = input_type.required_plugin() {
+            if !self._compiler.get_plugins().iter().any(|p| p == plugin) {
+                return Err(CompilerError::UnsupportedInputType(format!(
+                    "input type {} requires the {} compiler plugin",
+                    input_type, plugin
+                )));
+            }
+        }
+        self.set_options(&CompilerOptions::new().input_type(input_type))
+    }
+
+    /// Activates the registered plugins with the given names for the session
+    /// (`--iree-plugin`), e.g. to drive a custom target backend. Returns
+    /// `CompilerError::PluginNotFound` if a plugin is not listed by [`Compiler::get_plugins`].
+    pub fn activate_plugins(&mut self, plugins: &[&str]) -> Result<&mut Self, CompilerError> {
+        let registered_plugins = self._compiler.get_plugins();
+        let mut options = CompilerOptions::new();
+        for plugin in plugins {
+            if !registered_plugins.iter().any(|p| p == plugin) {
+                return Err(CompilerError::PluginNotFound(plugin.to_string()));
+            }
+            options = options.plugin(plugin);
+        }
+        self.set_options(&options)
+    }
+
+    /// Sets session flags from typed options.
+    pub fn set_options(&mut self, options: &CompilerOptions) -> Result<&mut Self, CompilerError> {
+        self.set_flags(options.to_flags())
+    }
+
     extern "C" fn capture_flags_callback(
         flag: *const std::os::raw::c_char,
         _length: usize,
@@ -275,6 +469,37 @@ impl<'a> Session<'a> {
         Invocation::new(self)
     }
 
+    /// Sets up the session for ONNX input and returns an invocation with the model at the given
+    /// path parsed, ready to run [`Pipeline::Std`]. ONNX protobufs (`.onnx`) are imported to MLIR
+    /// with the `iree-import-onnx` tool, which has to be on the `PATH`, e.g. from
+    /// `pip3 install iree-compiler[onnx]`. Other files are parsed as already imported MLIR.
+    pub fn import_onnx(&mut self, path: &Path) -> Result<Invocation<'_>, CompilerError> {
+        self.set_input_type(InputType::Onnx)?;
+        let session: &Self = self;
+        let mut invocation = session.create_invocation();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("onnx") => {
+                let mlir_path = import_onnx_model(path)?;
+                let parsed = invocation.parse_source_from_file(&mlir_path).map(|_| ());
+                let _ = std::fs::remove_file(&mlir_path);
+                parsed?;
+            }
+            _ => {
+                invocation.parse_source_from_file(path)?;
+            }
+        }
+        Ok(invocation)
+    }
+
+    /// Like [`Session::import_onnx`], but takes the ONNX protobuf of the model from memory.
+    pub fn import_onnx_from_buf(&mut self, model: &[u8]) -> Result<Invocation<'_>, CompilerError> {
+        let model_path = std::env::temp_dir().join(format!("eerie-{}.onnx", temp_file_id()));
+        std::fs::write(&model_path, model)?;
+        let invocation = self.import_onnx(&model_path);
+        let _ = std::fs::remove_file(&model_path);
+        invocation
+    }
+
     /// Creates a new source from a file.
     pub fn create_source_from_file(
         &'a self,
@@ -383,6 +608,10 @@ impl Diagnostics {
     fn push(&self, diagnostic: Diagnostic) {
         self.data.lock().unwrap().push(diagnostic);
     }
+
+    fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.data.lock().unwrap())
+    }
 }
 
 impl Clone for Diagnostics {
@@ -407,6 +636,23 @@ pub enum Pipeline {
     Precompile,
 }
 
+/// Phases of the standard pipeline, in the order they run, for
+/// [`Invocation::set_compile_from_phase`] and [`Invocation::set_compile_to_phase`]. Compiling to
+/// "end", the default, runs all of them and the final serialization.
+pub const COMPILE_PHASES: [&str; 11] = [
+    "input",
+    "abi",
+    "preprocessing",
+    "global-optimization",
+    "flow",
+    "stream",
+    "executable-sources",
+    "executable-targets",
+    "hal",
+    "vm",
+    "end",
+];
+
 impl From<Pipeline> for sys::iree_compiler_pipeline_t {
     fn from(val: Pipeline) -> Self {
         match val {
@@ -563,6 +809,51 @@ impl<'a> Invocation<'a> {
         }
     }
 
+    /// Runs the standard pipeline one phase at a time, and calls the callback with the name of
+    /// each phase and the textual IR after it, so that lowering issues can be inspected without
+    /// rerunning the compilation. The source must be parsed first. Afterwards the invocation
+    /// holds the fully compiled module, like after running [`Pipeline::Std`].
+    pub fn compile_with_ir_dumps(
+        &mut self,
+        mut callback: impl FnMut(&str, &[u8]) -> Result<(), CompilerError>,
+    ) -> Result<&mut Self, CompilerError> {
+        self.run_phases(|invocation, phase| {
+            let mut output = MemBufferOutput::new(invocation.session._compiler)?;
+            invocation.output_ir(&mut output)?;
+            callback(phase, output.map_memory()?)
+        })
+    }
+
+    // Runs the standard pipeline one phase at a time, calling `after_phase` after each phase.
+    fn run_phases(
+        &mut self,
+        mut after_phase: impl FnMut(&mut Self, &'static str) -> Result<(), CompilerError>,
+    ) -> Result<&mut Self, CompilerError> {
+        let mut from_phase = COMPILE_PHASES[0];
+        for phase in COMPILE_PHASES.iter().skip(1) {
+            debug!("Compiling from phase {} to phase {}", from_phase, phase);
+            self.set_compile_from_phase(from_phase)?
+                .set_compile_to_phase(phase)?
+                .pipeline(Pipeline::Std)?;
+            after_phase(self, phase)?;
+            from_phase = phase;
+        }
+        Ok(self)
+    }
+
+    /// Like [`Invocation::compile_with_ir_dumps`], but writes the IR after each phase to a file
+    /// in the given directory, named after the index and name of the phase, e.g.
+    /// `04-flow.mlir`. The directory is created if it does not exist.
+    pub fn dump_intermediates_to(&mut self, dir: &Path) -> Result<&mut Self, CompilerError> {
+        std::fs::create_dir_all(dir)?;
+        let mut index = 0;
+        self.compile_with_ir_dumps(|phase, ir| {
+            index += 1;
+            std::fs::write(dir.join(format!("{:02}-{}.mlir", index, phase)), ir)?;
+            Ok(())
+        })
+    }
+
     /// Outputs the current compiler state as textual IR to the output.
     pub fn output_ir(&self, output: &mut impl Output) -> Result<&Self, CompilerError> {
         debug!("Outputting IR");
@@ -819,6 +1110,26 @@ impl Drop for Source<'_, '_> {
 /// Outputs that can be written by the invocation
 pub trait Output {
     fn as_ptr(&self) -> *mut sys::iree_compiler_output_t;
+
+    /// Writes arbitrary bytes to the output, e.g. to combine custom metadata with IR or bytecode
+    /// written by an invocation into one artifact.
+    fn write_bytes(&mut self, data: &[u8]) -> Result<&mut Self, CompilerError>
+    where
+        Self: Sized,
+    {
+        debug!("Writing {} bytes to output", data.len());
+        let err_ptr = unsafe {
+            sys::ireeCompilerOutputWrite(self.as_ptr(), data.as_ptr() as *const _, data.len())
+        };
+        if err_ptr.is_null() {
+            Ok(self)
+        } else {
+            Err(CompilerError::IREECompilerError(
+                Error::from_ptr(err_ptr),
+                Diagnostics::default(),
+            ))
+        }
+    }
 }
 
 /// Output that writes by file name
@@ -911,6 +1222,84 @@ impl<'a, 'b> FileOutput<'a, 'b> {
     }
 }
 
+/// Output that streams to a `std::io::Write`, e.g. a socket or a compressor, without staging the
+/// whole artifact in memory. The written bytes are forwarded to the writer by a background
+/// thread, call [`StreamOutput::finish`] to wait for them to be written and get the writer back.
+pub struct StreamOutput<'a, W: std::io::Write + Send + 'static> {
+    ctx: *mut sys::iree_compiler_output_t,
+    pipe: Option<std::io::PipeWriter>,
+    forwarder: Option<std::thread::JoinHandle<std::io::Result<W>>>,
+    _compiler: &'a Compiler,
+}
+
+impl<W: std::io::Write + Send + 'static> Output for StreamOutput<'_, W> {
+    fn as_ptr(&self) -> *mut sys::iree_compiler_output_t {
+        self.ctx
+    }
+}
+
+impl<W: std::io::Write + Send + 'static> Drop for StreamOutput<'_, W> {
+    fn drop(&mut self) {
+        self.close();
+        if let Some(forwarder) = self.forwarder.take() {
+            let _ = forwarder.join();
+        }
+    }
+}
+
+impl<'a, W: std::io::Write + Send + 'static> StreamOutput<'a, W> {
+    /// Creates a new stream output that writes to the given writer
+    pub fn new(compiler: &'a Compiler, mut writer: W) -> Result<Self, CompilerError> {
+        debug!("Creating stream output");
+        let (mut reader, pipe) = std::io::pipe()?;
+        let mut output_ptr = std::ptr::null_mut();
+        let err_ptr = unsafe { sys::ireeCompilerOutputOpenFD(pipe.as_raw_fd(), &mut output_ptr) };
+        if !err_ptr.is_null() {
+            return Err(CompilerError::IREECompilerError(
+                Error::from_ptr(err_ptr),
+                Diagnostics::default(),
+            ));
+        }
+        let forwarder = std::thread::spawn(move || {
+            std::io::copy(&mut reader, &mut writer)?;
+            writer.flush()?;
+            Ok(writer)
+        });
+        Ok(StreamOutput {
+            ctx: output_ptr,
+            pipe: Some(pipe),
+            forwarder: Some(forwarder),
+            _compiler: compiler,
+        })
+    }
+
+    // Flushes the output and closes the pipe, which ends the forwarding thread.
+    fn close(&mut self) {
+        if !self.ctx.is_null() {
+            unsafe {
+                debug!("Destroying stream output");
+                sys::ireeCompilerOutputKeep(self.ctx);
+                sys::ireeCompilerOutputDestroy(self.ctx);
+            }
+            self.ctx = std::ptr::null_mut();
+        }
+        self.pipe.take();
+    }
+
+    /// Waits until everything written to the output has been written to the writer, and returns
+    /// the writer.
+    pub fn finish(mut self) -> Result<W, CompilerError> {
+        self.close();
+        let forwarder = self.forwarder.take().unwrap();
+        match forwarder.join() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(CompilerError::FileIoError(std::io::Error::other(
+                "stream output writer panicked",
+            ))),
+        }
+    }
+}
+
 /// Output that writes to a memory buffer
 pub struct MemBufferOutput<'a> {
     ctx: *mut sys::iree_compiler_output_t,
@@ -989,4 +1378,18 @@ pub enum CompilerError {
     FileNotFound(String),
     #[error(transparent)]
     FileIoError(#[from] std::io::Error),
+    #[error("Compilation was cancelled")]
+    Cancelled,
+    #[error("Failed to import model: {0}")]
+    ImportError(String),
+    #[error("Unsupported input type: {0}")]
+    UnsupportedInputType(String),
+    #[error("Compiler plugin not found: {0}")]
+    PluginNotFound(String),
+    #[cfg(feature = "emitc")]
+    #[error("Failed to build the C module: {0}")]
+    CBuildError(String),
+    #[cfg(feature = "compiler-dlopen")]
+    #[error("Failed to load the IREE compiler library: {0}")]
+    LibraryLoadError(String),
 }
@@ -0,0 +1,360 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// HAL target backends that the compiler can generate code for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetBackend {
+    /// CPU code generation through LLVM.
+    LlvmCpu,
+    /// Portable bytecode run by the VMVX interpreter.
+    Vmvx,
+    /// SPIR-V for Vulkan devices.
+    VulkanSpirv,
+    /// PTX for CUDA devices.
+    Cuda,
+    /// AMDGPU code for ROCm devices.
+    Rocm,
+    /// SPIR-V cross compiled to Metal shaders.
+    MetalSpirv,
+    /// SPIR-V cross compiled to WGSL for WebGPU.
+    WebGpuSpirv,
+    /// Any other registered backend, by name.
+    Other(String),
+}
+
+impl Display for TargetBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetBackend::LlvmCpu => write!(f, "llvm-cpu"),
+            TargetBackend::Vmvx => write!(f, "vmvx"),
+            TargetBackend::VulkanSpirv => write!(f, "vulkan-spirv"),
+            TargetBackend::Cuda => write!(f, "cuda"),
+            TargetBackend::Rocm => write!(f, "rocm"),
+            TargetBackend::MetalSpirv => write!(f, "metal-spirv"),
+            TargetBackend::WebGpuSpirv => write!(f, "webgpu-spirv"),
+            TargetBackend::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Input dialects that the compiler accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputType {
+    /// The input is already in IREE's input dialects.
+    None,
+    /// The input type is detected from the source.
+    Auto,
+    StableHlo,
+    Tosa,
+    Torch,
+    /// ONNX models imported to MLIR, e.g. with `iree-import-onnx`.
+    Onnx,
+    /// The TMTensor dialect of torch-mlir.
+    TmTensor,
+    /// Any other input type, by name.
+    Other(String),
+}
+
+impl InputType {
+    /// Returns the compiler plugin that provides the input pipeline, if the input type needs one.
+    /// Plugins are listed by [`super::Compiler::get_plugins`].
+    pub fn required_plugin(&self) -> Option<&'static str> {
+        match self {
+            InputType::StableHlo => Some("input_stablehlo"),
+            InputType::Tosa => Some("input_tosa"),
+            InputType::Torch | InputType::Onnx | InputType::TmTensor => Some("input_torch"),
+            InputType::None | InputType::Auto | InputType::Other(_) => None,
+        }
+    }
+}
+
+impl Display for InputType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputType::None => write!(f, "none"),
+            InputType::Auto => write!(f, "auto"),
+            InputType::StableHlo => write!(f, "stablehlo"),
+            InputType::Tosa => write!(f, "tosa"),
+            InputType::Torch => write!(f, "torch"),
+            InputType::Onnx => write!(f, "onnx"),
+            InputType::TmTensor => write!(f, "tm_tensor"),
+            InputType::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Optimization levels of the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizationLevel {
+    O0,
+    O1,
+    O2,
+    O3,
+}
+
+impl Display for OptimizationLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptimizationLevel::O0 => write!(f, "O0"),
+            OptimizationLevel::O1 => write!(f, "O1"),
+            OptimizationLevel::O2 => write!(f, "O2"),
+            OptimizationLevel::O3 => write!(f, "O3"),
+        }
+    }
+}
+
+/// Target machine of the `llvm-cpu` backend, e.g. to cross compile for an embedded aarch64
+/// device from an x86 host. Unset options are left at the compiler defaults, which target the
+/// host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LlvmCpuTarget {
+    triple: Option<String>,
+    cpu: Option<String>,
+    cpu_features: Vec<String>,
+    link_embedded: Option<bool>,
+    static_library_output: Option<PathBuf>,
+}
+
+impl LlvmCpuTarget {
+    /// Creates a target for the given LLVM target triple, e.g. `aarch64-none-elf`.
+    pub fn new(triple: &str) -> Self {
+        Self::default().triple(triple)
+    }
+
+    /// Creates a target for the host machine.
+    pub fn host() -> Self {
+        Self::default().triple("host").cpu("host")
+    }
+
+    /// Sets the LLVM target triple, e.g. `aarch64-linux-android29`.
+    pub fn triple(mut self, triple: &str) -> Self {
+        self.triple = Some(triple.to_string());
+        self
+    }
+
+    /// Sets the CPU to generate code for, e.g. `host` or `cortex-a78`.
+    pub fn cpu(mut self, cpu: &str) -> Self {
+        self.cpu = Some(cpu.to_string());
+        self
+    }
+
+    /// Adds a CPU feature, e.g. `+avx2`.
+    pub fn cpu_feature(mut self, feature: &str) -> Self {
+        self.cpu_features.push(feature.to_string());
+        self
+    }
+
+    /// Links executables as embedded ELFs that the runtime loads without the system linker, if
+    /// true, instead of as platform shared libraries. Embedded linking is the default.
+    pub fn link_embedded(mut self, link_embedded: bool) -> Self {
+        self.link_embedded = Some(link_embedded);
+        self
+    }
+
+    /// Links the executables of the module into a static library at the given path, e.g.
+    /// `simple_mul.o`, with a header declaring its query function next to it. The library is
+    /// linked into the application and registered with the runtime with
+    /// `hal::StaticLibraryLoader`, which is how executables run on bare-metal targets.
+    pub fn static_library_output(mut self, path: &Path) -> Self {
+        self.static_library_output = Some(path.to_path_buf());
+        self
+    }
+
+    /// Returns the session flags for the target.
+    pub fn to_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(triple) = &self.triple {
+            flags.push(format!("--iree-llvmcpu-target-triple={}", triple));
+        }
+        if let Some(cpu) = &self.cpu {
+            flags.push(format!("--iree-llvmcpu-target-cpu={}", cpu));
+        }
+        if !self.cpu_features.is_empty() {
+            flags.push(format!(
+                "--iree-llvmcpu-target-cpu-features={}",
+                self.cpu_features.join(",")
+            ));
+        }
+        if let Some(link_embedded) = self.link_embedded {
+            flags.push(format!("--iree-llvmcpu-link-embedded={}", link_embedded));
+        }
+        if let Some(path) = &self.static_library_output {
+            flags.push("--iree-llvmcpu-link-static".to_string());
+            flags.push(format!(
+                "--iree-llvmcpu-static-library-output-path={}",
+                path.display()
+            ));
+        }
+        flags
+    }
+}
+
+/// Typed compiler options, converted to session flags with [`CompilerOptions::to_flags`] or set
+/// on a session with [`super::Session::set_options`].
+///
+/// Flags that have no typed counterpart can be passed as is with [`CompilerOptions::flag`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilerOptions {
+    target_backends: Vec<TargetBackend>,
+    input_type: Option<InputType>,
+    optimization_level: Option<OptimizationLevel>,
+    llvm_cpu_target: LlvmCpuTarget,
+    import_parameters: Vec<ParameterArchive>,
+    export_parameters: Option<ParameterArchive>,
+    export_parameter_minimum_size: Option<u64>,
+    splat_parameters: Option<PathBuf>,
+    plugins: Vec<String>,
+    flags: Vec<String>,
+}
+
+/// A parameter archive, e.g. an `.irpa`, `.gguf` or `.safetensors` file, with the scope its
+/// parameters are referenced by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParameterArchive {
+    scope: Option<String>,
+    path: PathBuf,
+}
+
+impl Display for ParameterArchive {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.scope {
+            Some(scope) => write!(f, "{}={}", scope, self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+impl CompilerOptions {
+    /// Creates options that leave every flag at its default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a HAL target backend. Modules are compiled for every backend added.
+    pub fn target_backend(mut self, target_backend: TargetBackend) -> Self {
+        self.target_backends.push(target_backend);
+        self
+    }
+
+    /// Sets the input dialect of the sources.
+    pub fn input_type(mut self, input_type: InputType) -> Self {
+        self.input_type = Some(input_type);
+        self
+    }
+
+    /// Sets the optimization level (`--iree-opt-level`). Only supported by compilers that have
+    /// the flag.
+    pub fn optimization_level(mut self, optimization_level: OptimizationLevel) -> Self {
+        self.optimization_level = Some(optimization_level);
+        self
+    }
+
+    /// Sets the CPU that the `llvm-cpu` backend generates code for, e.g. `host` or `cortex-a78`.
+    pub fn target_cpu(mut self, target_cpu: &str) -> Self {
+        self.llvm_cpu_target = self.llvm_cpu_target.cpu(target_cpu);
+        self
+    }
+
+    /// Adds a CPU feature for the `llvm-cpu` backend, e.g. `+avx2`.
+    pub fn target_cpu_feature(mut self, feature: &str) -> Self {
+        self.llvm_cpu_target = self.llvm_cpu_target.cpu_feature(feature);
+        self
+    }
+
+    /// Sets the target machine of the `llvm-cpu` backend, replacing the CPU and CPU features set
+    /// before.
+    pub fn llvm_cpu_target(mut self, target: LlvmCpuTarget) -> Self {
+        self.llvm_cpu_target = target;
+        self
+    }
+
+    /// Imports the parameters of the given archive into the module as constants. Parameters are
+    /// matched by name, within the given scope if any.
+    pub fn import_parameters(mut self, scope: Option<&str>, path: &Path) -> Self {
+        self.import_parameters.push(ParameterArchive {
+            scope: scope.map(str::to_string),
+            path: path.to_path_buf(),
+        });
+        self
+    }
+
+    /// Moves the constants of the module into a parameter archive written to the given path,
+    /// instead of embedding them into the vmfb. The module references the parameters in the given
+    /// scope, which has to match the scope they are provided under at runtime.
+    pub fn export_parameters(mut self, scope: Option<&str>, path: &Path) -> Self {
+        self.export_parameters = Some(ParameterArchive {
+            scope: scope.map(str::to_string),
+            path: path.to_path_buf(),
+        });
+        self
+    }
+
+    /// Sets the minimum size in bytes of constants that are exported as parameters. Smaller
+    /// constants stay embedded in the module.
+    pub fn export_parameter_minimum_size(mut self, size: u64) -> Self {
+        self.export_parameter_minimum_size = Some(size);
+        self
+    }
+
+    /// Writes a parameter archive with every parameter the module references set to a splat
+    /// value to the given path, e.g. to benchmark a model without its real weights.
+    pub fn splat_parameters(mut self, path: &Path) -> Self {
+        self.splat_parameters = Some(path.to_path_buf());
+        self
+    }
+
+    /// Activates the registered compiler plugin with the given name, e.g. `input_stablehlo`.
+    pub fn plugin(mut self, plugin: &str) -> Self {
+        self.plugins.push(plugin.to_string());
+        self
+    }
+
+    /// Adds a raw flag, e.g. `--iree-opt-const-eval=false`. Raw flags are passed after the typed
+    /// options, so they take precedence.
+    pub fn flag(mut self, flag: &str) -> Self {
+        self.flags.push(flag.to_string());
+        self
+    }
+
+    /// Returns the session flags for the options.
+    pub fn to_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if !self.target_backends.is_empty() {
+            let target_backends = self
+                .target_backends
+                .iter()
+                .map(|target_backend| target_backend.to_string())
+                .collect::<Vec<_>>();
+            flags.push(format!(
+                "--iree-hal-target-backends={}",
+                target_backends.join(",")
+            ));
+        }
+        if let Some(input_type) = &self.input_type {
+            flags.push(format!("--iree-input-type={}", input_type));
+        }
+        if let Some(optimization_level) = &self.optimization_level {
+            flags.push(format!("--iree-opt-level={}", optimization_level));
+        }
+        flags.extend(self.llvm_cpu_target.to_flags());
+        for archive in self.import_parameters.iter() {
+            flags.push(format!("--iree-opt-import-parameters={}", archive));
+        }
+        if let Some(archive) = &self.export_parameters {
+            flags.push(format!("--iree-opt-export-parameters={}", archive));
+        }
+        if let Some(size) = self.export_parameter_minimum_size {
+            flags.push(format!("--iree-opt-export-parameter-minimum-size={}", size));
+        }
+        if let Some(path) = &self.splat_parameters {
+            flags.push(format!("--iree-opt-splat-parameters={}", path.display()));
+        }
+        for plugin in self.plugins.iter() {
+            flags.push(format!("--iree-plugin={}", plugin));
+        }
+        flags.extend(self.flags.iter().cloned());
+        flags
+    }
+}
@@ -4,3 +4,11 @@
 pub mod compiler;
 #[cfg(feature = "runtime")]
 pub mod runtime;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(all(feature = "runtime", feature = "std"))]
+pub use runtime::api::Model;
+#[cfg(feature = "runtime")]
+pub use runtime::tensor::Tensor;
+#[cfg(feature = "runtime")]
+pub use runtime::version::check_artifact_compatibility;
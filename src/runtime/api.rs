@@ -1,40 +1,71 @@
-use super::vm::{self, DynamicList, IsList, Undefined};
-use super::{base, hal::DriverRegistry};
+use super::vm::{self, DynamicList, IsArguments, IsList, IsReturnValues, ToValue, Undefined};
+use super::{base, hal::DriverRegistry, io};
 use super::{
     base::StringView,
     error::RuntimeError,
-    hal::{BufferView, ToElementType},
+    hal::{BufferView, Fence, Timeout, ToElementType},
+    vm::ToRef,
 };
 extern crate alloc;
-use alloc::string::ToString;
-use core::marker::PhantomData;
+use alloc::{string::ToString, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
 use eerie_sys::runtime as sys;
 use log::trace;
 #[cfg(feature = "std")]
-use std::{ffi::CString, path::Path};
+use std::path::Path;
+
+mod instruments;
+#[cfg(feature = "std")]
+mod model;
+mod policy;
+#[cfg(feature = "std")]
+mod pool;
+mod snapshot;
+pub use instruments::{InstrumentChunk, InstrumentChunkKind, InstrumentData};
+#[cfg(feature = "std")]
+pub use model::{DeviceSpec, Model};
+pub use policy::{CancellationToken, InvocationPolicy};
+#[cfg(feature = "std")]
+pub use pool::SessionPool;
+pub use snapshot::{StateAccessor, StateSnapshot};
 
 /// Options used to configure an instance.
 pub struct InstanceOptions<'a> {
     ctx: sys::iree_runtime_instance_options_t,
+    host_allocator: base::Allocator,
     marker: PhantomData<&'a mut DriverRegistry>,
 }
 
 impl<'a> InstanceOptions<'a> {
     /// Creates a new instance options struct.
     pub fn new(driver_registry: &'a mut DriverRegistry) -> Self {
-        let mut options = sys::iree_runtime_instance_options_t {
-            driver_registry: driver_registry.ctx,
-        };
+        let mut options = sys::iree_runtime_instance_options_t::default();
         unsafe {
             trace!("iree_runtime_instance_options_initialize");
             sys::iree_runtime_instance_options_initialize(&mut options);
         }
+        // Initializing the options resets them, so the registry is set afterwards.
+        options.driver_registry = driver_registry.ctx;
         Self {
             ctx: options,
+            host_allocator: base::Allocator::get_global(),
             marker: PhantomData,
         }
     }
 
+    /// Sets the allocator the instance and its sessions use for host memory, e.g. an arena or a
+    /// pool with a fixed capacity, see [`base::HostAllocator`]. By default, host memory is
+    /// allocated with the global Rust allocator. Device buffers are allocated by the device
+    /// allocators of the drivers instead.
+    ///
+    /// Allocations made by the runtime are aligned to [`base::HostAllocator::ALIGNMENT`], 16
+    /// bytes by default, and may outlive the instance, so the allocator must be `'static`. Failed
+    /// allocations are reported as `ResourceExhausted` errors.
+    pub fn host_allocator<A: base::HostAllocator>(mut self, allocator: &'static A) -> Self {
+        self.host_allocator = base::Allocator::from_host_allocator(allocator);
+        self
+    }
+
     /// Sets the instance to use all available registered in the current bindary. Sessions may
     /// query for the driver listing and select one(s) that are appropriate for their use.
     pub fn use_all_available_drivers(mut self) -> Self {
@@ -69,6 +100,27 @@ impl<'a> InstanceOptions<'a> {
 /// should be placed in different instances.
 pub struct Instance {
     ctx: *mut sys::iree_runtime_instance_t,
+    #[cfg(feature = "std")]
+    module_cache: std::sync::Mutex<Vec<CachedModule>>,
+}
+
+// A bytecode module of the module cache of an instance, with the data it was created from.
+#[cfg(feature = "std")]
+struct CachedModule {
+    hash: u64,
+    data: alloc::boxed::Box<[u8]>,
+    ctx: *mut sys::iree_vm_module_t,
+}
+
+#[cfg(feature = "std")]
+impl Drop for CachedModule {
+    fn drop(&mut self) {
+        // Releases the module before its data.
+        unsafe {
+            trace!("iree_vm_module_release");
+            sys::iree_vm_module_release(self.ctx);
+        }
+    }
 }
 
 // Instance is thread-safe.
@@ -77,18 +129,101 @@ unsafe impl Sync for Instance {}
 
 impl Instance {
     /// Creates a new instance with the given options.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn new(options: &InstanceOptions) -> Result<Self, RuntimeError> {
         let mut out_ptr = core::ptr::null_mut();
         base::Status::from_raw(unsafe {
             trace!("iree_runtime_instance_create");
             sys::iree_runtime_instance_create(
                 &options.ctx,
-                base::Allocator::get_global().ctx,
+                options.host_allocator.ctx,
                 &mut out_ptr as *mut *mut sys::iree_runtime_instance_t,
             )
         })
         .to_result()?;
-        Ok(Self { ctx: out_ptr })
+        Ok(Self {
+            ctx: out_ptr,
+            #[cfg(feature = "std")]
+            module_cache: std::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Loads a bytecode module from the given flatbuffer data, or returns the module loaded from
+    /// identical data before. Modules are cached by the hash of their contents, so appending the
+    /// same vmfb to many sessions only parses it once.
+    ///
+    /// The data is copied and verified like with [`Session::append_module_from_memory_checked`].
+    /// Cached modules stay alive until [`Instance::clear_module_cache`] is called or the instance
+    /// is dropped.
+    #[cfg(feature = "std")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = flatbuffer_data.len()))
+    )]
+    pub fn load_module_cached(
+        &self,
+        flatbuffer_data: &[u8],
+    ) -> Result<vm::Module<'_>, RuntimeError> {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        flatbuffer_data.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut module_cache = self.module_cache.lock().unwrap();
+        if let Some(cached) = module_cache
+            .iter()
+            .find(|cached| cached.hash == hash && *cached.data == *flatbuffer_data)
+        {
+            trace!("Module cache hit, hash: {:x}", hash);
+            // The cache keeps its reference, the returned module takes a new one.
+            unsafe {
+                trace!("iree_vm_module_retain");
+                sys::iree_vm_module_retain(cached.ctx);
+            }
+            return Ok(vm::Module {
+                ctx: cached.ctx,
+                instance: self,
+            });
+        }
+
+        io::verify_bytecode_header(flatbuffer_data)?;
+        let data = alloc::boxed::Box::<[u8]>::from(flatbuffer_data);
+        // The data is owned by the cache entry, which is only dropped after the module, and entries
+        // are only removed while no module of the instance is borrowed.
+        let data_ref = unsafe { core::slice::from_raw_parts(data.as_ptr(), data.len()) };
+        let module =
+            unsafe { vm::Module::from_bytecode(self, data_ref) }.map_err(|err| match err {
+                RuntimeError::StatusError(err) => RuntimeError::InvalidModule(error_message!(
+                    "bytecode of length {} failed verification: {}",
+                    data_ref.len(),
+                    err
+                )),
+                err => err,
+            })?;
+        trace!("Caching module {}, hash: {:x}", module.name(), hash);
+        unsafe {
+            trace!("iree_vm_module_retain");
+            sys::iree_vm_module_retain(module.ctx);
+        }
+        module_cache.push(CachedModule {
+            hash,
+            data,
+            ctx: module.ctx,
+        });
+        Ok(module)
+    }
+
+    /// Returns the number of modules in the module cache.
+    #[cfg(feature = "std")]
+    pub fn cached_module_count(&self) -> usize {
+        self.module_cache.lock().unwrap().len()
+    }
+
+    /// Releases the modules of the module cache. Sessions that modules were appended to keep them
+    /// alive, but the cache can no longer return them.
+    #[cfg(feature = "std")]
+    pub fn clear_module_cache(&mut self) {
+        self.module_cache.get_mut().unwrap().clear();
     }
 
     pub(crate) fn get_host_allocator(&self) -> base::Allocator {
@@ -96,12 +231,7 @@ impl Instance {
             trace!("iree_runtime_instance_host_allocator");
             sys::iree_runtime_instance_host_allocator(self.ctx)
         };
-        base::Allocator {
-            ctx: sys::iree_allocator_t {
-                self_: core::ptr::null_mut(),
-                ctl: out_ptr.ctl,
-            },
-        }
+        base::Allocator { ctx: out_ptr }
     }
 
     pub(crate) fn get_vm_instance(&self) -> *mut sys::iree_vm_instance_t {
@@ -119,6 +249,76 @@ impl Instance {
         }
     }
 
+    /// Returns the drivers registered in the driver registry of the instance.
+    pub fn available_drivers(&self) -> Result<Vec<super::hal::DriverInfo>, RuntimeError> {
+        let registry = unsafe {
+            trace!("iree_runtime_instance_driver_registry");
+            sys::iree_runtime_instance_driver_registry(self.ctx)
+        };
+        super::hal::enumerate_drivers(registry, self.get_host_allocator())
+    }
+
+    /// Creates a device from the given URI, e.g. `local-task://`, `vulkan://0`, `hip://0` or
+    /// `cuda://GPU-<uuid>`. The path after the driver name selects the device and is interpreted
+    /// by the driver, see [`super::hal::DeviceInfo::path`].
+    #[doc(alias = "create_device_by_uri")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(uri = uri))
+    )]
+    pub fn create_device(&self, uri: &str) -> Result<super::hal::Device, RuntimeError> {
+        let mut out_ptr = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_create_device, uri: {}", uri);
+            sys::iree_hal_create_device(
+                sys::iree_runtime_instance_driver_registry(self.ctx),
+                StringView::from(uri).ctx,
+                self.get_host_allocator().ctx,
+                &mut out_ptr,
+            )
+        })
+        .to_result()?;
+        Ok(super::hal::Device {
+            ctx: out_ptr,
+            marker: PhantomData,
+        })
+    }
+
+    /// Creates the device with the given identifier using the driver with the given name, e.g. to
+    /// target a specific GPU. Identifiers are listed by
+    /// [`super::hal::Driver::query_available_devices`].
+    pub fn create_device_by_id(
+        &self,
+        driver_name: &str,
+        device_id: usize,
+    ) -> Result<super::hal::Device, RuntimeError> {
+        // The device retains its driver, so the driver can be released right away.
+        self.lookup_driver(driver_name)?
+            .create_device_by_id(device_id)
+    }
+
+    /// Creates the driver with the given name, e.g. `cuda`, from the driver registry of the
+    /// instance. Use it to enumerate and select devices when the default device is not the
+    /// desired one.
+    pub fn lookup_driver(&self, name: &str) -> Result<super::hal::Driver, RuntimeError> {
+        let mut out_ptr = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_driver_registry_try_create, name: {}", name);
+            sys::iree_hal_driver_registry_try_create(
+                sys::iree_runtime_instance_driver_registry(self.ctx),
+                StringView::from(name).ctx,
+                self.get_host_allocator().ctx,
+                &mut out_ptr,
+            )
+        })
+        .to_result()?;
+        Ok(super::hal::Driver {
+            ctx: out_ptr,
+            name: name.to_string(),
+            marker: PhantomData,
+        })
+    }
+
     /// Creates a Device with the given name.
     pub fn try_create_default_device(
         &self,
@@ -148,6 +348,8 @@ impl Instance {
 
 impl Drop for Instance {
     fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        self.clear_module_cache();
         unsafe {
             trace!("iree_runtime_instance_release");
             sys::iree_runtime_instance_release(self.ctx);
@@ -157,6 +359,7 @@ impl Drop for Instance {
 
 /// Options used to configure a Session.
 #[repr(C)]
+#[derive(Clone)]
 pub struct SessionOptions {
     ctx: sys::iree_runtime_session_options_t,
 }
@@ -177,6 +380,73 @@ impl Default for SessionOptions {
     }
 }
 
+impl SessionOptions {
+    /// Sets the flags of the VM context the session creates, e.g.
+    /// `ContextFlags::TRACE_EXECUTION` to trace every invocation in the session.
+    pub fn context_flags(mut self, flags: vm::ContextFlags) -> Self {
+        self.ctx.context_flags = flags.bits();
+        self
+    }
+
+    /// Selects the builtin modules the session registers on creation. All of them are registered
+    /// by default.
+    ///
+    /// Use `SessionBuiltins::NONE` to register the modules by hand, e.g. a HAL module created with
+    /// [`super::hal::create_hal_module`] or the modules of a lighter HAL configuration, with
+    /// [`Session::append_module`].
+    pub fn builtin_modules(mut self, builtins: SessionBuiltins) -> Self {
+        self.ctx.builtin_modules = builtins.bits();
+        self
+    }
+}
+
+/// Builtin modules of a session, see [`SessionOptions::builtin_modules`].
+///
+/// Builtins can be combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionBuiltins(sys::iree_runtime_session_builtins_t);
+
+impl SessionBuiltins {
+    /// No builtin modules.
+    pub const NONE: Self = Self(0);
+    /// The full HAL module, `hal`.
+    pub const HAL: Self =
+        Self(sys::iree_runtime_session_builtins_bits_t_IREE_RUNTIME_SESSION_BUILTIN_HAL as _);
+    /// Every builtin module.
+    pub const ALL: Self =
+        Self(sys::iree_runtime_session_builtins_bits_t_IREE_RUNTIME_SESSION_BUILTIN_ALL as _);
+
+    /// Returns the raw builtin bits.
+    pub fn bits(&self) -> sys::iree_runtime_session_builtins_t {
+        self.0
+    }
+
+    /// Returns true if all builtins in `other` are selected.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for SessionBuiltins {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl core::ops::BitOr for SessionBuiltins {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for SessionBuiltins {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
 /// A runtime session.
 ///
 /// A session containing a set of loaded VM modules and their runtime state. Each session has its
@@ -189,10 +459,13 @@ impl Default for SessionOptions {
 /// transfer the resources across instances or incompatible devices within the same instance.
 ///
 /// Sessions are thread-compatible and may be used from any thread so long as the caller ensures
-/// synchronization.
+/// synchronization, so they are `Send` but not `Sync`. To serve calls from several threads, use a
+/// [`SessionPool`].
 pub struct Session<'a> {
     pub(crate) ctx: *mut sys::iree_runtime_session_t,
     pub(crate) instance: &'a Instance,
+    // Retained devices of the session, the device it was created with first.
+    devices: Vec<*mut sys::iree_hal_device_t>,
 }
 
 // Session is thread-compatible.
@@ -200,6 +473,13 @@ unsafe impl Send for Session<'_> {}
 
 impl<'a> Session<'a> {
     /// Creates a new session with the given options and device.
+    ///
+    /// To run modules compiled for several devices, e.g. a CPU and a GPU, create the session with
+    /// [`Session::create_with_devices`] instead.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(device = device.id()))
+    )]
     pub fn create_with_device(
         instance: &'a Instance,
         options: &SessionOptions,
@@ -220,9 +500,99 @@ impl<'a> Session<'a> {
         base::Status::from_raw(status)
             .to_result()
             .map_err(RuntimeError::StatusError)?;
+        unsafe {
+            trace!("iree_hal_device_retain");
+            sys::iree_hal_device_retain(device.ctx);
+        }
         Ok(Self {
             ctx: out_ptr,
             instance,
+            devices: Vec::from([device.ctx]),
+        })
+    }
+
+    /// Creates a new session bound to several devices, e.g. a CPU and a GPU, for modules compiled
+    /// with one `--iree-hal-target-device` flag per device. The HAL module of the session selects
+    /// the devices by ordinal in the order they are given, and [`Session::device`] returns the
+    /// first one.
+    ///
+    /// The device an asynchronous call signals its results on, and the queues it is ordered on,
+    /// are selected per call with [`InvocationPolicy::device`] and
+    /// [`InvocationPolicy::queue_affinity`].
+    ///
+    /// Returns an `InvalidArgument` error if no device is given.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(device_count = devices.len()))
+    )]
+    pub fn create_with_devices(
+        instance: &'a Instance,
+        options: &SessionOptions,
+        devices: &[&'a super::hal::Device],
+    ) -> Result<Self, RuntimeError> {
+        if devices.is_empty() {
+            base::Status::from_raw(base::allocate_status(
+                base::StatusErrorKind::InvalidArgument,
+                "a session needs at least one device",
+            ))
+            .to_result()?;
+        }
+        // The builtin HAL module only knows the device the session is created with, so it is
+        // replaced by one bound to all devices.
+        let with_hal = SessionBuiltins(options.ctx.builtin_modules).contains(SessionBuiltins::HAL);
+        let session_options = SessionOptions {
+            ctx: sys::iree_runtime_session_options_t {
+                context_flags: options.ctx.context_flags,
+                builtin_modules: options.ctx.builtin_modules & !SessionBuiltins::HAL.bits(),
+            },
+        };
+        let mut session = Self::create_with_device(instance, &session_options, devices[0])?;
+        for device in &devices[1..] {
+            unsafe {
+                trace!("iree_hal_device_retain");
+                sys::iree_hal_device_retain(device.ctx);
+            }
+            session.devices.push(device.ctx);
+        }
+        if with_hal {
+            let hal_module = super::hal::create_hal_module(instance, devices)?;
+            session.append_module(&hal_module)?;
+        }
+        Ok(session)
+    }
+
+    /// Returns the HAL device the session was created with.
+    pub fn device(&self) -> super::hal::Device<'_> {
+        let device = unsafe {
+            trace!("iree_runtime_session_device");
+            sys::iree_runtime_session_device(self.ctx)
+        };
+        unsafe {
+            trace!("iree_hal_device_retain");
+            sys::iree_hal_device_retain(device);
+        }
+        super::hal::Device {
+            ctx: device,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of devices the session is bound to.
+    pub fn device_count(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Returns the device of the session with the given ordinal, in the order given to
+    /// [`Session::create_with_devices`], or `None` if the ordinal is out of range.
+    pub fn device_at(&self, ordinal: usize) -> Option<super::hal::Device<'_>> {
+        let device = *self.devices.get(ordinal)?;
+        unsafe {
+            trace!("iree_hal_device_retain");
+            sys::iree_hal_device_retain(device);
+        }
+        Some(super::hal::Device {
+            ctx: device,
+            marker: PhantomData,
         })
     }
 
@@ -246,51 +616,205 @@ impl<'a> Session<'a> {
         .map_err(RuntimeError::StatusError)
     }
 
-    // pub fn append_module(&self, module: &Module) -> Result<(), RuntimeError> {
-    // TODO: implement this
+    /// Appends a module to the session context. The module is retained by the session, and
+    /// modules it imports from must have been appended before it. Returns
+    /// `RuntimeError::IncompatibleModule` without appending the module if its dependencies are not
+    /// satisfied, see [`Session::check_compatibility`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(module = module.name()))
+    )]
+    pub fn append_module(&self, module: &vm::Module) -> Result<(), RuntimeError> {
+        (self.instance.ctx == module.instance.ctx)
+            .then_some(())
+            .ok_or_else(|| {
+                RuntimeError::InstanceMismatch(error_message!(
+                    "The module must originate from the same instance of the runtime as the \
+                     session."
+                ))
+            })?;
+        self.check_compatibility(module)?;
+        self.append_module_unchecked(module)
+    }
+
+    // Appends the module without checking its dependencies, which the runtime still resolves
+    // when the module is registered.
+    fn append_module_unchecked(&self, module: &vm::Module) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_runtime_session_append_module, name: {}",
+                module.name()
+            );
+            sys::iree_runtime_session_append_module(self.ctx, module.ctx)
+        })
+        .to_result()
+        .map_err(RuntimeError::StatusError)
+    }
 
     /// Appends a bytecode module to the context loaded from the given memory blob.
     /// If the module exists as a file, prefer instead to use append_module_from_file to use memory
-    /// mapped I/O and reduce total memory consumption.
+    /// mapped I/O and reduce total memory consumption. Like [`Session::append_module`], returns
+    /// `RuntimeError::IncompatibleModule` if the dependencies of the module are not satisfied, e.g.
+    /// when it was compiled by a compiler that does not match the runtime.
     /// # Safety
     /// The runtime does not perform strict validation on the module data and assumes it is correct.
     /// Make sure that the bytecode data is valid and trusted before use.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = flatbuffer_data.len()))
+    )]
     pub unsafe fn append_module_from_memory(
         &self,
         flatbuffer_data: &'a [u8],
     ) -> Result<(), RuntimeError> {
-        let const_byte_span = base::ConstByteSpan::from(flatbuffer_data);
-        base::Status::from_raw(unsafe {
-            trace!(
-                "iree_runtime_session_append_bytecode_module_from_memory, bytecode length: {}",
-                flatbuffer_data.len()
-            );
-            sys::iree_runtime_session_append_bytecode_module_from_memory(
-                self.ctx,
-                const_byte_span.ctx,
-                base::Allocator::null_allocator().ctx,
-            )
-        })
-        .to_result()
-        .map_err(RuntimeError::StatusError)
+        let module = vm::Module::from_bytecode(self.instance, flatbuffer_data)?;
+        self.append_module(&module)
+    }
+
+    /// Appends a bytecode module embedded in the binary, e.g. with `include_bytes!`, without
+    /// allocating on the Rust side. Unlike [`Session::append_module_from_memory`], the
+    /// dependencies of the module are not collected into an incompatibility report. The runtime
+    /// still resolves its imports, and returns a status error if they are not satisfied.
+    ///
+    /// This is the way to register modules on targets without a heap, where the module data is
+    /// placed in flash.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is correct.
+    /// Make sure that the bytecode data is valid and trusted before use.
+    pub unsafe fn append_static_module(
+        &self,
+        flatbuffer_data: &'static [u8],
+    ) -> Result<(), RuntimeError> {
+        io::verify_bytecode_header(flatbuffer_data)?;
+        let module = vm::Module::from_bytecode(self.instance, flatbuffer_data)?;
+        self.append_module_unchecked(&module)
+    }
+
+    /// Appends a bytecode module to the context loaded from the given memory blob, verifying it
+    /// first. The header and the flatbuffer of the module are verified before it is loaded, which
+    /// makes this safe to use with untrusted data. Malformed modules are reported as
+    /// `RuntimeError::InvalidModule`, and modules whose dependencies are not satisfied as
+    /// `RuntimeError::IncompatibleModule`.
+    pub fn append_module_from_memory_checked(
+        &self,
+        flatbuffer_data: &'a [u8],
+    ) -> Result<(), RuntimeError> {
+        io::verify_bytecode_header(flatbuffer_data)?;
+        // Module creation runs the flatbuffer verifier of the runtime.
+        let module = unsafe { vm::Module::from_bytecode(self.instance, flatbuffer_data) }.map_err(
+            |err| match err {
+                RuntimeError::StatusError(err) => RuntimeError::InvalidModule(error_message!(
+                    "bytecode of length {} failed verification: {}",
+                    flatbuffer_data.len(),
+                    err
+                )),
+                err => err,
+            },
+        )?;
+        self.append_module(&module)
     }
 
-    /// Appends a bytecode module to the context loaded from the given file.
+    /// Appends a bytecode module to the context loaded from the given file. The file is memory
+    /// mapped, and like [`Session::append_module`], `RuntimeError::IncompatibleModule` is returned
+    /// if the dependencies of the module are not satisfied.
     /// # Safety
     /// The runtime does not perform strict validation on the module data and assumes it is correct.
     /// Make sure that the bytecode data is valid and trusted before use.
     #[cfg(feature = "std")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(path = ?path))
+    )]
     pub unsafe fn append_module_from_file(&self, path: &Path) -> Result<(), RuntimeError> {
-        let cstr = CString::new(path.to_str().unwrap()).unwrap();
-        base::Status::from_raw(unsafe {
-            trace!(
-                "iree_runtime_session_append_bytecode_module_from_file, path: {:?}",
-                path
-            );
-            sys::iree_runtime_session_append_bytecode_module_from_file(self.ctx, cstr.as_ptr())
-        })
-        .to_result()
-        .map_err(RuntimeError::StatusError)
+        let module = vm::Module::from_file(self.instance, path)?;
+        self.append_module(&module)
+    }
+
+    /// Appends a bytecode module to the context loaded from the given file handle, e.g. a vmfb
+    /// opened with [`io::FileHandle::open_mapped`]. The header of the module is checked before it
+    /// is loaded, and the session keeps the file handle alive for as long as it uses the module.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is correct.
+    /// Make sure that the bytecode data is valid and trusted before use.
+    pub unsafe fn append_module_from_file_handle(
+        &self,
+        file_handle: &io::FileHandle,
+    ) -> Result<(), RuntimeError> {
+        file_handle.verify_bytecode_header()?;
+        let module = vm::Module::from_file_handle(self.instance, file_handle)?;
+        self.append_module(&module)
+    }
+
+    /// Returns the modules registered in the session context, in registration order.
+    pub fn modules(&self) -> Vec<vm::Module<'a>> {
+        let context = self.context();
+        let count = unsafe {
+            trace!("iree_vm_context_module_count");
+            sys::iree_vm_context_module_count(context)
+        };
+        (0..count)
+            .map(|i| {
+                let ctx = unsafe {
+                    trace!("iree_vm_context_module_at, index: {}", i);
+                    let ctx = sys::iree_vm_context_module_at(context, i);
+                    sys::iree_vm_module_retain(ctx);
+                    ctx
+                };
+                vm::Module {
+                    ctx,
+                    instance: self.instance,
+                }
+            })
+            .collect()
+    }
+
+    /// Checks that the modules of the session satisfy the dependencies of the given bytecode
+    /// module, without appending it. See [`Session::check_compatibility`].
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is correct.
+    /// Make sure that the bytecode data is valid and trusted before use.
+    pub unsafe fn check_module_compatibility(
+        &self,
+        flatbuffer_data: &[u8],
+    ) -> Result<(), RuntimeError> {
+        let module = vm::Module::from_bytecode(self.instance, flatbuffer_data)?;
+        self.check_compatibility(&module)
+    }
+
+    /// Checks that the modules of the session satisfy the dependencies of the given module,
+    /// without appending it. Returns `RuntimeError::IncompatibleModule` listing every missing or
+    /// outdated dependency, e.g. when the module requires a newer `hal` module than the runtime
+    /// provides.
+    pub fn check_compatibility(&self, module: &vm::Module) -> Result<(), RuntimeError> {
+        let provided = self.modules();
+        let unsatisfied: Vec<_> = module
+            .dependencies()?
+            .into_iter()
+            .filter_map(|dependency| {
+                let provided_version = provided
+                    .iter()
+                    .find(|module| module.name() == dependency.name)
+                    .map(|module| module.version());
+                match provided_version {
+                    Some(version) if version >= dependency.minimum_version => None,
+                    None if dependency.optional => None,
+                    _ => Some(vm::UnsatisfiedDependency {
+                        name: dependency.name,
+                        required_version: dependency.minimum_version,
+                        provided_version,
+                    }),
+                }
+            })
+            .collect();
+        match unsatisfied.is_empty() {
+            true => Ok(()),
+            false => Err(RuntimeError::IncompatibleModule(
+                vm::IncompatibilityReport {
+                    module_name: module.name().to_string(),
+                    unsatisfied,
+                },
+            )),
+        }
     }
 
     pub fn lookup_function<'f>(&'f self, name: &str) -> Result<vm::Function<'f>, RuntimeError> {
@@ -311,6 +835,53 @@ impl<'a> Session<'a> {
         })
     }
 
+    /// Returns the functions exported by the modules of the session, in registration order.
+    ///
+    /// This includes the exports of the modules the session provides, e.g. the HAL module. Use
+    /// [`vm::Function::qualified_name`] to tell them apart and [`vm::Function::signature`] to
+    /// inspect their arguments and results.
+    pub fn list_functions(&self) -> Result<Vec<vm::Function<'_>>, RuntimeError> {
+        let mut functions = Vec::new();
+        for module in self.modules() {
+            let count = unsafe {
+                trace!("iree_vm_module_signature");
+                sys::iree_vm_module_signature(module.ctx).export_function_count
+            };
+            for ordinal in 0..count {
+                let mut out = sys::iree_vm_function_t::default();
+                base::Status::from_raw(unsafe {
+                    trace!(
+                        "iree_vm_module_lookup_function_by_ordinal, module: {}, ordinal: {}",
+                        module.name(),
+                        ordinal
+                    );
+                    sys::iree_vm_module_lookup_function_by_ordinal(
+                        module.ctx,
+                        sys::iree_vm_function_linkage_e_IREE_VM_FUNCTION_LINKAGE_EXPORT,
+                        ordinal,
+                        &mut out,
+                    )
+                })
+                .to_result()?;
+                // The module stays alive as long as the session context holds it.
+                functions.push(vm::Function {
+                    ctx: out,
+                    session: self,
+                });
+            }
+        }
+        Ok(functions)
+    }
+
+    /// Resolves the state the session context holds for the given module, which must have been
+    /// appended to the session.
+    pub fn resolve_module_state(
+        &self,
+        module: &vm::Module,
+    ) -> Result<vm::ModuleState<'_>, RuntimeError> {
+        vm::context::resolve_module_state(self.context(), module)
+    }
+
     pub(crate) fn context(&self) -> *mut sys::iree_vm_context_t {
         unsafe {
             trace!("iree_runtime_session_context");
@@ -324,10 +895,97 @@ impl Drop for Session<'_> {
         unsafe {
             trace!("iree_runtime_session_release");
             sys::iree_runtime_session_release(self.ctx);
+            for device in &self.devices {
+                trace!("iree_hal_device_release");
+                sys::iree_hal_device_release(*device);
+            }
         }
     }
 }
 
+/// Flags that control how a function is invoked, see [`Call::invoke_with_flags`].
+///
+/// Flags can be combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallFlags(sys::iree_vm_invocation_flags_t);
+
+impl CallFlags {
+    /// Invokes the function with no special behavior.
+    pub const NONE: Self =
+        Self(sys::iree_vm_invocation_flag_bits_t_IREE_VM_INVOCATION_FLAG_NONE as _);
+    /// Logs every instruction executed by the invocation. Only has an effect if the runtime was
+    /// built with execution tracing.
+    pub const TRACE_EXECUTION: Self =
+        Self(sys::iree_vm_invocation_flag_bits_t_IREE_VM_INVOCATION_FLAG_TRACE_EXECUTION as _);
+
+    /// Returns the raw flag bits.
+    pub fn bits(&self) -> sys::iree_vm_invocation_flags_t {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for CallFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for CallFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Timing of the invocations of a [`Call`], collected once enabled with
+/// [`Call::enable_statistics`].
+///
+/// Times are taken with the clock of the runtime, so they are zero on bare metal targets without
+/// one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallStatistics {
+    /// Number of invocations that have completed.
+    pub invocation_count: u64,
+    /// Time the calling thread spent in the last invocation. For invocations that do not wait for
+    /// the device, e.g. [`Call::invoke_async`], this only covers the submission.
+    pub last_host_duration: Duration,
+    /// Time from the start of the last completed invocation until its results were ready. For
+    /// invocations started with [`Call::begin`], completion is observed by [`Call::poll`] or
+    /// [`Call::wait`] when the signal fence of the device is reached.
+    pub last_invoke_duration: Duration,
+    /// Sum of the invoke durations of all completed invocations.
+    pub total_invoke_duration: Duration,
+}
+
+impl CallStatistics {
+    /// Returns the mean invoke duration of the completed invocations.
+    pub fn mean_invoke_duration(&self) -> Option<Duration> {
+        (self.invocation_count > 0).then(|| {
+            Duration::from_nanos(
+                (self.total_invoke_duration.as_nanos() / self.invocation_count as u128) as u64,
+            )
+        })
+    }
+
+    fn record_completion(&mut self, duration: Duration) {
+        self.invocation_count += 1;
+        self.last_invoke_duration = duration;
+        self.total_invoke_duration += duration;
+    }
+}
+
+// Returns the time elapsed since the given time of the runtime clock.
+fn elapsed_since(start: sys::iree_time_t) -> Duration {
+    let now = unsafe { sys::iree_time_now() };
+    Duration::from_nanos(now.saturating_sub(start).max(0) as u64)
+}
+
 /// A stateful VM function call builder.
 ///
 /// Application that will be calling the same function repeatedly can reuse the call to avoid
@@ -341,6 +999,10 @@ impl Drop for Session<'_> {
 pub struct Call<'a> {
     ctx: sys::iree_runtime_call_t,
     session: &'a Session<'a>,
+    // Signal fence of the invocation started with `begin`, if it has not completed yet, the time
+    // it was started at, and the token that cancels it.
+    pending: Option<(Fence, sys::iree_time_t, Option<CancellationToken>)>,
+    statistics: Option<CallStatistics>,
 }
 
 unsafe impl Send for Call<'_> {}
@@ -351,6 +1013,8 @@ impl<'a> Call<'a> {
         let mut call = Self {
             ctx: sys::iree_runtime_call_t::default(),
             session,
+            pending: None,
+            statistics: None,
         };
         base::Status::from_raw(unsafe {
             trace!("iree_runtime_call_initialize");
@@ -379,12 +1043,78 @@ impl<'a> Call<'a> {
         Ok(Self {
             ctx: unsafe { out.assume_init() },
             session,
+            pending: None,
+            statistics: None,
         })
     }
 
+    /// Returns the name of the function the call invokes, as exported by its module.
+    pub fn function_name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_function_name");
+            StringView::from_raw(sys::iree_vm_function_name(&self.ctx.function)).into()
+        }
+    }
+
+    /// Starts collecting the timing of invocations, see [`Call::statistics`]. Statistics collected
+    /// before are discarded.
+    pub fn enable_statistics(&mut self) {
+        self.statistics = Some(CallStatistics::default());
+    }
+
+    /// Stops collecting the timing of invocations.
+    pub fn disable_statistics(&mut self) {
+        self.statistics = None;
+    }
+
+    /// Returns the timing of the invocations since statistics were enabled, or `None` if they are
+    /// disabled.
+    pub fn statistics(&self) -> Option<CallStatistics> {
+        self.statistics
+    }
+
+    /// Returns the time from the start of the last completed invocation until its results were
+    /// ready, if statistics are enabled and an invocation has completed.
+    pub fn last_invoke_duration(&self) -> Option<Duration> {
+        self.statistics
+            .filter(|statistics| statistics.invocation_count > 0)
+            .map(|statistics| statistics.last_invoke_duration)
+    }
+
+    // Runs the given invocation and records its host time, and its invoke time if it completes
+    // before returning.
+    fn timed<R>(
+        &mut self,
+        completes: bool,
+        invoke: impl FnOnce(&mut Self) -> Result<R, RuntimeError>,
+    ) -> Result<R, RuntimeError> {
+        if self.statistics.is_none() {
+            return invoke(self);
+        }
+        let start = unsafe { sys::iree_time_now() };
+        let result = invoke(self)?;
+        let duration = elapsed_since(start);
+        if let Some(statistics) = &mut self.statistics {
+            statistics.last_host_duration = duration;
+            if completes {
+                statistics.record_completion(duration);
+            }
+        }
+        Ok(result)
+    }
+
     /// Invokes the call
+    ///
+    /// Use [`Call::invoke_with_flags`] to pass invocation flags.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
     pub fn invoke(&mut self) -> Result<(), RuntimeError> {
-        // TODO: Call flags interface, not fixed to 0
+        self.timed(true, Self::invoke_untimed)
+    }
+
+    fn invoke_untimed(&mut self) -> Result<(), RuntimeError> {
         base::Status::from_raw(unsafe {
             trace!("iree_runtime_call_invoke");
             sys::iree_runtime_call_invoke(&mut self.ctx, 0)
@@ -393,6 +1123,279 @@ impl<'a> Call<'a> {
         .map_err(RuntimeError::StatusError)
     }
 
+    /// Invokes the call with the given flags, e.g. `CallFlags::TRACE_EXECUTION` to trace a single
+    /// invocation instead of every invocation in the session.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name(), flags = flags.bits()))
+    )]
+    pub fn invoke_with_flags(&mut self, flags: CallFlags) -> Result<(), RuntimeError> {
+        self.invoke_with_policy(&InvocationPolicy::new().flags(flags))
+    }
+
+    /// Invokes the call with the given policy. Returns a `Cancelled` error without invoking the
+    /// function if the cancellation token of the policy has been cancelled.
+    ///
+    /// Like [`vm::Function::invoke_with_policy`], functions compiled with
+    /// `--iree-execution-model=async-external` are invoked through their fences when the policy has
+    /// a token, so cancelling it from another thread returns `Cancelled` while the call runs.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name(), flags = policy.invocation_flags().bits()))
+    )]
+    pub fn invoke_with_policy(&mut self, policy: &InvocationPolicy) -> Result<(), RuntimeError> {
+        policy.check_cancelled()?;
+        self.timed(true, |call| {
+            let (inputs, outputs) = unsafe {
+                trace!("iree_runtime_call_inputs");
+                let inputs = sys::iree_runtime_call_inputs(&call.ctx);
+                trace!("iree_runtime_call_outputs");
+                (inputs, sys::iree_runtime_call_outputs(&call.ctx))
+            };
+            call.function()
+                .invoke_raw_with_policy(inputs, outputs, policy)
+        })
+    }
+
+    fn function(&self) -> vm::Function<'a> {
+        vm::Function {
+            ctx: self.ctx.function,
+            session: self.session,
+        }
+    }
+
+    /// Invokes the call with a tuple of Rust arguments, and converts the results into a tuple of
+    /// Rust values, e.g. `let (out,): (BufferView<f32>,) = call.invoke_typed((&image, 0.5f32))?;`.
+    ///
+    /// Both tuples are checked against the calling convention of the function, as in
+    /// [`vm::Function::call`]. The input and output lists are reset before and after the
+    /// invocation, so anything pushed to them beforehand is discarded.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
+    pub fn invoke_typed<A, R>(&mut self, arguments: A) -> Result<R, RuntimeError>
+    where
+        A: vm::Arguments,
+        R: vm::ReturnValues<'a>,
+    {
+        self.function().check_call_types::<A, R>()?;
+        self.invoke_typed_unchecked(arguments)
+    }
+
+    /// Invokes the call once for every tuple of arguments, like [`Call::invoke_typed`], and
+    /// returns the results in the same order.
+    ///
+    /// The calling convention is checked once for the whole batch, and every invocation reuses
+    /// the input and output lists of the call, so serving many requests with the same function
+    /// avoids allocating lists and looking up the signature for each of them. Stops at the first
+    /// failing invocation.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
+    pub fn invoke_batch<A, R>(
+        &mut self,
+        batch: impl IntoIterator<Item = A>,
+    ) -> Result<Vec<R>, RuntimeError>
+    where
+        A: vm::Arguments,
+        R: vm::ReturnValues<'a>,
+    {
+        self.function().check_call_types::<A, R>()?;
+        batch
+            .into_iter()
+            .map(|arguments| self.invoke_typed_unchecked(arguments))
+            .collect()
+    }
+
+    fn invoke_typed_unchecked<A, R>(&mut self, arguments: A) -> Result<R, RuntimeError>
+    where
+        A: vm::Arguments,
+        R: vm::ReturnValues<'a>,
+    {
+        self.reset();
+        let instance = self.session.instance;
+        arguments.push_all(
+            unsafe {
+                trace!("iree_runtime_call_inputs");
+                sys::iree_runtime_call_inputs(&self.ctx)
+            },
+            instance,
+        )?;
+        self.invoke()?;
+        let results = R::from_list(
+            unsafe {
+                trace!("iree_runtime_call_outputs");
+                sys::iree_runtime_call_outputs(&self.ctx)
+            },
+            self.session,
+        );
+        self.reset();
+        results
+    }
+
+    /// Invokes the call without waiting for the device work to complete.
+    ///
+    /// This is meant for functions compiled with `--iree-execution-model=async-external`, which
+    /// take a wait fence and a signal fence as their last two arguments. Both fences are appended
+    /// to the input list after the regular inputs. The function starts executing once
+    /// `wait_fence` is signaled (or immediately if `None`), and the returned fence is signaled
+    /// once all results are ready. Outputs may only be popped after the returned fence has been
+    /// waited on, either with [`Fence::wait`] or, where threads are available, by awaiting it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
+    pub fn invoke_async(&mut self, wait_fence: Option<&Fence>) -> Result<Fence, RuntimeError> {
+        self.invoke_async_with_policy(wait_fence, &InvocationPolicy::new())
+    }
+
+    /// Invokes the call without waiting for the device work to complete, like
+    /// [`Call::invoke_async`], with the given policy. Returns a `Cancelled` error without invoking
+    /// the function if the cancellation token of the policy has been cancelled.
+    ///
+    /// The returned fence is created on the device selected with [`InvocationPolicy::device`], and
+    /// the invocation is ordered on the queues selected with [`InvocationPolicy::queue_affinity`].
+    pub fn invoke_async_with_policy(
+        &mut self,
+        wait_fence: Option<&Fence>,
+        policy: &InvocationPolicy,
+    ) -> Result<Fence, RuntimeError> {
+        policy.check_cancelled()?;
+        let (queue_wait_fence, signal_fence) = policy.async_fences(self.session, wait_fence)?;
+        let wait_fence = queue_wait_fence.as_ref().or(wait_fence);
+        let inputs = unsafe {
+            trace!("iree_runtime_call_inputs");
+            sys::iree_runtime_call_inputs(&self.ctx)
+        };
+        match wait_fence {
+            Some(wait_fence) => self.push_fence(inputs, wait_fence)?,
+            // A null wait fence is treated as already signaled.
+            None => base::Status::from_raw(unsafe {
+                trace!("iree_vm_list_push_ref_retain");
+                sys::iree_vm_list_push_ref_retain(inputs, &sys::iree_vm_ref_t::default())
+            })
+            .to_result()?,
+        }
+        self.push_fence(inputs, &signal_fence)?;
+        // The runtime call API does not forward invocation flags or policies, so the VM is
+        // invoked directly with the lists of the call.
+        self.timed(false, |call| {
+            let (inputs, outputs) = unsafe {
+                trace!("iree_runtime_call_inputs");
+                let inputs = sys::iree_runtime_call_inputs(&call.ctx);
+                trace!("iree_runtime_call_outputs");
+                (inputs, sys::iree_runtime_call_outputs(&call.ctx))
+            };
+            call.function().invoke_raw(inputs, outputs, policy)
+        })?;
+        Ok(signal_fence)
+    }
+
+    /// Starts the call without blocking, like [`Call::invoke_async`], and keeps track of its
+    /// completion so it can be driven with [`Call::poll`]. This allows many calls to be in flight
+    /// from a single thread, e.g. an async executor.
+    ///
+    /// Returns a `FailedPrecondition` error if a previous invocation started with `begin` has not
+    /// completed yet.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
+    pub fn begin(&mut self, wait_fence: Option<&Fence>) -> Result<(), RuntimeError> {
+        self.begin_with_policy(wait_fence, &InvocationPolicy::new())
+    }
+
+    /// Starts the call without blocking, like [`Call::begin`], with the given policy. Once the
+    /// cancellation token of the policy is cancelled, [`Call::poll`] and [`Call::wait`] return a
+    /// `Cancelled` error, even from another thread blocked in `wait`, and the signal fence of the
+    /// invocation is failed so that work waiting on it is aborted as well.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.function_name()))
+    )]
+    pub fn begin_with_policy(
+        &mut self,
+        wait_fence: Option<&Fence>,
+        policy: &InvocationPolicy,
+    ) -> Result<(), RuntimeError> {
+        if self.pending.is_some() {
+            base::Status::from_raw(base::allocate_status(
+                base::StatusErrorKind::FailedPrecondition,
+                "the call has a pending invocation",
+            ))
+            .to_result()?;
+        }
+        let start = unsafe { sys::iree_time_now() };
+        let signal_fence = self.invoke_async_with_policy(wait_fence, policy)?;
+        self.pending = Some((signal_fence, start, policy.cancellation_token().cloned()));
+        Ok(())
+    }
+
+    /// Checks whether the invocation started with [`Call::begin`] has completed, without
+    /// blocking. Once it returns `Poll::Ready`, the outputs may be popped. Returns `Poll::Ready`
+    /// if no invocation is pending.
+    pub fn poll(&mut self) -> Result<core::task::Poll<()>, RuntimeError> {
+        self.check_pending_cancelled()?;
+        if let Some((fence, _, _)) = &self.pending {
+            if !fence.is_signaled()? {
+                return Ok(core::task::Poll::Pending);
+            }
+        }
+        self.complete_pending();
+        Ok(core::task::Poll::Ready(()))
+    }
+
+    /// Blocks until the invocation started with [`Call::begin`] has completed or the timeout
+    /// elapses. Returns immediately if no invocation is pending.
+    pub fn wait(&mut self, timeout: Timeout) -> Result<(), RuntimeError> {
+        let result = match &self.pending {
+            Some((fence, _, Some(token))) => token.wait_for(&self.session.device(), fence, timeout),
+            Some((fence, _, None)) => fence.wait(timeout),
+            None => return Ok(()),
+        };
+        self.check_pending_cancelled()?;
+        result?;
+        self.complete_pending();
+        Ok(())
+    }
+
+    // Returns `Cancelled` if the token of the pending invocation has been cancelled, after
+    // failing its signal fence and dropping it.
+    fn check_pending_cancelled(&mut self) -> Result<(), RuntimeError> {
+        if let Some((fence, _, Some(token))) = &self.pending {
+            if token.is_cancelled() {
+                fence.fail(base::StatusErrorKind::Cancelled);
+                let result = token.check();
+                self.pending = None;
+                return result;
+            }
+        }
+        Ok(())
+    }
+
+    fn complete_pending(&mut self) {
+        if let (Some((_, start, _)), Some(statistics)) = (self.pending.take(), &mut self.statistics)
+        {
+            statistics.record_completion(elapsed_since(start));
+        }
+    }
+
+    fn push_fence(
+        &self,
+        inputs: *mut sys::iree_vm_list_t,
+        fence: &Fence,
+    ) -> Result<(), RuntimeError> {
+        let fence_ref = fence.to_ref(self.session.instance)?;
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_push_ref_retain");
+            sys::iree_vm_list_push_ref_retain(inputs, &fence_ref.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
     /// Pushes a buffer view to the call input list.
     ///
     /// The buffer view must originate from the same instance of the runtime as the call.
@@ -402,10 +1405,12 @@ impl<'a> Call<'a> {
     ) -> Result<(), RuntimeError> {
         (self.session.instance.ctx == buffer_view.session.instance.ctx)
             .then_some(())
-            .ok_or(RuntimeError::InstanceMismatch(
-                "The buffer view must originate from the same instance of the runtime as the call."
-                    .to_string(),
-            ))?;
+            .ok_or_else(|| {
+                RuntimeError::InstanceMismatch(error_message!(
+                    "The buffer view must originate from the same instance of the runtime as the \
+                     call."
+                ))
+            })?;
         base::Status::from_raw(unsafe {
             trace!("iree_runtime_call_inputs_push_back_buffer_view");
             sys::iree_runtime_call_inputs_push_back_buffer_view(&mut self.ctx, buffer_view.ctx)
@@ -427,6 +1432,47 @@ impl<'a> Call<'a> {
         Ok(unsafe { BufferView::from_ptr(out.assume_init(), self.session) })
     }
 
+    /// Pushes a scalar value, e.g. a seed or a threshold, to the call input list.
+    pub fn inputs_push_back_value<T: ToValue>(&mut self, value: T) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_push_value");
+            sys::iree_vm_list_push_value(
+                sys::iree_runtime_call_inputs(&self.ctx),
+                &value.to_value().ctx,
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Pops a scalar value from the call output list. The caller must specify the type of the
+    /// value, which must match the type of the first output.
+    pub fn outputs_pop_front_value<T: ToValue>(&mut self) -> Result<vm::Value<T>, RuntimeError> {
+        let outputs = unsafe {
+            trace!("iree_runtime_call_outputs");
+            sys::iree_runtime_call_outputs(&self.ctx)
+        };
+        let mut value = sys::iree_vm_value_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_get_value, idx: 0");
+            sys::iree_vm_list_get_value(outputs, 0, &mut value)
+        })
+        .to_result()?;
+        if value.type_ != T::to_value_type() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "output is a value of type {}, not {}",
+                value.type_,
+                T::to_value_type()
+            )));
+        }
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_erase, idx: 0");
+            sys::iree_vm_list_erase(outputs, 0)
+        })
+        .to_result()?;
+        Ok(vm::Value::from_raw(value))
+    }
+
     /// Resets the input and output lists back to 0-length in preparation for construction of
     /// another call.
     pub fn reset(&mut self) {
@@ -440,10 +1486,11 @@ impl<'a> Call<'a> {
     pub fn input_list(&mut self) -> DynamicList<'_, Undefined> {
         unsafe {
             trace!("iree_runtime_call_inputs");
-            DynamicList::from_raw(
-                self.session.instance,
-                sys::iree_runtime_call_inputs(&self.ctx),
-            )
+            let list = sys::iree_runtime_call_inputs(&self.ctx);
+            // The call keeps its reference, the returned list releases its own.
+            trace!("iree_vm_list_retain");
+            sys::iree_vm_list_retain(list);
+            DynamicList::from_raw(self.session.instance, list)
         }
     }
 
@@ -451,10 +1498,11 @@ impl<'a> Call<'a> {
     pub fn output_list(&mut self) -> DynamicList<'_, Undefined> {
         unsafe {
             trace!("iree_runtime_call_outputs");
-            DynamicList::from_raw(
-                self.session.instance,
-                sys::iree_runtime_call_outputs(&self.ctx),
-            )
+            let list = sys::iree_runtime_call_outputs(&self.ctx);
+            // The call keeps its reference, the returned list releases its own.
+            trace!("iree_vm_list_retain");
+            sys::iree_vm_list_retain(list);
+            DynamicList::from_raw(self.session.instance, list)
         }
     }
 }
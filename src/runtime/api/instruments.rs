@@ -0,0 +1,173 @@
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::{
+    super::{
+        base::StatusErrorKind,
+        error::RuntimeError,
+        hal::BufferView,
+        vm::{CallingConventionType, DynamicList, FunctionLinkage, List, Ref, Undefined},
+    },
+    Session,
+};
+
+// The function exported by modules compiled with `--iree-hal-instrument-dispatches`.
+const QUERY_INSTRUMENTS: &str = "__query_instruments";
+
+// The chunk header of instrument data, `iree_idbts_chunk_header_t`: a magic number, the chunk
+// type, the major and minor version, two reserved fields and the content length. The magic
+// reads `DBTS` in little endian.
+const CHUNK_MAGIC: u32 = 0x5354_4244;
+const CHUNK_HEADER_LENGTH: usize = 24;
+
+/// The kind of an [`InstrumentChunk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstrumentChunkKind {
+    /// Describes the instrumented dispatches, e.g. their names and source locations.
+    DispatchMetadata,
+    /// The ringbuffer the instrumented dispatches wrote their events to.
+    DispatchRingbuffer,
+    /// A chunk of a kind this version does not know.
+    Other(u16),
+}
+
+impl From<u16> for InstrumentChunkKind {
+    fn from(kind: u16) -> Self {
+        match kind {
+            0 => Self::DispatchMetadata,
+            1 => Self::DispatchRingbuffer,
+            kind => Self::Other(kind),
+        }
+    }
+}
+
+/// A chunk of [`InstrumentData`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrumentChunk<'d> {
+    /// The kind of the chunk.
+    pub kind: InstrumentChunkKind,
+    /// The major and minor version of the layout of the content.
+    pub version: (u16, u16),
+    /// The content of the chunk, following the chunk header.
+    pub content: &'d [u8],
+}
+
+/// The instrument data of the modules of a session compiled with
+/// `--iree-hal-instrument-dispatches`, read back with [`Session::query_instruments`].
+///
+/// The data is a sequence of chunks in the format of `iree-run-module --instrument_file`, and can
+/// be written to a file for the IREE instrument tools, or split into chunks with
+/// [`InstrumentData::chunks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentData {
+    bytes: Vec<u8>,
+}
+
+impl InstrumentData {
+    /// Wraps instrument data read from elsewhere, e.g. an instrument file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the raw instrument data.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the raw instrument data, without copying it.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Splits the data into chunks. Returns `RuntimeError::InvalidArchive` if a chunk header is
+    /// invalid or a chunk is truncated.
+    pub fn chunks(&self) -> Result<Vec<InstrumentChunk<'_>>, RuntimeError> {
+        parse_chunks(&self.bytes)
+    }
+}
+
+fn parse_chunks(mut data: &[u8]) -> Result<Vec<InstrumentChunk<'_>>, RuntimeError> {
+    let mut chunks = Vec::new();
+    while !data.is_empty() {
+        if data.len() < CHUNK_HEADER_LENGTH {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "the instrument data is truncated"
+            )));
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let magic = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        if magic != CHUNK_MAGIC {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "invalid instrument chunk magic {:#x}",
+                magic
+            )));
+        }
+        let kind = InstrumentChunkKind::from(u16_at(4));
+        let version = (u16_at(6), u16_at(8));
+        let content_length = u64::from_le_bytes(data[16..24].try_into().unwrap()) as usize;
+        let Some(end) = CHUNK_HEADER_LENGTH
+            .checked_add(content_length)
+            .filter(|&end| end <= data.len())
+        else {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "the instrument chunk is truncated"
+            )));
+        };
+        chunks.push(InstrumentChunk {
+            kind,
+            version,
+            content: &data[CHUNK_HEADER_LENGTH..end],
+        });
+        data = &data[end..];
+    }
+    Ok(chunks)
+}
+
+impl Session<'_> {
+    /// Reads back the instrument data of the modules of the session compiled with
+    /// `--iree-hal-instrument-dispatches`, after running them. Returns `None` if no module of
+    /// the session is instrumented.
+    ///
+    /// Instrumented modules export a query function that returns the instrument buffers,
+    /// which are copied to the host and concatenated in module order.
+    pub fn query_instruments(&self) -> Result<Option<InstrumentData>, RuntimeError> {
+        let mut bytes = None::<Vec<u8>>;
+        for module in self.modules() {
+            let function = match module
+                .lookup_function_by_name(FunctionLinkage::Export, QUERY_INSTRUMENTS)
+            {
+                Ok(function) => function.bind(self)?,
+                Err(RuntimeError::StatusError(err)) if err.code() == StatusErrorKind::NotFound => {
+                    continue
+                }
+                Err(err) => return Err(err),
+            };
+            let result_types = function.result_types()?;
+            if !function.argument_types()?.is_empty()
+                || result_types
+                    .iter()
+                    .any(|&result| result != CallingConventionType::Ref)
+            {
+                return Err(RuntimeError::SignatureMismatch(error_message!(
+                    "{}.{} must take no arguments and return buffer views",
+                    module.name(),
+                    QUERY_INSTRUMENTS
+                )));
+            }
+            let inputs = DynamicList::<Undefined>::new(0, self.instance)?;
+            let outputs =
+                DynamicList::<Ref<BufferView<u8>>>::new(result_types.len(), self.instance)?;
+            function.invoke(&inputs, &outputs)?;
+            let bytes = bytes.get_or_insert_with(Vec::new);
+            for idx in 0..result_types.len() {
+                bytes.extend(
+                    outputs
+                        .get_ref(idx)?
+                        .try_to_untyped_buffer_view(self)?
+                        .to_vec()?,
+                );
+            }
+        }
+        Ok(bytes.map(|bytes| InstrumentData { bytes }))
+    }
+}
@@ -0,0 +1,169 @@
+use std::{mem::ManuallyDrop, path::Path};
+
+use super::{
+    super::{
+        error::RuntimeError,
+        hal::{BufferView, DriverRegistry},
+        tensor::Tensor,
+        vm::{self, CallingConventionType, DynamicList, List, Ref, ToRef},
+    },
+    Instance, InstanceOptions, Session, SessionOptions,
+};
+
+/// The device a [`Model`] runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceSpec<'s> {
+    /// The default device of the driver with the given name, e.g. `local-task` or `cuda`.
+    Driver(&'s str),
+    /// The device with the given URI, e.g. `vulkan://0`, see [`Instance::create_device`].
+    Uri(&'s str),
+}
+
+impl Default for DeviceSpec<'_> {
+    fn default() -> Self {
+        Self::Driver("local-task")
+    }
+}
+
+/// A compiled module loaded into its own instance and session, for applications that run a single
+/// model and do not need to manage the runtime objects themselves.
+///
+/// The model owns the instance, the device and the session, and functions are called by name
+/// with tuples of Rust values, e.g. `model.call("serving_default", (&image,))`.
+pub struct Model {
+    // Borrows the boxed instance, so it must be dropped first.
+    session: ManuallyDrop<Session<'static>>,
+    instance: Box<Instance>,
+    module_name: String,
+}
+
+impl Model {
+    /// Loads the module in the given vmfb file, see [`Model::from_bytes`].
+    pub fn load(path: impl AsRef<Path>, device: DeviceSpec) -> Result<Self, RuntimeError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|err| {
+            RuntimeError::InvalidModule(error_message!(
+                "failed to read {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        Self::from_bytes(&data, device)
+    }
+
+    /// Creates an instance with all available drivers, a session on the given device, and
+    /// appends the given module to it. The data is copied and verified, so it can be untrusted.
+    pub fn from_bytes(flatbuffer_data: &[u8], device: DeviceSpec) -> Result<Self, RuntimeError> {
+        let instance = Box::new(Instance::new(
+            &InstanceOptions::new(&mut DriverRegistry::new()).use_all_available_drivers(),
+        )?);
+        let hal_device = match device {
+            DeviceSpec::Driver(name) => instance.try_create_default_device(name)?,
+            DeviceSpec::Uri(uri) => instance.create_device(uri)?,
+        };
+        let session =
+            Session::create_with_device(&instance, &SessionOptions::default(), &hal_device)?;
+        let module = instance.load_module_cached(flatbuffer_data)?;
+        session.append_module(&module)?;
+        let module_name = module.name().to_string();
+        drop(module);
+        drop(hal_device);
+        // The session retains the device and the module, and the instance is boxed, so the session
+        // stays valid for as long as the model owns the instance.
+        let session = unsafe { core::mem::transmute::<Session<'_>, Session<'static>>(session) };
+        Ok(Self {
+            session: ManuallyDrop::new(session),
+            instance,
+            module_name,
+        })
+    }
+
+    /// Calls the function with the given name, either relative to the module of the model, e.g.
+    /// `serving_default`, or fully qualified. The arguments and results are checked against the
+    /// signature of the function like with [`vm::Function::call`].
+    pub fn call<'a, A, R>(&'a self, function: &str, arguments: A) -> Result<R, RuntimeError>
+    where
+        A: vm::Arguments,
+        R: vm::ReturnValues<'a>,
+    {
+        self.function(function)?.call(arguments)
+    }
+
+    /// Calls the function with the given name like [`Model::call`], with inputs and results held
+    /// in host tensors, e.g. when the element types are only known at runtime. The function must
+    /// take one buffer view per input and only return buffer views.
+    pub fn call_tensors(
+        &self,
+        function: &str,
+        inputs: &[Tensor],
+    ) -> Result<Vec<Tensor>, RuntimeError> {
+        let session = self.session();
+        let func = self.function(function)?;
+        let argument_types = func.argument_types()?;
+        let result_types = func.result_types()?;
+        if argument_types.len() != inputs.len()
+            || argument_types
+                .iter()
+                .chain(&result_types)
+                .any(|&ty| ty != CallingConventionType::Ref)
+        {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "{} must take {} buffer views and only return buffer views",
+                function,
+                inputs.len()
+            )));
+        }
+        let buffer_views = inputs
+            .iter()
+            .map(|tensor| tensor.to_untyped_buffer_view(session))
+            .collect::<Result<Vec<_>, RuntimeError>>()?;
+        let input_list = DynamicList::<Ref<BufferView<u8>>>::new(inputs.len(), self.instance())?;
+        for buffer_view in &buffer_views {
+            input_list.push_ref(&buffer_view.to_ref(self.instance())?)?;
+        }
+        let output_list =
+            DynamicList::<Ref<BufferView<u8>>>::new(result_types.len(), self.instance())?;
+        func.invoke(&input_list, &output_list)?;
+        (0..result_types.len())
+            .map(|idx| {
+                Tensor::from_untyped_buffer_view(
+                    &output_list
+                        .get_ref(idx)?
+                        .try_to_untyped_buffer_view(session)?,
+                )
+            })
+            .collect()
+    }
+
+    /// Looks up the function with the given name, relative to the module of the model or fully
+    /// qualified.
+    pub fn function(&self, name: &str) -> Result<vm::Function<'_>, RuntimeError> {
+        let session = self.session();
+        if name.contains('.') {
+            session.lookup_function(name)
+        } else {
+            session.lookup_function(&format!("{}.{}", self.module_name, name))
+        }
+    }
+
+    /// Returns the name of the module of the model.
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    /// Returns the session of the model, e.g. to create buffer views for its inputs.
+    pub fn session(&self) -> &Session<'_> {
+        &self.session
+    }
+
+    /// Returns the instance of the model.
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+}
+
+impl Drop for Model {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.session) };
+    }
+}
@@ -0,0 +1,218 @@
+extern crate alloc;
+use alloc::vec::Vec;
+use eerie_sys::runtime as sys;
+
+use super::{
+    super::{
+        base::{self, StatusErrorKind},
+        error::RuntimeError,
+        hal::{Device, Fence, Semaphore, Timeout, WaitMode},
+    },
+    CallFlags, Session,
+};
+
+/// A token that aborts the invocations it is passed to through their [`InvocationPolicy`], e.g.
+/// to cancel a long-running model from another thread.
+///
+/// The token is backed by a HAL semaphore that is failed with `Cancelled` on
+/// [`CancellationToken::cancel`], so it can also be joined into the wait fences of device work.
+/// Clones share the same token.
+#[derive(Clone)]
+pub struct CancellationToken {
+    semaphore: Semaphore,
+}
+
+impl CancellationToken {
+    /// Creates a token on the given device that has not been cancelled.
+    pub fn new(device: &Device) -> Result<Self, RuntimeError> {
+        Ok(Self {
+            semaphore: Semaphore::new(device, 0)?,
+        })
+    }
+
+    /// Cancels the token. Invocations that have not started yet fail with `Cancelled`, and a
+    /// thread waiting on an invocation started with
+    /// [`Call::begin_with_policy`](super::Call::begin_with_policy), or on a synchronous invocation
+    /// of a function compiled with `--iree-execution-model=async-external`, is woken up.
+    pub fn cancel(&self) {
+        self.semaphore.fail(StatusErrorKind::Cancelled);
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.semaphore.query().is_err()
+    }
+
+    /// Returns the semaphore backing the token. It never reaches a value above 0, and fails once
+    /// the token is cancelled.
+    pub fn semaphore(&self) -> &Semaphore {
+        &self.semaphore
+    }
+
+    // Blocks until the fence is signaled, the timeout elapses or the token is cancelled. Once the
+    // token is cancelled, the fence is failed so that work waiting on it is aborted as well, and
+    // a `Cancelled` status error is returned.
+    pub(crate) fn wait_for(
+        &self,
+        device: &Device,
+        fence: &Fence,
+        timeout: Timeout,
+    ) -> Result<(), RuntimeError> {
+        // The signal fence of an invocation has a single timepoint, so this waits for either the
+        // invocation or the token, which fails once it is cancelled.
+        let timepoints = fence.timepoints();
+        let mut timepoints = timepoints
+            .iter()
+            .map(|(semaphore, value)| (semaphore, *value))
+            .collect::<Vec<_>>();
+        timepoints.push((&self.semaphore, 1));
+        let result = device.wait_semaphores(WaitMode::Any, &timepoints, timeout);
+        if self.is_cancelled() {
+            fence.fail(StatusErrorKind::Cancelled);
+            return self.check();
+        }
+        result
+    }
+
+    // Returns a `Cancelled` status error if the token has been cancelled.
+    pub(crate) fn check(&self) -> Result<(), RuntimeError> {
+        if self.is_cancelled() {
+            base::Status::from_raw(base::allocate_status(
+                StatusErrorKind::Cancelled,
+                "the invocation was cancelled",
+            ))
+            .to_result()?;
+        }
+        Ok(())
+    }
+}
+
+/// Controls how a function is invoked: the invocation flags, an optional [`CancellationToken`]
+/// that aborts the invocation, and the device and queues asynchronous invocations run on.
+///
+/// The VM cannot preempt a function once it runs on the host, so a cancelled token stops
+/// invocations before they start and unblocks the threads waiting on the fences of invocations,
+/// including synchronous invocations of functions compiled with the async-external model; device
+/// work that was already submitted runs to completion.
+#[derive(Clone, Default)]
+pub struct InvocationPolicy<'t> {
+    flags: CallFlags,
+    cancellation: Option<&'t CancellationToken>,
+    device: usize,
+    queue_affinity: Option<u64>,
+}
+
+impl<'t> InvocationPolicy<'t> {
+    /// Creates a policy with no flags and no cancellation token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the invocation flags, see [`CallFlags`].
+    pub fn flags(mut self, flags: CallFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Aborts the invocation once the given token is cancelled.
+    pub fn cancellation(mut self, token: &'t CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Selects, by ordinal, the device of a session created with
+    /// [`Session::create_with_devices`] that asynchronous invocations signal their results on.
+    /// Defaults to the first device.
+    pub fn device(mut self, ordinal: usize) -> Self {
+        self.device = ordinal;
+        self
+    }
+
+    /// Orders asynchronous invocations on the queues of their device selected by
+    /// `queue_affinity`, a bitmask of queue ordinals or
+    /// [`QUEUE_AFFINITY_ANY`](super::super::hal::QUEUE_AFFINITY_ANY): the function
+    /// starts once a barrier enqueued on these queues behind the wait fence is reached. By
+    /// default, the wait fence is passed to the function as is.
+    pub fn queue_affinity(mut self, queue_affinity: u64) -> Self {
+        self.queue_affinity = Some(queue_affinity);
+        self
+    }
+
+    /// Returns the invocation flags of the policy.
+    pub fn invocation_flags(&self) -> CallFlags {
+        self.flags
+    }
+
+    /// Returns the cancellation token of the policy, if any.
+    pub fn cancellation_token(&self) -> Option<&'t CancellationToken> {
+        self.cancellation
+    }
+
+    /// Returns the ordinal of the device asynchronous invocations signal their results on.
+    pub fn device_ordinal(&self) -> usize {
+        self.device
+    }
+
+    /// Returns the queues asynchronous invocations are ordered on, if any are selected.
+    pub fn selected_queue_affinity(&self) -> Option<u64> {
+        self.queue_affinity
+    }
+
+    // Creates the fences of an asynchronous invocation on the device selected by the policy: the
+    // fence the function waits on if it is not `wait_fence` itself, and the fence it signals.
+    // Returns an `OutOfRange` status error if the session has no such device.
+    pub(crate) fn async_fences(
+        &self,
+        session: &Session,
+        wait_fence: Option<&Fence>,
+    ) -> Result<(Option<Fence>, Fence), RuntimeError> {
+        let device = session.device_at(self.device).ok_or_else(|| {
+            RuntimeError::from(
+                base::Status::from_raw(base::allocate_status(
+                    StatusErrorKind::OutOfRange,
+                    "the session has no device with the ordinal of the policy",
+                ))
+                .to_result()
+                .unwrap_err(),
+            )
+        })?;
+        let queue_wait_fence = match self.queue_affinity {
+            Some(queue_affinity) => {
+                let fence = Fence::at(&Semaphore::new(&device, 0)?, 1)?;
+                device.queue_barrier(queue_affinity, wait_fence, &fence)?;
+                Some(fence)
+            }
+            None => None,
+        };
+        let signal_fence = Fence::at(&Semaphore::new(&device, 0)?, 1)?;
+        Ok((queue_wait_fence, signal_fence))
+    }
+
+    // Returns a `Cancelled` status error if the token of the policy has been cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<(), RuntimeError> {
+        match self.cancellation {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+
+    // Maps the error of a failed invocation to `Cancelled` if the token was cancelled while it
+    // ran, e.g. when it waited on a fence joined with the token.
+    pub(crate) fn map_result(&self, result: Result<(), RuntimeError>) -> Result<(), RuntimeError> {
+        match result {
+            Err(_)
+                if self
+                    .cancellation
+                    .is_some_and(CancellationToken::is_cancelled) =>
+            {
+                self.check_cancelled()
+            }
+            result => result,
+        }
+    }
+
+    // The runtime policy has no fields yet, so every policy maps to the default one.
+    pub(crate) fn to_raw(&self) -> sys::iree_vm_invocation_policy_t {
+        sys::iree_vm_invocation_policy_t::default()
+    }
+}
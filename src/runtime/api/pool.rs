@@ -0,0 +1,81 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+use super::{
+    super::{error::RuntimeError, hal::Device},
+    Instance, Session, SessionOptions,
+};
+
+/// A fixed number of sessions on the same instance and device, shared by several threads.
+///
+/// Sessions are thread-compatible but not thread-safe, so a single session can only run one call
+/// at a time. The pool hands out its sessions one thread at a time, in round-robin order, so
+/// calls from different threads run concurrently as long as there is an idle session. The pool
+/// is `Sync` and is usually shared with [`std::thread::scope`] or behind an `Arc`.
+pub struct SessionPool<'a> {
+    sessions: Vec<Mutex<Session<'a>>>,
+    next: AtomicUsize,
+}
+
+impl<'a> SessionPool<'a> {
+    /// Creates `size` sessions with the given options and device, and calls `setup` on each of
+    /// them, e.g. to append the modules the calls need.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(
+        instance: &'a Instance,
+        options: &SessionOptions,
+        device: &'a Device,
+        size: usize,
+        mut setup: impl FnMut(&Session<'a>) -> Result<(), RuntimeError>,
+    ) -> Result<Self, RuntimeError> {
+        assert!(size > 0, "a session pool needs at least one session");
+        let sessions = (0..size)
+            .map(|_| {
+                let session = Session::create_with_device(instance, options, device)?;
+                setup(&session)?;
+                Ok(Mutex::new(session))
+            })
+            .collect::<Result<_, RuntimeError>>()?;
+        Ok(Self {
+            sessions,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the number of sessions in the pool.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Returns true if the pool holds no sessions, which never happens for a pool created with
+    /// [`SessionPool::new`].
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Runs `f` with an idle session of the pool, waiting for one to become idle if all of them
+    /// are busy. Anything that borrows the session, e.g. buffer views, cannot escape `f`; use
+    /// [`BufferView::to_owned_view`](super::super::hal::BufferView::to_owned_view) to keep results.
+    pub fn with_session<R>(&self, f: impl FnOnce(&Session<'a>) -> R) -> R {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+        let session = (0..self.sessions.len())
+            .find_map(|offset| {
+                match self.sessions[(start + offset) % self.sessions.len()].try_lock() {
+                    Ok(session) => Some(session),
+                    Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+                    Err(TryLockError::WouldBlock) => None,
+                }
+            })
+            .unwrap_or_else(|| Self::lock(&self.sessions[start]));
+        f(&session)
+    }
+
+    // A panic in a call does not leave the session in a state the pool relies on, so poisoned
+    // sessions are still handed out.
+    fn lock(session: &Mutex<Session<'a>>) -> MutexGuard<'_, Session<'a>> {
+        session.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
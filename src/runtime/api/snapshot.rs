@@ -0,0 +1,236 @@
+extern crate alloc;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use eerie_sys::runtime as sys;
+
+use super::{
+    super::{
+        error::RuntimeError,
+        hal::{BufferView, ElementType},
+        tensor::Tensor,
+        vm::{self, CallingConventionType, DynamicList, List, Ref, ToRef, Undefined},
+    },
+    Session,
+};
+
+// Identifies serialized snapshots, followed by the version of the format.
+const MAGIC: &[u8; 8] = b"EERIESTA";
+const VERSION: u32 = 1;
+
+/// A pair of functions that read and write a piece of the mutable state of a module, e.g. the
+/// KV cache of a language model held in globals.
+///
+/// The runtime does not expose the layout of the state of compiled modules, so stateful models
+/// export accessors for it: the getter takes no arguments and returns the state as buffer views,
+/// and the setter takes the same buffer views and returns nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateAccessor<'n> {
+    /// The fully qualified name of the getter, e.g. `module.get_kv_cache`.
+    pub getter: &'n str,
+    /// The fully qualified name of the setter, e.g. `module.set_kv_cache`.
+    pub setter: &'n str,
+}
+
+/// A copy of the mutable state of the modules of a session, taken with
+/// [`Session::snapshot_state`] and written back with [`Session::restore_state`].
+///
+/// Snapshots live in host memory, and can be serialized with [`StateSnapshot::to_bytes`], e.g.
+/// to checkpoint a stateful model between process restarts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSnapshot {
+    entries: Vec<(String, Vec<Tensor>)>,
+}
+
+impl StateSnapshot {
+    /// Returns the state held for each setter, in the order of the accessors the snapshot was
+    /// taken with.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[Tensor])> {
+        self.entries
+            .iter()
+            .map(|(setter, tensors)| (setter.as_str(), tensors.as_slice()))
+    }
+
+    /// Serializes the snapshot. The format is only meant to be read back by
+    /// [`StateSnapshot::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (setter, tensors) in &self.entries {
+            out.extend_from_slice(&(setter.len() as u32).to_le_bytes());
+            out.extend_from_slice(setter.as_bytes());
+            out.extend_from_slice(&(tensors.len() as u32).to_le_bytes());
+            for tensor in tensors {
+                let element_type = sys::iree_hal_element_type_t::from(tensor.element_type());
+                out.extend_from_slice(&(element_type as u32).to_le_bytes());
+                out.extend_from_slice(&(tensor.shape().len() as u32).to_le_bytes());
+                for &dim in tensor.shape() {
+                    out.extend_from_slice(&(dim as u64).to_le_bytes());
+                }
+                out.extend_from_slice(&(tensor.as_bytes().len() as u64).to_le_bytes());
+                out.extend_from_slice(tensor.as_bytes());
+            }
+        }
+        out
+    }
+
+    /// Reads a snapshot serialized with [`StateSnapshot::to_bytes`]. Returns
+    /// `RuntimeError::InvalidArchive` if the data is not a snapshot of this version.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RuntimeError> {
+        let mut reader = Reader { data };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "the data is not a state snapshot"
+            )));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "unsupported state snapshot version {}",
+                version
+            )));
+        }
+        let entries = (0..reader.u32()?)
+            .map(|_| {
+                let length = reader.u32()? as usize;
+                let setter = core::str::from_utf8(reader.take(length)?)
+                    .map_err(|err| {
+                        RuntimeError::InvalidArchive(error_message!("invalid setter name: {}", err))
+                    })?
+                    .to_string();
+                let tensors = (0..reader.u32()?)
+                    .map(|_| {
+                        let element_type =
+                            ElementType::from(reader.u32()? as sys::iree_hal_element_type_t);
+                        let shape = (0..reader.u32()?)
+                            .map(|_| Ok(reader.u64()? as usize))
+                            .collect::<Result<Vec<_>, RuntimeError>>()?;
+                        let length = reader.u64()? as usize;
+                        if dense_byte_length(&shape, element_type) != Some(length) {
+                            return Err(RuntimeError::InvalidArchive(error_message!(
+                                "a {:?} tensor of shape {:?} cannot hold {} bytes",
+                                element_type,
+                                shape,
+                                length
+                            )));
+                        }
+                        Ok(Tensor::from_bytes(
+                            &shape,
+                            element_type,
+                            reader.take(length)?,
+                        ))
+                    })
+                    .collect::<Result<_, RuntimeError>>()?;
+                Ok((setter, tensors))
+            })
+            .collect::<Result<_, RuntimeError>>()?;
+        if !reader.data.is_empty() {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "trailing data after the state snapshot"
+            )));
+        }
+        Ok(Self { entries })
+    }
+}
+
+// Returns the byte length of a dense row major tensor, or None if it overflows. Sub-byte elements
+// are packed, as in the buffers of the runtime.
+fn dense_byte_length(shape: &[usize], element_type: ElementType) -> Option<usize> {
+    // The low byte of an element type holds its bit count.
+    let bits = (sys::iree_hal_element_type_t::from(element_type) & 0xff) as usize;
+    let element_count = shape
+        .iter()
+        .try_fold(1usize, |count, &dim| count.checked_mul(dim))?;
+    Some(element_count.checked_mul(bits)?.div_ceil(8))
+}
+
+struct Reader<'d> {
+    data: &'d [u8],
+}
+
+impl<'d> Reader<'d> {
+    fn take(&mut self, length: usize) -> Result<&'d [u8], RuntimeError> {
+        if self.data.len() < length {
+            return Err(RuntimeError::InvalidArchive(error_message!(
+                "the state snapshot is truncated"
+            )));
+        }
+        let (head, tail) = self.data.split_at(length);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, RuntimeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, RuntimeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+impl Session<'_> {
+    /// Copies the state read by the getters of the given accessors into host memory. Returns
+    /// `RuntimeError::SignatureMismatch` if a getter takes arguments or returns anything but
+    /// buffer views.
+    pub fn snapshot_state(
+        &self,
+        accessors: &[StateAccessor],
+    ) -> Result<StateSnapshot, RuntimeError> {
+        let entries = accessors
+            .iter()
+            .map(|accessor| {
+                let getter = self.lookup_function(accessor.getter)?;
+                let result_types = getter.result_types()?;
+                if !getter.argument_types()?.is_empty()
+                    || result_types
+                        .iter()
+                        .any(|&result| result != CallingConventionType::Ref)
+                {
+                    return Err(RuntimeError::SignatureMismatch(error_message!(
+                        "{} must take no arguments and return buffer views",
+                        accessor.getter
+                    )));
+                }
+                let inputs = DynamicList::<Undefined>::new(0, self.instance)?;
+                // The element type of the state is only known at runtime, so the buffer views
+                // are read as bytes.
+                let outputs =
+                    DynamicList::<Ref<BufferView<u8>>>::new(result_types.len(), self.instance)?;
+                getter.invoke(&inputs, &outputs)?;
+                let tensors = (0..result_types.len())
+                    .map(|idx| {
+                        Tensor::from_untyped_buffer_view(
+                            &outputs.get_ref(idx)?.try_to_untyped_buffer_view(self)?,
+                        )
+                    })
+                    .collect::<Result<_, RuntimeError>>()?;
+                Ok((accessor.setter.to_string(), tensors))
+            })
+            .collect::<Result<_, RuntimeError>>()?;
+        Ok(StateSnapshot { entries })
+    }
+
+    /// Writes the state of the given snapshot back with the setters it was taken for, e.g. after
+    /// restarting the process and loading the same modules into a new session.
+    pub fn restore_state(&self, snapshot: &StateSnapshot) -> Result<(), RuntimeError> {
+        for (setter, tensors) in &snapshot.entries {
+            let function: vm::Function = self.lookup_function(setter)?;
+            let inputs = DynamicList::<Ref<BufferView<u8>>>::new(tensors.len(), self.instance)?;
+            let buffer_views = tensors
+                .iter()
+                .map(|tensor| tensor.to_untyped_buffer_view(self))
+                .collect::<Result<Vec<_>, RuntimeError>>()?;
+            for buffer_view in &buffer_views {
+                inputs.push_ref(&buffer_view.to_ref(self.instance)?)?;
+            }
+            let outputs = DynamicList::<Undefined>::new(0, self.instance)?;
+            function.invoke(&inputs, &outputs)?;
+        }
+        Ok(())
+    }
+}
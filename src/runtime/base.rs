@@ -1,5 +1,12 @@
-use core::{alloc::Layout, ffi::c_void, fmt::Display, marker::PhantomData};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::c_void,
+    fmt::Display,
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 extern crate alloc;
+use alloc::{string::String, vec::Vec};
 use eerie_sys::runtime as sys;
 use log::trace;
 
@@ -83,8 +90,20 @@ impl<'a> From<&'a str> for StringView<'a> {
     }
 }
 
+impl StringView<'_> {
+    pub(crate) unsafe fn from_raw(ctx: sys::iree_string_view_t) -> Self {
+        Self {
+            ctx,
+            marker: core::marker::PhantomData,
+        }
+    }
+}
+
 impl<'a> From<StringView<'a>> for &'a str {
     fn from(string_view: StringView<'a>) -> Self {
+        if string_view.ctx.size == 0 {
+            return "";
+        }
         unsafe {
             core::str::from_utf8_unchecked_mut(core::slice::from_raw_parts_mut(
                 string_view.ctx.data as *mut u8,
@@ -100,9 +119,18 @@ pub(crate) struct Allocator {
 
 impl Allocator {
     pub fn get_global() -> Self {
+        Self::from_host_allocator(&RUST_GLOBAL)
+    }
+
+    // Allocates through the given allocator, which is passed to the control function as `self`.
+    pub fn from_host_allocator<A: HostAllocator>(allocator: &'static A) -> Self {
+        assert!(
+            A::ALIGNMENT.is_power_of_two() && A::ALIGNMENT >= 16,
+            "host allocations must be aligned to a power of two of at least 16 bytes"
+        );
         let allocator = sys::iree_allocator_t {
-            self_: core::ptr::null_mut(),
-            ctl: Some(rust_allocator_ctl),
+            self_: allocator as *const A as *mut c_void,
+            ctl: Some(host_allocator_ctl::<A>),
         };
         Self { ctx: allocator }
     }
@@ -116,7 +144,215 @@ impl Allocator {
     }
 }
 
-const ALIGNMENT: usize = 16;
+/// An allocator for the host memory of the runtime, e.g. an arena or a pool on an RTOS. It is set
+/// per instance with [`InstanceOptions::host_allocator`](super::api::InstanceOptions::host_allocator),
+/// and used for the instance, its sessions and modules, and host-side bookkeeping of the HAL.
+///
+/// Every [`GlobalAlloc`] is a host allocator, so the allocators of `no_std` crates can be used as
+/// they are. Only `allocate` and `deallocate` need to be implemented; a bump arena can leave
+/// `deallocate` empty. Wrap an allocator in an [`AlignedAllocator`] to raise the alignment of
+/// the allocations, and in a [`TrackingAllocator`] to measure how much memory the runtime uses.
+///
+/// # Safety
+///
+/// Like with [`GlobalAlloc`], memory returned by the allocator must be valid for the requested
+/// layout and must not be used by anything else until it is deallocated.
+pub unsafe trait HostAllocator: Sync {
+    /// The alignment of every allocation made by the runtime, a power of two of at least 16.
+    /// Each allocation is preceded by a header of this size, which records its size.
+    const ALIGNMENT: usize = 16;
+
+    /// Allocates memory for the given layout, or returns null if it cannot be allocated.
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// Allocates zeroed memory for the given layout, or returns null if it cannot be allocated.
+    fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.allocate(layout);
+        if !ptr.is_null() {
+            unsafe { core::ptr::write_bytes(ptr, 0, layout.size()) };
+        }
+        ptr
+    }
+
+    /// Grows or shrinks an allocation to `new_size` bytes, keeping its contents up to the smaller
+    /// size, or returns null and leaves the allocation untouched if it cannot be resized.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for the given layout.
+    unsafe fn reallocate(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.allocate(Layout::from_size_align_unchecked(new_size, layout.align()));
+        if !new_ptr.is_null() {
+            core::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.deallocate(ptr, layout);
+        }
+        new_ptr
+    }
+
+    /// Frees an allocation.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for the given layout.
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+unsafe impl<A: GlobalAlloc + Sync> HostAllocator for A {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        // Host allocations always have a size header, so the layout is never empty.
+        unsafe { self.alloc(layout) }
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.alloc_zeroed(layout) }
+    }
+
+    unsafe fn reallocate(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.dealloc(ptr, layout)
+    }
+}
+
+/// A host allocator whose allocations are aligned to `ALIGNMENT` bytes, e.g. 64 for devices that
+/// require cache line aligned staging memory. `ALIGNMENT` must be a power of two of at least 16.
+pub struct AlignedAllocator<A, const ALIGNMENT: usize>(pub A);
+
+unsafe impl<A: HostAllocator, const ALIGNMENT: usize> HostAllocator
+    for AlignedAllocator<A, ALIGNMENT>
+{
+    const ALIGNMENT: usize = ALIGNMENT;
+
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        self.0.allocate(layout)
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.allocate_zeroed(layout)
+    }
+
+    unsafe fn reallocate(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.0.reallocate(ptr, layout, new_size)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.0.deallocate(ptr, layout)
+    }
+}
+
+/// Statistics of the allocations made through a [`TrackingAllocator`]. Sizes include the header of
+/// each allocation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStatistics {
+    /// Bytes currently allocated.
+    pub current_bytes: usize,
+    /// Largest number of bytes allocated at the same time.
+    pub peak_bytes: usize,
+    /// Bytes allocated in total, including freed allocations.
+    pub total_bytes: usize,
+    /// Number of allocations, including freed allocations.
+    pub allocation_count: usize,
+}
+
+/// A host allocator that records how much memory is allocated through it, see
+/// [`TrackingAllocator::statistics`].
+pub struct TrackingAllocator<A> {
+    inner: A,
+    current_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    total_bytes: AtomicUsize,
+    allocation_count: AtomicUsize,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            current_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+            total_bytes: AtomicUsize::new(0),
+            allocation_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the statistics of the allocations so far.
+    pub fn statistics(&self) -> AllocationStatistics {
+        AllocationStatistics {
+            current_bytes: self.current_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            allocation_count: self.allocation_count.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_allocation(&self, size: usize) {
+        let current_bytes = self.current_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak_bytes.fetch_max(current_bytes, Ordering::Relaxed);
+        self.total_bytes.fetch_add(size, Ordering::Relaxed);
+        self.allocation_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: HostAllocator> HostAllocator for TrackingAllocator<A> {
+    const ALIGNMENT: usize = A::ALIGNMENT;
+
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.allocate(layout);
+        if !ptr.is_null() {
+            self.record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.allocate_zeroed(layout);
+        if !ptr.is_null() {
+            self.record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn reallocate(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.reallocate(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.current_bytes
+                .fetch_sub(layout.size(), Ordering::Relaxed);
+            self.record_allocation(new_size);
+        }
+        new_ptr
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.deallocate(ptr, layout);
+        self.current_bytes
+            .fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+// The Rust global allocator, i.e. the `#[global_allocator]` of the binary.
+struct RustGlobal;
+
+static RUST_GLOBAL: RustGlobal = RustGlobal;
+
+unsafe impl GlobalAlloc for RustGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc::alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc::alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        alloc::alloc::realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::alloc::dealloc(ptr, layout)
+    }
+}
 
 unsafe extern "C" fn null_allocator_ctl(
     _self_: *mut c_void,
@@ -138,45 +374,36 @@ unsafe extern "C" fn null_allocator_ctl(
     core::ptr::null_mut() as sys::iree_status_t
 }
 
-unsafe extern "C" fn rust_allocator_ctl(
-    _self_: *mut c_void,
+unsafe extern "C" fn host_allocator_ctl<A: HostAllocator>(
+    self_: *mut c_void,
     command: sys::iree_allocator_command_e,
     params: *const c_void,
     inout_ptr: *mut *mut c_void,
 ) -> sys::iree_status_t {
-    // use Rust Global Allocator
+    let allocator = &*(self_ as *const A);
+    // The size of each allocation is stored in front of it, so it can be freed with its layout.
     match command {
-        sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_MALLOC => {
+        sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_MALLOC
+        | sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_CALLOC => {
             let size = (*(params as *const sys::iree_allocator_alloc_params_t)).byte_length;
-            if size > core::isize::MAX as usize {
-                return Status::from_code(StatusErrorKind::OutOfRange).ctx;
+            if size > isize::MAX as usize - A::ALIGNMENT {
+                return status_from_kind(StatusErrorKind::OutOfRange);
             }
-            let ptr = alloc::alloc::alloc(Layout::from_size_align_unchecked(
-                size + ALIGNMENT,
-                ALIGNMENT,
-            ));
-            *(ptr as *mut usize) = size;
-            *inout_ptr = ptr.wrapping_add(ALIGNMENT) as *mut c_void;
-            trace!(
-                "rust_allocator_ctl: IREE_ALLOCATOR_COMMAND_MALLOC: size: {} -> {:?}",
-                size,
-                *inout_ptr
-            );
-            core::ptr::null_mut() as sys::iree_status_t
-        }
-        sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_CALLOC => {
-            let size = (*(params as *const sys::iree_allocator_alloc_params_t)).byte_length;
-            if size > core::isize::MAX as usize {
-                return Status::from_code(StatusErrorKind::OutOfRange).ctx;
+            let layout = Layout::from_size_align_unchecked(size + A::ALIGNMENT, A::ALIGNMENT);
+            let ptr = match command {
+                sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_CALLOC => {
+                    allocator.allocate_zeroed(layout)
+                }
+                _ => allocator.allocate(layout),
+            };
+            if ptr.is_null() {
+                return status_from_kind(StatusErrorKind::ResourceExhausted);
             }
-            let ptr = alloc::alloc::alloc_zeroed(Layout::from_size_align_unchecked(
-                size + ALIGNMENT,
-                ALIGNMENT,
-            ));
             *(ptr as *mut usize) = size;
-            *inout_ptr = ptr.wrapping_add(ALIGNMENT) as *mut c_void;
+            *inout_ptr = ptr.wrapping_add(A::ALIGNMENT) as *mut c_void;
             trace!(
-                "rust_allocator_ctl: IREE_ALLOCATOR_COMMAND_CALLOC: size: {} -> {:?}",
+                "host_allocator_ctl: command: {:?}, size: {} -> {:?}",
+                command,
                 size,
                 *inout_ptr
             );
@@ -185,50 +412,52 @@ unsafe extern "C" fn rust_allocator_ctl(
         sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_REALLOC => {
             if (*inout_ptr).is_null() {
                 // realloc of null is malloc
-                return rust_allocator_ctl(
-                    _self_,
+                return host_allocator_ctl::<A>(
+                    self_,
                     sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_MALLOC,
                     params,
                     inout_ptr,
                 );
             }
-            let ptr = (*inout_ptr).wrapping_sub(ALIGNMENT);
-            let old_size = unsafe { *(ptr as *mut usize) };
+            let ptr = (*inout_ptr).wrapping_sub(A::ALIGNMENT);
+            let old_size = *(ptr as *mut usize);
             let new_size = (*(params as *const sys::iree_allocator_alloc_params_t)).byte_length;
             trace!(
-                "rust_allocator_ctl: IREE_ALLOCATOR_COMMAND_REALLOC: {} -> {}",
+                "host_allocator_ctl: IREE_ALLOCATOR_COMMAND_REALLOC: {} -> {}",
                 old_size,
                 new_size
             );
-            if new_size > core::isize::MAX as usize {
-                return Status::from_code(StatusErrorKind::OutOfRange).ctx;
+            if new_size > isize::MAX as usize - A::ALIGNMENT {
+                return status_from_kind(StatusErrorKind::OutOfRange);
             }
-            let ptr = alloc::alloc::realloc(
+            let ptr = allocator.reallocate(
                 ptr as *mut u8,
-                Layout::from_size_align_unchecked(old_size + ALIGNMENT, ALIGNMENT),
-                new_size + ALIGNMENT,
+                Layout::from_size_align_unchecked(old_size + A::ALIGNMENT, A::ALIGNMENT),
+                new_size + A::ALIGNMENT,
             );
-            unsafe {
-                *(ptr as *mut usize) = new_size;
+            if ptr.is_null() {
+                // The original allocation is left untouched.
+                return status_from_kind(StatusErrorKind::ResourceExhausted);
             }
-            *inout_ptr = ptr.wrapping_add(ALIGNMENT) as *mut c_void;
+            *(ptr as *mut usize) = new_size;
+            *inout_ptr = ptr.wrapping_add(A::ALIGNMENT) as *mut c_void;
             core::ptr::null_mut() as sys::iree_status_t
         }
         sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_FREE => {
-            let ptr = (*inout_ptr).wrapping_sub(ALIGNMENT);
-            let size = unsafe { *(ptr as *mut usize) };
+            let ptr = (*inout_ptr).wrapping_sub(A::ALIGNMENT);
+            let size = *(ptr as *mut usize);
             trace!(
-                "rust_allocator_ctl: IREE_ALLOCATOR_COMMAND_FREE: size: {}->{:p}",
+                "host_allocator_ctl: IREE_ALLOCATOR_COMMAND_FREE: size: {}->{:p}",
                 size,
                 *inout_ptr
             );
-            alloc::alloc::dealloc(
+            allocator.deallocate(
                 ptr as *mut u8,
-                Layout::from_size_align_unchecked(size + ALIGNMENT, ALIGNMENT),
+                Layout::from_size_align_unchecked(size + A::ALIGNMENT, A::ALIGNMENT),
             );
             core::ptr::null_mut() as sys::iree_status_t
         }
-        _ => Status::from_code(StatusErrorKind::Unimplemented).ctx,
+        _ => status_from_kind(StatusErrorKind::Unimplemented),
     }
 }
 
@@ -242,17 +471,15 @@ impl Status {
         Self { ctx }
     }
 
-    pub(crate) fn from_code(status_kind: StatusErrorKind) -> Self {
-        let status: sys::iree_status_code_e = status_kind.into();
-        Status {
-            ctx: &STATUS_CODES[status as usize] as *const usize as *mut usize as *mut _,
-        }
-    }
-
     pub(crate) fn is_ok(&self) -> bool {
         self.ctx as usize == 0
     }
 
+    pub(crate) fn kind(&self) -> StatusErrorKind {
+        // The status code is packed into the low bits of the status pointer.
+        ((self.ctx as usize & STATUS_CODE_MASK) as sys::iree_status_code_e).into()
+    }
+
     /// Converts from `Status` to `Result<(), StatusError>`.
     pub fn to_result(self) -> Result<(), StatusError> {
         if self.is_ok() {
@@ -280,28 +507,166 @@ impl core::fmt::Debug for StatusError {
 
 impl Display for StatusError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.with_formatted(|formatted| write!(f, "Status: {:?}", formatted))
+            .unwrap_or_else(|| write!(f, "Status: <failed to convert to string>"))
+    }
+}
+
+/// IREE runtime status error
+pub struct StatusError {
+    status: Status,
+}
+
+// The error owns its status allocation, which is not tied to the thread it was created on.
+unsafe impl Send for StatusError {}
+
+/// The location in the runtime sources where a status was created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+/// A frame of the VM stack a status was raised from, innermost first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// The fully qualified name of the function, e.g. `module.main`.
+    pub function: String,
+    /// The program counter within the function, if it is a bytecode function.
+    pub pc: Option<u64>,
+    /// The location in the source the function was compiled from, e.g. `model.mlir:12:5`, if the
+    /// module was compiled with debug information.
+    pub location: Option<String>,
+}
+
+impl StatusError {
+    /// Returns the status code.
+    pub fn code(&self) -> StatusErrorKind {
+        self.status.kind()
+    }
+
+    /// Returns the message of the status, including any annotations added while it was
+    /// propagated. Statuses created from a bare code have an empty message.
+    pub fn message(&self) -> String {
+        let formatted = self.formatted().unwrap_or_default();
+        match formatted.split_once(&alloc::format!("{}; ", self.code_string())) {
+            Some((_, message)) => String::from(message),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the source location the status was created at, if the runtime was built with
+    /// source locations enabled.
+    pub fn source_location(&self) -> Option<SourceLocation> {
+        let formatted = self.formatted()?;
+        let code = self.code_string();
+        // Formatted as `file:line: CODE; message`.
+        let (location, _) = formatted.split_once(&alloc::format!(": {}", code))?;
+        let (file, line) = location.rsplit_once(':')?;
+        Some(SourceLocation {
+            file: String::from(file),
+            line: line.parse().ok()?,
+        })
+    }
+
+    /// Returns the VM stack frames annotated on the status when it was raised from a call, e.g.
+    /// to report which function and source location of a model failed. Empty if the runtime was
+    /// built without VM backtraces or the status was not raised by the VM.
+    pub fn stack_frames(&self) -> Vec<StackFrame> {
+        let formatted = self.formatted().unwrap_or_default();
+        // Each frame is formatted on its own line as `[ index] module.function+pc location`.
+        formatted
+            .lines()
+            .filter_map(|line| {
+                let (index, frame) = line.trim_start().strip_prefix('[')?.split_once(']')?;
+                index.trim().parse::<usize>().ok()?;
+                let frame = frame.trim();
+                let (symbol, location) = frame.split_once(' ').unwrap_or((frame, ""));
+                let (function, pc) = match symbol.rsplit_once('+') {
+                    Some((function, pc)) => (function, u64::from_str_radix(pc, 16).ok()),
+                    None => (symbol, None),
+                };
+                let location = location.trim();
+                Some(StackFrame {
+                    function: String::from(function),
+                    pc,
+                    location: (!location.is_empty()).then(|| String::from(location)),
+                })
+            })
+            .collect()
+    }
+
+    fn code_string(&self) -> &'static str {
+        let code = (self.status.ctx as usize & STATUS_CODE_MASK) as sys::iree_status_code_t;
+        unsafe {
+            trace!("iree_status_code_string");
+            core::ffi::CStr::from_ptr(sys::iree_status_code_string(code))
+                .to_str()
+                .unwrap_or_default()
+        }
+    }
+
+    /// Formats the status into the given buffer without allocating, e.g. to report errors on
+    /// targets without a heap. Returns `None` if the formatted status does not fit.
+    pub fn format_into<'b>(&self, buf: &'b mut [u8]) -> Option<&'b str> {
+        let mut length = 0;
+        let formatted = unsafe {
+            trace!("iree_status_format");
+            sys::iree_status_format(
+                self.status.ctx,
+                buf.len(),
+                buf.as_mut_ptr() as *mut core::ffi::c_char,
+                &mut length,
+            )
+        };
+        // The length excludes the terminating null character, which must fit as well.
+        if !formatted || length >= buf.len() {
+            return None;
+        }
+        core::str::from_utf8(&buf[..length]).ok()
+    }
+
+    fn formatted(&self) -> Option<String> {
+        self.with_formatted(String::from)
+    }
+
+    // Formats the status with iree_status_to_string, e.g. `file:line: CODE; message`. The string
+    // is allocated by the runtime and only lives for the duration of the closure.
+    fn with_formatted<R>(&self, f: impl FnOnce(&str) -> R) -> Option<R> {
         let mut bufptr = core::ptr::null_mut();
         let allocator = Allocator::get_global();
         let mut size: usize = 0;
         if !(unsafe {
             sys::iree_status_to_string(self.status.ctx, &allocator.ctx, &mut bufptr, &mut size)
         }) {
-            return write!(f, "Status: <failed to convert to string>");
+            return None;
         }
-        let buf =
-            core::str::from_utf8(unsafe { core::slice::from_raw_parts(bufptr as *const u8, size) })
-                .map_err(|_| core::fmt::Error)?;
-        let write_result = write!(f, "Status: {:?}", buf);
+        let buf = unsafe { core::slice::from_raw_parts(bufptr as *const u8, size) };
+        let formatted = core::str::from_utf8(buf).ok().map(f);
         unsafe {
             sys::iree_allocator_free(allocator.ctx, bufptr as *mut _);
         }
-        write_result
+        formatted
+    }
+
+    /// Releases ownership of the underlying status, e.g. to return it to the runtime.
+    pub(crate) fn into_raw(self) -> sys::iree_status_t {
+        let ctx = self.status.ctx;
+        core::mem::forget(self);
+        ctx
     }
 }
 
-/// IREE runtime status error
-pub struct StatusError {
-    status: Status,
+/// Allocates a status with the given code and message, to be handed over to the runtime.
+pub(crate) fn allocate_status(kind: StatusErrorKind, message: &str) -> sys::iree_status_t {
+    unsafe {
+        sys::iree_status_allocate(
+            kind.into(),
+            concat!(file!(), "\0").as_ptr() as *const core::ffi::c_char,
+            line!(),
+            StringView::from(message).ctx,
+        )
+    }
 }
 
 // TODO: change this when #![feature(error_in_core)] is stabilized
@@ -318,10 +683,18 @@ impl Drop for Status {
     }
 }
 
-// Necessary because status code lifetime is not specified in the C API
-static STATUS_CODES: [usize; 18] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17];
+const STATUS_CODE_MASK: usize = 0x1F;
+
+// Returns a status holding only the code of the given kind, packed into the low bits of the
+// status pointer like the runtime does. Code-only statuses carry no allocated payload, so
+// ownership can be handed to the runtime freely.
+pub(crate) fn status_from_kind(kind: StatusErrorKind) -> sys::iree_status_t {
+    let code: sys::iree_status_code_t = kind.into();
+    code as usize as sys::iree_status_t
+}
 
 /// IREE runtime status error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatusErrorKind {
     Cancelled,
     Unknown,
@@ -1,7 +1,69 @@
 #[cfg(feature = "std")]
 use thiserror::Error;
 extern crate alloc;
-use super::base;
+use super::{
+    base::{self, StatusErrorKind},
+    vm::IncompatibilityReport,
+};
+use eerie_sys::runtime as sys;
+
+/// The message of a [`RuntimeError`], formatted with the values involved, e.g. the shapes that
+/// do not match.
+#[cfg(feature = "std")]
+pub type ErrorMessage = String;
+
+/// The message of a [`RuntimeError`]. Without `std`, messages are the static text of the error,
+/// without the values involved, so that reporting an error never allocates.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorMessage(&'static str);
+
+#[cfg(not(feature = "std"))]
+impl ErrorMessage {
+    /// Creates a message from static text.
+    pub const fn new(text: &'static str) -> Self {
+        Self(text)
+    }
+
+    /// Returns the text of the message.
+    pub const fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::ops::Deref for ErrorMessage {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+// Builds an ErrorMessage from a format string and its arguments. With `std`, the message is
+// formatted; without it, the message is the format string itself, and the arguments are neither
+// evaluated nor formatted.
+#[cfg(feature = "std")]
+macro_rules! error_message {
+    ($($arg:tt)*) => {
+        ::std::format!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "std"))]
+macro_rules! error_message {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        let _ = || ($(&$arg,)*);
+        $crate::runtime::error::ErrorMessage::new($fmt)
+    }};
+}
 
 #[cfg(feature = "std")]
 #[derive(Error, Debug)]
@@ -9,13 +71,28 @@ pub enum RuntimeError {
     #[error("IREE runtime error: {0}")]
     StatusError(#[from] base::StatusError),
     #[error("IREE runtime error: {0}")]
-    InstanceMismatch(String),
+    InstanceMismatch(ErrorMessage),
+    #[error("Function signature mismatch: {0}")]
+    SignatureMismatch(ErrorMessage),
+    #[error("Shape mismatch: {0}")]
+    ShapeMismatch(ErrorMessage),
+    #[error("Incompatible module: {0}")]
+    IncompatibleModule(IncompatibilityReport),
+    #[error("Invalid module: {0}")]
+    InvalidModule(ErrorMessage),
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(ErrorMessage),
 }
 
 #[cfg(not(feature = "std"))]
 pub enum RuntimeError {
     StatusError(base::StatusError),
-    InstanceMismatch(alloc::string::String),
+    InstanceMismatch(ErrorMessage),
+    SignatureMismatch(ErrorMessage),
+    ShapeMismatch(ErrorMessage),
+    IncompatibleModule(IncompatibilityReport),
+    InvalidModule(ErrorMessage),
+    InvalidArchive(ErrorMessage),
 }
 
 #[cfg(not(feature = "std"))]
@@ -31,6 +108,44 @@ impl core::fmt::Debug for RuntimeError {
         match self {
             RuntimeError::StatusError(err) => write!(f, "IREE runtime error: {:?}", err),
             RuntimeError::InstanceMismatch(msg) => write!(f, "IREE runtime error: {}", msg),
+            RuntimeError::SignatureMismatch(msg) => {
+                write!(f, "Function signature mismatch: {}", msg)
+            }
+            RuntimeError::ShapeMismatch(msg) => write!(f, "Shape mismatch: {}", msg),
+            RuntimeError::IncompatibleModule(report) => {
+                write!(f, "Incompatible module: {}", report)
+            }
+            RuntimeError::InvalidModule(msg) => write!(f, "Invalid module: {}", msg),
+            RuntimeError::InvalidArchive(msg) => write!(f, "Invalid archive: {}", msg),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// Converts the error into a status that can be returned to the runtime, e.g. from a native
+    /// module function.
+    pub(crate) fn into_raw_status(self) -> sys::iree_status_t {
+        match self {
+            RuntimeError::StatusError(err) => err.into_raw(),
+            RuntimeError::InstanceMismatch(msg) => {
+                base::allocate_status(StatusErrorKind::FailedPrecondition, &msg)
+            }
+            RuntimeError::SignatureMismatch(msg) => {
+                base::allocate_status(StatusErrorKind::InvalidArgument, &msg)
+            }
+            RuntimeError::ShapeMismatch(msg) => {
+                base::allocate_status(StatusErrorKind::InvalidArgument, &msg)
+            }
+            RuntimeError::IncompatibleModule(report) => base::allocate_status(
+                StatusErrorKind::FailedPrecondition,
+                &alloc::format!("{}", report),
+            ),
+            RuntimeError::InvalidModule(msg) => {
+                base::allocate_status(StatusErrorKind::InvalidArgument, &msg)
+            }
+            RuntimeError::InvalidArchive(msg) => {
+                base::allocate_status(StatusErrorKind::InvalidArgument, &msg)
+            }
         }
     }
 }
@@ -1,15 +1,76 @@
-use core::fmt::{Debug, Formatter};
+extern crate alloc;
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::{
+    ffi::CStr,
+    fmt::{Debug, Formatter},
+};
 
 use eerie_sys::runtime as sys;
-use log::debug;
+use log::{debug, trace};
 
 use super::{
     api::{self, Instance},
     base::{self, ConstByteSpan},
     error::RuntimeError,
-    vm::{Ref, ToRef},
+    vm::{Module, Ref, ToRef},
+};
+
+#[cfg(feature = "ndarray")]
+mod array;
+pub mod channel;
+pub mod command_buffer;
+pub mod executable_cache;
+#[cfg(feature = "hal-inline")]
+pub mod inline;
+#[cfg(feature = "hal-inline")]
+pub use inline::{create_hal_inline_module, create_hal_loader_module};
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub mod local_task;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use local_task::LocalTaskDeviceParams;
+#[cfg(feature = "safetensors")]
+pub mod safetensors;
+#[cfg(feature = "safetensors")]
+pub use self::safetensors::{SafeTensors, TensorInfo};
+pub mod semaphore;
+#[cfg(feature = "static-library")]
+pub mod static_library;
+#[cfg(feature = "static-library")]
+pub use static_library::{LibraryQueryFn, StaticLibraryLoader};
+#[cfg(feature = "vulkan")]
+pub mod vulkan;
+pub use channel::{Channel, ChannelParams};
+pub use command_buffer::CommandBuffer;
+#[cfg(feature = "std")]
+pub use executable_cache::enable_persistent_driver_caches;
+pub use executable_cache::{Executable, ExecutableCache, ExecutableCachingMode};
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use semaphore::FenceFuture;
+pub use semaphore::{Fence, Semaphore, Timeout, WaitMode, QUEUE_AFFINITY_ANY};
+#[cfg(feature = "vulkan")]
+pub use vulkan::{
+    enumerate_vulkan_devices, VulkanDeviceType, VulkanPhysicalDevice, VulkanQueueFamily,
 };
 
+/// Names of the HAL drivers, to pass to [`api::Instance::try_create_default_device`] or
+/// [`api::Instance::lookup_driver`]. GPU drivers are only available with their feature enabled.
+pub mod drivers {
+    /// Runs on the calling thread.
+    pub const LOCAL_SYNC: &str = "local-sync";
+    /// Runs on a pool of worker threads. WebAssembly builds have no threads.
+    #[cfg(all(feature = "std", not(target_family = "wasm")))]
+    pub const LOCAL_TASK: &str = "local-task";
+    #[cfg(feature = "cuda")]
+    pub const CUDA: &str = "cuda";
+    #[cfg(feature = "hip")]
+    pub const HIP: &str = "hip";
+    #[cfg(feature = "vulkan")]
+    pub const VULKAN: &str = "vulkan";
+    /// Available on macOS with the `metal` feature, and always on iOS.
+    #[cfg(any(feature = "metal", target_os = "ios"))]
+    pub const METAL: &str = "metal";
+}
+
 /// A driver registry.
 pub struct DriverRegistry {
     pub(crate) ctx: *mut sys::iree_hal_driver_registry_t,
@@ -32,6 +93,90 @@ impl DriverRegistry {
         }
         Self { ctx: out_ptr }
     }
+
+    /// Creates a driver registry without any drivers. Register the drivers an instance may use
+    /// with [`DriverRegistry::register_driver`], instead of using all available drivers.
+    pub fn empty() -> Result<Self, RuntimeError> {
+        let mut out_ptr = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_driver_registry_allocate");
+            sys::iree_hal_driver_registry_allocate(base::Allocator::get_global().ctx, &mut out_ptr)
+        })
+        .to_result()?;
+        Ok(Self { ctx: out_ptr })
+    }
+
+    /// Registers the driver with the given name, one of [`drivers`], so instances created with
+    /// the registry can create its devices. Returns a `NotFound` error for drivers that are not
+    /// built into the runtime, and an `AlreadyExists` error if the driver was registered before.
+    pub fn register_driver(&mut self, name: &str) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_*_driver_module_register, name: {}", name);
+            match name {
+                drivers::LOCAL_SYNC => sys::iree_hal_local_sync_driver_module_register(self.ctx),
+                #[cfg(all(feature = "std", not(target_family = "wasm")))]
+                drivers::LOCAL_TASK => sys::iree_hal_local_task_driver_module_register(self.ctx),
+                #[cfg(feature = "cuda")]
+                drivers::CUDA => sys::iree_hal_cuda_driver_module_register(self.ctx),
+                #[cfg(feature = "hip")]
+                drivers::HIP => sys::iree_hal_hip_driver_module_register(self.ctx),
+                #[cfg(feature = "vulkan")]
+                drivers::VULKAN => sys::iree_hal_vulkan_driver_module_register(self.ctx),
+                #[cfg(any(feature = "metal", target_os = "ios"))]
+                drivers::METAL => sys::iree_hal_metal_driver_module_register(self.ctx),
+                _ => base::allocate_status(
+                    base::StatusErrorKind::NotFound,
+                    &format!("driver {} is not built into the runtime", name),
+                ),
+            }
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Returns the drivers registered in the registry, e.g. to present a device picker. The
+    /// drivers built into the binary are registered once an instance using all available drivers
+    /// has been created, see [`api::InstanceOptions::use_all_available_drivers`].
+    pub fn enumerate_drivers(&self) -> Result<Vec<DriverInfo>, RuntimeError> {
+        enumerate_drivers(self.ctx, base::Allocator::get_global())
+    }
+}
+
+pub(crate) fn enumerate_drivers(
+    registry: *mut sys::iree_hal_driver_registry_t,
+    allocator: base::Allocator,
+) -> Result<Vec<DriverInfo>, RuntimeError> {
+    let mut count = 0;
+    let mut infos = core::ptr::null_mut();
+    base::Status::from_raw(unsafe {
+        trace!("iree_hal_driver_registry_enumerate");
+        sys::iree_hal_driver_registry_enumerate(registry, allocator.ctx, &mut count, &mut infos)
+    })
+    .to_result()?;
+    if infos.is_null() {
+        return Ok(Vec::new());
+    }
+    let drivers = unsafe { core::slice::from_raw_parts(infos, count) }
+        .iter()
+        .map(|info| {
+            let (name, full_name): (&str, &str) = unsafe {
+                (
+                    base::StringView::from_raw(info.driver_name).into(),
+                    base::StringView::from_raw(info.full_name).into(),
+                )
+            };
+            DriverInfo {
+                name: String::from(name),
+                full_name: String::from(full_name),
+            }
+        })
+        .collect();
+    // The driver infos and their strings are allocated as a single block.
+    unsafe {
+        trace!("iree_allocator_free");
+        sys::iree_allocator_free(allocator.ctx, infos as *mut core::ffi::c_void);
+    }
+    Ok(drivers)
 }
 
 impl Default for DriverRegistry {
@@ -46,6 +191,10 @@ pub struct Device<'a> {
     pub(crate) marker: core::marker::PhantomData<&'a api::Session<'a>>,
 }
 
+// HAL devices are thread-safe, including their queues and allocators.
+unsafe impl Send for Device<'_> {}
+unsafe impl Sync for Device<'_> {}
+
 impl Drop for Device<'_> {
     fn drop(&mut self) {
         unsafe {
@@ -54,6 +203,395 @@ impl Drop for Device<'_> {
     }
 }
 
+/// Memory usage of the allocator of a device, see [`Device::allocator_statistics`]. Counters are
+/// zero when the runtime is built without statistics (`IREE_STATISTICS_ENABLE`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocatorStatistics {
+    /// Highest number of bytes of host memory allocated at once.
+    pub host_bytes_peak: u64,
+    /// Total bytes of host memory allocated.
+    pub host_bytes_allocated: u64,
+    /// Total bytes of host memory freed.
+    pub host_bytes_freed: u64,
+    /// Highest number of bytes of device memory allocated at once.
+    pub device_bytes_peak: u64,
+    /// Total bytes of device memory allocated.
+    pub device_bytes_allocated: u64,
+    /// Total bytes of device memory freed.
+    pub device_bytes_freed: u64,
+}
+
+impl AllocatorStatistics {
+    /// Returns the bytes of host memory currently allocated.
+    pub fn host_bytes_in_use(&self) -> u64 {
+        self.host_bytes_allocated
+            .saturating_sub(self.host_bytes_freed)
+    }
+
+    /// Returns the bytes of device memory currently allocated.
+    pub fn device_bytes_in_use(&self) -> u64 {
+        self.device_bytes_allocated
+            .saturating_sub(self.device_bytes_freed)
+    }
+}
+
+impl Device<'_> {
+    /// Returns the identifier of the device, e.g. `local-sync` or `cuda`.
+    pub fn id(&self) -> &str {
+        unsafe {
+            trace!("iree_hal_device_id");
+            base::StringView::from_raw(sys::iree_hal_device_id(self.ctx)).into()
+        }
+    }
+
+    fn query_allocator_statistics(&self) -> sys::iree_hal_allocator_statistics_t {
+        let mut statistics = sys::iree_hal_allocator_statistics_t::default();
+        unsafe {
+            trace!("iree_hal_allocator_query_statistics");
+            sys::iree_hal_allocator_query_statistics(
+                sys::iree_hal_device_allocator(self.ctx),
+                &mut statistics,
+            );
+        }
+        statistics
+    }
+
+    /// Returns the memory usage of the device allocator, e.g. to monitor the memory consumption
+    /// of sessions and [`Device::trim`] the device before it runs out of memory.
+    pub fn allocator_statistics(&self) -> AllocatorStatistics {
+        let statistics = self.query_allocator_statistics();
+        AllocatorStatistics {
+            host_bytes_peak: statistics.host_bytes_peak as u64,
+            host_bytes_allocated: statistics.host_bytes_allocated as u64,
+            host_bytes_freed: statistics.host_bytes_freed as u64,
+            device_bytes_peak: statistics.device_bytes_peak as u64,
+            device_bytes_allocated: statistics.device_bytes_allocated as u64,
+            device_bytes_freed: statistics.device_bytes_freed as u64,
+        }
+    }
+
+    /// Returns the memory usage of the device allocator as formatted by the runtime, with one
+    /// row per memory type.
+    pub fn allocator_statistics_report(&self) -> Result<String, RuntimeError> {
+        let statistics = self.query_allocator_statistics();
+        let mut builder = sys::iree_string_builder_t::default();
+        unsafe {
+            trace!("iree_string_builder_initialize");
+            sys::iree_string_builder_initialize(base::Allocator::get_global().ctx, &mut builder);
+        }
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_hal_allocator_statistics_format");
+            sys::iree_hal_allocator_statistics_format(&statistics, &mut builder)
+        })
+        .to_result();
+        let report = status.map(|_| {
+            let report: &str = unsafe {
+                trace!("iree_string_builder_view");
+                base::StringView::from_raw(sys::iree_string_builder_view(&builder)).into()
+            };
+            String::from(report)
+        });
+        unsafe {
+            trace!("iree_string_builder_deinitialize");
+            sys::iree_string_builder_deinitialize(&mut builder);
+        }
+        Ok(report?)
+    }
+
+    /// Trims pools and caches of the device, e.g. after a burst of work. They may be expensive to
+    /// rematerialize, so this should only be called when the resources will not be needed soon.
+    pub fn trim(&self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_trim");
+            sys::iree_hal_device_trim(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+/// What a device records while it is profiled, see [`Device::profiling_begin`].
+///
+/// Modes can be combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeviceProfilingMode(sys::iree_hal_device_profiling_mode_t);
+
+impl DeviceProfilingMode {
+    /// Records nothing.
+    pub const NONE: Self = Self(0);
+    /// Records the timing of queue operations, e.g. submissions and transfers.
+    pub const QUEUE_OPERATIONS: Self = Self(
+        sys::iree_hal_device_profiling_mode_bits_t_IREE_HAL_DEVICE_PROFILING_MODE_QUEUE_OPERATIONS
+            as _,
+    );
+    /// Records hardware counters of each dispatch. This is expensive.
+    pub const DISPATCH_COUNTERS: Self = Self(
+        sys::iree_hal_device_profiling_mode_bits_t_IREE_HAL_DEVICE_PROFILING_MODE_DISPATCH_COUNTERS
+            as _,
+    );
+    /// Records hardware counters of each executable, aggregated over its dispatches.
+    pub const EXECUTABLE_COUNTERS: Self = Self(
+        sys::iree_hal_device_profiling_mode_bits_t_IREE_HAL_DEVICE_PROFILING_MODE_EXECUTABLE_COUNTERS
+            as _,
+    );
+
+    /// Returns the raw mode bits.
+    pub fn bits(&self) -> sys::iree_hal_device_profiling_mode_t {
+        self.0
+    }
+
+    /// Returns true if all modes in `other` are set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for DeviceProfilingMode {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for DeviceProfilingMode {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Device<'_> {
+    /// Begins a profiling capture of the device with the tools of its driver, e.g. RenderDoc for
+    /// Vulkan or Nsight for CUDA. Drivers that write the capture to a file use the given path.
+    /// Devices without profiling support ignore the call.
+    pub fn profiling_begin(
+        &self,
+        mode: DeviceProfilingMode,
+        file_path: Option<&CStr>,
+    ) -> Result<(), RuntimeError> {
+        let options = sys::iree_hal_device_profiling_options_t {
+            mode: mode.bits(),
+            file_path: file_path.map_or(core::ptr::null(), CStr::as_ptr),
+        };
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_profiling_begin");
+            sys::iree_hal_device_profiling_begin(self.ctx, &options)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Flushes the profiling data recorded so far, e.g. between frames of a long capture.
+    pub fn profiling_flush(&self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_profiling_flush");
+            sys::iree_hal_device_profiling_flush(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Ends the profiling capture begun with [`Device::profiling_begin`].
+    pub fn profiling_end(&self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_profiling_end");
+            sys::iree_hal_device_profiling_end(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+/// Creates the HAL module for the given devices. Sessions register it on their own, it is only
+/// needed when modules are assembled into a [`super::vm::Context`] by hand.
+///
+/// Modules compiled for several devices, e.g. with one `--iree-hal-target-device` flag per
+/// device, select them by ordinal in the order they are given here.
+pub fn create_hal_module<'a>(
+    instance: &'a Instance,
+    devices: &[&Device],
+) -> Result<Module<'a>, RuntimeError> {
+    if devices.is_empty() {
+        base::Status::from_raw(base::allocate_status(
+            base::StatusErrorKind::InvalidArgument,
+            "the HAL module needs at least one device",
+        ))
+        .to_result()?;
+    }
+    let mut devices: Vec<_> = devices.iter().map(|device| device.ctx).collect();
+    let mut out = core::ptr::null_mut();
+    base::Status::from_raw(unsafe {
+        trace!("iree_hal_module_create");
+        sys::iree_hal_module_create(
+            instance.get_vm_instance(),
+            devices.len(),
+            devices.as_mut_ptr(),
+            sys::iree_hal_module_flag_bits_t_IREE_HAL_MODULE_FLAG_NONE,
+            instance.get_host_allocator().ctx,
+            &mut out,
+        )
+    })
+    .to_result()?;
+    Ok(Module { ctx: out, instance })
+}
+
+/// A HAL driver, e.g. `local-task` or `cuda`. Drivers enumerate the devices they can reach and
+/// create them, which allows selecting a specific device when more than one is available.
+pub struct Driver<'a> {
+    pub(crate) ctx: *mut sys::iree_hal_driver_t,
+    pub(crate) name: String,
+    pub(crate) marker: core::marker::PhantomData<&'a Instance>,
+}
+
+/// Information about a driver registered in a driver registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverInfo {
+    /// Name used to create the driver and in device URIs, e.g. `local-task`.
+    pub name: String,
+    /// Human readable name of the driver.
+    pub full_name: String,
+}
+
+/// Information about a device that a driver can create.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Opaque identifier of the device, only valid for the driver that reported it.
+    pub id: usize,
+    /// Stable path of the device, e.g. the UUID of a CUDA device.
+    pub path: String,
+    /// Human readable name of the device.
+    pub name: String,
+}
+
+impl<'a> Driver<'a> {
+    /// Returns the name of the driver.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Queries the devices that are available to the driver. The order of the devices is stable
+    /// for the lifetime of the process, so it can be used to select a device by ordinal.
+    #[doc(alias = "enumerate_devices")]
+    pub fn query_available_devices(&self) -> Result<Vec<DeviceInfo>, RuntimeError> {
+        let allocator = base::Allocator::get_global();
+        let mut count = 0;
+        let mut infos = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_driver_query_available_devices");
+            sys::iree_hal_driver_query_available_devices(
+                self.ctx,
+                allocator.ctx,
+                &mut count,
+                &mut infos,
+            )
+        })
+        .to_result()?;
+        if infos.is_null() {
+            return Ok(Vec::new());
+        }
+        let devices = unsafe { core::slice::from_raw_parts(infos, count) }
+            .iter()
+            .map(|info| {
+                let (path, name): (&str, &str) = unsafe {
+                    (
+                        base::StringView::from_raw(info.path).into(),
+                        base::StringView::from_raw(info.name).into(),
+                    )
+                };
+                DeviceInfo {
+                    id: info.device_id,
+                    path: String::from(path),
+                    name: String::from(name),
+                }
+            })
+            .collect();
+        // The device infos and their strings are allocated as a single block.
+        unsafe {
+            trace!("iree_allocator_free");
+            sys::iree_allocator_free(allocator.ctx, infos as *mut core::ffi::c_void);
+        }
+        Ok(devices)
+    }
+
+    /// Creates the default device of the driver.
+    pub fn create_default_device(&self) -> Result<Device<'a>, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_driver_create_default_device, driver: {}",
+                self.name
+            );
+            sys::iree_hal_driver_create_default_device(
+                self.ctx,
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Device {
+            ctx: out,
+            marker: core::marker::PhantomData,
+        })
+    }
+
+    /// Creates the device with the given info, as returned by `query_available_devices`.
+    pub fn create_device(&self, info: &DeviceInfo) -> Result<Device<'a>, RuntimeError> {
+        self.create_device_by_id(info.id)
+    }
+
+    /// Creates the device with the given identifier.
+    pub fn create_device_by_id(&self, id: usize) -> Result<Device<'a>, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_driver_create_device_by_id, id: {}", id);
+            sys::iree_hal_driver_create_device_by_id(
+                self.ctx,
+                id,
+                0,
+                core::ptr::null(),
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Device {
+            ctx: out,
+            marker: core::marker::PhantomData,
+        })
+    }
+
+    /// Creates the device with the given path, e.g. the UUID of a CUDA device.
+    pub fn create_device_by_path(&self, path: &str) -> Result<Device<'a>, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_driver_create_device_by_path, path: {}", path);
+            sys::iree_hal_driver_create_device_by_path(
+                self.ctx,
+                base::StringView::from(self.name.as_str()).ctx,
+                base::StringView::from(path).ctx,
+                0,
+                core::ptr::null(),
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Device {
+            ctx: out,
+            marker: core::marker::PhantomData,
+        })
+    }
+}
+
+impl Drop for Driver<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_driver_release");
+            sys::iree_hal_driver_release(self.ctx);
+        }
+    }
+}
+
 /// An encoding type.
 pub enum EncodingType {
     Opaque,
@@ -71,6 +609,7 @@ impl From<EncodingType> for sys::iree_hal_encoding_types_t {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ElementType {
     None,
     Opaque8,
@@ -139,6 +678,46 @@ impl From<ElementType> for sys::iree_hal_element_type_t {
     }
 }
 
+// Unknown element types, e.g. ones added by newer runtimes, map to `ElementType::None`.
+impl From<sys::iree_hal_element_type_t> for ElementType {
+    fn from(element_type: sys::iree_hal_element_type_t) -> Self {
+        match element_type {
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_NONE => ElementType::None,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_8 => ElementType::Opaque8,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_16 => ElementType::Opaque16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_32 => ElementType::Opaque32,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_OPAQUE_64 => ElementType::Opaque64,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BOOL_8 => ElementType::Bool8,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_4 => ElementType::Int4,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_4 => ElementType::Sint4,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_4 => ElementType::Uint4,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_8 => ElementType::Int8,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_8 => ElementType::Sint8,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_8 => ElementType::Uint8,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_16 => ElementType::Int16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_16 => ElementType::Sint16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_16 => ElementType::Uint16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_32 => ElementType::Int32,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_32 => ElementType::Sint32,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_32 => ElementType::Uint32,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_INT_64 => ElementType::Int64,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_SINT_64 => ElementType::Sint64,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_UINT_64 => ElementType::Uint64,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_16 => ElementType::Float16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_32 => ElementType::Float32,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_FLOAT_64 => ElementType::Float64,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_BFLOAT_16 => ElementType::BFloat16,
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_64 => {
+                ElementType::ComplexFloat64
+            }
+            sys::iree_hal_element_types_t_IREE_HAL_ELEMENT_TYPE_COMPLEX_FLOAT_128 => {
+                ElementType::ComplexFloat128
+            }
+            _ => ElementType::None,
+        }
+    }
+}
+
 pub trait ToElementType {
     fn to_element_type() -> ElementType;
 }
@@ -166,6 +745,74 @@ impl_to_element_type!(f32, Float32);
 impl_to_element_type!(f64, Float64);
 impl_to_element_type!(bool, Bool8);
 
+/// A HAL buffer without a shape or element type, e.g. a transient buffer allocated with
+/// [`Device::queue_alloca`]. Wrap it with [`BufferView::from_buffer`] to pass it to functions.
+pub struct Buffer {
+    pub(crate) ctx: *mut sys::iree_hal_buffer_t,
+}
+
+// Buffers are reference counted atomically and their handles can be used from any thread.
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    /// Returns the size of the buffer in bytes.
+    pub fn byte_length(&self) -> usize {
+        unsafe {
+            trace!("iree_hal_buffer_byte_length");
+            sys::iree_hal_buffer_byte_length(self.ctx) as usize
+        }
+    }
+}
+
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_buffer_retain");
+            sys::iree_hal_buffer_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_buffer_release");
+            sys::iree_hal_buffer_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> ToRef<'a> for Buffer {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        semaphore::wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("hal.buffer".into())
+    }
+}
+
+impl<'a> Ref<'a, Buffer> {
+    /// Returns the buffer held by the Ref, which is retained, e.g. a buffer returned by a function.
+    /// Returns `RuntimeError::SignatureMismatch` if the ref is null or does not hold a
+    /// `hal.buffer`.
+    pub fn to_buffer(&self) -> Result<Buffer, RuntimeError> {
+        self.check_type("hal.buffer")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_hal_buffer_t;
+        unsafe {
+            trace!("iree_hal_buffer_retain");
+            sys::iree_hal_buffer_retain(ctx);
+        }
+        Ok(Buffer { ctx })
+    }
+}
+
 /// A shaped and typed view into a storage buffer.
 /// This is the closest thing to a "tensor" we have.
 pub struct BufferView<'a, T: ToElementType> {
@@ -176,26 +823,43 @@ pub struct BufferView<'a, T: ToElementType> {
 
 impl<'a, T: ToElementType> BufferView<'a, T> {
     /// Create a new BufferView for the given data
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(shape = ?shape, byte_length = core::mem::size_of_val(data)))
+    )]
     pub fn new(
         session: &'a api::Session,
         shape: &[usize],
         encoding_type: EncodingType,
         data: &[T],
     ) -> Result<Self, RuntimeError> {
-        let mut out_ptr = core::ptr::null_mut();
-        let bytespan: ConstByteSpan = unsafe {
+        let bytes = unsafe {
             core::slice::from_raw_parts(data.as_ptr() as *const u8, core::mem::size_of_val(data))
-        }
-        .into();
+        };
+        Self::allocate_copy(session, shape, T::to_element_type(), encoding_type, bytes)
+    }
+
+    // Allocates a buffer view of the given element type in the session's device allocator,
+    // holding a copy of the given bytes. The element type is not checked against `T`, so it can
+    // be used for buffer views whose element type is only known at runtime.
+    pub(crate) fn allocate_copy(
+        session: &'a api::Session,
+        shape: &[usize],
+        element_type: ElementType,
+        encoding_type: EncodingType,
+        bytes: &[u8],
+    ) -> Result<Self, RuntimeError> {
+        let mut out_ptr = core::ptr::null_mut();
+        let bytespan: ConstByteSpan = bytes.into();
         debug!("shape: {:?}", shape);
-        debug!("data len: {}", core::mem::size_of_val(data));
+        debug!("data len: {}", bytes.len());
         base::Status::from_raw(unsafe {
             sys::iree_hal_buffer_view_allocate_buffer_copy(
                 sys::iree_runtime_session_device(session.ctx),
                 sys::iree_runtime_session_device_allocator(session.ctx),
                 shape.len(),
                 shape.as_ptr(),
-                T::to_element_type().into(),
+                element_type.into(),
                 encoding_type.into(),
                 sys::iree_hal_buffer_params_t {
                     usage: sys::iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
@@ -216,6 +880,210 @@ impl<'a, T: ToElementType> BufferView<'a, T> {
         })
     }
 
+    /// Create a new BufferView that wraps the given host memory without copying it.
+    ///
+    /// The memory is imported into the session's device allocator, so devices that cannot access
+    /// host memory directly will return an error instead of falling back to a copy. The returned
+    /// BufferView borrows `data` mutably, as the device may read and write it in place. Prefer
+    /// [`BufferView::wrap_host_buffer`] when the memory can be handed over to the runtime.
+    ///
+    /// # Safety
+    ///
+    /// The runtime may retain the underlying buffer beyond the lifetime of the returned
+    /// BufferView, for example when it is pushed into a list or returned from a function call.
+    /// The caller must ensure that no such reference is used after `data` goes out of scope, and
+    /// that `data` is not accessed while the device may be reading or writing it.
+    pub unsafe fn from_slice_external(
+        session: &'a api::Session,
+        shape: &[usize],
+        encoding_type: EncodingType,
+        data: &'a mut [T],
+    ) -> Result<Self, RuntimeError> {
+        Self::import_host_allocation(
+            session,
+            shape,
+            encoding_type,
+            data.as_mut_ptr() as *mut core::ffi::c_void,
+            core::mem::size_of_val(data),
+            sys::iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ALL as u16,
+            // The memory is owned by the caller, so there is nothing to release.
+            sys::iree_hal_buffer_release_callback_t {
+                fn_: None,
+                user_data: core::ptr::null_mut(),
+            },
+        )
+    }
+
+    /// Create a new BufferView that takes ownership of the given host memory without copying it.
+    ///
+    /// The memory is imported into the session's device allocator, so devices that cannot access
+    /// host memory directly will return an error instead of falling back to a copy. The memory is
+    /// freed once the runtime releases the last reference to the underlying buffer, which may be
+    /// after the returned BufferView is dropped.
+    pub fn wrap_host_buffer(
+        session: &'a api::Session,
+        shape: &[usize],
+        encoding_type: EncodingType,
+        data: Vec<T>,
+    ) -> Result<Self, RuntimeError>
+    where
+        T: Send + 'static,
+    {
+        unsafe extern "C" fn release<T>(
+            user_data: *mut core::ffi::c_void,
+            _: *mut sys::iree_hal_buffer_t,
+        ) {
+            trace!("Releasing host buffer...");
+            drop(Box::from_raw(user_data as *mut Vec<T>));
+        }
+
+        let mut data = Box::new(data);
+        let ptr = data.as_mut_ptr() as *mut core::ffi::c_void;
+        let byte_length = core::mem::size_of_val(data.as_slice());
+        unsafe {
+            Self::import_host_allocation(
+                session,
+                shape,
+                encoding_type,
+                ptr,
+                byte_length,
+                sys::iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_ALL as u16,
+                sys::iree_hal_buffer_release_callback_t {
+                    fn_: Some(release::<T>),
+                    user_data: Box::into_raw(data) as *mut core::ffi::c_void,
+                },
+            )
+        }
+    }
+
+    // Imports a host allocation as a buffer and wraps it in a buffer view. The shape must cover
+    // the allocation exactly. The release callback is invoked exactly once, either when the buffer
+    // is destroyed or when the import fails.
+    pub(crate) unsafe fn import_host_allocation(
+        session: &'a api::Session,
+        shape: &[usize],
+        encoding_type: EncodingType,
+        ptr: *mut core::ffi::c_void,
+        byte_length: usize,
+        access: sys::iree_hal_memory_access_t,
+        release_callback: sys::iree_hal_buffer_release_callback_t,
+    ) -> Result<Self, RuntimeError> {
+        let shape_length = shape.iter().product::<usize>() * core::mem::size_of::<T>();
+        if shape_length != byte_length {
+            if let Some(release) = release_callback.fn_ {
+                release(release_callback.user_data, core::ptr::null_mut());
+            }
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "shape {:?} needs {} bytes, but the host allocation holds {}",
+                shape,
+                shape_length,
+                byte_length
+            )));
+        }
+        let mut external_buffer = sys::iree_hal_external_buffer_t {
+            type_:
+                sys::iree_hal_external_buffer_type_e_IREE_HAL_EXTERNAL_BUFFER_TYPE_HOST_ALLOCATION,
+            flags: 0,
+            size: byte_length as sys::iree_device_size_t,
+            ..Default::default()
+        };
+        external_buffer.handle.host_allocation.ptr = ptr;
+        debug!("shape: {:?}", shape);
+        debug!("external data len: {}", byte_length);
+
+        let mut buffer = core::ptr::null_mut();
+        let status = base::Status::from_raw(sys::iree_hal_allocator_import_buffer(
+            sys::iree_runtime_session_device_allocator(session.ctx),
+            sys::iree_hal_buffer_params_t {
+                usage: sys::iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
+                access,
+                type_: sys::iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_HOST_LOCAL
+                    | sys::iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_VISIBLE,
+                queue_affinity: 0,
+                min_alignment: 0,
+            },
+            &mut external_buffer,
+            release_callback,
+            &mut buffer,
+        ))
+        .to_result();
+        if let Err(err) = status {
+            // The buffer was never created, so it will not invoke the callback.
+            if let Some(release) = release_callback.fn_ {
+                release(release_callback.user_data, core::ptr::null_mut());
+            }
+            return Err(err.into());
+        }
+
+        let mut out_ptr = core::ptr::null_mut();
+        let status = base::Status::from_raw(sys::iree_hal_buffer_view_create(
+            buffer,
+            shape.len(),
+            shape.as_ptr(),
+            T::to_element_type().into(),
+            encoding_type.into(),
+            sys::iree_runtime_session_host_allocator(session.ctx),
+            &mut out_ptr,
+        ))
+        .to_result();
+        // The buffer view retains the buffer on success.
+        sys::iree_hal_buffer_release(buffer);
+        status?;
+        Ok(Self {
+            ctx: out_ptr,
+            session,
+            marker: core::marker::PhantomData,
+        })
+    }
+
+    /// Creates a BufferView of the given shape over a buffer, which must be exactly as large as
+    /// the shape. The buffer view retains the buffer.
+    pub fn from_buffer(
+        session: &'a api::Session,
+        buffer: &Buffer,
+        shape: &[usize],
+        encoding_type: EncodingType,
+    ) -> Result<Self, RuntimeError> {
+        let byte_length = shape.iter().product::<usize>() * core::mem::size_of::<T>();
+        if byte_length != buffer.byte_length() {
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "shape {:?} needs {} bytes, but the buffer holds {}",
+                shape,
+                byte_length,
+                buffer.byte_length()
+            )));
+        }
+        let mut out_ptr = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_buffer_view_create");
+            sys::iree_hal_buffer_view_create(
+                buffer.ctx,
+                shape.len(),
+                shape.as_ptr(),
+                T::to_element_type().into(),
+                encoding_type.into(),
+                sys::iree_runtime_session_host_allocator(session.ctx),
+                &mut out_ptr,
+            )
+        })
+        .to_result()?;
+        Ok(Self {
+            ctx: out_ptr,
+            session,
+            marker: core::marker::PhantomData,
+        })
+    }
+
+    /// Returns the buffer the view is backed by.
+    pub fn buffer(&self) -> Buffer {
+        let ctx = self.get_buffer();
+        unsafe {
+            trace!("iree_hal_buffer_retain");
+            sys::iree_hal_buffer_retain(ctx);
+        }
+        Buffer { ctx }
+    }
+
     pub(crate) unsafe fn from_ptr(
         ctx: *mut sys::iree_hal_buffer_view_t,
         session: &'a api::Session,
@@ -234,6 +1102,177 @@ impl<'a, T: ToElementType> BufferView<'a, T> {
     pub fn byte_length(&self) -> usize {
         unsafe { sys::iree_hal_buffer_view_byte_length(self.ctx) }
     }
+
+    /// Returns the shape of the buffer view.
+    pub fn shape(&self) -> Vec<usize> {
+        unsafe {
+            let rank = sys::iree_hal_buffer_view_shape_rank(self.ctx);
+            (0..rank)
+                .map(|i| sys::iree_hal_buffer_view_shape_dim(self.ctx, i))
+                .collect()
+        }
+    }
+
+    /// Returns the number of dimensions of the buffer view.
+    pub fn rank(&self) -> usize {
+        unsafe { sys::iree_hal_buffer_view_shape_rank(self.ctx) }
+    }
+
+    /// Returns the number of elements of the buffer view, i.e. the product of its dimensions.
+    pub fn element_count(&self) -> usize {
+        unsafe { sys::iree_hal_buffer_view_element_count(self.ctx) }
+    }
+
+    /// Returns the element type of the buffer view as reported by the runtime.
+    pub fn element_type(&self) -> ElementType {
+        unsafe { sys::iree_hal_buffer_view_element_type(self.ctx) }.into()
+    }
+
+    /// Copies the contents of the buffer view into a new vector, in row major order.
+    ///
+    /// Unlike [`BufferMapping`], this also works for buffers that live in device memory which
+    /// cannot be mapped by the host.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = self.byte_length()))
+    )]
+    pub fn to_vec(&self) -> Result<Vec<T>, RuntimeError> {
+        let length = self.byte_length() / core::mem::size_of::<T>();
+        let mut out = Vec::<T>::with_capacity(length);
+        unsafe {
+            transfer_to_host(
+                sys::iree_runtime_session_device(self.session.ctx),
+                self.get_buffer(),
+                out.as_mut_ptr() as *mut core::ffi::c_void,
+                length * core::mem::size_of::<T>(),
+            )?;
+            // The transfer initialized every element.
+            out.set_len(length);
+        }
+        Ok(out)
+    }
+
+    /// Copies the contents of the buffer view into the given slice, which must hold exactly as
+    /// many bytes as the buffer view. Like [`BufferView::to_vec`], this uses a transfer on the
+    /// device and works for device memory that cannot be mapped by the host.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = core::mem::size_of_val(data)))
+    )]
+    pub fn copy_to_host(&self, data: &mut [T]) -> Result<(), RuntimeError> {
+        let data_length = core::mem::size_of_val(data);
+        self.check_transfer_length(data_length)?;
+        unsafe {
+            transfer_to_host(
+                sys::iree_runtime_session_device(self.session.ctx),
+                self.get_buffer(),
+                data.as_mut_ptr() as *mut core::ffi::c_void,
+                data_length,
+            )
+        }
+    }
+
+    /// Overwrites the contents of the buffer view with the given slice, which must hold exactly
+    /// as many bytes as the buffer view. The copy is done with a transfer on the device, so the
+    /// buffer does not need to be mappable by the host.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = core::mem::size_of_val(data)))
+    )]
+    pub fn copy_from_host(&self, data: &[T]) -> Result<(), RuntimeError> {
+        let data_length = core::mem::size_of_val(data);
+        self.check_transfer_length(data_length)?;
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_transfer_h2d, length: {}", data_length);
+            sys::iree_hal_device_transfer_h2d(
+                sys::iree_runtime_session_device(self.session.ctx),
+                data.as_ptr() as *const core::ffi::c_void,
+                self.get_buffer(),
+                0,
+                data_length as sys::iree_device_size_t,
+                sys::iree_hal_transfer_buffer_flag_bits_t_IREE_HAL_TRANSFER_BUFFER_FLAG_DEFAULT,
+                Timeout::Infinite.into(),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Overwrites the contents of the buffer view with the contents of another buffer view of
+    /// the same byte length, without a round trip through host memory. Both buffer views must
+    /// live on the device of this buffer view's session.
+    pub fn copy_from_device(&self, source: &BufferView<'_, T>) -> Result<(), RuntimeError> {
+        let data_length = source.byte_length();
+        self.check_transfer_length(data_length)?;
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_transfer_d2d, length: {}", data_length);
+            sys::iree_hal_device_transfer_d2d(
+                sys::iree_runtime_session_device(self.session.ctx),
+                source.get_buffer(),
+                0,
+                self.get_buffer(),
+                0,
+                data_length as sys::iree_device_size_t,
+                sys::iree_hal_transfer_buffer_flag_bits_t_IREE_HAL_TRANSFER_BUFFER_FLAG_DEFAULT,
+                Timeout::Infinite.into(),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    fn check_transfer_length(&self, data_length: usize) -> Result<(), RuntimeError> {
+        check_transfer_length(data_length, self.byte_length())
+    }
+
+    /// Returns a handle to the buffer view that does not borrow the session, e.g. to keep the
+    /// results of a call after the session is dropped or to send them to another thread.
+    pub fn to_owned_view(&self) -> OwnedBufferView<T> {
+        let device = unsafe { sys::iree_runtime_session_device(self.session.ctx) };
+        unsafe {
+            trace!("iree_hal_buffer_view_retain");
+            sys::iree_hal_buffer_view_retain(self.ctx);
+            trace!("iree_hal_device_retain");
+            sys::iree_hal_device_retain(device);
+        }
+        OwnedBufferView {
+            ctx: self.ctx,
+            device,
+            marker: core::marker::PhantomData,
+        }
+    }
+}
+
+fn check_transfer_length(data_length: usize, byte_length: usize) -> Result<(), RuntimeError> {
+    if data_length != byte_length {
+        return Err(RuntimeError::ShapeMismatch(error_message!(
+            "cannot transfer {} bytes to or from a buffer view of {} bytes",
+            data_length,
+            byte_length
+        )));
+    }
+    Ok(())
+}
+
+// Copies `length` bytes from the start of the buffer into host memory at `ptr`.
+unsafe fn transfer_to_host(
+    device: *mut sys::iree_hal_device_t,
+    buffer: *mut sys::iree_hal_buffer_t,
+    ptr: *mut core::ffi::c_void,
+    length: usize,
+) -> Result<(), RuntimeError> {
+    trace!("iree_hal_device_transfer_d2h, length: {}", length);
+    base::Status::from_raw(sys::iree_hal_device_transfer_d2h(
+        device,
+        buffer,
+        0,
+        ptr,
+        length as sys::iree_device_size_t,
+        sys::iree_hal_transfer_buffer_flag_bits_t_IREE_HAL_TRANSFER_BUFFER_FLAG_DEFAULT,
+        Timeout::Infinite.into(),
+    ))
+    .to_result()?;
+    Ok(())
 }
 
 impl<T: ToElementType> Debug for BufferView<'_, T> {
@@ -248,7 +1287,7 @@ impl<T: ToElementType> Debug for BufferView<'_, T> {
                 buf.as_mut_ptr(),
                 &mut len as *mut usize,
             );
-            core::ffi::CStr::from_ptr(buf.as_ptr()).to_str().unwrap()
+            CStr::from_ptr(buf.as_ptr()).to_str().unwrap()
         })
     }
 }
@@ -286,6 +1325,119 @@ impl<'a, T: ToElementType> ToRef<'a> for BufferView<'a, T> {
     }
 }
 
+/// A buffer view that does not borrow a session, created with [`BufferView::to_owned_view`].
+///
+/// It retains the buffer view and the device of the session it was created in, so it is
+/// `'static` and can be stored or moved to another thread freely. Convert it back with
+/// [`OwnedBufferView::to_buffer_view`] to pass it to a function.
+pub struct OwnedBufferView<T: ToElementType> {
+    ctx: *mut sys::iree_hal_buffer_view_t,
+    device: *mut sys::iree_hal_device_t,
+    marker: core::marker::PhantomData<T>,
+}
+
+// Buffer views and devices are reference counted atomically and are safe to use from any thread.
+unsafe impl<T: ToElementType + Send> Send for OwnedBufferView<T> {}
+unsafe impl<T: ToElementType + Sync> Sync for OwnedBufferView<T> {}
+
+impl<T: ToElementType> OwnedBufferView<T> {
+    /// Returns a BufferView of the same buffer in the given session, which must use the device
+    /// the buffer view was created on.
+    pub fn to_buffer_view<'a>(
+        &self,
+        session: &'a api::Session,
+    ) -> Result<BufferView<'a, T>, RuntimeError> {
+        if unsafe { sys::iree_runtime_session_device(session.ctx) } != self.device {
+            return Err(RuntimeError::InstanceMismatch(error_message!(
+                "the session does not use the device of the buffer view"
+            )));
+        }
+        unsafe {
+            trace!("iree_hal_buffer_view_retain");
+            sys::iree_hal_buffer_view_retain(self.ctx);
+            Ok(BufferView::from_ptr(self.ctx, session))
+        }
+    }
+
+    pub fn byte_length(&self) -> usize {
+        unsafe { sys::iree_hal_buffer_view_byte_length(self.ctx) }
+    }
+
+    /// Returns the shape of the buffer view.
+    pub fn shape(&self) -> Vec<usize> {
+        unsafe {
+            let rank = sys::iree_hal_buffer_view_shape_rank(self.ctx);
+            (0..rank)
+                .map(|i| sys::iree_hal_buffer_view_shape_dim(self.ctx, i))
+                .collect()
+        }
+    }
+
+    /// Returns the element type of the buffer view as reported by the runtime.
+    pub fn element_type(&self) -> ElementType {
+        unsafe { sys::iree_hal_buffer_view_element_type(self.ctx) }.into()
+    }
+
+    /// Copies the contents of the buffer view into a new vector, in row major order.
+    pub fn to_vec(&self) -> Result<Vec<T>, RuntimeError> {
+        let length = self.byte_length() / core::mem::size_of::<T>();
+        let mut out = Vec::<T>::with_capacity(length);
+        unsafe {
+            transfer_to_host(
+                self.device,
+                sys::iree_hal_buffer_view_buffer(self.ctx),
+                out.as_mut_ptr() as *mut core::ffi::c_void,
+                length * core::mem::size_of::<T>(),
+            )?;
+            // The transfer initialized every element.
+            out.set_len(length);
+        }
+        Ok(out)
+    }
+
+    /// Copies the contents of the buffer view into the given slice, which must hold exactly as
+    /// many bytes as the buffer view.
+    pub fn copy_to_host(&self, data: &mut [T]) -> Result<(), RuntimeError> {
+        let data_length = core::mem::size_of_val(data);
+        check_transfer_length(data_length, self.byte_length())?;
+        unsafe {
+            transfer_to_host(
+                self.device,
+                sys::iree_hal_buffer_view_buffer(self.ctx),
+                data.as_mut_ptr() as *mut core::ffi::c_void,
+                data_length,
+            )
+        }
+    }
+}
+
+impl<T: ToElementType> Clone for OwnedBufferView<T> {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_buffer_view_retain");
+            sys::iree_hal_buffer_view_retain(self.ctx);
+            trace!("iree_hal_device_retain");
+            sys::iree_hal_device_retain(self.device);
+        }
+        Self {
+            ctx: self.ctx,
+            device: self.device,
+            marker: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: ToElementType> Drop for OwnedBufferView<T> {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_buffer_view_release");
+            sys::iree_hal_buffer_view_release(self.ctx);
+            trace!("iree_hal_device_release");
+            sys::iree_hal_device_release(self.device);
+        }
+    }
+}
+
 /// A buffer mapping. This is used to read or write data from a buffer.
 pub struct BufferMapping<'a, T: ToElementType> {
     ctx: sys::iree_hal_buffer_mapping_t,
@@ -0,0 +1,78 @@
+use ndarray::{ArrayBase, ArrayD, ArrayViewD, Data, Dimension, IxDyn};
+
+use super::{
+    super::{api::Session, error::RuntimeError},
+    BufferView, EncodingType, ToElementType,
+};
+
+impl<'a, T: ToElementType + Clone> BufferView<'a, T> {
+    /// Create a new BufferView from an ndarray of any dimensionality, e.g. an `ArrayD`, an
+    /// `Array2` or an `ArrayViewD`. Arrays that are not in standard (row major) layout, such as
+    /// transposed or sliced views, are copied into one before being uploaded.
+    pub fn from_array<S, D>(
+        session: &'a Session,
+        array: &ArrayBase<S, D>,
+    ) -> Result<Self, RuntimeError>
+    where
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        let array = array.as_standard_layout();
+        // A standard layout array is always contiguous.
+        let data = array.as_slice().unwrap();
+        Self::new(session, array.shape(), EncodingType::DenseRowMajor, data)
+    }
+
+    /// Copies the contents of the BufferView into a new ndarray with the same shape.
+    pub fn to_array(&self) -> Result<ArrayD<T>, RuntimeError> {
+        let shape = self.shape();
+        ArrayD::from_shape_vec(IxDyn(&shape), self.to_vec()?).map_err(|err| {
+            RuntimeError::ShapeMismatch(error_message!(
+                "buffer view of shape {:?} cannot be converted to an array: {}",
+                shape,
+                err
+            ))
+        })
+    }
+
+    /// Consumes the BufferView and copies its contents into a new ndarray with the same shape.
+    pub fn into_array(self) -> Result<ArrayD<T>, RuntimeError> {
+        self.to_array()
+    }
+}
+
+impl<'a, T: ToElementType + Clone> TryFrom<(&'a Session<'a>, &ArrayD<T>)> for BufferView<'a, T> {
+    type Error = RuntimeError;
+
+    fn try_from((session, array): (&'a Session<'a>, &ArrayD<T>)) -> Result<Self, Self::Error> {
+        Self::from_array(session, array)
+    }
+}
+
+impl<'a, T: ToElementType + Clone> TryFrom<(&'a Session<'a>, ArrayViewD<'_, T>)>
+    for BufferView<'a, T>
+{
+    type Error = RuntimeError;
+
+    fn try_from(
+        (session, array): (&'a Session<'a>, ArrayViewD<'_, T>),
+    ) -> Result<Self, Self::Error> {
+        Self::from_array(session, &array)
+    }
+}
+
+impl<T: ToElementType + Clone> TryFrom<BufferView<'_, T>> for ArrayD<T> {
+    type Error = RuntimeError;
+
+    fn try_from(buffer_view: BufferView<'_, T>) -> Result<Self, Self::Error> {
+        buffer_view.into_array()
+    }
+}
+
+impl<T: ToElementType + Clone> TryFrom<&BufferView<'_, T>> for ArrayD<T> {
+    type Error = RuntimeError;
+
+    fn try_from(buffer_view: &BufferView<'_, T>) -> Result<Self, Self::Error> {
+        buffer_view.to_array()
+    }
+}
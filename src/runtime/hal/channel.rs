@@ -0,0 +1,147 @@
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{
+        api::Instance,
+        base::{self, ConstByteSpan, StringView},
+        error::RuntimeError,
+        vm::{Ref, ToRef},
+    },
+    semaphore::wrap_retain,
+    Device,
+};
+
+/// Parameters of a [`Channel`]. Unset ranks and counts are provided by the channel provider of
+/// the device, e.g. from the environment of an MPI launcher.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelParams<'a> {
+    /// Identifier shared by all participants, e.g. an NCCL unique id.
+    pub id: &'a [u8],
+    /// Name of the group the channel belongs to.
+    pub group: &'a str,
+    /// Rank of this participant in the group.
+    pub rank: Option<i32>,
+    /// Number of participants in the group.
+    pub count: Option<i32>,
+}
+
+/// A HAL collective channel, connecting the participants of collective operations, e.g. the
+/// devices of a model compiled for multiple GPUs.
+///
+/// Channels are passed to functions that take a `!hal.channel`, typically one per device with
+/// its own rank.
+pub struct Channel {
+    pub(crate) ctx: *mut sys::iree_hal_channel_t,
+}
+
+// Channels are reference counted atomically.
+unsafe impl Send for Channel {}
+unsafe impl Sync for Channel {}
+
+// Makes the runtime pick the rank or count, see IREE_HAL_CHANNEL_RANK_DEFAULT and
+// IREE_HAL_CHANNEL_COUNT_DEFAULT.
+const DEFAULT_RANK_OR_COUNT: i32 = -1;
+
+impl Channel {
+    /// Creates a channel on the device queues selected by `queue_affinity`. Returns an
+    /// `Unimplemented` error if the device does not support collectives.
+    pub fn new(
+        device: &Device,
+        queue_affinity: u64,
+        params: &ChannelParams,
+    ) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_channel_create, rank: {:?}, count: {:?}",
+                params.rank,
+                params.count
+            );
+            sys::iree_hal_channel_create(
+                device.ctx,
+                queue_affinity,
+                sys::iree_hal_channel_params_t {
+                    flags: 0,
+                    id: ConstByteSpan::from(params.id).ctx,
+                    group: StringView::from(params.group).ctx,
+                    rank: params.rank.unwrap_or(DEFAULT_RANK_OR_COUNT),
+                    count: params.count.unwrap_or(DEFAULT_RANK_OR_COUNT),
+                },
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Returns the rank of this participant and the number of participants.
+    pub fn rank_and_count(&self) -> (i32, i32) {
+        let mut rank = 0;
+        let mut count = 0;
+        unsafe {
+            trace!("iree_hal_channel_query_rank_and_count");
+            sys::iree_hal_channel_query_rank_and_count(self.ctx, &mut rank, &mut count);
+        }
+        (rank, count)
+    }
+
+    /// Splits the channel into subgroups. Participants passing the same `color` end up in the
+    /// same subgroup, ranked by `key`.
+    pub fn split(&self, color: i32, key: i32) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_channel_split, color: {}, key: {}", color, key);
+            sys::iree_hal_channel_split(self.ctx, color, key, 0, &mut out)
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+}
+
+impl Clone for Channel {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_channel_retain");
+            sys::iree_hal_channel_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_channel_release");
+            sys::iree_hal_channel_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> ToRef<'a> for Channel {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("hal.channel".into())
+    }
+}
+
+impl<'a> Ref<'a, Channel> {
+    /// Returns the channel held by the Ref, which is retained. Returns
+    /// `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `hal.channel`.
+    pub fn to_channel(&self) -> Result<Channel, RuntimeError> {
+        self.check_type("hal.channel")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_hal_channel_t;
+        unsafe {
+            trace!("iree_hal_channel_retain");
+            sys::iree_hal_channel_retain(ctx);
+        }
+        Ok(Channel { ctx })
+    }
+}
@@ -0,0 +1,226 @@
+extern crate alloc;
+use alloc::vec::Vec;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{base, error::RuntimeError},
+    semaphore::wait_semaphore_list,
+    Buffer, Device, Executable, Fence,
+};
+
+/// A one-shot HAL command buffer, recording transfer and dispatch commands for a device queue,
+/// e.g. to fill, copy or normalize tensors on the device without a round trip through host
+/// memory.
+///
+/// Recording starts when the command buffer is created. Once [`CommandBuffer::end`] is called,
+/// it can be submitted with [`Device::queue_execute`]. The command buffer retains the buffers and
+/// executables it records commands for until it is destroyed.
+pub struct CommandBuffer {
+    pub(crate) ctx: *mut sys::iree_hal_command_buffer_t,
+}
+
+// Command buffers are reference counted atomically. Recording requires `&mut self`.
+unsafe impl Send for CommandBuffer {}
+unsafe impl Sync for CommandBuffer {}
+
+impl CommandBuffer {
+    /// Creates a command buffer for the device queues selected by `queue_affinity` and begins
+    /// recording.
+    pub fn new(device: &Device, queue_affinity: u64) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_create");
+            sys::iree_hal_command_buffer_create(
+                device.ctx,
+                sys::iree_hal_command_buffer_mode_bits_t_IREE_HAL_COMMAND_BUFFER_MODE_ONE_SHOT,
+                sys::iree_hal_command_category_bits_t_IREE_HAL_COMMAND_CATEGORY_ANY,
+                queue_affinity,
+                0,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        let command_buffer = Self { ctx: out };
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_begin");
+            sys::iree_hal_command_buffer_begin(command_buffer.ctx)
+        })
+        .to_result()?;
+        Ok(command_buffer)
+    }
+
+    /// Records filling `length` bytes of the buffer from `offset` with a repeated pattern of 1, 2
+    /// or 4 bytes.
+    pub fn fill_buffer(
+        &mut self,
+        buffer: &Buffer,
+        offset: usize,
+        length: usize,
+        pattern: &[u8],
+    ) -> Result<&mut Self, RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_fill_buffer, length: {}", length);
+            sys::iree_hal_command_buffer_fill_buffer(
+                self.ctx,
+                buffer.ctx,
+                offset as sys::iree_device_size_t,
+                length as sys::iree_device_size_t,
+                pattern.as_ptr() as *const core::ffi::c_void,
+                pattern.len(),
+            )
+        })
+        .to_result()?;
+        Ok(self)
+    }
+
+    /// Records copying `length` bytes from the source buffer at `source_offset` to the target
+    /// buffer at `target_offset`.
+    pub fn copy_buffer(
+        &mut self,
+        source: &Buffer,
+        source_offset: usize,
+        target: &Buffer,
+        target_offset: usize,
+        length: usize,
+    ) -> Result<&mut Self, RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_copy_buffer, length: {}", length);
+            sys::iree_hal_command_buffer_copy_buffer(
+                self.ctx,
+                source.ctx,
+                source_offset as sys::iree_device_size_t,
+                target.ctx,
+                target_offset as sys::iree_device_size_t,
+                length as sys::iree_device_size_t,
+            )
+        })
+        .to_result()?;
+        Ok(self)
+    }
+
+    /// Records a dispatch of the given entry point of an executable, prepared with
+    /// [`super::ExecutableCache::prepare_executable`], over a grid of `workgroup_count`
+    /// workgroups. The whole of each buffer of `bindings` is bound, in order, to the bindings of
+    /// the entry point, and `constants` are passed as its push constants.
+    ///
+    /// Dispatches recorded one after the other may run concurrently; record an
+    /// [`CommandBuffer::execution_barrier`] between dispatches that depend on each other.
+    pub fn dispatch(
+        &mut self,
+        executable: &Executable,
+        entry_point: u32,
+        workgroup_count: [u32; 3],
+        constants: &[u32],
+        bindings: &[&Buffer],
+    ) -> Result<&mut Self, RuntimeError> {
+        let bindings = bindings
+            .iter()
+            .map(|buffer| sys::iree_hal_buffer_ref_t {
+                buffer: buffer.ctx,
+                offset: 0,
+                length: buffer.byte_length() as sys::iree_device_size_t,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_command_buffer_dispatch, entry point: {}, workgroups: {:?}",
+                entry_point,
+                workgroup_count
+            );
+            sys::iree_hal_command_buffer_dispatch(
+                self.ctx,
+                executable.ctx,
+                entry_point as _,
+                workgroup_count.as_ptr(),
+                sys::iree_const_byte_span_t {
+                    data: constants.as_ptr() as *const u8,
+                    data_length: core::mem::size_of_val(constants),
+                },
+                sys::iree_hal_buffer_ref_list_t {
+                    count: bindings.len(),
+                    values: bindings.as_ptr(),
+                },
+                // No dispatch flags.
+                0,
+            )
+        })
+        .to_result()?;
+        Ok(self)
+    }
+
+    /// Records a barrier, so that commands recorded after it only start once the commands
+    /// recorded before it have completed, e.g. to copy a buffer after filling it.
+    pub fn execution_barrier(&mut self) -> Result<&mut Self, RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_execution_barrier");
+            sys::iree_hal_command_buffer_execution_barrier(
+                self.ctx,
+                sys::iree_hal_execution_stage_bits_t_IREE_HAL_EXECUTION_STAGE_COMMAND_RETIRE,
+                sys::iree_hal_execution_stage_bits_t_IREE_HAL_EXECUTION_STAGE_COMMAND_ISSUE,
+                sys::iree_hal_execution_barrier_flag_bits_t_IREE_HAL_EXECUTION_BARRIER_FLAG_NONE,
+                0,
+                core::ptr::null(),
+                0,
+                core::ptr::null(),
+            )
+        })
+        .to_result()?;
+        Ok(self)
+    }
+
+    /// Ends recording. No commands can be recorded afterwards.
+    pub fn end(&mut self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_command_buffer_end");
+            sys::iree_hal_command_buffer_end(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+impl Drop for CommandBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_command_buffer_release");
+            sys::iree_hal_command_buffer_release(self.ctx);
+        }
+    }
+}
+
+impl Device<'_> {
+    /// Submits recorded command buffers to the device queues selected by `queue_affinity`. They
+    /// run in order once `wait_fence` is signaled, and `signal_fence` is signaled when they have
+    /// completed. The call does not block.
+    pub fn queue_execute(
+        &self,
+        queue_affinity: u64,
+        wait_fence: Option<&Fence>,
+        signal_fence: &Fence,
+        command_buffers: &[&CommandBuffer],
+    ) -> Result<(), RuntimeError> {
+        let mut command_buffers = command_buffers
+            .iter()
+            .map(|command_buffer| command_buffer.ctx)
+            .collect::<Vec<_>>();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_device_queue_execute, count: {}",
+                command_buffers.len()
+            );
+            sys::iree_hal_device_queue_execute(
+                self.ctx,
+                queue_affinity,
+                wait_semaphore_list(wait_fence),
+                sys::iree_hal_fence_semaphore_list(signal_fence.ctx),
+                command_buffers.len(),
+                command_buffers.as_mut_ptr(),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use eerie_sys::runtime as sys;
+use log::trace;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use super::{
+    super::{
+        base::{self, ConstByteSpan, StringView},
+        error::RuntimeError,
+    },
+    Device,
+};
+
+/// How an [`ExecutableCache`] may prepare an executable.
+///
+/// Modes can be combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutableCachingMode(sys::iree_hal_executable_caching_mode_t);
+
+impl ExecutableCachingMode {
+    /// Prepares the executable with the default behavior of the driver.
+    pub const DEFAULT: Self = Self(0);
+    /// Allows the driver to cache the prepared executable outside of the process, e.g. in the
+    /// pipeline cache of the driver, so that later runs prepare it faster.
+    pub const ALLOW_PERSISTENT_CACHING: Self = Self(
+        sys::iree_hal_executable_caching_mode_bits_t_IREE_HAL_EXECUTABLE_CACHING_MODE_ALLOW_PERSISTENT_CACHING
+            as _,
+    );
+    /// Allows the driver to spend time optimizing the executable while preparing it.
+    pub const ALLOW_OPTIMIZATION: Self = Self(
+        sys::iree_hal_executable_caching_mode_bits_t_IREE_HAL_EXECUTABLE_CACHING_MODE_ALLOW_OPTIMIZATION
+            as _,
+    );
+    /// Keeps debug information, e.g. to step through the executable in a debugger.
+    pub const ENABLE_DEBUGGING: Self = Self(
+        sys::iree_hal_executable_caching_mode_bits_t_IREE_HAL_EXECUTABLE_CACHING_MODE_ENABLE_DEBUGGING
+            as _,
+    );
+    /// Keeps the instrumentation needed to profile the executable.
+    pub const ENABLE_PROFILING: Self = Self(
+        sys::iree_hal_executable_caching_mode_bits_t_IREE_HAL_EXECUTABLE_CACHING_MODE_ENABLE_PROFILING
+            as _,
+    );
+    /// Skips the verification of the executable. Only use this with trusted executables.
+    pub const DISABLE_VERIFICATION: Self = Self(
+        sys::iree_hal_executable_caching_mode_bits_t_IREE_HAL_EXECUTABLE_CACHING_MODE_DISABLE_VERIFICATION
+            as _,
+    );
+
+    /// Returns the raw mode bits.
+    pub fn bits(&self) -> sys::iree_hal_executable_caching_mode_t {
+        self.0
+    }
+
+    /// Returns true if all modes in `other` are set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for ExecutableCachingMode {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for ExecutableCachingMode {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A cache of the executables prepared for a device, e.g. compiled pipelines of a GPU driver.
+///
+/// The HAL module keeps its own cache for the executables of the modules it loads; this cache
+/// prepares executables from their binaries directly, e.g. to warm up a driver before the first
+/// inference. Drivers that support it reuse the executables prepared with
+/// [`ExecutableCachingMode::ALLOW_PERSISTENT_CACHING`] across process runs, see
+/// [`enable_persistent_driver_caches`].
+pub struct ExecutableCache {
+    ctx: *mut sys::iree_hal_executable_cache_t,
+}
+
+// Executable caches are thread-safe.
+unsafe impl Send for ExecutableCache {}
+unsafe impl Sync for ExecutableCache {}
+
+impl ExecutableCache {
+    /// Creates a cache on the given device. The identifier names the cache, e.g. after the
+    /// application, for drivers that persist it.
+    pub fn new(device: &Device, identifier: &str) -> Result<Self, RuntimeError> {
+        // Executables are prepared synchronously, so the cache is given no loop to schedule work.
+        let null_loop = sys::iree_loop_t {
+            self_: core::ptr::null_mut(),
+            ctl: Some(sys::iree_loop_null_ctl),
+        };
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_executable_cache_create, identifier: {}",
+                identifier
+            );
+            sys::iree_hal_executable_cache_create(
+                device.ctx,
+                StringView::from(identifier).ctx,
+                null_loop,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Returns true if the cache can prepare executables of the given format, e.g.
+    /// `embedded-elf-x86_64` or `vulkan-spirv-fb`, with the given mode.
+    pub fn can_prepare_format(&self, format: &str, mode: ExecutableCachingMode) -> bool {
+        unsafe {
+            trace!(
+                "iree_hal_executable_cache_can_prepare_format, format: {}",
+                format
+            );
+            sys::iree_hal_executable_cache_can_prepare_format(
+                self.ctx,
+                mode.0,
+                StringView::from(format).ctx,
+            )
+        }
+    }
+
+    /// Prepares an executable of the given format from its binary, as embedded in compiled
+    /// modules. The data is copied by the driver, so it does not need to outlive the executable.
+    pub fn prepare_executable(
+        &self,
+        format: &str,
+        data: &[u8],
+        mode: ExecutableCachingMode,
+    ) -> Result<Executable, RuntimeError> {
+        let mut params = core::mem::MaybeUninit::<sys::iree_hal_executable_params_t>::uninit();
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_executable_params_initialize");
+            sys::iree_hal_executable_params_initialize(params.as_mut_ptr());
+            let params = params.assume_init_mut();
+            params.caching_mode = mode.0;
+            params.executable_format = StringView::from(format).ctx;
+            params.executable_data = ConstByteSpan::from(data).ctx;
+            trace!(
+                "iree_hal_executable_cache_prepare_executable, format: {}, length: {}",
+                format,
+                data.len()
+            );
+            sys::iree_hal_executable_cache_prepare_executable(self.ctx, params, &mut out)
+        })
+        .to_result()?;
+        Ok(Executable { ctx: out })
+    }
+}
+
+impl Clone for ExecutableCache {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_executable_cache_retain");
+            sys::iree_hal_executable_cache_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for ExecutableCache {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_executable_cache_release");
+            sys::iree_hal_executable_cache_release(self.ctx);
+        }
+    }
+}
+
+/// An executable prepared for a device by an [`ExecutableCache`].
+pub struct Executable {
+    pub(crate) ctx: *mut sys::iree_hal_executable_t,
+}
+
+// Prepared executables are immutable.
+unsafe impl Send for Executable {}
+unsafe impl Sync for Executable {}
+
+impl Clone for Executable {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_executable_retain");
+            sys::iree_hal_executable_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Executable {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_executable_release");
+            sys::iree_hal_executable_release(self.ctx);
+        }
+    }
+}
+
+/// Points the on-disk caches of the GPU drivers at subdirectories of the given directory, so
+/// that pipelines and kernels compiled by the driver in one run are reused by the next ones. The
+/// subdirectories are created if they do not exist.
+///
+/// The HAL does not serialize its executable caches, but the drivers below it keep their own
+/// caches of compiled code, configured through environment variables: `CUDA_CACHE_PATH` for the
+/// CUDA JIT cache, `MESA_SHADER_CACHE_DIR` for the Mesa Vulkan drivers, and
+/// `__GL_SHADER_DISK_CACHE_PATH` for the NVIDIA Vulkan driver. Variables that are already set are
+/// left as is.
+///
+/// Drivers read the variables when they are loaded, so this must be called before the first
+/// device is created.
+///
+/// # Safety
+///
+/// This sets environment variables with [`std::env::set_var`], so no other thread may read or
+/// write the environment at the same time, e.g. through `std::env` or `getenv` in C code. Call
+/// it at the start of `main`, before spawning threads.
+#[cfg(feature = "std")]
+pub unsafe fn enable_persistent_driver_caches(dir: &Path) -> std::io::Result<()> {
+    for (variable, subdirectory) in [
+        ("CUDA_CACHE_PATH", "cuda"),
+        ("MESA_SHADER_CACHE_DIR", "mesa"),
+        ("__GL_SHADER_DISK_CACHE_PATH", "nvidia"),
+    ] {
+        if std::env::var_os(variable).is_none() {
+            let path = dir.join(subdirectory);
+            std::fs::create_dir_all(&path)?;
+            trace!("set {}", variable);
+            std::env::set_var(variable, path);
+        }
+    }
+    if std::env::var_os("__GL_SHADER_DISK_CACHE").is_none() {
+        std::env::set_var("__GL_SHADER_DISK_CACHE", "1");
+    }
+    Ok(())
+}
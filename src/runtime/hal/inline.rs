@@ -0,0 +1,101 @@
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{
+        api::{Instance, Session, SessionBuiltins, SessionOptions},
+        base,
+        error::RuntimeError,
+        vm::Module,
+    },
+    Device,
+};
+
+// Upper bound of executable loaders a runtime can be built with.
+const MAX_EXECUTABLE_LOADERS: usize = 8;
+
+/// Creates the `hal_inline` module, a lightweight HAL that runs modules compiled with
+/// `--iree-execution-model=inline-static` or `inline-dynamic` synchronously on the host. Buffers
+/// are allocated from the allocator of the given device.
+pub fn create_hal_inline_module<'a>(
+    instance: &'a Instance,
+    device: &Device,
+) -> Result<Module<'a>, RuntimeError> {
+    let mut out = core::ptr::null_mut();
+    base::Status::from_raw(unsafe {
+        trace!("iree_hal_inline_module_create");
+        sys::iree_hal_inline_module_create(
+            instance.get_vm_instance(),
+            sys::iree_hal_inline_module_flag_bits_t_IREE_HAL_INLINE_MODULE_FLAG_NONE,
+            sys::iree_hal_device_allocator(device.ctx),
+            instance.get_host_allocator().ctx,
+            &mut out,
+        )
+    })
+    .to_result()?;
+    Ok(Module { ctx: out, instance })
+}
+
+/// Creates the `hal_loader` module, which loads the executables of modules compiled with
+/// `--iree-execution-model=inline-dynamic` with every executable loader the runtime was built
+/// with, e.g. embedded ELF and VMVX. It imports from `hal_inline`.
+pub fn create_hal_loader_module(instance: &Instance) -> Result<Module<'_>, RuntimeError> {
+    let host_allocator = instance.get_host_allocator();
+    let mut loaders = [core::ptr::null_mut(); MAX_EXECUTABLE_LOADERS];
+    let mut loader_count = 0;
+    base::Status::from_raw(unsafe {
+        trace!("iree_hal_create_all_available_executable_loaders");
+        sys::iree_hal_create_all_available_executable_loaders(
+            core::ptr::null_mut(),
+            loaders.len(),
+            &mut loader_count,
+            loaders.as_mut_ptr(),
+            host_allocator.ctx,
+        )
+    })
+    .to_result()?;
+    let mut out = core::ptr::null_mut();
+    let status = base::Status::from_raw(unsafe {
+        trace!(
+            "iree_hal_loader_module_create, loader count: {}",
+            loader_count
+        );
+        sys::iree_hal_loader_module_create(
+            instance.get_vm_instance(),
+            sys::iree_hal_loader_module_flag_bits_t_IREE_HAL_LOADER_MODULE_FLAG_NONE,
+            loader_count,
+            loaders.as_mut_ptr(),
+            host_allocator.ctx,
+            &mut out,
+        )
+    })
+    .to_result();
+    // The module retains the loaders it uses.
+    for loader in loaders.iter().take(loader_count) {
+        unsafe {
+            trace!("iree_hal_executable_loader_release");
+            sys::iree_hal_executable_loader_release(*loader);
+        }
+    }
+    status?;
+    Ok(Module { ctx: out, instance })
+}
+
+impl<'a> Session<'a> {
+    /// Creates a session that runs modules on the inline HAL instead of the full HAL, see
+    /// [`create_hal_inline_module`] and [`create_hal_loader_module`].
+    ///
+    /// The builtin modules selected in the options are ignored, as the full HAL module cannot be
+    /// registered alongside the inline one. The device only provides the buffer allocator.
+    pub fn create_with_inline_hal(
+        instance: &'a Instance,
+        options: &SessionOptions,
+        device: &'a Device,
+    ) -> Result<Self, RuntimeError> {
+        let options = options.clone().builtin_modules(SessionBuiltins::NONE);
+        let session = Self::create_with_device(instance, &options, device)?;
+        session.append_module(&create_hal_inline_module(instance, device)?)?;
+        session.append_module(&create_hal_loader_module(instance)?)?;
+        Ok(session)
+    }
+}
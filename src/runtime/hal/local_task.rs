@@ -0,0 +1,209 @@
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{api::Instance, base, error::RuntimeError},
+    Device,
+};
+
+// Upper bound of executable loaders a runtime can be built with.
+const MAX_EXECUTABLE_LOADERS: usize = 8;
+
+/// Parameters of a `local-task` device created with [`Instance::create_local_task_device`]: the
+/// number of worker threads, their stack and local memory sizes, and the CPUs they are pinned to.
+///
+/// The default device of the driver sizes its worker pool from the flags of the runtime, which
+/// is rarely what a server wants, e.g. when several models share a machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalTaskDeviceParams {
+    worker_count: Option<usize>,
+    worker_stack_size: Option<usize>,
+    worker_local_memory_size: Option<usize>,
+    cpu_affinity: Vec<u32>,
+}
+
+impl LocalTaskDeviceParams {
+    /// Creates parameters with one unpinned worker per logical CPU and the default stack and
+    /// local memory sizes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of worker threads. Defaults to the number of CPUs of the affinity set, or
+    /// to the number of logical CPUs if no affinity is set.
+    pub fn worker_count(mut self, worker_count: usize) -> Self {
+        self.worker_count = Some(worker_count);
+        self
+    }
+
+    /// Sets the minimum stack size of the worker threads in bytes.
+    pub fn worker_stack_size(mut self, size: usize) -> Self {
+        self.worker_stack_size = Some(size);
+        self
+    }
+
+    /// Sets the size in bytes of the scratch memory each worker provides to the dispatches it
+    /// runs.
+    pub fn worker_local_memory_size(mut self, size: usize) -> Self {
+        self.worker_local_memory_size = Some(size);
+        self
+    }
+
+    /// Pins the workers to the given logical CPUs, one worker per CPU in order, e.g. the cores of
+    /// a single NUMA node. If there are more workers than CPUs, the CPUs are reused in order.
+    pub fn cpu_affinity(mut self, cpus: &[u32]) -> Self {
+        self.cpu_affinity = cpus.to_vec();
+        self
+    }
+
+    // Returns the number of workers to create.
+    fn resolved_worker_count(&self) -> usize {
+        match (self.worker_count, self.cpu_affinity.len()) {
+            (Some(count), _) => count,
+            (None, 0) => std::thread::available_parallelism().map_or(1, |count| count.get()),
+            (None, cpus) => cpus,
+        }
+    }
+}
+
+impl Instance {
+    /// Creates a `local-task` device with the given worker parameters, that loads executables
+    /// with every executable loader the runtime was built with. Buffers are allocated from the
+    /// heap of the host allocator.
+    pub fn create_local_task_device(
+        &self,
+        params: &LocalTaskDeviceParams,
+    ) -> Result<Device<'_>, RuntimeError> {
+        let host_allocator = self.get_host_allocator();
+        let worker_count = params.resolved_worker_count();
+
+        let mut topology = core::mem::MaybeUninit::<sys::iree_task_topology_t>::uninit();
+        unsafe {
+            trace!("iree_task_topology_initialize");
+            sys::iree_task_topology_initialize(topology.as_mut_ptr());
+        }
+        // Every group of the topology becomes a worker.
+        let mut status = Ok(());
+        for idx in 0..worker_count {
+            let mut group = core::mem::MaybeUninit::<sys::iree_task_topology_group_t>::uninit();
+            status = base::Status::from_raw(unsafe {
+                trace!("iree_task_topology_group_initialize, index: {}", idx);
+                sys::iree_task_topology_group_initialize(idx as u8, group.as_mut_ptr());
+                let group = group.assume_init_mut();
+                if !params.cpu_affinity.is_empty() {
+                    let cpu = params.cpu_affinity[idx % params.cpu_affinity.len()];
+                    group.processor_index = cpu;
+                    group.ideal_thread_affinity.set_specified(1);
+                    group.ideal_thread_affinity.set_id(cpu);
+                }
+                trace!("iree_task_topology_push_group");
+                sys::iree_task_topology_push_group(topology.as_mut_ptr(), group)
+            })
+            .to_result();
+            if status.is_err() {
+                break;
+            }
+        }
+
+        let mut executor = core::ptr::null_mut();
+        if status.is_ok() {
+            let mut options = core::mem::MaybeUninit::<sys::iree_task_executor_options_t>::uninit();
+            status = base::Status::from_raw(unsafe {
+                trace!("iree_task_executor_options_initialize");
+                sys::iree_task_executor_options_initialize(options.as_mut_ptr());
+                let options = options.assume_init_mut();
+                if let Some(size) = params.worker_stack_size {
+                    options.worker_stack_size = size as _;
+                }
+                if let Some(size) = params.worker_local_memory_size {
+                    options.worker_local_memory_size = size as _;
+                }
+                trace!("iree_task_executor_create, worker count: {}", worker_count);
+                sys::iree_task_executor_create(
+                    *options,
+                    topology.as_ptr(),
+                    host_allocator.ctx,
+                    &mut executor,
+                )
+            })
+            .to_result();
+        }
+        // The executor copies the topology.
+        unsafe {
+            trace!("iree_task_topology_deinitialize");
+            sys::iree_task_topology_deinitialize(topology.as_mut_ptr());
+        }
+        status?;
+
+        let mut loaders = [core::ptr::null_mut(); MAX_EXECUTABLE_LOADERS];
+        let mut loader_count = 0;
+        let mut device_allocator = core::ptr::null_mut();
+        let mut out = core::ptr::null_mut();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_hal_create_all_available_executable_loaders");
+            sys::iree_hal_create_all_available_executable_loaders(
+                core::ptr::null_mut(),
+                loaders.len(),
+                &mut loader_count,
+                loaders.as_mut_ptr(),
+                host_allocator.ctx,
+            )
+        })
+        .to_result()
+        .and_then(|()| {
+            base::Status::from_raw(unsafe {
+                trace!("iree_hal_allocator_create_heap");
+                sys::iree_hal_allocator_create_heap(
+                    base::StringView::from("local").ctx,
+                    host_allocator.ctx,
+                    host_allocator.ctx,
+                    &mut device_allocator,
+                )
+            })
+            .to_result()
+        })
+        .and_then(|()| {
+            let mut device_params =
+                core::mem::MaybeUninit::<sys::iree_hal_task_device_params_t>::uninit();
+            let executors = [executor];
+            base::Status::from_raw(unsafe {
+                trace!("iree_hal_task_device_params_initialize");
+                sys::iree_hal_task_device_params_initialize(device_params.as_mut_ptr());
+                trace!(
+                    "iree_hal_task_device_create, loader count: {}",
+                    loader_count
+                );
+                sys::iree_hal_task_device_create(
+                    base::StringView::from(super::drivers::LOCAL_TASK).ctx,
+                    device_params.as_ptr(),
+                    executors.len(),
+                    executors.as_ptr(),
+                    loader_count,
+                    loaders.as_mut_ptr(),
+                    device_allocator,
+                    host_allocator.ctx,
+                    &mut out,
+                )
+            })
+            .to_result()
+        });
+        // The device retains its executor, loaders and allocator.
+        unsafe {
+            for loader in loaders.iter().take(loader_count) {
+                trace!("iree_hal_executable_loader_release");
+                sys::iree_hal_executable_loader_release(*loader);
+            }
+            if !device_allocator.is_null() {
+                trace!("iree_hal_allocator_release");
+                sys::iree_hal_allocator_release(device_allocator);
+            }
+            trace!("iree_task_executor_release");
+            sys::iree_task_executor_release(executor);
+        }
+        status?;
+        Ok(Device {
+            ctx: out,
+            marker: core::marker::PhantomData,
+        })
+    }
+}
@@ -0,0 +1,172 @@
+extern crate alloc;
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use eerie_sys::runtime as sys;
+use log::trace;
+use std::path::Path;
+
+#[cfg(feature = "parameters")]
+use super::super::io::ParameterIndex;
+use super::{
+    super::{api::Session, error::RuntimeError, io::FileHandle},
+    BufferView, ElementType, EncodingType, ToElementType,
+};
+
+/// A named tensor of a [`SafeTensors`] archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorInfo {
+    pub name: String,
+    pub element_type: ElementType,
+    pub shape: Vec<usize>,
+    offset: usize,
+    length: usize,
+}
+
+/// A memory mapped `.safetensors` archive.
+///
+/// Tensors are imported into a session's device allocator as [`BufferView`]s that point into the
+/// mapping, so their contents are never copied.
+pub struct SafeTensors {
+    file_handle: FileHandle,
+    name: String,
+    tensors: Vec<TensorInfo>,
+}
+
+impl SafeTensors {
+    /// Maps the archive at the given path into memory and reads its header.
+    pub fn open(path: &Path) -> Result<Self, RuntimeError> {
+        let file_handle = FileHandle::open_mapped(path)?;
+        Self::from_file_handle(&path.to_string_lossy(), file_handle)
+    }
+
+    /// Reads the header of the archive held by the given file handle. The name is used in error
+    /// messages and to select the archive format when building a parameter index.
+    pub fn from_file_handle(name: &str, file_handle: FileHandle) -> Result<Self, RuntimeError> {
+        let (header_length, metadata) =
+            ::safetensors::SafeTensors::read_metadata(file_handle.contents()).map_err(|err| {
+                RuntimeError::InvalidArchive(error_message!(
+                    "{} has an invalid header: {:?}",
+                    name,
+                    err
+                ))
+            })?;
+        // Tensor data follows the 8 byte header length and the header itself.
+        let data_start = 8 + header_length;
+        let mut tensors = metadata
+            .tensors()
+            .into_iter()
+            .map(|(tensor_name, info)| TensorInfo {
+                element_type: to_element_type(info.dtype),
+                shape: info.shape.clone(),
+                offset: data_start + info.data_offsets.0,
+                length: info.data_offsets.1 - info.data_offsets.0,
+                name: tensor_name,
+            })
+            .collect::<Vec<_>>();
+        tensors.sort_by_key(|tensor| tensor.offset);
+        trace!("{} holds {} tensors", name, tensors.len());
+        Ok(Self {
+            file_handle,
+            name: String::from(name),
+            tensors,
+        })
+    }
+
+    /// Returns the tensors of the archive, in the order they are stored.
+    pub fn tensors(&self) -> &[TensorInfo] {
+        &self.tensors
+    }
+
+    /// Returns the tensor with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&TensorInfo> {
+        self.tensors.iter().find(|tensor| tensor.name == name)
+    }
+
+    /// Returns the raw contents of the tensor with the given name, if any.
+    pub fn data(&self, name: &str) -> Option<&[u8]> {
+        self.get(name).map(|tensor| {
+            &self.file_handle.contents()[tensor.offset..tensor.offset + tensor.length]
+        })
+    }
+
+    /// Imports the tensor with the given name into the session's device allocator without
+    /// copying it. The mapping is kept alive until the runtime releases the buffer.
+    ///
+    /// The element type of the tensor must match `T`. The buffer is read-only, and devices that
+    /// cannot access host memory directly return an error.
+    pub fn buffer_view<'a, T: ToElementType>(
+        &self,
+        session: &'a Session,
+        name: &str,
+    ) -> Result<BufferView<'a, T>, RuntimeError> {
+        unsafe extern "C" fn release(
+            user_data: *mut core::ffi::c_void,
+            _: *mut sys::iree_hal_buffer_t,
+        ) {
+            trace!("Releasing safetensors archive...");
+            drop(Box::from_raw(user_data as *mut FileHandle));
+        }
+
+        let tensor = self.get(name).ok_or_else(|| {
+            RuntimeError::InvalidArchive(error_message!(
+                "{} has no tensor named {:?}",
+                self.name,
+                name
+            ))
+        })?;
+        if tensor.element_type != T::to_element_type() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "tensor {:?} has element type {:?}, not {:?}",
+                name,
+                tensor.element_type,
+                T::to_element_type()
+            )));
+        }
+        let ptr = self.file_handle.contents()[tensor.offset..].as_ptr();
+        unsafe {
+            BufferView::import_host_allocation(
+                session,
+                &tensor.shape,
+                EncodingType::DenseRowMajor,
+                ptr as *mut core::ffi::c_void,
+                tensor.length,
+                sys::iree_hal_memory_access_bits_t_IREE_HAL_MEMORY_ACCESS_READ as u16,
+                sys::iree_hal_buffer_release_callback_t {
+                    fn_: Some(release),
+                    user_data: Box::into_raw(Box::new(self.file_handle.clone()))
+                        as *mut core::ffi::c_void,
+                },
+            )
+        }
+    }
+
+    /// Returns a parameter index holding the tensors of the archive, to serve them to modules
+    /// compiled with external parameters.
+    #[cfg(feature = "parameters")]
+    pub fn parameter_index(&self) -> Result<ParameterIndex, RuntimeError> {
+        let index = ParameterIndex::new()?;
+        index.add_archive(&self.name, &self.file_handle)?;
+        Ok(index)
+    }
+}
+
+fn to_element_type(dtype: ::safetensors::Dtype) -> ElementType {
+    use ::safetensors::Dtype;
+    match dtype {
+        Dtype::BOOL => ElementType::Bool8,
+        Dtype::U8 => ElementType::Uint8,
+        Dtype::I8 => ElementType::Sint8,
+        Dtype::U16 => ElementType::Uint16,
+        Dtype::I16 => ElementType::Sint16,
+        Dtype::F16 => ElementType::Float16,
+        Dtype::BF16 => ElementType::BFloat16,
+        Dtype::U32 => ElementType::Uint32,
+        Dtype::I32 => ElementType::Sint32,
+        Dtype::F32 => ElementType::Float32,
+        Dtype::U64 => ElementType::Uint64,
+        Dtype::I64 => ElementType::Sint64,
+        Dtype::F64 => ElementType::Float64,
+        // E.g. 8 bit floats, which have no HAL element type here.
+        _ => ElementType::None,
+    }
+}
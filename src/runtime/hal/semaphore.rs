@@ -0,0 +1,616 @@
+use core::time::Duration;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+use core::{
+    future::{Future, IntoFuture},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+extern crate alloc;
+use alloc::vec::Vec;
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{
+        api::Instance,
+        base::{self, StatusErrorKind},
+        error::RuntimeError,
+        vm::{Ref, ToRef},
+    },
+    Buffer, Device,
+};
+
+/// A timeout used when waiting on semaphores and fences.
+pub enum Timeout {
+    /// Does not block, and fails with `DeadlineExceeded` if the wait is not yet satisfied.
+    Immediate,
+    /// Blocks for at most the given duration.
+    Relative(Duration),
+    /// Blocks until the wait is satisfied.
+    Infinite,
+}
+
+impl From<Timeout> for sys::iree_timeout_t {
+    fn from(timeout: Timeout) -> Self {
+        match timeout {
+            Timeout::Immediate => sys::iree_timeout_t {
+                type_: sys::iree_timeout_type_e_IREE_TIMEOUT_ABSOLUTE,
+                nanos: i64::MIN,
+            },
+            Timeout::Relative(duration) => sys::iree_timeout_t {
+                type_: sys::iree_timeout_type_e_IREE_TIMEOUT_RELATIVE,
+                nanos: duration.as_nanos().min(i64::MAX as u128) as i64,
+            },
+            Timeout::Infinite => sys::iree_timeout_t {
+                type_: sys::iree_timeout_type_e_IREE_TIMEOUT_ABSOLUTE,
+                nanos: i64::MAX,
+            },
+        }
+    }
+}
+
+/// A HAL timeline semaphore.
+///
+/// A semaphore holds a monotonically increasing 64-bit payload. Device and host work can wait
+/// until the payload reaches a given value, and signal it to a new value once complete.
+pub struct Semaphore {
+    pub(crate) ctx: *mut sys::iree_hal_semaphore_t,
+}
+
+// Semaphores are thread-safe.
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    /// Creates a new semaphore on the given device with the given initial value.
+    pub fn new(device: &Device, initial_value: u64) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_semaphore_create, initial_value: {}",
+                initial_value
+            );
+            sys::iree_hal_semaphore_create(device.ctx, initial_value, &mut out)
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Returns the current payload value of the semaphore. Returns an error if the semaphore has
+    /// failed.
+    pub fn query(&self) -> Result<u64, RuntimeError> {
+        let mut value = 0;
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_semaphore_query");
+            sys::iree_hal_semaphore_query(self.ctx, &mut value)
+        })
+        .to_result()?;
+        Ok(value)
+    }
+
+    /// Signals the semaphore to the given payload value from the host. The value must be greater
+    /// than the current value.
+    pub fn signal(&self, value: u64) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_semaphore_signal, value: {}", value);
+            sys::iree_hal_semaphore_signal(self.ctx, value)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Marks the semaphore as failed with the given status code. All current and future waiters
+    /// will receive the failure.
+    pub fn fail(&self, kind: StatusErrorKind) {
+        unsafe {
+            trace!("iree_hal_semaphore_fail");
+            sys::iree_hal_semaphore_fail(self.ctx, base::status_from_kind(kind));
+        }
+    }
+
+    /// Blocks the calling thread until the semaphore reaches the given value or the timeout
+    /// elapses.
+    pub fn wait(&self, value: u64, timeout: Timeout) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_semaphore_wait, value: {}", value);
+            sys::iree_hal_semaphore_wait(self.ctx, value, timeout.into())
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+/// Whether a wait on multiple semaphores is satisfied by all or any of them.
+pub enum WaitMode {
+    /// The wait is satisfied once every semaphore reaches its value.
+    All,
+    /// The wait is satisfied once any semaphore reaches its value.
+    Any,
+}
+
+impl From<WaitMode> for sys::iree_hal_wait_mode_t {
+    fn from(mode: WaitMode) -> Self {
+        match mode {
+            WaitMode::All => sys::iree_hal_wait_mode_e_IREE_HAL_WAIT_MODE_ALL,
+            WaitMode::Any => sys::iree_hal_wait_mode_e_IREE_HAL_WAIT_MODE_ANY,
+        }
+    }
+}
+
+impl Device<'_> {
+    /// Blocks the calling thread until all or any of the given semaphores reach their values, or
+    /// the timeout elapses. Unlike waiting on the whole device, this only waits for the given
+    /// work, so other submissions can keep running.
+    pub fn wait_semaphores(
+        &self,
+        mode: WaitMode,
+        timepoints: &[(&Semaphore, u64)],
+        timeout: Timeout,
+    ) -> Result<(), RuntimeError> {
+        let mut semaphores = timepoints
+            .iter()
+            .map(|(semaphore, _)| semaphore.ctx)
+            .collect::<Vec<_>>();
+        let mut values = timepoints
+            .iter()
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_device_wait_semaphores, count: {}",
+                timepoints.len()
+            );
+            sys::iree_hal_device_wait_semaphores(
+                self.ctx,
+                mode.into(),
+                sys::iree_hal_semaphore_list_t {
+                    count: timepoints.len(),
+                    semaphores: semaphores.as_mut_ptr(),
+                    payload_values: values.as_mut_ptr(),
+                },
+                timeout.into(),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+/// A queue affinity that allows the device to pick any of its queues.
+pub const QUEUE_AFFINITY_ANY: u64 = u64::MAX;
+
+impl Device<'_> {
+    /// Enqueues a barrier on the device queues selected by `queue_affinity` (a bitmask of queue
+    /// ordinals, or [`QUEUE_AFFINITY_ANY`]). Once `wait_fence` is signaled, the device signals
+    /// `signal_fence`. The call does not block. Combined with the fences of async invocations,
+    /// barriers order work across queues without synchronizing the whole device.
+    pub fn queue_barrier(
+        &self,
+        queue_affinity: u64,
+        wait_fence: Option<&Fence>,
+        signal_fence: &Fence,
+    ) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_queue_barrier");
+            sys::iree_hal_device_queue_barrier(
+                self.ctx,
+                queue_affinity,
+                wait_semaphore_list(wait_fence),
+                sys::iree_hal_fence_semaphore_list(signal_fence.ctx),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Enqueues the allocation of a transient device local buffer of the given size on the
+    /// device queues selected by `queue_affinity`. The buffer is returned right away, but may only
+    /// be used by work ordered after `signal_fence` is signaled, which happens once `wait_fence`
+    /// is signaled and the memory is available. The call does not block.
+    ///
+    /// Release the memory in queue order with [`Device::queue_dealloca`].
+    pub fn queue_alloca(
+        &self,
+        queue_affinity: u64,
+        wait_fence: Option<&Fence>,
+        signal_fence: &Fence,
+        allocation_size: usize,
+    ) -> Result<Buffer, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_queue_alloca, size: {}", allocation_size);
+            sys::iree_hal_device_queue_alloca(
+                self.ctx,
+                queue_affinity,
+                wait_semaphore_list(wait_fence),
+                sys::iree_hal_fence_semaphore_list(signal_fence.ctx),
+                sys::iree_hal_allocator_pool_bits_t_IREE_HAL_ALLOCATOR_POOL_DEFAULT,
+                sys::iree_hal_buffer_params_t {
+                    usage: sys::iree_hal_buffer_usage_bits_t_IREE_HAL_BUFFER_USAGE_DEFAULT,
+                    access: 0,
+                    type_: sys::iree_hal_memory_type_bits_t_IREE_HAL_MEMORY_TYPE_DEVICE_LOCAL,
+                    queue_affinity,
+                    min_alignment: 0,
+                },
+                allocation_size as sys::iree_device_size_t,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Buffer { ctx: out })
+    }
+
+    /// Enqueues the deallocation of a buffer allocated with [`Device::queue_alloca`]. The memory
+    /// is released once `wait_fence` is signaled, i.e. after the work using the buffer, and
+    /// `signal_fence` is signaled afterwards. The call does not block.
+    pub fn queue_dealloca(
+        &self,
+        queue_affinity: u64,
+        wait_fence: Option<&Fence>,
+        signal_fence: &Fence,
+        buffer: &Buffer,
+    ) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_device_queue_dealloca");
+            sys::iree_hal_device_queue_dealloca(
+                self.ctx,
+                queue_affinity,
+                wait_semaphore_list(wait_fence),
+                sys::iree_hal_fence_semaphore_list(signal_fence.ctx),
+                buffer.ctx,
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+// Returns the timepoints of the given fence, or an empty list to not wait at all.
+pub(crate) fn wait_semaphore_list(wait_fence: Option<&Fence>) -> sys::iree_hal_semaphore_list_t {
+    match wait_fence {
+        Some(wait_fence) => unsafe { sys::iree_hal_fence_semaphore_list(wait_fence.ctx) },
+        None => sys::iree_hal_semaphore_list_t {
+            count: 0,
+            semaphores: core::ptr::null_mut(),
+            payload_values: core::ptr::null_mut(),
+        },
+    }
+}
+
+impl Clone for Semaphore {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_semaphore_retain");
+            sys::iree_hal_semaphore_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_semaphore_release");
+            sys::iree_hal_semaphore_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> ToRef<'a> for Semaphore {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("hal.semaphore".into())
+    }
+}
+
+impl<'a> Ref<'a, Semaphore> {
+    /// Returns the semaphore held by the Ref, which is retained. Returns
+    /// `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `hal.semaphore`.
+    pub fn to_semaphore(&self) -> Result<Semaphore, RuntimeError> {
+        self.check_type("hal.semaphore")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_hal_semaphore_t;
+        unsafe {
+            trace!("iree_hal_semaphore_retain");
+            sys::iree_hal_semaphore_retain(ctx);
+        }
+        Ok(Semaphore { ctx })
+    }
+}
+
+pub(crate) fn wrap_retain<'a, T: ToRef<'a>>(
+    ptr: *mut core::ffi::c_void,
+    instance: &'a Instance,
+    ref_type: sys::iree_vm_ref_type_t,
+) -> Result<Ref<'a, T>, RuntimeError> {
+    let mut out = core::mem::MaybeUninit::<sys::iree_vm_ref_t>::zeroed();
+    base::Status::from_raw(unsafe {
+        trace!("iree_vm_ref_wrap_retain");
+        sys::iree_vm_ref_wrap_retain(ptr, ref_type, out.as_mut_ptr())
+    })
+    .to_result()?;
+    Ok(Ref {
+        ctx: unsafe { out.assume_init() },
+        _instance: instance,
+        _marker: core::marker::PhantomData,
+    })
+}
+
+/// A HAL fence.
+///
+/// A fence is a set of semaphore timepoints that is considered signaled once every semaphore has
+/// reached its payload value. Fences are used as the wait and signal arguments of functions
+/// compiled with the async-external execution model.
+pub struct Fence {
+    pub(crate) ctx: *mut sys::iree_hal_fence_t,
+}
+
+// Fences are thread-safe.
+unsafe impl Send for Fence {}
+unsafe impl Sync for Fence {}
+
+impl Fence {
+    /// Creates an empty fence with room for the given number of timepoints. An empty fence is
+    /// always signaled.
+    pub fn new(capacity: usize) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_create, capacity: {}", capacity);
+            sys::iree_hal_fence_create(capacity, base::Allocator::get_global().ctx, &mut out)
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Creates a fence that is signaled once all of the given fences are signaled.
+    pub fn join(fences: &[&Fence]) -> Result<Self, RuntimeError> {
+        let mut ptrs = fences.iter().map(|fence| fence.ctx).collect::<Vec<_>>();
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_join, count: {}", ptrs.len());
+            sys::iree_hal_fence_join(
+                ptrs.len(),
+                ptrs.as_mut_ptr(),
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Creates a fence with a single timepoint at the given semaphore value.
+    pub fn at(semaphore: &Semaphore, value: u64) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_create_at, value: {}", value);
+            sys::iree_hal_fence_create_at(
+                semaphore.ctx,
+                value,
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Creates a fence from the given semaphore timepoints, e.g. ones exported from another fence
+    /// with [`Fence::timepoints`].
+    pub fn from_timepoints(timepoints: &[(&Semaphore, u64)]) -> Result<Self, RuntimeError> {
+        let mut fence = Self::new(timepoints.len())?;
+        for (semaphore, value) in timepoints {
+            fence.insert(semaphore, *value)?;
+        }
+        Ok(fence)
+    }
+
+    /// Returns the semaphore timepoints of the fence, so they can be waited on or signaled
+    /// individually, or handed to an external pipeline.
+    pub fn timepoints(&self) -> Vec<(Semaphore, u64)> {
+        unsafe {
+            trace!("iree_hal_fence_semaphore_list");
+            let list = sys::iree_hal_fence_semaphore_list(self.ctx);
+            if list.count == 0 {
+                return Vec::new();
+            }
+            core::slice::from_raw_parts(list.semaphores, list.count)
+                .iter()
+                .zip(core::slice::from_raw_parts(list.payload_values, list.count))
+                .map(|(&ctx, &value)| {
+                    trace!("iree_hal_semaphore_retain");
+                    sys::iree_hal_semaphore_retain(ctx);
+                    (Semaphore { ctx }, value)
+                })
+                .collect()
+        }
+    }
+
+    /// Inserts a timepoint into the fence. If the semaphore is already in the fence, the larger of
+    /// the two values is kept.
+    pub fn insert(&mut self, semaphore: &Semaphore, value: u64) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_insert, value: {}", value);
+            sys::iree_hal_fence_insert(self.ctx, semaphore.ctx, value)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Returns the number of timepoints in the fence.
+    pub fn timepoint_count(&self) -> usize {
+        unsafe {
+            trace!("iree_hal_fence_timepoint_count");
+            sys::iree_hal_fence_timepoint_count(self.ctx)
+        }
+    }
+
+    /// Signals all timepoints of the fence from the host.
+    pub fn signal(&self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_signal");
+            sys::iree_hal_fence_signal(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Marks all semaphores of the fence as failed with the given status code.
+    pub fn fail(&self, kind: StatusErrorKind) {
+        unsafe {
+            trace!("iree_hal_fence_fail");
+            sys::iree_hal_fence_fail(self.ctx, base::status_from_kind(kind));
+        }
+    }
+
+    /// Returns true if all timepoints of the fence have been reached. Returns an error if any of
+    /// the semaphores has failed.
+    pub fn is_signaled(&self) -> Result<bool, RuntimeError> {
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_query");
+            sys::iree_hal_fence_query(self.ctx)
+        });
+        if matches!(status.kind(), StatusErrorKind::Deferred) {
+            return Ok(false);
+        }
+        status.to_result()?;
+        Ok(true)
+    }
+
+    /// Blocks the calling thread until the fence is signaled or the timeout elapses.
+    pub fn wait(&self, timeout: Timeout) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_fence_wait");
+            sys::iree_hal_fence_wait(self.ctx, timeout.into())
+        })
+        .to_result()?;
+        Ok(())
+    }
+}
+
+impl Clone for Fence {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_hal_fence_retain");
+            sys::iree_hal_fence_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_fence_release");
+            sys::iree_hal_fence_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> ToRef<'a> for Fence {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("hal.fence".into())
+    }
+}
+
+impl<'a> Ref<'a, Fence> {
+    /// Returns the fence held by the Ref, which is retained, e.g. a fence returned by a function.
+    /// Returns `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `hal.fence`.
+    pub fn to_fence(&self) -> Result<Fence, RuntimeError> {
+        self.check_type("hal.fence")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_hal_fence_t;
+        unsafe {
+            trace!("iree_hal_fence_retain");
+            sys::iree_hal_fence_retain(ctx);
+        }
+        Ok(Fence { ctx })
+    }
+}
+
+/// A future that resolves once a fence is signaled.
+///
+/// The first poll that finds the fence pending starts a thread that blocks in [`Fence::wait`] and
+/// wakes the task once the wait returns, so the executor is not kept busy. The thread lives until
+/// the fence is signaled or fails, even if the future is dropped before.
+///
+/// Fences can only be awaited where threads are available, so not in no-std or WebAssembly
+/// builds. There, check them with [`Fence::is_signaled`] from the event loop of the application,
+/// or drive calls with [`Call::poll`](super::super::api::Call::poll).
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub struct FenceFuture {
+    fence: Fence,
+    waiter: Option<std::sync::Arc<std::sync::Mutex<FenceWaiter>>>,
+}
+
+// The state shared between a FenceFuture and its waiter thread.
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+#[derive(Default)]
+struct FenceWaiter {
+    result: Option<Result<(), RuntimeError>>,
+    waker: Option<core::task::Waker>,
+}
+
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+impl Future for FenceFuture {
+    type Output = Result<(), RuntimeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.fence.is_signaled() {
+            Ok(true) => return Poll::Ready(Ok(())),
+            Ok(false) => {}
+            Err(err) => return Poll::Ready(Err(err)),
+        }
+        let this = &mut *self;
+        let fence = &this.fence;
+        let waiter = this.waiter.get_or_insert_with(|| {
+            let fence = fence.clone();
+            let waiter = std::sync::Arc::new(std::sync::Mutex::new(FenceWaiter::default()));
+            let shared = waiter.clone();
+            std::thread::spawn(move || {
+                let result = fence.wait(Timeout::Infinite);
+                let mut shared = shared.lock().unwrap();
+                shared.result = Some(result);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            });
+            waiter
+        });
+        let mut waiter = waiter.lock().unwrap();
+        match waiter.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                waiter.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+impl IntoFuture for Fence {
+    type Output = Result<(), RuntimeError>;
+    type IntoFuture = FenceFuture;
+
+    fn into_future(self) -> Self::IntoFuture {
+        FenceFuture {
+            fence: self,
+            waiter: None,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{api::Instance, base, error::RuntimeError},
+    Device,
+};
+
+/// The query function of a static executable library, generated by the compiler next to the
+/// library when it is built with `--iree-llvmcpu-link-static`. Declare it as an `extern "C"`
+/// function with the name from the generated header, e.g. `simple_mul_dispatch_0_library_query`,
+/// and pass it as `Some(query_fn)`.
+pub type LibraryQueryFn = sys::iree_hal_executable_library_query_fn_t;
+
+/// An executable loader that serves executables linked into the application, instead of loading
+/// them from the module at runtime. This is how modules run with compiled code on bare-metal
+/// targets, see [`Instance::create_static_library_device`].
+pub struct StaticLibraryLoader {
+    pub(crate) ctx: *mut sys::iree_hal_executable_loader_t,
+}
+
+impl StaticLibraryLoader {
+    /// Creates a loader for the given static libraries. Executables of modules are matched to
+    /// the libraries by name when the modules are loaded.
+    pub fn new(instance: &Instance, libraries: &[LibraryQueryFn]) -> Result<Self, RuntimeError> {
+        // The static libraries are self-contained, so the null import provider is used.
+        let import_provider: sys::iree_hal_executable_import_provider_t =
+            unsafe { core::mem::zeroed() };
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_hal_static_library_loader_create, library count: {}",
+                libraries.len()
+            );
+            sys::iree_hal_static_library_loader_create(
+                libraries.len(),
+                libraries.as_ptr(),
+                import_provider,
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+}
+
+impl Drop for StaticLibraryLoader {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_hal_executable_loader_release");
+            sys::iree_hal_executable_loader_release(self.ctx);
+        }
+    }
+}
+
+impl Instance {
+    /// Creates a `local-sync` device that runs executables with the given static library loader.
+    /// Buffers are allocated from the heap of the host allocator. The device retains the loader.
+    pub fn create_static_library_device(
+        &self,
+        loader: &StaticLibraryLoader,
+    ) -> Result<Device<'_>, RuntimeError> {
+        let host_allocator = self.get_host_allocator();
+        let mut device_allocator = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_hal_allocator_create_heap");
+            sys::iree_hal_allocator_create_heap(
+                base::StringView::from("local").ctx,
+                host_allocator.ctx,
+                host_allocator.ctx,
+                &mut device_allocator,
+            )
+        })
+        .to_result()?;
+
+        let mut params = core::mem::MaybeUninit::<sys::iree_hal_sync_device_params_t>::uninit();
+        let mut loaders = [loader.ctx];
+        let mut out = core::ptr::null_mut();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_hal_sync_device_params_initialize");
+            sys::iree_hal_sync_device_params_initialize(params.as_mut_ptr());
+            trace!("iree_hal_sync_device_create");
+            sys::iree_hal_sync_device_create(
+                base::StringView::from(super::drivers::LOCAL_SYNC).ctx,
+                params.as_ptr(),
+                loaders.len(),
+                loaders.as_mut_ptr(),
+                device_allocator,
+                host_allocator.ctx,
+                &mut out,
+            )
+        })
+        .to_result();
+        // The device retains its allocator.
+        unsafe {
+            trace!("iree_hal_allocator_release");
+            sys::iree_hal_allocator_release(device_allocator);
+        }
+        status?;
+        Ok(Device {
+            ctx: out,
+            marker: core::marker::PhantomData,
+        })
+    }
+}
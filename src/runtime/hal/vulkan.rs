@@ -0,0 +1,158 @@
+extern crate alloc;
+use alloc::{format, string::String, vec::Vec};
+use core::ffi::CStr;
+
+use ash::vk;
+use log::trace;
+
+use super::{
+    super::{api::Instance, base, error::RuntimeError},
+    Device,
+};
+
+/// The kind of a Vulkan physical device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VulkanDeviceType {
+    Other,
+    IntegratedGpu,
+    DiscreteGpu,
+    VirtualGpu,
+    Cpu,
+}
+
+impl From<vk::PhysicalDeviceType> for VulkanDeviceType {
+    fn from(device_type: vk::PhysicalDeviceType) -> Self {
+        match device_type {
+            vk::PhysicalDeviceType::INTEGRATED_GPU => VulkanDeviceType::IntegratedGpu,
+            vk::PhysicalDeviceType::DISCRETE_GPU => VulkanDeviceType::DiscreteGpu,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => VulkanDeviceType::VirtualGpu,
+            vk::PhysicalDeviceType::CPU => VulkanDeviceType::Cpu,
+            _ => VulkanDeviceType::Other,
+        }
+    }
+}
+
+/// A queue family of a Vulkan physical device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulkanQueueFamily {
+    /// Index of the queue family on its device.
+    pub index: u32,
+    /// Number of queues in the family.
+    pub queue_count: u32,
+    pub graphics: bool,
+    pub compute: bool,
+    pub transfer: bool,
+}
+
+/// A Vulkan physical device, as reported by the Vulkan loader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulkanPhysicalDevice {
+    /// Index of the device in the order the loader enumerates them, which is also the order
+    /// the IREE Vulkan driver uses.
+    pub index: usize,
+    pub name: String,
+    pub device_type: VulkanDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Highest Vulkan version supported by the device, as `(major, minor, patch)`.
+    pub api_version: (u32, u32, u32),
+    /// Vendor specific version of the driver.
+    pub driver_version: u32,
+    pub queue_families: Vec<VulkanQueueFamily>,
+}
+
+impl VulkanPhysicalDevice {
+    /// Returns the URI that selects the device, e.g. `vulkan://0`, see
+    /// [`Instance::create_device`].
+    pub fn uri(&self) -> String {
+        format!("{}://{}", super::drivers::VULKAN, self.index)
+    }
+
+    /// Returns true if the device has a queue family that supports compute, which IREE needs to
+    /// dispatch work to it.
+    pub fn supports_compute(&self) -> bool {
+        self.queue_families.iter().any(|family| family.compute)
+    }
+}
+
+fn unavailable(message: &str) -> RuntimeError {
+    base::Status::from_raw(base::allocate_status(
+        base::StatusErrorKind::Unavailable,
+        message,
+    ))
+    .to_result()
+    .unwrap_err()
+    .into()
+}
+
+/// Lists the Vulkan physical devices of the system with their properties and queue families.
+///
+/// The Vulkan loader is loaded at runtime. Returns an `Unavailable` error if it is missing.
+pub fn enumerate_vulkan_devices() -> Result<Vec<VulkanPhysicalDevice>, RuntimeError> {
+    let entry = unsafe { ash::Entry::load() }
+        .map_err(|err| unavailable(&format!("failed to load the Vulkan loader: {}", err)))?;
+    let app_info = vk::ApplicationInfo::builder().api_version(vk::make_api_version(0, 1, 0, 0));
+    let create_info = vk::InstanceCreateInfo::builder().application_info(&app_info);
+    trace!("vkCreateInstance");
+    let vk_instance = unsafe { entry.create_instance(&create_info, None) }
+        .map_err(|err| unavailable(&format!("failed to create a Vulkan instance: {}", err)))?;
+
+    let physical_devices = unsafe { vk_instance.enumerate_physical_devices() };
+    let devices = physical_devices.map(|physical_devices| {
+        physical_devices
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| {
+                let properties =
+                    unsafe { vk_instance.get_physical_device_properties(physical_device) };
+                let queue_families = unsafe {
+                    vk_instance.get_physical_device_queue_family_properties(physical_device)
+                };
+                let name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) };
+                VulkanPhysicalDevice {
+                    index,
+                    name: name.to_string_lossy().into_owned(),
+                    device_type: properties.device_type.into(),
+                    vendor_id: properties.vendor_id,
+                    device_id: properties.device_id,
+                    api_version: (
+                        vk::api_version_major(properties.api_version),
+                        vk::api_version_minor(properties.api_version),
+                        vk::api_version_patch(properties.api_version),
+                    ),
+                    driver_version: properties.driver_version,
+                    queue_families: queue_families
+                        .iter()
+                        .enumerate()
+                        .map(|(index, family)| VulkanQueueFamily {
+                            index: index as u32,
+                            queue_count: family.queue_count,
+                            graphics: family.queue_flags.contains(vk::QueueFlags::GRAPHICS),
+                            compute: family.queue_flags.contains(vk::QueueFlags::COMPUTE),
+                            transfer: family.queue_flags.contains(vk::QueueFlags::TRANSFER),
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    });
+    trace!("vkDestroyInstance");
+    unsafe { vk_instance.destroy_instance(None) };
+    devices.map_err(|err| {
+        unavailable(&format!(
+            "failed to enumerate Vulkan physical devices: {}",
+            err
+        ))
+    })
+}
+
+impl Instance {
+    /// Creates a device on the given Vulkan physical device, e.g. to pick the discrete GPU of a
+    /// system returned by [`enumerate_vulkan_devices`].
+    pub fn create_vulkan_device(
+        &self,
+        physical_device: &VulkanPhysicalDevice,
+    ) -> Result<Device<'_>, RuntimeError> {
+        self.create_device(&physical_device.uri())
+    }
+}
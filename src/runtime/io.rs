@@ -0,0 +1,263 @@
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+use core::ffi::c_void;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use super::{base, error::RuntimeError};
+
+#[cfg(feature = "parameters")]
+mod parameters;
+#[cfg(feature = "parameters")]
+pub use parameters::{ParameterIndex, ParameterProvider};
+
+/// File identifier of IREE bytecode module flatbuffers.
+const BYTECODE_FILE_IDENTIFIER: &[u8; 4] = b"IREE";
+
+/// Paging hints for memory mapped files, passed to `madvise`. They are ignored on platforms
+/// without it.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MmapAdvice {
+    /// No special treatment.
+    #[default]
+    Normal,
+    /// Pages are accessed in order, so they can be read ahead aggressively.
+    Sequential,
+    /// Pages are accessed in random order, so read ahead is of little use.
+    Random,
+    /// Pages will be accessed soon, so they can be read in ahead of time.
+    WillNeed,
+}
+
+#[cfg(feature = "std")]
+impl MmapAdvice {
+    /// Applies the advice to the given mapping.
+    pub(crate) fn apply(self, mmap: &memmap2::Mmap) -> Result<(), RuntimeError> {
+        #[cfg(unix)]
+        {
+            let advice = match self {
+                MmapAdvice::Normal => memmap2::Advice::Normal,
+                MmapAdvice::Sequential => memmap2::Advice::Sequential,
+                MmapAdvice::Random => memmap2::Advice::Random,
+                MmapAdvice::WillNeed => memmap2::Advice::WillNeed,
+            };
+            trace!("madvise {:?}, length: {}", self, mmap.len());
+            mmap.advise(advice).map_err(|err| {
+                RuntimeError::InvalidModule(error_message!("failed to advise mapping: {}", err))
+            })?;
+        }
+        #[cfg(not(unix))]
+        let _ = mmap;
+        Ok(())
+    }
+}
+
+// A mapping shared by a file handle and its owner, e.g. a `MappedModule`.
+#[cfg(feature = "std")]
+struct SharedMmap(std::sync::Arc<memmap2::Mmap>);
+
+#[cfg(feature = "std")]
+impl AsRef<[u8]> for SharedMmap {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Opens the file at the given path and maps it into memory read-only.
+#[cfg(feature = "std")]
+pub(crate) fn map_file(path: &Path) -> Result<memmap2::Mmap, RuntimeError> {
+    let file = std::fs::File::open(path).map_err(|err| {
+        RuntimeError::InvalidModule(error_message!("failed to open {:?}: {}", path, err))
+    })?;
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(|err| {
+        RuntimeError::InvalidModule(error_message!("failed to map {:?}: {}", path, err))
+    })?;
+    trace!("mapped {:?}, length: {}", path, mmap.len());
+    Ok(mmap)
+}
+
+/// A reference counted handle to file contents, e.g. a memory mapped vmfb.
+///
+/// The contents are kept alive until the last reference is released, including references held
+/// by modules created from the handle.
+pub struct FileHandle {
+    pub(crate) ctx: *mut sys::iree_io_file_handle_t,
+}
+
+// File handles are immutable once created and reference counted atomically.
+unsafe impl Send for FileHandle {}
+unsafe impl Sync for FileHandle {}
+
+impl FileHandle {
+    /// Opens the file at the given path and maps it into memory read-only.
+    ///
+    /// The file must not be modified while it is mapped.
+    #[cfg(feature = "std")]
+    pub fn open_mapped(path: &Path) -> Result<Self, RuntimeError> {
+        Self::wrap(Box::new(map_file(path)?))
+    }
+
+    /// Creates a file handle that shares the given mapping.
+    #[cfg(feature = "std")]
+    pub(crate) fn from_shared_mmap(
+        mmap: std::sync::Arc<memmap2::Mmap>,
+    ) -> Result<Self, RuntimeError> {
+        Self::wrap(Box::new(SharedMmap(mmap)))
+    }
+
+    /// Creates a file handle that takes ownership of the given bytes.
+    pub fn from_vec(data: Vec<u8>) -> Result<Self, RuntimeError> {
+        Self::wrap(Box::new(data))
+    }
+
+    fn wrap<T: AsRef<[u8]>>(contents: Box<T>) -> Result<Self, RuntimeError> {
+        unsafe extern "C" fn release<T>(
+            user_data: *mut c_void,
+            _: sys::iree_io_file_handle_primitive_t,
+        ) {
+            trace!("file handle release");
+            drop(Box::from_raw(user_data as *mut T));
+        }
+
+        let data = (*contents).as_ref();
+        let host_allocation = sys::iree_byte_span_t {
+            data: data.as_ptr() as *mut u8,
+            data_length: data.len(),
+        };
+        let user_data = Box::into_raw(contents);
+        let mut out = core::ptr::null_mut();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_io_file_handle_wrap_host_allocation");
+            sys::iree_io_file_handle_wrap_host_allocation(
+                sys::iree_io_file_access_bits_t_IREE_IO_FILE_ACCESS_READ,
+                host_allocation,
+                sys::iree_io_file_handle_release_callback_t {
+                    fn_: Some(release::<T>),
+                    user_data: user_data as *mut c_void,
+                },
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result();
+        if let Err(err) = status {
+            drop(unsafe { Box::from_raw(user_data) });
+            return Err(err.into());
+        }
+        Ok(Self { ctx: out })
+    }
+
+    /// Returns the contents of the file.
+    pub fn contents(&self) -> &[u8] {
+        unsafe {
+            trace!("iree_io_file_handle_primitive");
+            let primitive = sys::iree_io_file_handle_primitive(self.ctx);
+            let span = primitive.value.host_allocation;
+            if span.data_length == 0 {
+                return &[];
+            }
+            core::slice::from_raw_parts(span.data, span.data_length)
+        }
+    }
+
+    /// Checks that the file looks like an IREE bytecode module, i.e. it is large enough to hold a
+    /// flatbuffer header and carries the bytecode module file identifier.
+    pub fn verify_bytecode_header(&self) -> Result<(), RuntimeError> {
+        verify_bytecode_header(self.contents())
+    }
+
+    /// Returns an allocator that releases a reference to the handle when the memory the handle
+    /// holds is freed, e.g. when a module created from the contents is destroyed.
+    pub(crate) fn archive_allocator(&self) -> sys::iree_allocator_t {
+        unsafe {
+            trace!("iree_io_file_handle_retain");
+            sys::iree_io_file_handle_retain(self.ctx);
+        }
+        sys::iree_allocator_t {
+            self_: self.ctx as *mut c_void,
+            ctl: Some(file_handle_allocator_ctl),
+        }
+    }
+}
+
+unsafe extern "C" fn file_handle_allocator_ctl(
+    self_: *mut c_void,
+    command: sys::iree_allocator_command_e,
+    _params: *const c_void,
+    _inout_ptr: *mut *mut c_void,
+) -> sys::iree_status_t {
+    match command {
+        sys::iree_allocator_command_e_IREE_ALLOCATOR_COMMAND_FREE => {
+            trace!("iree_io_file_handle_release");
+            sys::iree_io_file_handle_release(self_ as *mut sys::iree_io_file_handle_t);
+            core::ptr::null_mut()
+        }
+        _ => base::allocate_status(
+            base::StatusErrorKind::Unimplemented,
+            "file handle allocators can only free",
+        ),
+    }
+}
+
+impl Clone for FileHandle {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_io_file_handle_retain");
+            sys::iree_io_file_handle_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for FileHandle {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_io_file_handle_release");
+            sys::iree_io_file_handle_release(self.ctx);
+        }
+    }
+}
+
+/// Checks that the data looks like an IREE bytecode module, i.e. it is large enough to hold a
+/// flatbuffer header and carries the bytecode module file identifier. Both plain and size
+/// prefixed flatbuffers are accepted.
+///
+/// This only rejects data that is obviously not a module. The module itself is verified by the
+/// runtime when it is loaded.
+pub fn verify_bytecode_header(data: &[u8]) -> Result<(), RuntimeError> {
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let has_identifier =
+        |offset: usize| data.get(offset..offset + 4) == Some(&BYTECODE_FILE_IDENTIFIER[..]);
+    let (flatbuffer_offset, flatbuffer_length) = if has_identifier(4) {
+        (0, data.len())
+    } else if has_identifier(8) {
+        // The size prefix excludes itself.
+        (4, read_u32(0).unwrap())
+    } else {
+        return Err(RuntimeError::InvalidModule(error_message!(
+            "data of length {} does not carry the bytecode module file identifier",
+            data.len()
+        )));
+    };
+    if flatbuffer_offset + flatbuffer_length > data.len() {
+        return Err(RuntimeError::InvalidModule(error_message!(
+            "flatbuffer of length {} does not fit into data of length {}",
+            flatbuffer_length,
+            data.len()
+        )));
+    }
+    match read_u32(flatbuffer_offset) {
+        Some(root_offset) if root_offset < flatbuffer_length => Ok(()),
+        _ => Err(RuntimeError::InvalidModule(error_message!(
+            "flatbuffer root table is out of bounds of data of length {}",
+            data.len()
+        ))),
+    }
+}
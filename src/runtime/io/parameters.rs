@@ -0,0 +1,173 @@
+extern crate alloc;
+use alloc::vec::Vec;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+use std::path::Path;
+
+use super::{
+    super::{
+        api::Session,
+        base::{self, StringView},
+        error::RuntimeError,
+        vm::Module,
+    },
+    FileHandle,
+};
+
+/// An index of named parameters, e.g. the weights of a model compiled with external parameters.
+///
+/// Parameters are added from archives in any of the formats supported by the runtime: `.irpa`,
+/// `.gguf` and `.safetensors`. The index keeps the archives alive for as long as it is used.
+pub struct ParameterIndex {
+    pub(crate) ctx: *mut sys::iree_io_parameter_index_t,
+}
+
+// Parameter indices are reference counted atomically and synchronize their entries.
+unsafe impl Send for ParameterIndex {}
+unsafe impl Sync for ParameterIndex {}
+
+impl ParameterIndex {
+    /// Creates an empty parameter index.
+    pub fn new() -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_io_parameter_index_create");
+            sys::iree_io_parameter_index_create(base::Allocator::get_global().ctx, &mut out)
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+
+    /// Adds the parameters of the archive at the given path. The file is memory mapped, and its
+    /// format is detected from the extension of the path.
+    pub fn load(&self, path: &Path) -> Result<(), RuntimeError> {
+        let file_handle = FileHandle::open_mapped(path)?;
+        self.add_archive(&path.to_string_lossy(), &file_handle)
+    }
+
+    /// Adds the parameters of the archive held by the given file handle. The format of the
+    /// archive is detected from the extension of the given name, e.g. `weights.safetensors`.
+    pub fn add_archive(&self, name: &str, file_handle: &FileHandle) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_io_parse_file_index, name: {}", name);
+            sys::iree_io_parse_file_index(StringView::from(name).ctx, file_handle.ctx, self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Returns the number of parameters in the index.
+    pub fn len(&self) -> usize {
+        unsafe {
+            trace!("iree_io_parameter_index_count");
+            sys::iree_io_parameter_index_count(self.ctx)
+        }
+    }
+
+    /// Returns true if the index holds no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Clone for ParameterIndex {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_io_parameter_index_retain");
+            sys::iree_io_parameter_index_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for ParameterIndex {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_io_parameter_index_release");
+            sys::iree_io_parameter_index_release(self.ctx);
+        }
+    }
+}
+
+/// Provides parameters to the modules of a session, see
+/// [`Session::append_parameter_providers`].
+pub struct ParameterProvider {
+    pub(crate) ctx: *mut sys::iree_io_parameter_provider_t,
+}
+
+unsafe impl Send for ParameterProvider {}
+unsafe impl Sync for ParameterProvider {}
+
+impl ParameterProvider {
+    /// Creates a provider that serves the parameters of the given index under the given scope.
+    /// The scope has to match the one the module was compiled with, and may be empty.
+    pub fn from_index(scope: &str, index: &ParameterIndex) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_io_parameter_index_provider_create, scope: {}", scope);
+            sys::iree_io_parameter_index_provider_create(
+                StringView::from(scope).ctx,
+                index.ctx,
+                sys::IREE_IO_PARAMETER_INDEX_PROVIDER_DEFAULT_MAX_CONCURRENT_OPERATIONS as usize,
+                base::Allocator::get_global().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out })
+    }
+}
+
+impl Clone for ParameterProvider {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_io_parameter_provider_retain");
+            sys::iree_io_parameter_provider_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for ParameterProvider {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_io_parameter_provider_release");
+            sys::iree_io_parameter_provider_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> Session<'a> {
+    /// Appends the `io_parameters` module backed by the given providers to the session. It must
+    /// be appended before the modules that load parameters from it.
+    pub fn append_parameter_providers(
+        &self,
+        providers: &[&ParameterProvider],
+    ) -> Result<(), RuntimeError> {
+        let mut provider_ptrs = providers
+            .iter()
+            .map(|provider| provider.ctx)
+            .collect::<Vec<_>>();
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_io_parameters_module_create, provider count: {}",
+                provider_ptrs.len()
+            );
+            sys::iree_io_parameters_module_create(
+                self.instance.get_vm_instance(),
+                provider_ptrs.len(),
+                provider_ptrs.as_mut_ptr(),
+                self.instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        let module = Module {
+            ctx: out,
+            instance: self.instance,
+        };
+        self.append_module(&module)
+    }
+}
@@ -1,5 +1,15 @@
+// Declared first, so that its macros are available to the other modules.
+#[macro_use]
+pub mod error;
 pub mod api;
 pub mod base;
-pub mod error;
 pub mod hal;
+pub mod io;
+pub mod pipelines;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod tensor;
+pub mod version;
+#[cfg(feature = "vision")]
+pub mod vision;
 pub mod vm;
@@ -0,0 +1,280 @@
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use super::super::{
+    api::{Call, Session},
+    error::RuntimeError,
+    hal::{BufferView, EncodingType, ToElementType},
+    vm::{CallingConventionType, DynamicList, List, Ref, Undefined},
+};
+
+/// How the next token is picked from the logits of a decode step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Picks the token with the highest logit.
+    Greedy,
+    /// Samples from the `top_k` tokens with the highest logits, with probabilities given by the
+    /// softmax of the logits divided by `temperature`. The generator is seeded with `seed`, so
+    /// the same prompt always yields the same tokens.
+    TopK {
+        temperature: f32,
+        top_k: usize,
+        seed: u64,
+    },
+}
+
+/// Options of [`TextGenerator::generate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationConfig {
+    /// The maximum number of tokens generated after the prompt.
+    pub max_new_tokens: usize,
+    /// Tokens that end the generation, e.g. the end of sequence token. The stop token is included
+    /// in the generated tokens.
+    pub stop_tokens: Vec<i64>,
+    /// How the generated tokens are picked from the logits.
+    pub sampling: Sampling,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            max_new_tokens: 128,
+            stop_tokens: Vec::new(),
+            sampling: Sampling::Greedy,
+        }
+    }
+}
+
+/// Runs the decode loop of a llama-style language model, one token per invocation.
+///
+/// The decode function takes the input token as an `i64` buffer view of shape `[1, 1]`, the
+/// position of the token as an `i64`, and the KV cache as buffer views of element type `K`. It
+/// returns the `f32` logits over the vocabulary, followed by the updated KV cache in the same
+/// order, i.e. its calling convention is `0rIr..._rr...`.
+///
+/// The generator keeps the KV cache on the device between steps and passes the buffer views
+/// returned by one step to the next one, without copying them. The token buffer view, the host
+/// logits and the input and output lists are allocated once and reused, so a step only
+/// transfers the token to the device and the logits back to the host.
+pub struct TextGenerator<'a, K: ToElementType> {
+    session: &'a Session<'a>,
+    call: Call<'a>,
+    token: BufferView<'a, i64>,
+    kv_cache: Vec<BufferView<'a, K>>,
+    logits: Vec<f32>,
+    // Indices of the logits, reordered when sampling from the top k.
+    candidates: Vec<usize>,
+    position: usize,
+    rng_state: u64,
+}
+
+impl<'a, K: ToElementType> TextGenerator<'a, K> {
+    /// Creates a generator for the decode function with the given fully qualified name, starting
+    /// from the given KV cache, e.g. zero-filled buffer views of the shape the model expects.
+    /// Returns `RuntimeError::SignatureMismatch` if the calling convention of the function does
+    /// not match the number of KV cache buffer views.
+    pub fn new(
+        session: &'a Session<'a>,
+        function: &str,
+        kv_cache: Vec<BufferView<'a, K>>,
+    ) -> Result<Self, RuntimeError> {
+        let decode = session.lookup_function(function)?;
+        let mut expected_arguments = vec![CallingConventionType::Ref, CallingConventionType::I64];
+        expected_arguments.extend(kv_cache.iter().map(|_| CallingConventionType::Ref));
+        let expected_results = vec![CallingConventionType::Ref; kv_cache.len() + 1];
+        if decode.argument_types()? != expected_arguments
+            || decode.result_types()? != expected_results
+        {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "{} must take a token, a position and {} KV cache buffer views, and return the \
+                 logits and the updated KV cache",
+                function,
+                kv_cache.len()
+            )));
+        }
+        Ok(Self {
+            session,
+            call: Call::from_func_name(session, function)?,
+            token: BufferView::new(session, &[1, 1], EncodingType::DenseRowMajor, &[0i64])?,
+            kv_cache,
+            logits: Vec::new(),
+            candidates: Vec::new(),
+            position: 0,
+            // xorshift needs a non-zero state, the seed of the sampling replaces it.
+            rng_state: 1,
+        })
+    }
+
+    /// Runs a single decode step for the given token at the current position, and returns the
+    /// logits for the next token.
+    pub fn step(&mut self, token: i64) -> Result<&[f32], RuntimeError> {
+        self.token.copy_from_host(&[token])?;
+        self.call.reset();
+        self.call.inputs_push_back_buffer_view(&self.token)?;
+        self.call.inputs_push_back_value(self.position as i64)?;
+        for cache in &self.kv_cache {
+            self.call.inputs_push_back_buffer_view(cache)?;
+        }
+        self.call.invoke()?;
+
+        let outputs = self.call.output_list();
+        if outputs.size() != self.kv_cache.len() + 1 {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "the decode function returned {} values, expected {}",
+                outputs.size(),
+                self.kv_cache.len() + 1
+            )));
+        }
+        let logits = buffer_view_at::<f32>(&outputs, 0, self.session)?;
+        // Only allocates on the first step, or if the vocabulary size changes.
+        self.logits.resize(logits.element_count(), 0.0);
+        logits.copy_to_host(&mut self.logits)?;
+        for (idx, cache) in self.kv_cache.iter_mut().enumerate() {
+            *cache = buffer_view_at(&outputs, idx + 1, self.session)?;
+        }
+        drop(outputs);
+        // Releases the outputs, which are retained by the generator as needed.
+        self.call.reset();
+        self.position += 1;
+        Ok(&self.logits)
+    }
+
+    /// Feeds the prompt to the model, then samples and feeds new tokens until a stop token or
+    /// `max_new_tokens` is reached. Returns the generated tokens. `on_token` is called with every
+    /// generated token, e.g. to stream them, and stops the generation early if it returns false.
+    ///
+    /// The generation continues from the current position, so calling it again extends the
+    /// previous sequence; use [`TextGenerator::reset`] to start a new one.
+    pub fn generate(
+        &mut self,
+        prompt: &[i64],
+        config: &GenerationConfig,
+        mut on_token: impl FnMut(i64) -> bool,
+    ) -> Result<Vec<i64>, RuntimeError> {
+        let Some((&last, rest)) = prompt.split_last() else {
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "the prompt must hold at least one token"
+            )));
+        };
+        if let Sampling::TopK { seed, .. } = config.sampling {
+            self.rng_state = seed | 1;
+        }
+        for &token in rest {
+            self.step(token)?;
+        }
+        let mut generated = Vec::with_capacity(config.max_new_tokens);
+        let mut token = last;
+        while generated.len() < config.max_new_tokens {
+            self.step(token)?;
+            token = self.sample(config.sampling) as i64;
+            generated.push(token);
+            if !on_token(token) || config.stop_tokens.contains(&token) {
+                break;
+            }
+        }
+        Ok(generated)
+    }
+
+    /// Picks the next token from the logits of the last step.
+    pub fn sample(&mut self, sampling: Sampling) -> usize {
+        match sampling {
+            Sampling::Greedy => self.argmax(),
+            Sampling::TopK {
+                temperature, top_k, ..
+            } => self.sample_top_k(temperature, top_k),
+        }
+    }
+
+    fn argmax(&self) -> usize {
+        self.logits
+            .iter()
+            .enumerate()
+            .fold((0, f32::NEG_INFINITY), |best, (idx, &logit)| {
+                if logit > best.1 {
+                    (idx, logit)
+                } else {
+                    best
+                }
+            })
+            .0
+    }
+
+    fn sample_top_k(&mut self, temperature: f32, top_k: usize) -> usize {
+        let top_k = top_k.clamp(1, self.logits.len().max(1));
+        if temperature <= 0.0 || top_k == 1 || self.logits.is_empty() {
+            return self.argmax();
+        }
+        let logits = &self.logits;
+        self.candidates.clear();
+        self.candidates.extend(0..logits.len());
+        if top_k < logits.len() {
+            self.candidates
+                .select_nth_unstable_by(top_k - 1, |&a, &b| logits[b].total_cmp(&logits[a]));
+        }
+        let candidates = &self.candidates[..top_k];
+        let max = candidates
+            .iter()
+            .map(|&idx| logits[idx])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let weight = |idx: usize| ((logits[idx] - max) / temperature).exp();
+        let total: f32 = candidates.iter().map(|&idx| weight(idx)).sum();
+        let mut threshold = next_uniform(&mut self.rng_state) * total;
+        for &idx in candidates {
+            threshold -= weight(idx);
+            if threshold <= 0.0 {
+                return idx;
+            }
+        }
+        candidates[top_k - 1]
+    }
+
+    /// Returns the logits of the last step.
+    pub fn logits(&self) -> &[f32] {
+        &self.logits
+    }
+
+    /// Returns the current KV cache.
+    pub fn kv_cache(&self) -> &[BufferView<'a, K>] {
+        &self.kv_cache
+    }
+
+    /// Replaces the KV cache, e.g. with zero-filled buffer views to start over.
+    pub fn set_kv_cache(&mut self, kv_cache: Vec<BufferView<'a, K>>) {
+        self.kv_cache = kv_cache;
+    }
+
+    /// Returns the position of the next token, i.e. the number of tokens fed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Starts a new sequence at position 0. The KV cache is kept, as models only attend to the
+    /// entries before the current position; use [`TextGenerator::set_kv_cache`] to clear it.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+}
+
+// Returns the buffer view at the given index of the list, retained so that it outlives the list.
+// Fails if the element is not a buffer view of `T`.
+fn buffer_view_at<'a, T: ToElementType>(
+    list: &DynamicList<Undefined>,
+    idx: usize,
+    session: &'a Session,
+) -> Result<BufferView<'a, T>, RuntimeError> {
+    let buffer_view: Ref<BufferView<T>> = list.get_ref(idx)?;
+    let buffer_view = buffer_view.try_to_buffer_view(session)?;
+    // Rebinds the retained buffer view to the session, as it is borrowed from the list.
+    let ctx = buffer_view.ctx;
+    core::mem::forget(buffer_view);
+    Ok(unsafe { BufferView::from_ptr(ctx, session) })
+}
+
+// Returns a uniformly distributed number in [0, 1) from a xorshift64* generator.
+fn next_uniform(state: &mut u64) -> f32 {
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    let bits = state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40;
+    bits as f32 / (1u64 << 24) as f32
+}
@@ -0,0 +1,36 @@
+//! Tracy profiling of the runtime, available with the `profiling` feature.
+//!
+//! The runtime is built with IREE tracing, so sessions, invocations and HAL work show up as zones
+//! in a Tracy capture. Connect the Tracy profiler to the process after [`start_capture`].
+
+use core::ffi::CStr;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+/// Starts the Tracy profiler of the process, so that a Tracy server can connect and capture.
+pub fn start_capture() {
+    unsafe {
+        trace!("iree_tracing_tracy_initialize");
+        sys::iree_tracing_tracy_initialize();
+    }
+}
+
+/// Stops the Tracy profiler, after the connected server has received the pending events.
+pub fn stop_capture() {
+    unsafe {
+        trace!("iree_tracing_tracy_deinitialize");
+        sys::iree_tracing_tracy_deinitialize();
+    }
+}
+
+/// Marks the end of a frame, e.g. one inference of a serving loop, in the default frame set.
+pub fn frame_mark() {
+    unsafe { sys::___tracy_emit_frame_mark(core::ptr::null()) };
+}
+
+/// Marks the end of a frame in the frame set with the given name. Tracy identifies frame sets by
+/// the address of the name, so it has to be static.
+pub fn frame_mark_named(name: &'static CStr) {
+    unsafe { sys::___tracy_emit_frame_mark(name.as_ptr()) };
+}
@@ -0,0 +1,142 @@
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use super::{
+    api::Session,
+    error::RuntimeError,
+    hal::{BufferView, ElementType, EncodingType, ToElementType},
+};
+
+/// An owned tensor on the host: a shape, an element type and the elements in row major order.
+///
+/// Unlike a [`BufferView`], a tensor does not borrow a session, so it can be stored in user data
+/// structures and sent to other threads. It is converted to and from buffer views with
+/// [`Tensor::to_buffer_view`] and [`Tensor::from_buffer_view`], which copy the elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    shape: Vec<usize>,
+    element_type: ElementType,
+    // Backed by u64 so that the elements of any element type are aligned.
+    storage: Vec<u64>,
+    byte_length: usize,
+}
+
+impl Tensor {
+    /// Creates a tensor of the given shape holding a copy of the given elements. Returns
+    /// `RuntimeError::ShapeMismatch` if the number of elements does not match the shape.
+    pub fn new<T: ToElementType + Copy>(shape: &[usize], data: &[T]) -> Result<Self, RuntimeError> {
+        let element_count = shape.iter().product::<usize>();
+        if element_count != data.len() {
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "a tensor of shape {:?} holds {} elements, got {}",
+                shape,
+                element_count,
+                data.len()
+            )));
+        }
+        let bytes = unsafe {
+            core::slice::from_raw_parts(data.as_ptr() as *const u8, core::mem::size_of_val(data))
+        };
+        Ok(Self::from_bytes(shape, T::to_element_type(), bytes))
+    }
+
+    // Creates a tensor holding a copy of the given bytes, without checking them against the
+    // shape, e.g. for element types that are only known at runtime.
+    pub(crate) fn from_bytes(shape: &[usize], element_type: ElementType, bytes: &[u8]) -> Self {
+        let byte_length = bytes.len();
+        let mut storage = vec![0u64; byte_length.div_ceil(core::mem::size_of::<u64>())];
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                storage.as_mut_ptr() as *mut u8,
+                byte_length,
+            );
+        }
+        Self {
+            shape: shape.to_vec(),
+            element_type,
+            storage,
+            byte_length,
+        }
+    }
+
+    /// Copies the shape, element type and contents of the given buffer view into a tensor.
+    pub fn from_buffer_view<T: ToElementType + Copy>(
+        buffer_view: &BufferView<T>,
+    ) -> Result<Self, RuntimeError> {
+        Self::new(&buffer_view.shape(), &buffer_view.to_vec()?)
+    }
+
+    /// Creates a dense row major buffer view holding a copy of the tensor in the session's device
+    /// allocator. Returns `RuntimeError::SignatureMismatch` if `T` is not the element type of the
+    /// tensor.
+    pub fn to_buffer_view<'a, T: ToElementType + Copy>(
+        &self,
+        session: &'a Session,
+    ) -> Result<BufferView<'a, T>, RuntimeError> {
+        BufferView::new(
+            session,
+            &self.shape,
+            EncodingType::DenseRowMajor,
+            self.as_slice()?,
+        )
+    }
+
+    // Copies the tensor into a buffer view read as bytes, e.g. for element types that are only
+    // known at runtime.
+    pub(crate) fn to_untyped_buffer_view<'a>(
+        &self,
+        session: &'a Session,
+    ) -> Result<BufferView<'a, u8>, RuntimeError> {
+        BufferView::<u8>::allocate_copy(
+            session,
+            &self.shape,
+            self.element_type,
+            EncodingType::DenseRowMajor,
+            self.as_bytes(),
+        )
+    }
+
+    // Copies a buffer view of any element type, read as bytes, into a tensor.
+    pub(crate) fn from_untyped_buffer_view(
+        buffer_view: &BufferView<u8>,
+    ) -> Result<Self, RuntimeError> {
+        Ok(Self::from_bytes(
+            &buffer_view.shape(),
+            buffer_view.element_type(),
+            &buffer_view.to_vec()?,
+        ))
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn element_type(&self) -> ElementType {
+        self.element_type
+    }
+
+    /// Returns the elements of the tensor. Returns `RuntimeError::SignatureMismatch` if `T` is
+    /// not the element type of the tensor.
+    pub fn as_slice<T: ToElementType + Copy>(&self) -> Result<&[T], RuntimeError> {
+        if T::to_element_type() != self.element_type {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "the tensor has element type {:?}, not {:?}",
+                self.element_type,
+                T::to_element_type()
+            )));
+        }
+        // The storage was filled with elements of type `T`.
+        Ok(unsafe {
+            core::slice::from_raw_parts(
+                self.storage.as_ptr() as *const T,
+                self.byte_length / core::mem::size_of::<T>(),
+            )
+        })
+    }
+
+    /// Returns the raw contents of the tensor.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.storage.as_ptr() as *const u8, self.byte_length) }
+    }
+}
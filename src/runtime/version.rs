@@ -0,0 +1,70 @@
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use super::{
+    api::{Instance, InstanceOptions, Session, SessionOptions},
+    error::RuntimeError,
+    hal::{drivers, DriverRegistry},
+    io,
+    vm::Module,
+};
+
+/// A module provided by the runtime, e.g. `hal`, and its version. Compiled modules import from
+/// these modules and require a minimum version of each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleVersion {
+    pub name: String,
+    pub version: u32,
+}
+
+/// Returns the versions of the modules registered in the given session.
+pub fn module_versions(session: &Session) -> Vec<ModuleVersion> {
+    session
+        .modules()
+        .iter()
+        .map(|module| ModuleVersion {
+            name: String::from(module.name()),
+            version: module.version(),
+        })
+        .collect()
+}
+
+/// Returns the versions of the builtin modules the runtime registers in sessions created with the
+/// default options. A `local-sync` device is created to instantiate them.
+pub fn builtin_module_versions() -> Result<Vec<ModuleVersion>, RuntimeError> {
+    with_default_session(|_, session| Ok(module_versions(session)))
+}
+
+/// Checks that the runtime can load the given compiled module (vmfb), without a session of the
+/// caller.
+///
+/// Returns `RuntimeError::InvalidModule` if the data is not a bytecode module or the runtime
+/// rejects its bytecode, e.g. because its bytecode version is not supported, and
+/// `RuntimeError::IncompatibleModule` listing the required and provided versions if it imports
+/// builtin modules that are missing or older than required. This usually means the module was
+/// compiled by a compiler that does not match the runtime.
+pub fn check_artifact_compatibility(vmfb: &[u8]) -> Result<(), RuntimeError> {
+    io::verify_bytecode_header(vmfb)?;
+    with_default_session(|instance, session| {
+        // Module creation runs the flatbuffer verifier of the runtime.
+        let module = unsafe { Module::from_bytecode(instance, vmfb) }.map_err(|err| match err {
+            RuntimeError::StatusError(err) => RuntimeError::InvalidModule(error_message!(
+                "the runtime rejected the module: {}",
+                err
+            )),
+            err => err,
+        })?;
+        session.check_compatibility(&module)
+    })
+}
+
+fn with_default_session<T>(
+    f: impl FnOnce(&Instance, &Session) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    let mut driver_registry = DriverRegistry::new();
+    let instance =
+        Instance::new(&InstanceOptions::new(&mut driver_registry).use_all_available_drivers())?;
+    let device = instance.try_create_default_device(drivers::LOCAL_SYNC)?;
+    let session = Session::create_with_device(&instance, &SessionOptions::default(), &device)?;
+    f(&instance, &session)
+}
@@ -0,0 +1,244 @@
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+
+use super::{
+    api::Session,
+    error::RuntimeError,
+    hal::{BufferView, EncodingType},
+};
+
+/// The order of the dimensions of an image tensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// `[1, 3, height, width]`, used by most PyTorch models.
+    #[default]
+    Nchw,
+    /// `[1, height, width, 3]`, used by most TensorFlow models.
+    Nhwc,
+}
+
+/// How an RGB image is turned into an `f32` tensor. Every channel value `x` becomes
+/// `(x * scale - mean[c]) / std[c]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageOptions {
+    /// The size the image is resized to as `(width, height)`, or `None` to keep its size.
+    pub resize: Option<(u32, u32)>,
+    /// The filter used to resize the image.
+    pub filter: FilterType,
+    /// The order of the dimensions of the tensor.
+    pub layout: Layout,
+    /// Applied to the 8-bit channel values first, e.g. `1.0 / 255.0` to map them to `[0, 1]`.
+    pub scale: f32,
+    /// The mean of each channel after scaling, subtracted from it.
+    pub mean: [f32; 3],
+    /// The standard deviation of each channel after scaling, that it is divided by.
+    pub std: [f32; 3],
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            resize: None,
+            filter: FilterType::Triangle,
+            layout: Layout::Nchw,
+            scale: 1.0 / 255.0,
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+impl ImageOptions {
+    /// The normalization of models trained on ImageNet, e.g. ResNet, at the given size.
+    pub fn imagenet(width: u32, height: u32) -> Self {
+        Self {
+            resize: Some((width, height)),
+            mean: [0.485, 0.456, 0.406],
+            std: [0.229, 0.224, 0.225],
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts an image into an `f32` buffer view in the session's device allocator, see
+/// [`ImageOptions`].
+pub fn image_to_buffer_view<'a>(
+    session: &'a Session,
+    image: &DynamicImage,
+    options: &ImageOptions,
+) -> Result<BufferView<'a, f32>, RuntimeError> {
+    rgb_image_to_buffer_view(session, &image.to_rgb8(), options)
+}
+
+/// Converts interleaved RGB8 pixels in row major order, e.g. a camera frame, into an `f32`
+/// buffer view. Returns `RuntimeError::ShapeMismatch` if `data` does not hold
+/// `width * height * 3` bytes.
+pub fn rgb8_to_buffer_view<'a>(
+    session: &'a Session,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    options: &ImageOptions,
+) -> Result<BufferView<'a, f32>, RuntimeError> {
+    let image = RgbImage::from_raw(width, height, data.to_vec()).ok_or_else(|| {
+        RuntimeError::ShapeMismatch(error_message!(
+            "a {}x{} RGB8 image holds {} bytes, got {}",
+            width,
+            height,
+            width as usize * height as usize * 3,
+            data.len()
+        ))
+    })?;
+    rgb_image_to_buffer_view(session, &image, options)
+}
+
+fn rgb_image_to_buffer_view<'a>(
+    session: &'a Session,
+    image: &RgbImage,
+    options: &ImageOptions,
+) -> Result<BufferView<'a, f32>, RuntimeError> {
+    let resized;
+    let image = match options.resize {
+        Some((width, height)) if image.dimensions() != (width, height) => {
+            resized = image::imageops::resize(image, width, height, options.filter);
+            &resized
+        }
+        _ => image,
+    };
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut data = vec![0.0f32; width * height * 3];
+    for (idx, pixel) in image.pixels().enumerate() {
+        for channel in 0..3 {
+            let value = (pixel.0[channel] as f32 * options.scale - options.mean[channel])
+                / options.std[channel];
+            let offset = match options.layout {
+                Layout::Nchw => channel * width * height + idx,
+                Layout::Nhwc => idx * 3 + channel,
+            };
+            data[offset] = value;
+        }
+    }
+    let shape = match options.layout {
+        Layout::Nchw => [1, 3, height, width],
+        Layout::Nhwc => [1, height, width, 3],
+    };
+    BufferView::new(session, &shape, EncodingType::DenseRowMajor, &data)
+}
+
+/// The layout of the raw output of a detection model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionFormat {
+    /// `[1, boxes, 5 + classes]`, each row holding the box center, size, objectness and class
+    /// scores, as produced by YOLOv5 and YOLOv7.
+    YoloV5,
+    /// `[1, 4 + classes, boxes]`, each column holding the box center, size and class scores, as
+    /// produced by YOLOv8 and later.
+    YoloV8,
+}
+
+/// A detected object, in the coordinates of the model input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// The box as `[x_min, y_min, x_max, y_max]`.
+    pub bbox: [f32; 4],
+    /// The confidence of the detection, including the objectness for YOLOv5 outputs.
+    pub score: f32,
+    /// The index of the class with the highest score.
+    pub class: usize,
+}
+
+impl Detection {
+    /// Returns the detection with its box scaled by the given factors, e.g. from the model input
+    /// size back to the size of the original image.
+    pub fn scaled(&self, x: f32, y: f32) -> Self {
+        let [x_min, y_min, x_max, y_max] = self.bbox;
+        Self {
+            bbox: [x_min * x, y_min * y, x_max * x, y_max * y],
+            ..*self
+        }
+    }
+
+    fn iou(&self, other: &Self) -> f32 {
+        let [ax0, ay0, ax1, ay1] = self.bbox;
+        let [bx0, by0, bx1, by1] = other.bbox;
+        let intersection =
+            (ax1.min(bx1) - ax0.max(bx0)).max(0.0) * (ay1.min(by1) - ay0.max(by0)).max(0.0);
+        let union = (ax1 - ax0) * (ay1 - ay0) + (bx1 - bx0) * (by1 - by0) - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Decodes the raw output of a detection model of the given shape, keeps the boxes scoring at
+/// least `score_threshold`, and removes overlapping boxes of the same class whose intersection
+/// over union exceeds `iou_threshold`. Detections are sorted by decreasing score. Returns
+/// `RuntimeError::ShapeMismatch` if the shape does not match the format.
+pub fn decode_detections(
+    output: &[f32],
+    shape: &[usize],
+    format: DetectionFormat,
+    score_threshold: f32,
+    iou_threshold: f32,
+) -> Result<Vec<Detection>, RuntimeError> {
+    let (box_count, values, extra) = match (format, shape) {
+        (DetectionFormat::YoloV5, &[1, boxes, values]) if values > 5 => (boxes, values, 5),
+        (DetectionFormat::YoloV8, &[1, values, boxes]) if values > 4 => (boxes, values, 4),
+        _ => {
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "{:?} detections cannot have shape {:?}",
+                format,
+                shape
+            )))
+        }
+    };
+    if output.len() != box_count * values {
+        return Err(RuntimeError::ShapeMismatch(error_message!(
+            "an output of shape {:?} holds {} values, got {}",
+            shape,
+            box_count * values,
+            output.len()
+        )));
+    }
+    let value = |idx: usize, offset: usize| match format {
+        DetectionFormat::YoloV5 => output[idx * values + offset],
+        DetectionFormat::YoloV8 => output[offset * box_count + idx],
+    };
+    let mut candidates = (0..box_count)
+        .filter_map(|idx| {
+            let objectness = match format {
+                DetectionFormat::YoloV5 => value(idx, 4),
+                DetectionFormat::YoloV8 => 1.0,
+            };
+            let (class, class_score) = (0..values - extra)
+                .map(|class| (class, value(idx, extra + class)))
+                .fold((0, f32::NEG_INFINITY), |best, candidate| {
+                    if candidate.1 > best.1 {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+            let score = objectness * class_score;
+            (score >= score_threshold).then(|| {
+                let (cx, cy, w, h) = (value(idx, 0), value(idx, 1), value(idx, 2), value(idx, 3));
+                Detection {
+                    bbox: [cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0],
+                    score,
+                    class,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut detections: Vec<Detection> = Vec::new();
+    for candidate in candidates {
+        if detections.iter().all(|detection| {
+            detection.class != candidate.class || detection.iou(&candidate) <= iou_threshold
+        }) {
+            detections.push(candidate);
+        }
+    }
+    Ok(detections)
+}
@@ -1,13 +1,44 @@
+//! The IREE VM: functions, lists, values and refs.
+//!
+//! There is a single implementation of VM lists, values and refs, defined in this module:
+//! [`List`] with [`DynamicList`] and [`StaticList`], [`Value`], [`Ref`], and the type-erased
+//! [`NativeRef`] and [`Variant`] for elements whose types are only known at runtime. The
+//! submodules build on these types and are re-exported here, so `vm::Module` and
+//! `vm::module::Module` name the same type:
+//!
+//! - [`buffer`]: VM byte buffers.
+//! - [`module`]: bytecode modules and their dependencies.
+//! - [`context`]: VM contexts, to use modules without a session.
+//! - [`native_module`]: modules implemented in Rust.
+//! - [`abi`]: function signatures from reflection attributes.
+extern crate alloc;
+use alloc::{format, string::String, vec::Vec};
 use eerie_sys::runtime as sys;
 use log::trace;
 
 use super::{
     api::{self, Instance},
-    base::{self, ByteSpan},
+    base::{self, ByteSpan, StringView},
     error::RuntimeError,
-    hal::{BufferView, ToElementType},
+    hal::{self, semaphore::wrap_retain, BufferView, Fence, Semaphore, ToElementType},
 };
 
+pub mod abi;
+pub mod buffer;
+pub mod context;
+pub mod module;
+pub mod native_module;
+pub use abi::{AbiSignature, AbiType, AbiValue, TensorType};
+pub use buffer::Buffer;
+pub use context::{Context, ContextFlags, ContextFunction, ContextOptions, ModuleState};
+#[cfg(feature = "std")]
+pub use module::MappedModule;
+pub use module::{
+    CModuleCreateFn, FunctionLinkage, IncompatibilityReport, Module, ModuleDependency,
+    UnsatisfiedDependency,
+};
+pub use native_module::{NativeModuleBuilder, NativeRef, NativeValue};
+
 /// An IREE function reference.
 pub struct Function<'a> {
     pub(crate) ctx: sys::iree_vm_function_t,
@@ -17,6 +48,10 @@ pub struct Function<'a> {
 impl<'a> Function<'a> {
     /// Synchronously invokes the function with the given arguments.
     /// The function will be run to completion and may block on external resources.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.name()))
+    )]
     pub fn invoke<'b, T1, T2>(
         &self,
         input_list: &impl List<'b, T1>,
@@ -26,23 +61,882 @@ impl<'a> Function<'a> {
         T1: Type,
         T2: Type,
     {
-        base::Status::from_raw(unsafe {
-            trace!("iree_vm_invoke");
+        self.invoke_with_policy(input_list, output_list, &api::InvocationPolicy::new())
+    }
+
+    /// Synchronously invokes the function like [`Function::invoke`], with the given flags and
+    /// cancellation token. Returns a `Cancelled` error without invoking the function if the token
+    /// has been cancelled.
+    ///
+    /// Functions compiled with `--iree-execution-model=async-external` are invoked through their
+    /// fences when the policy has a token: the wait and signal fences are appended to the inputs,
+    /// as in [`Function::invoke_async`], unless the inputs already end with them, and the call
+    /// blocks until the signal fence is signaled or the token is cancelled. Cancelling the token
+    /// from another thread then returns `Cancelled` while the device work is running, and fails
+    /// the signal fence. Functions compiled with the synchronous model block in the VM until they
+    /// return, so the token is only checked before they start.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.name()))
+    )]
+    pub fn invoke_with_policy<'b, T1, T2>(
+        &self,
+        input_list: &impl List<'b, T1>,
+        output_list: &impl List<'b, T2>,
+        policy: &api::InvocationPolicy,
+    ) -> Result<(), RuntimeError>
+    where
+        T1: Type,
+        T2: Type,
+    {
+        policy.check_cancelled()?;
+        self.invoke_raw_with_policy(input_list.to_raw(), output_list.to_raw(), policy)
+    }
+
+    // Invokes the function with the given raw lists, like `Function::invoke_with_policy`.
+    pub(crate) fn invoke_raw_with_policy(
+        &self,
+        inputs: *mut sys::iree_vm_list_t,
+        outputs: *mut sys::iree_vm_list_t,
+        policy: &api::InvocationPolicy,
+    ) -> Result<(), RuntimeError> {
+        match policy.cancellation_token() {
+            Some(token) if self.lookup_attr("iree.abi.model") == Some("coarse-fences") => {
+                self.invoke_until_cancelled(inputs, outputs, policy, token)
+            }
+            _ => self.invoke_raw(inputs, outputs, policy),
+        }
+    }
+
+    // Invokes the function with the VM, mapping a failure to `Cancelled` if the token of the
+    // policy was cancelled while it ran.
+    pub(crate) fn invoke_raw(
+        &self,
+        inputs: *mut sys::iree_vm_list_t,
+        outputs: *mut sys::iree_vm_list_t,
+        policy: &api::InvocationPolicy,
+    ) -> Result<(), RuntimeError> {
+        let raw_policy = policy.to_raw();
+        let result = base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_invoke, flags: {:#x}",
+                policy.invocation_flags().bits()
+            );
             sys::iree_vm_invoke(
                 self.session.context(),
                 self.ctx,
-                sys::iree_vm_invocation_flag_bits_t_IREE_VM_INVOCATION_FLAG_NONE,
-                core::ptr::null_mut(),
-                input_list.to_raw(),
-                output_list.to_raw(),
+                policy.invocation_flags().bits(),
+                &raw_policy,
+                inputs,
+                outputs,
                 self.session.get_allocator().ctx,
             )
         })
+        .to_result()
+        .map_err(RuntimeError::from);
+        policy.map_result(result)
+    }
+
+    // Invokes a function with the async-external ABI and blocks until its signal fence is
+    // signaled or the token is cancelled. The fences are appended to the inputs unless they are
+    // already there.
+    fn invoke_until_cancelled(
+        &self,
+        inputs: *mut sys::iree_vm_list_t,
+        outputs: *mut sys::iree_vm_list_t,
+        policy: &api::InvocationPolicy,
+        token: &api::CancellationToken,
+    ) -> Result<(), RuntimeError> {
+        let (argument_count, _) = self.signature().count_arguments_and_results()?;
+        let size = unsafe {
+            trace!("iree_vm_list_size");
+            sys::iree_vm_list_size(inputs)
+        };
+        let signal_fence = if size + 2 == argument_count {
+            self.invoke_async_raw(inputs, outputs, None, policy)?
+        } else {
+            self.invoke_raw(inputs, outputs, policy)?;
+            list_fence(self.session.instance, inputs, size.saturating_sub(1))?
+        };
+        // Waiting on each timepoint keeps the wait from returning once any of them is reached.
+        let device = self.session.device();
+        for (semaphore, value) in signal_fence.timepoints() {
+            token.wait_for(
+                &device,
+                &Fence::at(&semaphore, value)?,
+                hal::Timeout::Infinite,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the function as exported by its module.
+    pub fn name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_function_name");
+            StringView::from_raw(sys::iree_vm_function_name(&self.ctx)).into()
+        }
+    }
+
+    /// Returns the fully qualified name of the function, e.g. `module.serving_default`, as
+    /// accepted by [`api::Session::lookup_function`].
+    pub fn qualified_name(&self) -> String {
+        let module_name: &str = unsafe {
+            trace!("iree_vm_module_name");
+            StringView::from_raw(sys::iree_vm_module_name(self.ctx.module)).into()
+        };
+        format!("{}.{}", module_name, self.name())
+    }
+
+    /// Returns the calling convention signature of the function.
+    pub fn signature(&self) -> FunctionSignature<'_> {
+        FunctionSignature {
+            ctx: unsafe {
+                trace!("iree_vm_function_signature");
+                sys::iree_vm_function_signature(&self.ctx)
+            },
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    /// Looks up a reflection attribute by key, e.g. `iree.abi.declaration`.
+    /// Returns `None` if the function has no such attribute.
+    pub fn lookup_attr(&self, key: &str) -> Option<&str> {
+        let value: &str = unsafe {
+            trace!("iree_vm_function_lookup_attr_by_name, key: {}", key);
+            StringView::from_raw(sys::iree_vm_function_lookup_attr_by_name(
+                &self.ctx,
+                StringView::from(key).ctx,
+            ))
+            .into()
+        };
+        (!value.is_empty()).then_some(value)
+    }
+
+    /// Returns the ABI declaration of the function, e.g.
+    /// `sync func @simple_mul(%input0: tensor<4xf32>, %input1: tensor<4xf32>) -> (%output0: tensor<4xf32>)`.
+    /// Unlike the calling convention, it describes the shapes and element types of tensor
+    /// arguments. Returns `None` if the module was compiled without reflection metadata.
+    pub fn abi_declaration(&self) -> Option<&str> {
+        self.lookup_attr("iree.abi.declaration")
+    }
+
+    /// Parses the ABI declaration of the function into a typed signature, e.g. to check the
+    /// shapes of inputs before calling it. Returns `RuntimeError::SignatureMismatch` if the module
+    /// was compiled without reflection metadata or the declaration cannot be parsed.
+    pub fn abi_signature(&self) -> Result<AbiSignature, RuntimeError> {
+        let declaration = self.abi_declaration().ok_or_else(|| {
+            RuntimeError::SignatureMismatch(error_message!(
+                "function {} has no ABI declaration",
+                self.name()
+            ))
+        })?;
+        AbiSignature::parse(declaration)
+    }
+
+    /// Returns all reflection attributes of the function as key/value pairs.
+    pub fn reflection_attrs(&self) -> Vec<(String, String)> {
+        let mut attrs = Vec::new();
+        loop {
+            let mut pair = sys::iree_string_pair_t::default();
+            let status = base::Status::from_raw(unsafe {
+                trace!("iree_vm_function_get_attr, index: {}", attrs.len());
+                sys::iree_vm_function_get_attr(self.ctx, attrs.len(), &mut pair)
+            });
+            if status.to_result().is_err() {
+                break;
+            }
+            let (key, value): (&str, &str) = unsafe {
+                (
+                    StringView::from_raw(pair.__bindgen_anon_1.key).into(),
+                    StringView::from_raw(pair.__bindgen_anon_2.value).into(),
+                )
+            };
+            attrs.push((String::from(key), String::from(value)));
+        }
+        attrs
+    }
+
+    /// Synchronously invokes the function after checking the input list against the function
+    /// signature. Returns `RuntimeError::SignatureMismatch` describing the first offending
+    /// argument instead of an opaque runtime status when the inputs do not match.
+    pub fn invoke_typed<'b, T1, T2>(
+        &self,
+        input_list: &impl List<'b, T1>,
+        output_list: &impl List<'b, T2>,
+    ) -> Result<(), RuntimeError>
+    where
+        T1: Type,
+        T2: Type,
+    {
+        let arguments = self.signature().argument_types()?;
+        let size = input_list.size();
+        if size != arguments.len() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "function {} expects {} arguments, but {} were given",
+                self.name(),
+                arguments.len(),
+                size
+            )));
+        }
+        for (idx, expected) in arguments.iter().enumerate() {
+            let actual = list_element_type(input_list.to_raw(), idx)?;
+            if !expected.accepts(&actual) {
+                return Err(RuntimeError::SignatureMismatch(error_message!(
+                    "function {} expects argument {} to be {:?}, but got value type {} and ref \
+                     type {}",
+                    self.name(),
+                    idx,
+                    expected,
+                    actual.value_type_bits(),
+                    actual.ref_type_bits()
+                )));
+            }
+        }
+        self.invoke(input_list, output_list)
+    }
+
+    /// Invokes the function without waiting for the device work to complete.
+    ///
+    /// This is meant for functions compiled with `--iree-execution-model=async-external`, which
+    /// take a wait fence and a signal fence as their last two arguments. The inputs are copied
+    /// into a new list followed by both fences. The function starts executing once `wait_fence`
+    /// is signaled (or immediately if `None`), and the returned fence is signaled once all
+    /// results are ready. The output list may only be read after the returned fence has been
+    /// waited on, either with [`Fence::wait`] or, where threads are available, by awaiting it.
+    pub fn invoke_async<'b, T1, T2>(
+        &self,
+        input_list: &impl List<'b, T1>,
+        output_list: &impl List<'b, T2>,
+        wait_fence: Option<&Fence>,
+    ) -> Result<Fence, RuntimeError>
+    where
+        T1: Type,
+        T2: Type,
+    {
+        self.invoke_async_with_policy(
+            input_list,
+            output_list,
+            wait_fence,
+            &api::InvocationPolicy::new(),
+        )
+    }
+
+    /// Invokes the function without waiting for the device work to complete, like
+    /// [`Function::invoke_async`], with the given policy. Returns a `Cancelled` error without
+    /// invoking the function if the cancellation token of the policy has been cancelled.
+    ///
+    /// The returned fence is created on the device selected with
+    /// [`api::InvocationPolicy::device`], and the invocation is ordered on the queues selected
+    /// with [`api::InvocationPolicy::queue_affinity`], e.g. to run a module compiled for a CPU and
+    /// a GPU on a session created with [`api::Session::create_with_devices`].
+    pub fn invoke_async_with_policy<'b, T1, T2>(
+        &self,
+        input_list: &impl List<'b, T1>,
+        output_list: &impl List<'b, T2>,
+        wait_fence: Option<&Fence>,
+        policy: &api::InvocationPolicy,
+    ) -> Result<Fence, RuntimeError>
+    where
+        T1: Type,
+        T2: Type,
+    {
+        policy.check_cancelled()?;
+        self.invoke_async_raw(
+            input_list.to_raw(),
+            output_list.to_raw(),
+            wait_fence,
+            policy,
+        )
+    }
+
+    // Invokes the function with a copy of the given raw inputs followed by the fences, like
+    // `Function::invoke_async_with_policy`.
+    fn invoke_async_raw(
+        &self,
+        input_list: *mut sys::iree_vm_list_t,
+        output_list: *mut sys::iree_vm_list_t,
+        wait_fence: Option<&Fence>,
+        policy: &api::InvocationPolicy,
+    ) -> Result<Fence, RuntimeError> {
+        let instance = self.session.instance;
+        let (queue_wait_fence, signal_fence) = policy.async_fences(self.session, wait_fence)?;
+        let wait_fence = queue_wait_fence.as_ref().or(wait_fence);
+        let size = unsafe {
+            trace!("iree_vm_list_size");
+            sys::iree_vm_list_size(input_list)
+        };
+        let inputs = DynamicList::<Undefined>::new(size + 2, instance)?;
+        for idx in 0..size {
+            let mut variant = sys::iree_vm_variant_t::default();
+            base::Status::from_raw(unsafe {
+                trace!("iree_vm_list_get_variant_assign, idx: {}", idx);
+                sys::iree_vm_list_get_variant_assign(input_list, idx, &mut variant)
+            })
+            .to_result()?;
+            base::Status::from_raw(unsafe {
+                trace!("iree_vm_list_push_variant_retain");
+                sys::iree_vm_list_push_variant_retain(inputs.ctx, &variant)
+            })
+            .to_result()?;
+        }
+        wait_fence.push(inputs.ctx, instance)?;
+        (&signal_fence).push(inputs.ctx, instance)?;
+        self.invoke_raw(inputs.ctx, output_list, policy)?;
+        Ok(signal_fence)
+    }
+
+    /// Synchronously invokes the function with a tuple of Rust arguments, and converts the
+    /// results into a tuple of Rust values, e.g.
+    /// `let (out,): (BufferView<f32>,) = function.call((&lhs, &rhs))?;`.
+    /// The arity and types of both tuples are checked against the calling convention of the
+    /// function before it is invoked.
+    #[doc(alias = "call_typed")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = self.name()))
+    )]
+    pub fn call<A, R>(&self, arguments: A) -> Result<R, RuntimeError>
+    where
+        A: Arguments,
+        R: ReturnValues<'a>,
+    {
+        self.check_call_types::<A, R>()?;
+        let instance = self.session.instance;
+        let input_list =
+            DynamicList::<Undefined>::new(A::calling_convention_types().len(), instance)?;
+        arguments.push_all(input_list.ctx, instance)?;
+        let output_list =
+            DynamicList::<Undefined>::new(R::calling_convention_types().len(), instance)?;
+        self.invoke(&input_list, &output_list)?;
+        R::from_list(output_list.ctx, self.session)
+    }
+
+    // Checks the arity and types of argument and result tuples against the calling convention.
+    pub(crate) fn check_call_types<A, R>(&self) -> Result<(), RuntimeError>
+    where
+        A: Arguments,
+        R: ReturnValues<'a>,
+    {
+        let signature = self.signature();
+        for (kind, expected, actual) in [
+            (
+                "arguments",
+                signature.argument_types()?,
+                A::calling_convention_types(),
+            ),
+            (
+                "results",
+                signature.result_types()?,
+                R::calling_convention_types(),
+            ),
+        ] {
+            if expected != actual {
+                return Err(RuntimeError::SignatureMismatch(error_message!(
+                    "function {} expects {} {:?}, but the tuple has {:?}",
+                    self.name(),
+                    kind,
+                    expected,
+                    actual
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A Rust value that can be passed as an argument to [`Function::call`] and
+/// [`api::Call::invoke_typed`].
+#[allow(private_bounds)]
+pub trait Argument: IsArgument {}
+
+pub(crate) trait IsArgument {
+    const CALLING_CONVENTION_TYPE: CallingConventionType;
+
+    fn push(&self, list: *mut sys::iree_vm_list_t, instance: &Instance)
+        -> Result<(), RuntimeError>;
+}
+
+/// A Rust value that can be returned from [`Function::call`] and [`api::Call::invoke_typed`].
+#[allow(private_bounds)]
+pub trait ReturnValue<'a>: IsReturnValue<'a> {}
+
+pub(crate) trait IsReturnValue<'a>: Sized {
+    const CALLING_CONVENTION_TYPE: CallingConventionType;
+
+    fn get(
+        list: *mut sys::iree_vm_list_t,
+        idx: usize,
+        session: &'a api::Session<'a>,
+    ) -> Result<Self, RuntimeError>;
+}
+
+// Macro to implement Argument and ReturnValue for primitive types.
+macro_rules! impl_call_value {
+    ($type:ty, $cconv:ident) => {
+        impl IsArgument for $type {
+            const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::$cconv;
+
+            fn push(
+                &self,
+                list: *mut sys::iree_vm_list_t,
+                _: &Instance,
+            ) -> Result<(), RuntimeError> {
+                base::Status::from_raw(unsafe {
+                    trace!("iree_vm_list_push_value");
+                    sys::iree_vm_list_push_value(list, &self.to_value().ctx)
+                })
+                .to_result()?;
+                Ok(())
+            }
+        }
+
+        impl Argument for $type {}
+
+        impl IsReturnValue<'_> for $type {
+            const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::$cconv;
+
+            fn get(
+                list: *mut sys::iree_vm_list_t,
+                idx: usize,
+                _: &api::Session,
+            ) -> Result<Self, RuntimeError> {
+                let mut out = sys::iree_vm_value_t::default();
+                base::Status::from_raw(unsafe {
+                    trace!("iree_vm_list_get_value, idx: {}", idx);
+                    sys::iree_vm_list_get_value(list, idx, &mut out)
+                })
+                .to_result()?;
+                Ok(Value::<$type> {
+                    ctx: out,
+                    _marker: core::marker::PhantomData,
+                }
+                .from_value())
+            }
+        }
+
+        impl ReturnValue<'_> for $type {}
+    };
+}
+
+impl_call_value!(i32, I32);
+impl_call_value!(i64, I64);
+impl_call_value!(f32, F32);
+impl_call_value!(f64, F64);
+
+impl<T: ToElementType> IsArgument for &BufferView<'_, T> {
+    const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::Ref;
+
+    fn push(
+        &self,
+        list: *mut sys::iree_vm_list_t,
+        instance: &Instance,
+    ) -> Result<(), RuntimeError> {
+        let mut out = sys::iree_vm_ref_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_ref_wrap_retain");
+            sys::iree_vm_ref_wrap_retain(
+                self.ctx as *mut core::ffi::c_void,
+                BufferView::<T>::to_ref_type(instance),
+                &mut out,
+            )
+        })
         .to_result()?;
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_push_ref_retain");
+            sys::iree_vm_list_push_ref_retain(list, &out)
+        });
+        unsafe {
+            trace!("iree_vm_ref_release");
+            sys::iree_vm_ref_release(&mut out);
+        }
+        status.to_result()?;
         Ok(())
     }
 }
 
+impl<T: ToElementType> Argument for &BufferView<'_, T> {}
+
+// A missing fence is passed as a null ref, which async functions treat as already signaled.
+impl IsArgument for Option<&Fence> {
+    const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::Ref;
+
+    fn push(
+        &self,
+        list: *mut sys::iree_vm_list_t,
+        instance: &Instance,
+    ) -> Result<(), RuntimeError> {
+        match self {
+            Some(fence) => fence.push(list, instance),
+            None => {
+                base::Status::from_raw(unsafe {
+                    trace!("iree_vm_list_push_ref_retain");
+                    sys::iree_vm_list_push_ref_retain(list, &sys::iree_vm_ref_t::default())
+                })
+                .to_result()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Argument for Option<&Fence> {}
+
+// Semaphores, fences, and HAL and VM buffers are plain reference counted objects, so refs to them are passed as is.
+macro_rules! impl_call_ref {
+    ($type:ty, $raw:ty, $retain:ident) => {
+        impl IsArgument for &$type {
+            const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::Ref;
+
+            fn push(
+                &self,
+                list: *mut sys::iree_vm_list_t,
+                instance: &Instance,
+            ) -> Result<(), RuntimeError> {
+                let value_ref = self.to_ref(instance)?;
+                base::Status::from_raw(unsafe {
+                    trace!("iree_vm_list_push_ref_retain");
+                    sys::iree_vm_list_push_ref_retain(list, &value_ref.ctx)
+                })
+                .to_result()?;
+                Ok(())
+            }
+        }
+
+        impl IsReturnValue<'_> for $type {
+            const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::Ref;
+
+            fn get(
+                list: *mut sys::iree_vm_list_t,
+                idx: usize,
+                session: &api::Session,
+            ) -> Result<Self, RuntimeError> {
+                // Releases the ref retained from the list once the object has been retained.
+                let mut value_ref: Ref<$type> = Ref {
+                    ctx: sys::iree_vm_ref_t::default(),
+                    _instance: session.instance,
+                    _marker: core::marker::PhantomData,
+                };
+                base::Status::from_raw(unsafe {
+                    trace!("iree_vm_list_get_ref_retain, idx: {}", idx);
+                    sys::iree_vm_list_get_ref_retain(list, idx, &mut value_ref.ctx)
+                })
+                .to_result()?;
+                if value_ref.ctx.type_ != <$type>::to_ref_type(session.instance) {
+                    return Err(RuntimeError::SignatureMismatch(error_message!(
+                        "result {} is not a {}",
+                        idx,
+                        stringify!($type)
+                    )));
+                }
+                let ctx = value_ref.ctx.ptr as *mut $raw;
+                unsafe {
+                    trace!(stringify!($retain));
+                    sys::$retain(ctx);
+                }
+                Ok(Self { ctx })
+            }
+        }
+
+        impl ReturnValue<'_> for $type {}
+    };
+}
+
+impl_call_ref!(
+    Semaphore,
+    sys::iree_hal_semaphore_t,
+    iree_hal_semaphore_retain
+);
+impl_call_ref!(Fence, sys::iree_hal_fence_t, iree_hal_fence_retain);
+impl_call_ref!(hal::Buffer, sys::iree_hal_buffer_t, iree_hal_buffer_retain);
+impl_call_ref!(Buffer, sys::iree_vm_buffer_t, iree_vm_buffer_retain);
+
+impl Argument for &Semaphore {}
+impl Argument for &Fence {}
+impl Argument for &hal::Buffer {}
+impl Argument for &Buffer {}
+
+impl<'a, T: ToElementType> IsReturnValue<'a> for BufferView<'a, T> {
+    const CALLING_CONVENTION_TYPE: CallingConventionType = CallingConventionType::Ref;
+
+    fn get(
+        list: *mut sys::iree_vm_list_t,
+        idx: usize,
+        session: &'a api::Session<'a>,
+    ) -> Result<Self, RuntimeError> {
+        let mut out = sys::iree_vm_ref_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_get_ref_retain, idx: {}", idx);
+            sys::iree_vm_list_get_ref_retain(list, idx, &mut out)
+        })
+        .to_result()?;
+        if out.ptr.is_null() || out.type_ != Self::to_ref_type(session.instance) {
+            unsafe {
+                trace!("iree_vm_ref_release");
+                sys::iree_vm_ref_release(&mut out);
+            }
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "result {} is not a buffer view",
+                idx
+            )));
+        }
+        // The BufferView takes over the retained reference.
+        let buffer_view =
+            unsafe { BufferView::from_ptr(out.ptr as *mut sys::iree_hal_buffer_view_t, session) };
+        let element_type = unsafe {
+            trace!("iree_hal_buffer_view_element_type");
+            sys::iree_hal_buffer_view_element_type(buffer_view.ctx)
+        };
+        if element_type != T::to_element_type().into() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "result {} is not a buffer view of the requested element type",
+                idx
+            )));
+        }
+        Ok(buffer_view)
+    }
+}
+
+impl<'a, T: ToElementType> ReturnValue<'a> for BufferView<'a, T> {}
+
+/// A tuple of [`Argument`]s that can be passed to [`Function::call`].
+#[allow(private_bounds)]
+pub trait Arguments: IsArguments {}
+
+pub(crate) trait IsArguments {
+    fn calling_convention_types() -> Vec<CallingConventionType>;
+
+    fn push_all(
+        &self,
+        list: *mut sys::iree_vm_list_t,
+        instance: &Instance,
+    ) -> Result<(), RuntimeError>;
+}
+
+/// A tuple of [`ReturnValue`]s that can be returned from [`Function::call`].
+#[allow(private_bounds)]
+pub trait ReturnValues<'a>: IsReturnValues<'a> {}
+
+pub(crate) trait IsReturnValues<'a>: Sized {
+    fn calling_convention_types() -> Vec<CallingConventionType>;
+
+    fn from_list(
+        list: *mut sys::iree_vm_list_t,
+        session: &'a api::Session<'a>,
+    ) -> Result<Self, RuntimeError>;
+}
+
+// Macro to implement Arguments and ReturnValues for tuples.
+macro_rules! impl_call_tuple {
+    ($($type:ident $var:ident),*) => {
+        impl<$($type: Argument),*> IsArguments for ($($type,)*) {
+            fn calling_convention_types() -> Vec<CallingConventionType> {
+                alloc::vec![$(<$type as IsArgument>::CALLING_CONVENTION_TYPE),*]
+            }
+
+            #[allow(unused_variables)]
+            fn push_all(
+                &self,
+                list: *mut sys::iree_vm_list_t,
+                instance: &Instance,
+            ) -> Result<(), RuntimeError> {
+                let ($($var,)*) = self;
+                $($var.push(list, instance)?;)*
+                Ok(())
+            }
+        }
+
+        impl<$($type: Argument),*> Arguments for ($($type,)*) {}
+
+        impl<'a, $($type: ReturnValue<'a>),*> IsReturnValues<'a> for ($($type,)*) {
+            fn calling_convention_types() -> Vec<CallingConventionType> {
+                alloc::vec![$(<$type as IsReturnValue<'a>>::CALLING_CONVENTION_TYPE),*]
+            }
+
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn from_list(
+                list: *mut sys::iree_vm_list_t,
+                session: &'a api::Session<'a>,
+            ) -> Result<Self, RuntimeError> {
+                let mut idx = 0;
+                $(
+                    let $var = <$type as IsReturnValue<'a>>::get(list, idx, session)?;
+                    idx += 1;
+                )*
+                Ok(($($var,)*))
+            }
+        }
+
+        impl<'a, $($type: ReturnValue<'a>),*> ReturnValues<'a> for ($($type,)*) {}
+    };
+}
+
+impl_call_tuple!();
+impl_call_tuple!(A a);
+impl_call_tuple!(A a, B b);
+impl_call_tuple!(A a, B b, C c);
+impl_call_tuple!(A a, B b, C c, D d);
+impl_call_tuple!(A a, B b, C c, D d, E e);
+impl_call_tuple!(A a, B b, C c, D d, E e, F f);
+impl_call_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_call_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+/// A type in a function calling convention string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallingConventionType {
+    Void,
+    I32,
+    I64,
+    F32,
+    F64,
+    Ref,
+}
+
+impl CallingConventionType {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'v' => Some(Self::Void),
+            'i' => Some(Self::I32),
+            'I' => Some(Self::I64),
+            'f' => Some(Self::F32),
+            'F' => Some(Self::F64),
+            'r' => Some(Self::Ref),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a list element of the given type can be passed for this type.
+    fn accepts(&self, type_def: &sys::iree_vm_type_def_t) -> bool {
+        let value_type = type_def.value_type_bits() as sys::iree_vm_value_type_e;
+        match self {
+            Self::Void => false,
+            // Smaller integers are promoted by the VM when marshaling i32 arguments.
+            Self::I32 => matches!(
+                value_type,
+                sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I8
+                    | sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I16
+                    | sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I32
+            ),
+            Self::I64 => value_type == sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I64,
+            Self::F32 => value_type == sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_F32,
+            Self::F64 => value_type == sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_F64,
+            // Refs may also be null, which carry no value type either.
+            Self::Ref => value_type == sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_NONE,
+        }
+    }
+}
+
+/// The calling convention signature of a function.
+///
+/// The calling convention is a string of the form `0<arguments>_<results>`, for example `0rr_r`
+/// for a function taking two refs and returning one ref.
+pub struct FunctionSignature<'a> {
+    pub(crate) ctx: sys::iree_vm_function_signature_t,
+    _marker: core::marker::PhantomData<&'a Function<'a>>,
+}
+
+impl<'a> FunctionSignature<'a> {
+    /// Returns the raw calling convention string.
+    pub fn calling_convention(&self) -> &'a str {
+        unsafe { StringView::from_raw(self.ctx.calling_convention).into() }
+    }
+
+    /// Returns the arguments and results fragments of the calling convention string.
+    pub fn get_cconv_fragments(&self) -> Result<(&'a str, &'a str), RuntimeError> {
+        let mut arguments = sys::iree_string_view_t::default();
+        let mut results = sys::iree_string_view_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_function_call_get_cconv_fragments");
+            sys::iree_vm_function_call_get_cconv_fragments(&self.ctx, &mut arguments, &mut results)
+        })
+        .to_result()?;
+        unsafe {
+            Ok((
+                StringView::from_raw(arguments).into(),
+                StringView::from_raw(results).into(),
+            ))
+        }
+    }
+
+    /// Returns the number of arguments and results of the function.
+    pub fn count_arguments_and_results(&self) -> Result<(usize, usize), RuntimeError> {
+        let mut argument_count = 0;
+        let mut result_count = 0;
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_function_call_count_arguments_and_results");
+            sys::iree_vm_function_call_count_arguments_and_results(
+                &self.ctx,
+                &mut argument_count,
+                &mut result_count,
+            )
+        })
+        .to_result()?;
+        Ok((argument_count, result_count))
+    }
+
+    /// Returns the argument types of the function.
+    pub fn argument_types(&self) -> Result<Vec<CallingConventionType>, RuntimeError> {
+        parse_cconv_fragment(self.get_cconv_fragments()?.0)
+    }
+
+    /// Returns the result types of the function.
+    pub fn result_types(&self) -> Result<Vec<CallingConventionType>, RuntimeError> {
+        parse_cconv_fragment(self.get_cconv_fragments()?.1)
+    }
+}
+
+pub(crate) fn parse_cconv_fragment(
+    fragment: &str,
+) -> Result<Vec<CallingConventionType>, RuntimeError> {
+    fragment
+        .chars()
+        .filter(|&c| c != 'v')
+        .map(|c| {
+            CallingConventionType::from_char(c).ok_or_else(|| {
+                RuntimeError::SignatureMismatch(error_message!(
+                    "unsupported calling convention type '{}' in \"{}\"",
+                    c,
+                    fragment
+                ))
+            })
+        })
+        .collect()
+}
+
+// Returns the fence at the given index of a list, checking that the element is one.
+fn list_fence(
+    instance: &Instance,
+    list: *mut sys::iree_vm_list_t,
+    idx: usize,
+) -> Result<Fence, RuntimeError> {
+    let mut out = sys::iree_vm_ref_t::default();
+    base::Status::from_raw(unsafe {
+        trace!("iree_vm_list_get_ref_retain, idx: {}", idx);
+        sys::iree_vm_list_get_ref_retain(list, idx, &mut out)
+    })
+    .to_result()?;
+    let fence_ref: Ref<'_, Fence> = Ref {
+        ctx: out,
+        _instance: instance,
+        _marker: core::marker::PhantomData,
+    };
+    fence_ref.to_fence()
+}
+
+/// Returns the type of the list element at the given index.
+pub(crate) fn list_element_type(
+    list: *mut sys::iree_vm_list_t,
+    idx: usize,
+) -> Result<sys::iree_vm_type_def_t, RuntimeError> {
+    let mut out = sys::iree_vm_variant_t::default();
+    base::Status::from_raw(unsafe {
+        trace!("iree_vm_list_get_variant_assign, idx: {}", idx);
+        sys::iree_vm_list_get_variant_assign(list, idx, &mut out)
+    })
+    .to_result()?;
+    Ok(out.type_)
+}
+
 /// Trait for types that can be used as a List type.
 pub trait Type {
     fn to_raw(instance: &Instance) -> sys::iree_vm_type_def_t;
@@ -89,6 +983,15 @@ pub struct Value<T: ToValue> {
     _marker: core::marker::PhantomData<T>,
 }
 
+impl<T: ToValue> Value<T> {
+    pub(crate) fn from_raw(ctx: sys::iree_vm_value_t) -> Self {
+        Self {
+            ctx,
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
 // This means that Value can be inserted into a List.
 impl<T: ToValue> Type for Value<T> {
     fn to_raw(_: &Instance) -> sys::iree_vm_type_def_t {
@@ -143,15 +1046,128 @@ impl<'a, T: ToRef<'a>> Drop for Ref<'a, T> {
     }
 }
 
+impl<'a, T: ToRef<'a>> Ref<'a, T> {
+    /// Returns true if the ref holds no object, e.g. a list element that was never set.
+    pub fn is_null(&self) -> bool {
+        self.ctx.ptr.is_null()
+    }
+
+    /// Moves the reference out of the Ref, leaving a null ref behind, without retaining it.
+    pub fn take(&mut self) -> Self {
+        let mut out = sys::iree_vm_ref_t::default();
+        unsafe {
+            trace!("iree_vm_ref_move");
+            sys::iree_vm_ref_move(&mut self.ctx, &mut out);
+        }
+        Self {
+            ctx: out,
+            _instance: self._instance,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    // Returns the name of the runtime type of the ref, e.g. `hal.buffer_view`.
+    fn type_name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_ref_type_name");
+            StringView::from_raw(sys::iree_vm_ref_type_name(self.ctx.type_)).into()
+        }
+    }
+
+    // Returns `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `T`, whose
+    // runtime type is named `expected`, before the object it points to is read.
+    pub(crate) fn check_type(&self, expected: &str) -> Result<(), RuntimeError> {
+        if self.is_null() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "the ref is null, not a {}",
+                expected
+            )));
+        }
+        if self.ctx.type_ != T::to_ref_type(self._instance) {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "the ref holds a {}, not a {}",
+                self.type_name(),
+                expected
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: ToRef<'a>> Clone for Ref<'a, T> {
+    fn clone(&self) -> Self {
+        // The source is only read, the runtime takes a mutable pointer for historical reasons.
+        let mut source = self.ctx;
+        let mut out = sys::iree_vm_ref_t::default();
+        unsafe {
+            trace!("iree_vm_ref_retain");
+            sys::iree_vm_ref_retain(&mut source, &mut out);
+        }
+        Self {
+            ctx: out,
+            _instance: self._instance,
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
+// Refs are equal if they point to the same object.
+impl<'a, T: ToRef<'a>> PartialEq for Ref<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe {
+            trace!("iree_vm_ref_equal");
+            sys::iree_vm_ref_equal(&self.ctx, &other.ctx)
+        }
+    }
+}
+
+impl<'a, T: ToRef<'a>> Eq for Ref<'a, T> {}
+
 impl<'a, T: ToElementType> Ref<'a, BufferView<'a, T>> {
-    /// Returns Ref to the BufferView
+    /// Returns the BufferView the Ref points to, which is retained. The type of the ref is not
+    /// checked, use [`Ref::try_to_buffer_view`] for refs read from lists of unknown types.
     pub fn to_buffer_view(&self, session: &'a api::Session) -> BufferView<'a, T> {
+        let ctx = self.ctx.ptr as *mut sys::iree_hal_buffer_view_t;
+        unsafe {
+            trace!("iree_hal_buffer_view_retain");
+            sys::iree_hal_buffer_view_retain(ctx);
+        }
         BufferView {
-            ctx: self.ctx.ptr as *mut sys::iree_hal_buffer_view_t,
+            ctx,
             session,
             marker: core::marker::PhantomData,
         }
     }
+
+    /// Returns the BufferView the Ref points to, checking that the ref holds a buffer view with
+    /// elements of type `T`. Returns `RuntimeError::SignatureMismatch` otherwise, e.g. for a null
+    /// ref or a buffer view of `i32` read as `f32`.
+    pub fn try_to_buffer_view(
+        &self,
+        session: &'a api::Session,
+    ) -> Result<BufferView<'a, T>, RuntimeError> {
+        let buffer_view = self.try_to_untyped_buffer_view(session)?;
+        if buffer_view.element_type() != T::to_element_type() {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "the buffer view has element type {:?}, not {:?}",
+                buffer_view.element_type(),
+                T::to_element_type()
+            )));
+        }
+        Ok(buffer_view)
+    }
+
+    /// Returns the BufferView the Ref points to, checking that the ref holds a buffer view but
+    /// not its element type, e.g. to read results whose element type is only known at runtime as
+    /// `BufferView<u8>`. Returns `RuntimeError::SignatureMismatch` for a null ref or a ref of
+    /// another type.
+    pub fn try_to_untyped_buffer_view(
+        &self,
+        session: &'a api::Session,
+    ) -> Result<BufferView<'a, T>, RuntimeError> {
+        self.check_type("hal.buffer_view")?;
+        Ok(self.to_buffer_view(session))
+    }
 }
 
 /// Trait for types that can be used as VM references.
@@ -184,6 +1200,14 @@ pub(crate) trait IsList<'a, T: Type> {
 #[allow(private_bounds)]
 // Private bounds are needed because the IsList cannot be implemented for other types.
 pub trait List<'a, T: Type>: IsList<'a, T> {
+    /// Returns the number of elements in the list.
+    fn size(&self) -> usize {
+        unsafe {
+            trace!("iree_vm_list_size");
+            sys::iree_vm_list_size(self.to_raw())
+        }
+    }
+
     /// Returns value at the given index. The caller must specify the type of the value, which
     /// must match the type of the value at the given index.
     fn get_value<A: ToValue>(&self, idx: usize) -> Result<Value<A>, RuntimeError> {
@@ -247,10 +1271,252 @@ pub trait List<'a, T: Type>: IsList<'a, T> {
             _marker: core::marker::PhantomData,
         })
     }
+
+    /// Get a Ref from the list without retaining it. The Ref borrows the reference held by the
+    /// list, so it must not be dropped into a release, which `ManuallyDrop` prevents.
+    /// # Safety
+    /// The element must not be replaced or removed, and the list must not be dropped, while the
+    /// Ref is in use. The type `A` must match the type of the Ref at the given index.
+    unsafe fn get_ref_assign<A: ToRef<'a>>(
+        &'a self,
+        idx: usize,
+    ) -> Result<core::mem::ManuallyDrop<Ref<'a, A>>, RuntimeError> {
+        let mut out = sys::iree_vm_ref_t::default();
+        let status = unsafe {
+            trace!("iree_vm_list_get_ref_assign, idx: {}", idx);
+            sys::iree_vm_list_get_ref_assign(self.to_raw(), idx, &mut out)
+        };
+        base::Status::from_raw(status).to_result()?;
+        Ok(core::mem::ManuallyDrop::new(Ref {
+            ctx: out,
+            _instance: self.instance(),
+            _marker: core::marker::PhantomData,
+        }))
+    }
+
+    /// Sets the Ref at the given index, replacing the element there. The Ref is retained.
+    fn set_ref<A: ToRef<'a>>(&self, idx: usize, value: &Ref<'a, A>) -> Result<(), RuntimeError> {
+        let status = unsafe {
+            trace!("iree_vm_list_set_ref_retain, idx: {}", idx);
+            sys::iree_vm_list_set_ref_retain(self.to_raw(), idx, &value.ctx)
+        };
+        base::Status::from_raw(status).to_result()?;
+        Ok(())
+    }
+
+    /// Sets the Ref at the given index, moving the reference into the list instead of retaining
+    /// it.
+    fn set_ref_move<A: ToRef<'a>>(
+        &self,
+        idx: usize,
+        mut value: Ref<'a, A>,
+    ) -> Result<(), RuntimeError> {
+        // On success the runtime resets the moved ref, so dropping it releases nothing.
+        let status = unsafe {
+            trace!("iree_vm_list_set_ref_move, idx: {}", idx);
+            sys::iree_vm_list_set_ref_move(self.to_raw(), idx, &mut value.ctx)
+        };
+        base::Status::from_raw(status).to_result()?;
+        Ok(())
+    }
+
+    /// Push a Ref to the end of the list, moving the reference into the list instead of
+    /// retaining it.
+    fn push_ref_move<A: ToRef<'a>>(&self, mut value: Ref<'a, A>) -> Result<(), RuntimeError> {
+        let status = unsafe {
+            trace!("iree_vm_list_push_ref_move");
+            sys::iree_vm_list_push_ref_move(self.to_raw(), &mut value.ctx)
+        };
+        base::Status::from_raw(status).to_result()?;
+        Ok(())
+    }
+
+    /// Returns the type of the element at the given index, e.g. to unpack the outputs of a
+    /// function whose result types are not known in advance.
+    fn element_type(&self, idx: usize) -> Result<VariantType, RuntimeError> {
+        list_element_type(self.to_raw(), idx).map(|type_def| VariantType::from_raw(&type_def))
+    }
+
+    /// Returns the element at the given index whatever its type. Refs are retained.
+    fn get_variant(&self, idx: usize) -> Result<Variant, RuntimeError> {
+        let mut out = sys::iree_vm_variant_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_list_get_variant_assign, idx: {}", idx);
+            sys::iree_vm_list_get_variant_assign(self.to_raw(), idx, &mut out)
+        })
+        .to_result()?;
+        let value = unsafe {
+            match VariantType::from_raw(&out.type_) {
+                VariantType::Empty => Variant::Empty,
+                VariantType::I8 => Variant::I8(out.__bindgen_anon_1.i8_),
+                VariantType::I16 => Variant::I16(out.__bindgen_anon_1.i16_),
+                VariantType::I32 => Variant::I32(out.__bindgen_anon_1.i32_),
+                VariantType::I64 => Variant::I64(out.__bindgen_anon_1.i64_),
+                VariantType::F32 => Variant::F32(out.__bindgen_anon_1.f32_),
+                VariantType::F64 => Variant::F64(out.__bindgen_anon_1.f64_),
+                VariantType::Ref => Variant::Ref(NativeRef::retain(&mut out.__bindgen_anon_1.ref_)),
+            }
+        };
+        Ok(value)
+    }
+
+    /// Returns an iterator over the elements of the list whatever their types, see
+    /// [`List::get_variant`].
+    fn iter(&self) -> ListIter<'_, Self, Variant>
+    where
+        Self: Sized,
+    {
+        ListIter::new(self, self.size(), Self::get_variant)
+    }
+
+    /// Returns an iterator over the elements of a list of values of type `A`, see
+    /// [`List::get_value`].
+    fn values<A: ToValue>(&self) -> ListIter<'_, Self, Value<A>>
+    where
+        Self: Sized,
+    {
+        ListIter::new(self, self.size(), Self::get_value::<A>)
+    }
+
+    /// Returns an iterator over the elements of a list of refs of type `A`, see
+    /// [`List::get_ref`]. Refs are retained.
+    fn refs<A: ToRef<'a>>(&'a self) -> ListIter<'a, Self, Ref<'a, A>>
+    where
+        Self: Sized,
+    {
+        ListIter::new(self, self.size(), Self::get_ref::<A>)
+    }
+}
+
+/// An iterator over the elements of a [`List`], see [`List::iter`]. Elements are read when the
+/// iterator reaches them, so reading one may fail if its type does not match.
+pub struct ListIter<'l, L, I> {
+    list: &'l L,
+    indices: core::ops::Range<usize>,
+    get: fn(&'l L, usize) -> Result<I, RuntimeError>,
+}
+
+impl<'l, L, I> ListIter<'l, L, I> {
+    fn new(list: &'l L, size: usize, get: fn(&'l L, usize) -> Result<I, RuntimeError>) -> Self {
+        Self {
+            list,
+            indices: 0..size,
+            get,
+        }
+    }
+}
+
+impl<L, I> Iterator for ListIter<'_, L, I> {
+    type Item = Result<I, RuntimeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|idx| (self.get)(self.list, idx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<L, I> DoubleEndedIterator for ListIter<'_, L, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.indices
+            .next_back()
+            .map(|idx| (self.get)(self.list, idx))
+    }
+}
+
+impl<L, I> ExactSizeIterator for ListIter<'_, L, I> {}
+
+/// The type of a list element, see [`List::element_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantType {
+    /// An element without a value, e.g. of a list that was resized.
+    Empty,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    /// A ref of any type. Use [`NativeRef::is`] on the element to tell ref types apart.
+    Ref,
+}
+
+impl VariantType {
+    fn from_raw(type_def: &sys::iree_vm_type_def_t) -> Self {
+        match type_def.value_type_bits() as sys::iree_vm_value_type_e {
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I8 => Self::I8,
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I16 => Self::I16,
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I32 => Self::I32,
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_I64 => Self::I64,
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_F32 => Self::F32,
+            sys::iree_vm_value_type_e_IREE_VM_VALUE_TYPE_F64 => Self::F64,
+            _ if type_def.ref_type_bits()
+                != sys::iree_vm_ref_type_bits_t_IREE_VM_REF_TYPE_NULL as usize =>
+            {
+                Self::Ref
+            }
+            _ => Self::Empty,
+        }
+    }
+}
+
+/// A list element of any type, see [`List::get_variant`].
+#[derive(Debug, Clone)]
+pub enum Variant {
+    Empty,
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    /// A ref, which may be null. Use [`NativeRef::to_ref`] to get it as a typed Ref.
+    Ref(NativeRef),
+}
+
+impl Variant {
+    /// Returns the type of the element.
+    pub fn variant_type(&self) -> VariantType {
+        match self {
+            Self::Empty => VariantType::Empty,
+            Self::I8(_) => VariantType::I8,
+            Self::I16(_) => VariantType::I16,
+            Self::I32(_) => VariantType::I32,
+            Self::I64(_) => VariantType::I64,
+            Self::F32(_) => VariantType::F32,
+            Self::F64(_) => VariantType::F64,
+            Self::Ref(_) => VariantType::Ref,
+        }
+    }
+}
+
+/// Aligned storage for a [`StaticList`] of up to `N` bytes, e.g. on the stack or in a `static`,
+/// so that lists can be used without a heap.
+#[repr(C, align(16))]
+pub struct ListStorage<const N: usize>([u8; N]);
+
+impl<const N: usize> ListStorage<N> {
+    pub const fn new() -> Self {
+        Self([0; N])
+    }
+
+    /// Returns the storage as a byte span to initialize a [`StaticList`] with.
+    pub fn as_byte_span(&mut self) -> ByteSpan<'_> {
+        ByteSpan::from(&mut self.0[..])
+    }
+}
+
+impl<const N: usize> Default for ListStorage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Static list type, used for passing lists of values to functions. Use when the size of the list
-/// is known at compile time.
+/// is known at compile time. The list lives in the given storage and never allocates, see
+/// [`ListStorage`].
 pub struct StaticList<'a, T: Type> {
     pub(crate) ctx: *mut sys::iree_vm_list_t,
     instance: &'a super::api::Instance,
@@ -258,18 +1524,34 @@ pub struct StaticList<'a, T: Type> {
 }
 
 impl<'a, T: Type> StaticList<'a, T> {
-    /// Creates a new static list with the given capacity at the given buffer.
+    /// Returns the number of bytes of storage a list with the given capacity needs.
+    pub fn storage_size(capacity: usize, instance: &super::api::Instance) -> usize {
+        unsafe {
+            trace!("iree_vm_list_storage_size");
+            sys::iree_vm_list_storage_size(&T::to_raw(instance), capacity)
+        }
+    }
+
+    /// Creates a new static list with the given capacity at the given buffer. Returns
+    /// `RuntimeError::ShapeMismatch` if the buffer is smaller than [`StaticList::storage_size`].
     pub fn new(
         storage: ByteSpan<'a>,
         capacity: usize,
         instance: &'a super::api::Instance,
     ) -> Result<Self, RuntimeError> {
+        let size = Self::storage_size(capacity, instance);
+        if storage.ctx.data_length < size {
+            return Err(RuntimeError::ShapeMismatch(error_message!(
+                "a list of capacity {} needs {} bytes of storage, got {}",
+                capacity,
+                size,
+                storage.ctx.data_length
+            )));
+        }
         let mut out = core::ptr::null_mut();
         let status = unsafe {
-            trace!("iree_vm_list_storage_size");
-            let size = sys::iree_vm_list_storage_size(&T::to_raw(instance), capacity);
             trace!("iree_vm_list_initialize, size: {}", size);
-            sys::iree_vm_list_initialize(storage.ctx, &T::to_raw(instance), size, &mut out)
+            sys::iree_vm_list_initialize(storage.ctx, &T::to_raw(instance), capacity, &mut out)
         };
         base::Status::from_raw(status).to_result()?;
         Ok(Self {
@@ -300,6 +1582,15 @@ impl<'a, T: Type> IsList<'a, T> for StaticList<'a, T> {
     }
 }
 
+impl<'l, 'a, T: Type> IntoIterator for &'l StaticList<'a, T> {
+    type Item = Result<Variant, RuntimeError>;
+    type IntoIter = ListIter<'l, StaticList<'a, T>, Variant>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl<T: Type> Drop for StaticList<'_, T> {
     fn drop(&mut self) {
         unsafe {
@@ -395,6 +1686,50 @@ impl<'a, T: Type> IsList<'a, T> for DynamicList<'a, T> {
     }
 }
 
+// Lists are refs themselves, so they can be nested in lists and passed to functions taking a
+// `!util.list`.
+impl<'a, T: Type> ToRef<'a> for DynamicList<'a, T> {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("vm.list".into())
+    }
+}
+
+impl<'a, T: Type> Ref<'a, DynamicList<'a, T>> {
+    /// Returns the list held by the Ref, which is retained, e.g. a list returned by a function.
+    /// Returns `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `vm.list`.
+    /// The element type of the list is not checked against `T`.
+    pub fn to_list(&self) -> Result<DynamicList<'a, T>, RuntimeError> {
+        self.check_type("vm.list")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_vm_list_t;
+        unsafe {
+            trace!("iree_vm_list_retain");
+            sys::iree_vm_list_retain(ctx);
+        }
+        Ok(DynamicList {
+            ctx,
+            _instance: self._instance,
+            _marker: core::marker::PhantomData,
+        })
+    }
+}
+
+impl<'l, 'a, T: Type> IntoIterator for &'l DynamicList<'a, T> {
+    type Item = Result<Variant, RuntimeError>;
+    type IntoIter = ListIter<'l, DynamicList<'a, T>, Variant>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl<T: Type> Drop for DynamicList<'_, T> {
     fn drop(&mut self) {
         unsafe {
@@ -0,0 +1,240 @@
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use super::super::{error::RuntimeError, hal::ElementType};
+
+/// A function signature parsed from its `iree.abi.declaration` reflection attribute, see
+/// [`super::Function::abi_signature`].
+///
+/// Unlike the calling convention, which only distinguishes values from refs, it describes the
+/// shapes and element types of tensor arguments and results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiSignature {
+    /// Name of the function, without the module name.
+    pub name: String,
+    /// True if the function takes a wait fence and a signal fence after its arguments.
+    pub is_async: bool,
+    pub arguments: Vec<AbiValue>,
+    pub results: Vec<AbiValue>,
+}
+
+/// An argument or result of an [`AbiSignature`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiValue {
+    /// Name of the value in the declaration, e.g. `input0`.
+    pub name: String,
+    pub value_type: AbiType,
+}
+
+/// The type of an [`AbiValue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    /// A tensor, passed as a buffer view.
+    Tensor(TensorType),
+    /// A scalar, e.g. `i32` or `f32`.
+    Scalar(ElementType),
+    /// Any other type, e.g. `!hal.fence` or `!util.list<?>`, as written in the declaration.
+    Other(String),
+}
+
+/// The shape and element type of a tensor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorType {
+    /// Dimensions of the tensor. Dynamic dimensions are `None`.
+    pub shape: Vec<Option<usize>>,
+    /// Element type of the tensor. Types without a HAL counterpart are `ElementType::None`.
+    pub element_type: ElementType,
+}
+
+impl TensorType {
+    /// Returns the shape of the tensor if none of its dimensions are dynamic.
+    pub fn static_shape(&self) -> Option<Vec<usize>> {
+        self.shape.iter().copied().collect()
+    }
+
+    /// Returns true if a buffer view of the given shape can be passed for the tensor, i.e. the
+    /// ranks are equal and every static dimension matches.
+    pub fn accepts_shape(&self, shape: &[usize]) -> bool {
+        self.shape.len() == shape.len()
+            && self
+                .shape
+                .iter()
+                .zip(shape)
+                .all(|(expected, actual)| expected.is_none() || *expected == Some(*actual))
+    }
+}
+
+impl AbiSignature {
+    /// Parses an ABI declaration, e.g.
+    /// `sync func @simple_mul(%input0: tensor<4xf32>, %input1: tensor<4xf32>) -> (%output0: tensor<4xf32>)`.
+    pub fn parse(declaration: &str) -> Result<Self, RuntimeError> {
+        let invalid = || {
+            RuntimeError::SignatureMismatch(error_message!(
+                "invalid ABI declaration \"{}\"",
+                declaration
+            ))
+        };
+        let declaration = declaration.trim();
+        let (is_async, rest) = if let Some(rest) = declaration.strip_prefix("async func @") {
+            (true, rest)
+        } else if let Some(rest) = declaration.strip_prefix("sync func @") {
+            (false, rest)
+        } else {
+            return Err(invalid());
+        };
+        let open = rest.find('(').ok_or_else(invalid)?;
+        let close = matching_paren(rest, open).ok_or_else(invalid)?;
+        let name = rest[..open].trim();
+        let arguments = parse_values(&rest[open + 1..close]).ok_or_else(invalid)?;
+        let results = match rest[close + 1..].trim() {
+            "" => Vec::new(),
+            results => {
+                let results = results.strip_prefix("->").ok_or_else(invalid)?.trim();
+                let results = match results.strip_prefix('(') {
+                    Some(inner) => inner.strip_suffix(')').ok_or_else(invalid)?,
+                    None => results,
+                };
+                parse_values(results).ok_or_else(invalid)?
+            }
+        };
+        Ok(Self {
+            name: String::from(name),
+            is_async,
+            arguments,
+            results,
+        })
+    }
+}
+
+// Returns the index of the parenthesis closing the one at `open`.
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|&(i, _)| i < open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Splits at commas that are not nested in brackets, e.g. in `tensor<4xf32, #encoding>`.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn parse_values(s: &str) -> Option<Vec<AbiValue>> {
+    if s.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    split_top_level(s)
+        .into_iter()
+        .map(|value| {
+            let (name, value_type) = value.split_once(':')?;
+            Some(AbiValue {
+                name: String::from(name.trim().trim_start_matches('%')),
+                value_type: parse_type(strip_attributes(value_type.trim()))?,
+            })
+        })
+        .collect()
+}
+
+// Drops a trailing attribute dictionary, e.g. `{iree.abi.name = "x"}`.
+fn strip_attributes(s: &str) -> &str {
+    match attributes_start(s) {
+        Some(i) => s[..i].trim(),
+        None => s,
+    }
+}
+
+fn attributes_start(s: &str) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            '{' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_type(s: &str) -> Option<AbiType> {
+    if s.is_empty() {
+        return None;
+    }
+    if let Some(inner) = s.strip_prefix("tensor<").and_then(|s| s.strip_suffix('>')) {
+        // Drops the encoding, if any.
+        let mut rest = split_top_level(inner)[0].trim();
+        let mut shape = Vec::new();
+        // Stops at the element type, which may contain an `x` itself, e.g. `complex<f32>`.
+        while let Some((dim, tail)) = rest.split_once('x') {
+            match dim {
+                "?" => shape.push(None),
+                dim => match dim.parse() {
+                    Ok(dim) => shape.push(Some(dim)),
+                    Err(_) => break,
+                },
+            }
+            rest = tail;
+        }
+        return Some(AbiType::Tensor(TensorType {
+            shape,
+            element_type: parse_element_type(rest),
+        }));
+    }
+    match parse_element_type(s) {
+        ElementType::None => Some(AbiType::Other(String::from(s))),
+        element_type => Some(AbiType::Scalar(element_type)),
+    }
+}
+
+fn parse_element_type(s: &str) -> ElementType {
+    match s {
+        "i1" => ElementType::Bool8,
+        "i4" => ElementType::Int4,
+        "si4" => ElementType::Sint4,
+        "ui4" => ElementType::Uint4,
+        "i8" => ElementType::Int8,
+        "si8" => ElementType::Sint8,
+        "ui8" => ElementType::Uint8,
+        "i16" => ElementType::Int16,
+        "si16" => ElementType::Sint16,
+        "ui16" => ElementType::Uint16,
+        "i32" => ElementType::Int32,
+        "si32" => ElementType::Sint32,
+        "ui32" => ElementType::Uint32,
+        "i64" => ElementType::Int64,
+        "si64" => ElementType::Sint64,
+        "ui64" => ElementType::Uint64,
+        "f16" => ElementType::Float16,
+        "bf16" => ElementType::BFloat16,
+        "f32" => ElementType::Float32,
+        "f64" => ElementType::Float64,
+        "complex<f32>" => ElementType::ComplexFloat64,
+        "complex<f64>" => ElementType::ComplexFloat128,
+        _ => ElementType::None,
+    }
+}
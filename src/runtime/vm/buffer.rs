@@ -0,0 +1,146 @@
+extern crate alloc;
+use alloc::{string::String, vec, vec::Vec};
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{api::Instance, base, error::RuntimeError, hal::semaphore::wrap_retain},
+    Ref, ToRef,
+};
+
+// Alignment of the contents, enough for any element type the host reads from them.
+const BUFFER_ALIGNMENT: usize = 16;
+
+/// A VM byte buffer (`!vm.buffer`), e.g. tokenized text or a serialized message passed to or
+/// returned from a function.
+///
+/// Unlike HAL buffers, VM buffers live in host memory and are read and written by the VM
+/// itself.
+pub struct Buffer {
+    pub(crate) ctx: *mut sys::iree_vm_buffer_t,
+}
+
+// VM buffers are reference counted atomically and their handles can be used from any thread.
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    /// Creates a mutable buffer holding a copy of the given bytes.
+    pub fn from_bytes(instance: &Instance, data: &[u8]) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_buffer_create, length: {}", data.len());
+            sys::iree_vm_buffer_create(
+                sys::iree_vm_buffer_access_bits_t_IREE_VM_BUFFER_ACCESS_MUTABLE
+                    | sys::iree_vm_buffer_access_bits_t_IREE_VM_BUFFER_ACCESS_ORIGIN_HOST,
+                data.len(),
+                BUFFER_ALIGNMENT,
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        let buffer = Self { ctx: out };
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_buffer_write_elements");
+            sys::iree_vm_buffer_write_elements(
+                data.as_ptr() as *const core::ffi::c_void,
+                buffer.ctx,
+                0,
+                data.len(),
+                1,
+            )
+        })
+        .to_result()?;
+        Ok(buffer)
+    }
+
+    /// Creates a mutable buffer holding a copy of the given string, without a terminating null
+    /// character.
+    pub fn from_str(instance: &Instance, data: &str) -> Result<Self, RuntimeError> {
+        Self::from_bytes(instance, data.as_bytes())
+    }
+
+    /// Returns the length of the buffer in bytes.
+    pub fn len(&self) -> usize {
+        unsafe {
+            trace!("iree_vm_buffer_length");
+            sys::iree_vm_buffer_length(self.ctx)
+        }
+    }
+
+    /// Returns true if the buffer holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the contents of the buffer into a new vector.
+    pub fn to_vec(&self) -> Result<Vec<u8>, RuntimeError> {
+        let mut data = vec![0u8; self.len()];
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_buffer_read_elements");
+            sys::iree_vm_buffer_read_elements(
+                self.ctx,
+                0,
+                data.as_mut_ptr() as *mut core::ffi::c_void,
+                data.len(),
+                1,
+            )
+        })
+        .to_result()?;
+        Ok(data)
+    }
+
+    /// Copies the contents of the buffer into a string, replacing invalid UTF-8 sequences.
+    pub fn to_string_lossy(&self) -> Result<String, RuntimeError> {
+        Ok(String::from_utf8_lossy(&self.to_vec()?).into_owned())
+    }
+}
+
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_vm_buffer_retain");
+            sys::iree_vm_buffer_retain(self.ctx);
+        }
+        Self { ctx: self.ctx }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_vm_buffer_release");
+            sys::iree_vm_buffer_release(self.ctx);
+        }
+    }
+}
+
+impl<'a> ToRef<'a> for Buffer {
+    fn to_ref(&'a self, instance: &'a Instance) -> Result<Ref<'a, Self>, RuntimeError> {
+        wrap_retain(
+            self.ctx as *mut core::ffi::c_void,
+            instance,
+            Self::to_ref_type(instance),
+        )
+    }
+
+    fn to_ref_type(instance: &Instance) -> sys::iree_vm_ref_type_t {
+        instance.lookup_type("vm.buffer".into())
+    }
+}
+
+impl<'a> Ref<'a, Buffer> {
+    /// Returns the buffer held by the Ref, which is retained, e.g. a buffer returned by a function.
+    /// Returns `RuntimeError::SignatureMismatch` if the ref is null or does not hold a `vm.buffer`.
+    pub fn to_buffer(&self) -> Result<Buffer, RuntimeError> {
+        self.check_type("vm.buffer")?;
+        let ctx = self.ctx.ptr as *mut sys::iree_vm_buffer_t;
+        unsafe {
+            trace!("iree_vm_buffer_retain");
+            sys::iree_vm_buffer_retain(ctx);
+        }
+        Ok(Buffer { ctx })
+    }
+}
@@ -0,0 +1,334 @@
+extern crate alloc;
+use alloc::vec::Vec;
+
+use core::marker::PhantomData;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{
+        api::Instance,
+        base::{self, StringView},
+        error::RuntimeError,
+    },
+    IsList, List, Module, Type,
+};
+
+/// Flags of a VM context, see [`ContextOptions::flags`] and
+/// [`super::super::api::SessionOptions::context_flags`].
+///
+/// Flags can be combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextFlags(sys::iree_vm_context_flags_t);
+
+impl ContextFlags {
+    /// A context with no special behavior.
+    pub const NONE: Self = Self(sys::iree_vm_context_flag_bits_t_IREE_VM_CONTEXT_FLAG_NONE as _);
+    /// Logs every instruction executed in the context. Only has an effect if the runtime was
+    /// built with execution tracing.
+    pub const TRACE_EXECUTION: Self =
+        Self(sys::iree_vm_context_flag_bits_t_IREE_VM_CONTEXT_FLAG_TRACE_EXECUTION as _);
+    /// Allows the context to be used from multiple threads concurrently, at the cost of
+    /// synchronizing module state.
+    pub const CONCURRENT: Self =
+        Self(sys::iree_vm_context_flag_bits_t_IREE_VM_CONTEXT_FLAG_CONCURRENT as _);
+
+    /// Returns the raw flag bits.
+    pub fn bits(&self) -> sys::iree_vm_context_flags_t {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for ContextFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for ContextFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Options used to configure a [`Context`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextOptions {
+    flags: ContextFlags,
+}
+
+impl ContextOptions {
+    /// Creates options for a context with no flags set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the flags of the context, e.g. `ContextFlags::CONCURRENT`.
+    pub fn flags(mut self, flags: ContextFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+/// A VM context, the low-level counterpart of a session.
+///
+/// A context holds the modules it was given and the state they need to run, without creating any
+/// of them on its own. Use it instead of a session when the set of modules, including the HAL
+/// module created with [`super::super::hal::create_hal_module`], is assembled by hand.
+pub struct Context<'a> {
+    pub(crate) ctx: *mut sys::iree_vm_context_t,
+    instance: &'a Instance,
+}
+
+// Context is thread-compatible.
+unsafe impl Send for Context<'_> {}
+
+impl<'a> Context<'a> {
+    /// Creates an empty context with default options. Modules can be registered until the
+    /// context is frozen.
+    pub fn new(instance: &'a Instance) -> Result<Self, RuntimeError> {
+        Self::with_options(instance, &ContextOptions::default())
+    }
+
+    /// Creates an empty context with the given options.
+    pub fn with_options(
+        instance: &'a Instance,
+        options: &ContextOptions,
+    ) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_context_create, flags: {:#x}", options.flags.bits());
+            sys::iree_vm_context_create(
+                instance.get_vm_instance(),
+                options.flags.bits(),
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out, instance })
+    }
+
+    /// Creates a context with the given modules, in registration order. Modules must be given
+    /// after the modules they import from.
+    pub fn create_with_modules(
+        instance: &'a Instance,
+        modules: &[&Module],
+    ) -> Result<Self, RuntimeError> {
+        Self::create_with_modules_and_options(instance, modules, &ContextOptions::default())
+    }
+
+    /// Creates a context with the given modules and options.
+    pub fn create_with_modules_and_options(
+        instance: &'a Instance,
+        modules: &[&Module],
+        options: &ContextOptions,
+    ) -> Result<Self, RuntimeError> {
+        let mut module_ptrs = Self::module_ptrs(instance, modules)?;
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_context_create_with_modules, module count: {}",
+                module_ptrs.len()
+            );
+            sys::iree_vm_context_create_with_modules(
+                instance.get_vm_instance(),
+                options.flags.bits(),
+                module_ptrs.len(),
+                module_ptrs.as_mut_ptr(),
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out, instance })
+    }
+
+    fn module_ptrs(
+        instance: &Instance,
+        modules: &[&Module],
+    ) -> Result<Vec<*mut sys::iree_vm_module_t>, RuntimeError> {
+        modules
+            .iter()
+            .map(|module| {
+                (instance.ctx == module.instance.ctx)
+                    .then_some(module.ctx)
+                    .ok_or_else(|| {
+                        RuntimeError::InstanceMismatch(error_message!(
+                            "The module must originate from the same instance of the runtime as \
+                             the context."
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Registers the given modules with the context, in order. The modules are retained by the
+    /// context.
+    pub fn register_modules(&self, modules: &[&Module]) -> Result<(), RuntimeError> {
+        let mut module_ptrs = Self::module_ptrs(self.instance, modules)?;
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_context_register_modules, module count: {}",
+                module_ptrs.len()
+            );
+            sys::iree_vm_context_register_modules(
+                self.ctx,
+                module_ptrs.len(),
+                module_ptrs.as_mut_ptr(),
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Freezes the context, after which no more modules can be registered.
+    pub fn freeze(&self) -> Result<(), RuntimeError> {
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_context_freeze");
+            sys::iree_vm_context_freeze(self.ctx)
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Returns the instance the context was created with.
+    pub fn instance(&self) -> &'a Instance {
+        self.instance
+    }
+
+    /// Resolves the state the context holds for the given module, which must be registered with
+    /// it.
+    pub fn resolve_module_state(&self, module: &Module) -> Result<ModuleState<'_>, RuntimeError> {
+        resolve_module_state(self.ctx, module)
+    }
+
+    /// Resolves a function by its fully qualified name, e.g. `module.function`.
+    pub fn resolve_function(&self, name: &str) -> Result<ContextFunction<'_>, RuntimeError> {
+        let mut out = sys::iree_vm_function_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_context_resolve_function, name: {:?}", name);
+            sys::iree_vm_context_resolve_function(self.ctx, StringView::from(name).ctx, &mut out)
+        })
+        .to_result()?;
+        Ok(ContextFunction {
+            ctx: out,
+            context: self,
+        })
+    }
+}
+
+impl Clone for Context<'_> {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_vm_context_retain");
+            sys::iree_vm_context_retain(self.ctx);
+        }
+        Self {
+            ctx: self.ctx,
+            instance: self.instance,
+        }
+    }
+}
+
+impl Drop for Context<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_vm_context_release");
+            sys::iree_vm_context_release(self.ctx);
+        }
+    }
+}
+
+/// The state of a module within a context, e.g. the globals of a bytecode module or the state
+/// allocated by a native module.
+///
+/// The state is owned by the context and opaque to the VM: its layout is private to the module,
+/// so it can only be inspected by native code that knows the module, see
+/// [`ModuleState::as_ptr`]. The globals of compiled modules are snapshotted through functions of
+/// the module instead, see [`super::super::api::Session::snapshot_state`].
+pub struct ModuleState<'a> {
+    ctx: *mut sys::iree_vm_module_state_t,
+    marker: PhantomData<&'a ()>,
+}
+
+impl ModuleState<'_> {
+    /// Returns the raw state, e.g. to pass it to native code of the module.
+    pub fn as_ptr(&self) -> *mut sys::iree_vm_module_state_t {
+        self.ctx
+    }
+}
+
+pub(crate) fn resolve_module_state<'a>(
+    context: *mut sys::iree_vm_context_t,
+    module: &Module,
+) -> Result<ModuleState<'a>, RuntimeError> {
+    let mut out = core::ptr::null_mut();
+    base::Status::from_raw(unsafe {
+        trace!(
+            "iree_vm_context_resolve_module_state, module: {:?}",
+            module.name()
+        );
+        sys::iree_vm_context_resolve_module_state(context, module.ctx, &mut out)
+    })
+    .to_result()?;
+    Ok(ModuleState {
+        ctx: out,
+        marker: PhantomData,
+    })
+}
+
+/// A function resolved from a [`Context`].
+pub struct ContextFunction<'a> {
+    pub(crate) ctx: sys::iree_vm_function_t,
+    pub(crate) context: &'a Context<'a>,
+}
+
+impl<'a> ContextFunction<'a> {
+    /// Returns the context the function was resolved from.
+    pub fn context(&self) -> &'a Context<'a> {
+        self.context
+    }
+
+    /// Synchronously invokes the function with the given arguments.
+    pub fn invoke<'b, T1, T2>(
+        &self,
+        input_list: &impl List<'b, T1>,
+        output_list: &impl List<'b, T2>,
+    ) -> Result<(), RuntimeError>
+    where
+        T1: Type,
+        T2: Type,
+    {
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_invoke");
+            sys::iree_vm_invoke(
+                self.context.ctx,
+                self.ctx,
+                sys::iree_vm_invocation_flag_bits_t_IREE_VM_INVOCATION_FLAG_NONE,
+                core::ptr::null_mut(),
+                input_list.to_raw(),
+                output_list.to_raw(),
+                self.context.instance.get_host_allocator().ctx,
+            )
+        })
+        .to_result()?;
+        Ok(())
+    }
+
+    /// Returns the name of the function as exported by its module.
+    pub fn name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_function_name");
+            StringView::from_raw(sys::iree_vm_function_name(&self.ctx)).into()
+        }
+    }
+}
@@ -0,0 +1,549 @@
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+#[cfg(feature = "std")]
+use super::super::io::{map_file, MmapAdvice};
+use super::super::{
+    api::{Instance, Session},
+    base::{self, ConstByteSpan, StringView},
+    error::RuntimeError,
+    io::FileHandle,
+    vm::{self, Context, ContextFunction},
+};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// The create function of a module compiled to C source, generated by the compiler as
+/// `<module name>_create`. Declare it as an `extern "C"` function and pass it to
+/// [`Module::from_c_module`].
+pub type CModuleCreateFn = unsafe extern "C" fn(
+    instance: *mut sys::iree_vm_instance_t,
+    allocator: sys::iree_allocator_t,
+    out_module: *mut *mut sys::iree_vm_module_t,
+) -> sys::iree_status_t;
+
+/// A VM module.
+///
+/// Modules are shared, reference counted objects that can be appended to one or more sessions
+/// created from the same instance.
+pub struct Module<'a> {
+    pub(crate) ctx: *mut sys::iree_vm_module_t,
+    pub(crate) instance: &'a Instance,
+}
+
+impl<'a> Module<'a> {
+    /// Returns the name of the module.
+    pub fn name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_module_name");
+            StringView::from_raw(sys::iree_vm_module_name(self.ctx)).into()
+        }
+    }
+
+    /// Returns the instance the module was created with.
+    pub fn instance(&self) -> &'a Instance {
+        self.instance
+    }
+
+    /// Creates a bytecode module from the given flatbuffer data, without appending it to a
+    /// session. The data must outlive the module.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is
+    /// correct. Make sure that the bytecode data is valid and trusted before use.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(byte_length = flatbuffer_data.len()))
+    )]
+    pub unsafe fn from_bytecode(
+        instance: &'a Instance,
+        flatbuffer_data: &'a [u8],
+    ) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_bytecode_module_create, bytecode length: {}",
+                flatbuffer_data.len()
+            );
+            sys::iree_vm_bytecode_module_create(
+                instance.get_vm_instance(),
+                ConstByteSpan::from(flatbuffer_data).ctx,
+                base::Allocator::null_allocator().ctx,
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out, instance })
+    }
+
+    /// Creates a bytecode module from the contents of the given file handle, without appending it
+    /// to a session. The module keeps the file handle alive until it is destroyed.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is
+    /// correct. Make sure that the bytecode data is valid and trusted before use.
+    pub unsafe fn from_file_handle(
+        instance: &'a Instance,
+        file_handle: &FileHandle,
+    ) -> Result<Self, RuntimeError> {
+        let archive_allocator = file_handle.archive_allocator();
+        let mut out = core::ptr::null_mut();
+        let status = base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_bytecode_module_create, bytecode length: {}",
+                file_handle.contents().len()
+            );
+            sys::iree_vm_bytecode_module_create(
+                instance.get_vm_instance(),
+                ConstByteSpan::from(file_handle.contents()).ctx,
+                archive_allocator,
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result();
+        if let Err(err) = status {
+            // The archive allocator is only taken over by the module on success.
+            unsafe {
+                trace!("iree_io_file_handle_release");
+                sys::iree_io_file_handle_release(file_handle.ctx);
+            }
+            return Err(err.into());
+        }
+        Ok(Self { ctx: out, instance })
+    }
+
+    /// Creates a bytecode module from the file at the given path, without appending it to a
+    /// session. The file is memory mapped and its header is checked before the module is created.
+    /// The module keeps the mapping alive until it is destroyed.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is
+    /// correct. Make sure that the bytecode data is valid and trusted before use.
+    #[cfg(feature = "std")]
+    pub unsafe fn from_file(instance: &'a Instance, path: &Path) -> Result<Self, RuntimeError> {
+        let file_handle = FileHandle::open_mapped(path)?;
+        file_handle.verify_bytecode_header()?;
+        Self::from_file_handle(instance, &file_handle)
+    }
+
+    /// Creates a module compiled to C source and linked into the application, e.g. with
+    /// `compiler::CModuleBuild`, without appending it to a session. Unlike bytecode modules, the
+    /// functions of the module run as native code, without the bytecode interpreter.
+    /// # Safety
+    /// `create` must be the create function generated for the module, and the module must have
+    /// been compiled against the headers of the runtime eerie links.
+    pub unsafe fn from_c_module(
+        instance: &'a Instance,
+        create: CModuleCreateFn,
+    ) -> Result<Self, RuntimeError> {
+        let mut out = core::ptr::null_mut();
+        base::Status::from_raw(unsafe {
+            trace!("<module>_create");
+            create(
+                instance.get_vm_instance(),
+                instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Self { ctx: out, instance })
+    }
+
+    /// Returns the version of the module. Importers may require a minimum version.
+    pub fn version(&self) -> u32 {
+        unsafe {
+            trace!("iree_vm_module_signature");
+            sys::iree_vm_module_signature(self.ctx).version
+        }
+    }
+
+    /// Returns the modules this module imports from, with the minimum version required of each.
+    pub fn dependencies(&self) -> Result<Vec<ModuleDependency>, RuntimeError> {
+        let mut dependencies = Vec::<ModuleDependency>::new();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_module_enumerate_dependencies");
+            sys::iree_vm_module_enumerate_dependencies(
+                self.ctx,
+                Some(push_dependency),
+                &mut dependencies as *mut Vec<ModuleDependency> as *mut core::ffi::c_void,
+            )
+        })
+        .to_result()?;
+        Ok(dependencies)
+    }
+
+    /// Looks up a function of the module by its name, without a session or context, e.g.
+    /// `simple_mul` for an export. Bind it with [`Function::bind`] or [`Function::bind_context`]
+    /// to call it.
+    pub fn lookup_function_by_name(
+        &self,
+        linkage: FunctionLinkage,
+        name: &str,
+    ) -> Result<Function<'_>, RuntimeError> {
+        let mut out = sys::iree_vm_function_t::default();
+        base::Status::from_raw(unsafe {
+            trace!("iree_vm_module_lookup_function_by_name, name: {:?}", name);
+            sys::iree_vm_module_lookup_function_by_name(
+                self.ctx,
+                linkage.into(),
+                StringView::from(name).ctx,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Function {
+            ctx: out,
+            module: self,
+        })
+    }
+
+    /// Looks up a function of the module by its ordinal within the functions of the given
+    /// linkage, e.g. `0..export_count()` for exports.
+    pub fn lookup_function_by_ordinal(
+        &self,
+        linkage: FunctionLinkage,
+        ordinal: usize,
+    ) -> Result<Function<'_>, RuntimeError> {
+        let mut out = sys::iree_vm_function_t::default();
+        base::Status::from_raw(unsafe {
+            trace!(
+                "iree_vm_module_lookup_function_by_ordinal, ordinal: {}",
+                ordinal
+            );
+            sys::iree_vm_module_lookup_function_by_ordinal(
+                self.ctx,
+                linkage.into(),
+                ordinal,
+                &mut out,
+            )
+        })
+        .to_result()?;
+        Ok(Function {
+            ctx: out,
+            module: self,
+        })
+    }
+
+    /// Returns the number of functions exported by the module.
+    pub fn export_count(&self) -> usize {
+        unsafe {
+            trace!("iree_vm_module_signature");
+            sys::iree_vm_module_signature(self.ctx).export_function_count
+        }
+    }
+
+    /// Returns the functions exported by the module, in ordinal order.
+    pub fn exports(&self) -> Result<Vec<Function<'_>>, RuntimeError> {
+        (0..self.export_count())
+            .map(|ordinal| self.lookup_function_by_ordinal(FunctionLinkage::Export, ordinal))
+            .collect()
+    }
+}
+
+/// The linkage of a function within its module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionLinkage {
+    /// A function only callable from within the module.
+    Internal,
+    /// A function the module imports from another module.
+    Import,
+    /// An import the module can be loaded without, if no module provides it.
+    ImportOptional,
+    /// A function the module exports.
+    Export,
+}
+
+impl From<FunctionLinkage> for sys::iree_vm_function_linkage_t {
+    fn from(linkage: FunctionLinkage) -> Self {
+        match linkage {
+            FunctionLinkage::Internal => {
+                sys::iree_vm_function_linkage_e_IREE_VM_FUNCTION_LINKAGE_INTERNAL
+            }
+            FunctionLinkage::Import => {
+                sys::iree_vm_function_linkage_e_IREE_VM_FUNCTION_LINKAGE_IMPORT
+            }
+            FunctionLinkage::ImportOptional => {
+                sys::iree_vm_function_linkage_e_IREE_VM_FUNCTION_LINKAGE_IMPORT_OPTIONAL
+            }
+            FunctionLinkage::Export => {
+                sys::iree_vm_function_linkage_e_IREE_VM_FUNCTION_LINKAGE_EXPORT
+            }
+        }
+    }
+}
+
+/// A function looked up from a [`Module`] directly. It is only callable once it is bound to a
+/// session or context the module was appended to, which holds the state of the module.
+pub struct Function<'m> {
+    ctx: sys::iree_vm_function_t,
+    module: &'m Module<'m>,
+}
+
+impl<'m> Function<'m> {
+    /// Returns the name of the function.
+    pub fn name(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_function_name");
+            StringView::from_raw(sys::iree_vm_function_name(&self.ctx)).into()
+        }
+    }
+
+    /// Returns the ordinal of the function within the functions of its linkage.
+    pub fn ordinal(&self) -> usize {
+        self.ctx.ordinal as usize
+    }
+
+    /// Returns the module the function was looked up from.
+    pub fn module(&self) -> &'m Module<'m> {
+        self.module
+    }
+
+    /// Returns the calling convention of the function, e.g. `0rr_r`.
+    pub fn calling_convention(&self) -> &str {
+        unsafe {
+            trace!("iree_vm_function_signature");
+            StringView::from_raw(sys::iree_vm_function_signature(&self.ctx).calling_convention)
+                .into()
+        }
+    }
+
+    /// Resolves the program counter of a frame in the function, e.g. [`base::StackFrame::pc`], to
+    /// the location in the source the module was compiled from, e.g. `model.mlir:12:5`. Returns
+    /// `None` if the module carries no debug information for it, e.g. for native functions.
+    pub fn resolve_source_location(&self, pc: u64) -> Result<Option<String>, RuntimeError> {
+        let mut location = sys::iree_vm_source_location_t::default();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_vm_module_resolve_source_location, pc: {}", pc);
+            sys::iree_vm_module_resolve_source_location(
+                self.module.ctx,
+                self.ctx,
+                pc as sys::iree_vm_source_offset_t,
+                &mut location,
+            )
+        })
+        .to_result();
+        match status {
+            Ok(()) => {}
+            Err(err)
+                if matches!(
+                    err.code(),
+                    base::StatusErrorKind::NotFound | base::StatusErrorKind::Unavailable
+                ) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let mut builder = sys::iree_string_builder_t::default();
+        unsafe {
+            trace!("iree_string_builder_initialize");
+            sys::iree_string_builder_initialize(base::Allocator::get_global().ctx, &mut builder);
+        }
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_vm_source_location_format");
+            sys::iree_vm_source_location_format(
+                &mut location,
+                sys::iree_vm_source_location_format_flag_bits_e_IREE_VM_SOURCE_LOCATION_FORMAT_FLAG_SINGLE_LINE,
+                &mut builder,
+            )
+        })
+        .to_result();
+        let formatted = status.map(|_| {
+            let formatted: &str = unsafe {
+                trace!("iree_string_builder_view");
+                StringView::from_raw(sys::iree_string_builder_view(&builder)).into()
+            };
+            String::from(formatted)
+        });
+        unsafe {
+            trace!("iree_string_builder_deinitialize");
+            sys::iree_string_builder_deinitialize(&mut builder);
+        }
+        Ok(Some(formatted?))
+    }
+
+    /// Binds the function to a session its module was appended to, so it can be called like a
+    /// function looked up with [`Session::lookup_function`]. Returns `RuntimeError::InvalidModule`
+    /// if the module is not in the session.
+    pub fn bind<'s>(&self, session: &'s Session) -> Result<vm::Function<'s>, RuntimeError> {
+        self.check_registered(session.context())?;
+        Ok(vm::Function {
+            ctx: self.ctx,
+            session,
+        })
+    }
+
+    /// Binds the function to a context its module was registered with. Returns
+    /// `RuntimeError::InvalidModule` if the module is not in the context.
+    pub fn bind_context<'c>(
+        &self,
+        context: &'c Context<'c>,
+    ) -> Result<ContextFunction<'c>, RuntimeError> {
+        self.check_registered(context.ctx)?;
+        Ok(ContextFunction {
+            ctx: self.ctx,
+            context,
+        })
+    }
+
+    fn check_registered(&self, context: *mut sys::iree_vm_context_t) -> Result<(), RuntimeError> {
+        let count = unsafe {
+            trace!("iree_vm_context_module_count");
+            sys::iree_vm_context_module_count(context)
+        };
+        let registered = (0..count).any(|i| unsafe {
+            trace!("iree_vm_context_module_at, index: {}", i);
+            sys::iree_vm_context_module_at(context, i) == self.module.ctx
+        });
+        if !registered {
+            return Err(RuntimeError::InvalidModule(error_message!(
+                "module {} of function {} is not registered with the context",
+                self.module.name(),
+                self.name()
+            )));
+        }
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn push_dependency(
+    user_data: *mut core::ffi::c_void,
+    dependency: *const sys::iree_vm_module_dependency_t,
+) -> sys::iree_status_t {
+    let dependencies = &mut *(user_data as *mut Vec<ModuleDependency>);
+    let dependency = &*dependency;
+    let name: &str = StringView::from_raw(dependency.name).into();
+    dependencies.push(ModuleDependency {
+        name: String::from(name),
+        minimum_version: dependency.minimum_version,
+        optional: dependency.flags
+            & sys::iree_vm_module_dependency_flag_bits_t_IREE_VM_MODULE_DEPENDENCY_FLAG_OPTIONAL
+            != 0,
+    });
+    core::ptr::null_mut()
+}
+
+/// A module imported by another module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleDependency {
+    /// Name of the imported module, e.g. `hal`.
+    pub name: String,
+    /// Minimum version of the imported module.
+    pub minimum_version: u32,
+    /// Whether the module can be loaded without this dependency.
+    pub optional: bool,
+}
+
+impl Clone for Module<'_> {
+    fn clone(&self) -> Self {
+        unsafe {
+            trace!("iree_vm_module_retain");
+            sys::iree_vm_module_retain(self.ctx);
+        }
+        Self {
+            ctx: self.ctx,
+            instance: self.instance,
+        }
+    }
+}
+
+impl Drop for Module<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_vm_module_release");
+            sys::iree_vm_module_release(self.ctx);
+        }
+    }
+}
+
+/// A bytecode module created from a vmfb that is memory mapped explicitly, e.g. to control the
+/// paging of a very large model with [`MmapAdvice`].
+///
+/// The mapping stays alive as long as the module is used by any session. Like any module, it can
+/// be appended to every session of its instance with [`MappedModule::module`].
+#[cfg(feature = "std")]
+pub struct MappedModule<'a> {
+    module: Module<'a>,
+    mmap: std::sync::Arc<memmap2::Mmap>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> MappedModule<'a> {
+    /// Maps the vmfb at the given path into memory, applies the given advice and creates a
+    /// module from it. The header of the file is checked before the module is created.
+    /// # Safety
+    /// The runtime does not perform strict validation on the module data and assumes it is
+    /// correct. Make sure that the bytecode data is valid and trusted before use. The file must
+    /// not be modified while it is mapped.
+    pub unsafe fn open(
+        instance: &'a Instance,
+        path: &Path,
+        advice: MmapAdvice,
+    ) -> Result<Self, RuntimeError> {
+        let mmap = std::sync::Arc::new(map_file(path)?);
+        advice.apply(&mmap)?;
+        let file_handle = FileHandle::from_shared_mmap(mmap.clone())?;
+        file_handle.verify_bytecode_header()?;
+        let module = Module::from_file_handle(instance, &file_handle)?;
+        Ok(Self { module, mmap })
+    }
+
+    /// Returns the module, to append it to sessions.
+    pub fn module(&self) -> &Module<'a> {
+        &self.module
+    }
+
+    /// Returns the mapped contents of the vmfb.
+    pub fn contents(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Applies the given advice to the mapping, e.g. [`MmapAdvice::WillNeed`] before a model is
+    /// first run.
+    pub fn advise(&self, advice: MmapAdvice) -> Result<(), RuntimeError> {
+        advice.apply(&self.mmap)
+    }
+}
+
+/// A dependency of a module that is not satisfied by the modules of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsatisfiedDependency {
+    /// Name of the imported module.
+    pub name: String,
+    /// Minimum version of the imported module required by the module.
+    pub required_version: u32,
+    /// Version of the module provided by the session, or `None` if it is missing.
+    pub provided_version: Option<u32>,
+}
+
+/// Lists the dependencies of a module that a session cannot satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompatibilityReport {
+    /// Name of the module that was checked.
+    pub module_name: String,
+    /// Dependencies that are missing or older than required.
+    pub unsatisfied: Vec<UnsatisfiedDependency>,
+}
+
+impl core::fmt::Display for IncompatibilityReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "module {} cannot be loaded:", self.module_name)?;
+        for dependency in self.unsatisfied.iter() {
+            match dependency.provided_version {
+                Some(version) => write!(
+                    f,
+                    " requires {} version >= {}, but version {} is provided;",
+                    dependency.name, dependency.required_version, version
+                )?,
+                None => write!(
+                    f,
+                    " requires {} version >= {}, but it is not available;",
+                    dependency.name, dependency.required_version
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+extern crate alloc;
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::ffi::c_void;
+
+use eerie_sys::runtime as sys;
+use log::trace;
+
+use super::{
+    super::{
+        api::Instance,
+        base::{self, StatusErrorKind, StringView},
+        error::RuntimeError,
+    },
+    parse_cconv_fragment, CallingConventionType, Fence, Module, Ref, Semaphore, ToRef,
+};
+
+/// A value passed to or returned from a native module function.
+///
+/// Semaphore and fence refs are passed as [`NativeValue::Semaphore`] and [`NativeValue::Fence`],
+/// all other refs, e.g. buffer views, as [`NativeValue::Ref`].
+#[derive(Clone)]
+pub enum NativeValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Semaphore(Semaphore),
+    Fence(Fence),
+    Ref(NativeRef),
+}
+
+impl NativeValue {
+    fn calling_convention_type(&self) -> CallingConventionType {
+        match self {
+            Self::I32(_) => CallingConventionType::I32,
+            Self::I64(_) => CallingConventionType::I64,
+            Self::F32(_) => CallingConventionType::F32,
+            Self::F64(_) => CallingConventionType::F64,
+            Self::Semaphore(_) | Self::Fence(_) | Self::Ref(_) => CallingConventionType::Ref,
+        }
+    }
+}
+
+impl core::fmt::Debug for NativeValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::I32(v) => write!(f, "I32({})", v),
+            Self::I64(v) => write!(f, "I64({})", v),
+            Self::F32(v) => write!(f, "F32({})", v),
+            Self::F64(v) => write!(f, "F64({})", v),
+            Self::Semaphore(v) => write!(f, "Semaphore({:p})", v.ctx),
+            Self::Fence(v) => write!(f, "Fence({:p})", v.ctx),
+            Self::Ref(v) => write!(f, "{:?}", v),
+        }
+    }
+}
+
+/// An untyped VM reference passed to or returned from a native module function, e.g. a buffer
+/// view or a fence. It may be null.
+pub struct NativeRef {
+    pub(crate) ctx: sys::iree_vm_ref_t,
+}
+
+impl NativeRef {
+    /// Creates a NativeRef that retains the object held by the given Ref.
+    pub fn new<'a, T: ToRef<'a>>(value: &Ref<'a, T>) -> Self {
+        // The source is only read, the runtime takes a mutable pointer for historical reasons.
+        let mut source = value.ctx;
+        Self::retain(&mut source)
+    }
+
+    /// Creates a null NativeRef.
+    pub fn null() -> Self {
+        Self {
+            ctx: sys::iree_vm_ref_t::default(),
+        }
+    }
+
+    pub(crate) fn retain(source: &mut sys::iree_vm_ref_t) -> Self {
+        let mut ctx = sys::iree_vm_ref_t::default();
+        unsafe {
+            trace!("iree_vm_ref_retain");
+            sys::iree_vm_ref_retain(source, &mut ctx);
+        }
+        Self { ctx }
+    }
+
+    /// Returns true if the ref is null.
+    pub fn is_null(&self) -> bool {
+        self.ctx.ptr.is_null()
+    }
+
+    /// Returns true if the ref is not null and holds an object of type `T`.
+    pub fn is<'a, T: ToRef<'a>>(&self, instance: &Instance) -> bool {
+        !self.is_null() && self.ctx.type_ == T::to_ref_type(instance)
+    }
+
+    /// Returns a typed Ref to the object if it is of type `T`, or `None` otherwise.
+    pub fn to_ref<'a, T: ToRef<'a>>(&self, instance: &'a Instance) -> Option<Ref<'a, T>> {
+        if !self.is::<T>(instance) {
+            return None;
+        }
+        let mut source = self.ctx;
+        Some(Ref {
+            ctx: Self::retain(&mut source).into_raw(),
+            _instance: instance,
+            _marker: core::marker::PhantomData,
+        })
+    }
+
+    // Hands the reference over to the caller.
+    fn into_raw(self) -> sys::iree_vm_ref_t {
+        let ctx = self.ctx;
+        core::mem::forget(self);
+        ctx
+    }
+}
+
+impl Clone for NativeRef {
+    fn clone(&self) -> Self {
+        let mut source = self.ctx;
+        Self::retain(&mut source)
+    }
+}
+
+impl core::fmt::Debug for NativeRef {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "NativeRef({:p})", self.ctx.ptr)
+    }
+}
+
+impl Drop for NativeRef {
+    fn drop(&mut self) {
+        unsafe {
+            trace!("iree_vm_ref_release");
+            sys::iree_vm_ref_release(&mut self.ctx);
+        }
+    }
+}
+
+type NativeFunction =
+    dyn Fn(&[NativeValue]) -> Result<Vec<NativeValue>, RuntimeError> + Send + Sync + 'static;
+
+// Ref types resolved when the module is built, so that closures do not need the instance.
+struct NativeRefTypes {
+    semaphore: sys::iree_vm_ref_type_t,
+    fence: sys::iree_vm_ref_type_t,
+}
+
+impl NativeRefTypes {
+    // Arguments are borrowed from the caller, so refs are retained.
+    unsafe fn unpack(&self, mut value: sys::iree_vm_ref_t) -> NativeValue {
+        if value.ptr.is_null() {
+            return NativeValue::Ref(NativeRef::null());
+        }
+        if value.type_ == self.semaphore {
+            let ctx = value.ptr as *mut sys::iree_hal_semaphore_t;
+            trace!("iree_hal_semaphore_retain");
+            sys::iree_hal_semaphore_retain(ctx);
+            NativeValue::Semaphore(Semaphore { ctx })
+        } else if value.type_ == self.fence {
+            let ctx = value.ptr as *mut sys::iree_hal_fence_t;
+            trace!("iree_hal_fence_retain");
+            sys::iree_hal_fence_retain(ctx);
+            NativeValue::Fence(Fence { ctx })
+        } else {
+            NativeValue::Ref(NativeRef::retain(&mut value))
+        }
+    }
+
+    // Results are owned by the caller, so the reference is moved.
+    fn pack(&self, value: NativeValue) -> sys::iree_vm_ref_t {
+        let mut out = sys::iree_vm_ref_t::default();
+        match value {
+            NativeValue::Semaphore(v) => {
+                out.ptr = v.ctx as *mut c_void;
+                out.type_ = self.semaphore;
+                core::mem::forget(v);
+            }
+            NativeValue::Fence(v) => {
+                out.ptr = v.ctx as *mut c_void;
+                out.type_ = self.fence;
+                core::mem::forget(v);
+            }
+            NativeValue::Ref(v) => out = v.into_raw(),
+            _ => unreachable!(),
+        }
+        out
+    }
+}
+
+struct NativeExport {
+    name: String,
+    calling_convention: String,
+    arguments: Vec<CallingConventionType>,
+    results: Vec<CallingConventionType>,
+    function: Box<NativeFunction>,
+}
+
+impl NativeExport {
+    unsafe fn call(
+        &self,
+        ref_types: &NativeRefTypes,
+        arguments: sys::iree_byte_span_t,
+        results: sys::iree_byte_span_t,
+    ) -> Result<(), RuntimeError> {
+        let mut offset = 0;
+        let mut args = Vec::with_capacity(self.arguments.len());
+        for ty in self.arguments.iter() {
+            let ptr = arguments.data.add(offset);
+            let (value, size) = match ty {
+                CallingConventionType::I32 => {
+                    (NativeValue::I32(ptr.cast::<i32>().read_unaligned()), 4)
+                }
+                CallingConventionType::I64 => {
+                    (NativeValue::I64(ptr.cast::<i64>().read_unaligned()), 8)
+                }
+                CallingConventionType::F32 => {
+                    (NativeValue::F32(ptr.cast::<f32>().read_unaligned()), 4)
+                }
+                CallingConventionType::F64 => {
+                    (NativeValue::F64(ptr.cast::<f64>().read_unaligned()), 8)
+                }
+                CallingConventionType::Ref => (
+                    ref_types.unpack(ptr.cast::<sys::iree_vm_ref_t>().read_unaligned()),
+                    core::mem::size_of::<sys::iree_vm_ref_t>(),
+                ),
+                CallingConventionType::Void => unreachable!(),
+            };
+            args.push(value);
+            offset += size;
+        }
+
+        let rets = (self.function)(&args)?;
+        if rets.len() != self.results.len()
+            || rets
+                .iter()
+                .zip(self.results.iter())
+                .any(|(value, ty)| value.calling_convention_type() != *ty)
+        {
+            return Err(RuntimeError::SignatureMismatch(error_message!(
+                "native function {} returned {:?}, but its calling convention is {}",
+                self.name,
+                rets,
+                self.calling_convention
+            )));
+        }
+
+        let mut offset = 0;
+        for value in rets {
+            let ptr = results.data.add(offset);
+            offset += match value {
+                NativeValue::I32(v) => {
+                    ptr.cast::<i32>().write_unaligned(v);
+                    4
+                }
+                NativeValue::I64(v) => {
+                    ptr.cast::<i64>().write_unaligned(v);
+                    8
+                }
+                NativeValue::F32(v) => {
+                    ptr.cast::<f32>().write_unaligned(v);
+                    4
+                }
+                NativeValue::F64(v) => {
+                    ptr.cast::<f64>().write_unaligned(v);
+                    8
+                }
+                value => {
+                    ptr.cast::<sys::iree_vm_ref_t>()
+                        .write_unaligned(ref_types.pack(value));
+                    core::mem::size_of::<sys::iree_vm_ref_t>()
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+// Owned by the native module and freed by its destroy callback. The runtime keeps pointers into the
+// descriptor, so everything it references must stay alive and unmoved for the module lifetime.
+struct NativeModuleState {
+    name: String,
+    exports: Vec<NativeExport>,
+    ref_types: NativeRefTypes,
+    export_descriptors: Vec<sys::iree_vm_native_export_descriptor_t>,
+    function_ptrs: Vec<sys::iree_vm_native_function_ptr_t>,
+    descriptor: sys::iree_vm_native_module_descriptor_t,
+}
+
+unsafe extern "C" fn native_module_destroy(self_: *mut c_void) {
+    trace!("native_module_destroy");
+    drop(Box::from_raw(self_ as *mut NativeModuleState));
+}
+
+unsafe extern "C" fn native_module_begin_call(
+    self_: *mut c_void,
+    stack: *mut sys::iree_vm_stack_t,
+    call: sys::iree_vm_function_call_t,
+) -> sys::iree_status_t {
+    let state = &*(self_ as *const NativeModuleState);
+    let Some(export) = state.exports.get(call.function.ordinal as usize) else {
+        return base::allocate_status(
+            StatusErrorKind::OutOfRange,
+            "native function ordinal out of range",
+        );
+    };
+    trace!("native_module_begin_call: {}.{}", state.name, export.name);
+
+    let mut frame = core::ptr::null_mut();
+    let status = sys::iree_vm_stack_function_enter(
+        stack,
+        &call.function,
+        sys::iree_vm_stack_frame_type_e_IREE_VM_STACK_FRAME_NATIVE,
+        0,
+        None,
+        &mut frame,
+    );
+    if !status.is_null() {
+        return status;
+    }
+    let result = export.call(&state.ref_types, call.arguments, call.results);
+    let status = sys::iree_vm_stack_function_leave(stack);
+    match result {
+        Ok(()) => status,
+        Err(err) => {
+            drop(base::Status::from_raw(status));
+            err.into_raw_status()
+        }
+    }
+}
+
+/// A builder for VM modules whose functions are implemented in Rust.
+///
+/// Native modules let compiled programs call back into the host, e.g. for custom ops, logging, or
+/// I/O. Functions are declared with an IREE calling convention string such as `0ii_i` (two i32
+/// arguments, one i32 result) and are backed by closures. Ref arguments (`r`) are passed as
+/// [`NativeValue`] refs that the closure may keep, and ref results are handed over to the caller.
+/// The resulting [`Module`] can be appended to a session before the modules that import it.
+pub struct NativeModuleBuilder<'a> {
+    instance: &'a Instance,
+    name: String,
+    version: u32,
+    exports: Vec<NativeExport>,
+}
+
+impl<'a> NativeModuleBuilder<'a> {
+    /// Creates a new builder for a module with the given name.
+    pub fn new(instance: &'a Instance, name: &str) -> Self {
+        Self {
+            instance,
+            name: String::from(name),
+            version: 0,
+            exports: Vec::new(),
+        }
+    }
+
+    /// Sets the version of the module reported to importers.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Exports a function with the given name and calling convention, backed by the given closure.
+    /// The closure receives the arguments in order and must return values matching the result
+    /// types of the calling convention.
+    pub fn export<F>(
+        mut self,
+        name: &str,
+        calling_convention: &str,
+        function: F,
+    ) -> Result<Self, RuntimeError>
+    where
+        F: Fn(&[NativeValue]) -> Result<Vec<NativeValue>, RuntimeError> + Send + Sync + 'static,
+    {
+        let (arguments, results) = calling_convention
+            .strip_prefix('0')
+            .and_then(|cconv| cconv.split_once('_'))
+            .ok_or_else(|| {
+                RuntimeError::SignatureMismatch(error_message!(
+                    "invalid calling convention \"{}\"",
+                    calling_convention
+                ))
+            })?;
+        let arguments = parse_cconv_fragment(arguments)?;
+        let results = parse_cconv_fragment(results)?;
+        self.exports.push(NativeExport {
+            name: String::from(name),
+            calling_convention: String::from(calling_convention),
+            arguments,
+            results,
+            function: Box::new(function),
+        });
+        Ok(self)
+    }
+
+    /// Creates the module.
+    pub fn build(mut self) -> Result<Module<'a>, RuntimeError> {
+        // The runtime looks up exports by binary search.
+        self.exports.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut state = Box::new(NativeModuleState {
+            name: self.name,
+            exports: self.exports,
+            ref_types: NativeRefTypes {
+                semaphore: Semaphore::to_ref_type(self.instance),
+                fence: Fence::to_ref_type(self.instance),
+            },
+            export_descriptors: Vec::new(),
+            function_ptrs: Vec::new(),
+            descriptor: sys::iree_vm_native_module_descriptor_t::default(),
+        });
+        state.export_descriptors = state
+            .exports
+            .iter()
+            .map(|export| sys::iree_vm_native_export_descriptor_t {
+                local_name: StringView::from(export.name.as_str()).ctx,
+                calling_convention: StringView::from(export.calling_convention.as_str()).ctx,
+                attr_count: 0,
+                attrs: core::ptr::null(),
+            })
+            .collect();
+        // Calls are dispatched through begin_call, so the function table is left empty.
+        state.function_ptrs = state
+            .exports
+            .iter()
+            .map(|_| sys::iree_vm_native_function_ptr_t::default())
+            .collect();
+        state.descriptor = sys::iree_vm_native_module_descriptor_t {
+            name: StringView::from(state.name.as_str()).ctx,
+            version: self.version,
+            export_count: state.export_descriptors.len(),
+            exports: state.export_descriptors.as_ptr(),
+            function_count: state.function_ptrs.len(),
+            functions: state.function_ptrs.as_ptr(),
+            ..Default::default()
+        };
+        let state = Box::into_raw(state);
+
+        let mut interface = sys::iree_vm_module_t::default();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_vm_module_initialize");
+            sys::iree_vm_module_initialize(&mut interface, state as *mut c_void)
+        })
+        .to_result();
+        if let Err(err) = status {
+            drop(unsafe { Box::from_raw(state) });
+            return Err(err.into());
+        }
+        interface.destroy = Some(native_module_destroy);
+        interface.begin_call = Some(native_module_begin_call);
+
+        let mut out = core::ptr::null_mut();
+        let status = base::Status::from_raw(unsafe {
+            trace!("iree_vm_native_module_create");
+            sys::iree_vm_native_module_create(
+                &interface,
+                &(*state).descriptor,
+                self.instance.get_vm_instance(),
+                self.instance.get_host_allocator().ctx,
+                &mut out,
+            )
+        })
+        .to_result();
+        if let Err(err) = status {
+            drop(unsafe { Box::from_raw(state) });
+            return Err(err.into());
+        }
+        Ok(Module {
+            ctx: out,
+            instance: self.instance,
+        })
+    }
+}
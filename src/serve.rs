@@ -0,0 +1,324 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+    thread::JoinHandle,
+};
+
+use crate::{
+    runtime::{
+        api::{DeviceSpec, Model},
+        base::{self, StatusErrorKind},
+        error::RuntimeError,
+    },
+    Tensor,
+};
+
+/// What an [`Inference`] does when the request queue of its [`AsyncModel`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// The future stays pending until a worker takes a request off the queue.
+    #[default]
+    Wait,
+    /// The future fails with a `ResourceExhausted` status error, e.g. so that a server answers
+    /// with `503 Service Unavailable` instead of piling up requests.
+    Reject,
+}
+
+/// Options used to configure an [`AsyncModel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeOptions {
+    function: String,
+    workers: usize,
+    queue_capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl ServeOptions {
+    /// Creates options that serve the function with the given name, relative to the module of
+    /// the model or fully qualified, with a single worker and a queue of 16 requests.
+    pub fn new(function: &str) -> Self {
+        Self {
+            function: function.to_string(),
+            workers: 1,
+            queue_capacity: 16,
+            backpressure: Backpressure::default(),
+        }
+    }
+
+    /// Sets the number of worker threads, each running its own copy of the model. Only used by
+    /// [`AsyncModel::from_bytes`].
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the number of requests that wait for a worker before [`Backpressure`] applies. This
+    /// does not include the requests the workers are running.
+    pub fn queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.queue_capacity = queue_capacity;
+        self
+    }
+
+    /// Sets what happens to requests sent while the queue is full.
+    pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+}
+
+struct Job {
+    inputs: Vec<Tensor>,
+    reply: Arc<Reply>,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    // Futures waiting for room in the queue.
+    senders: Vec<Waker>,
+    closed: bool,
+}
+
+impl Queue {
+    fn wake_senders(&mut self) {
+        for waker in self.senders.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+    capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Default)]
+struct Reply {
+    state: Mutex<ReplyState>,
+}
+
+#[derive(Default)]
+struct ReplyState {
+    result: Option<Result<Vec<Tensor>, RuntimeError>>,
+    waker: Option<Waker>,
+}
+
+impl Reply {
+    fn lock(&self) -> MutexGuard<'_, ReplyState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// An asynchronous facade over one or more [`Model`]s, for async servers such as axum or tonic
+/// handlers that must not block their executor on an invocation.
+///
+/// Every model runs on its own worker thread, and requests sent with [`AsyncModel::infer`] are
+/// queued until a worker is idle. The queue is bounded, and [`Backpressure`] decides whether
+/// requests sent while it is full wait or fail. The facade does not depend on an async runtime,
+/// so its futures can be awaited from tokio as well as any other executor. It is `Send` and
+/// `Sync`, and is usually shared behind an `Arc`.
+///
+/// Dropping the facade stops accepting requests, lets the workers finish the queued ones, and
+/// joins them.
+pub struct AsyncModel {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl AsyncModel {
+    /// Loads one model per worker from the given vmfb data, see [`Model::from_bytes`], and serves
+    /// them.
+    pub fn from_bytes(
+        flatbuffer_data: &[u8],
+        device: DeviceSpec,
+        options: ServeOptions,
+    ) -> Result<Self, RuntimeError> {
+        let models = (0..options.workers)
+            .map(|_| Model::from_bytes(flatbuffer_data, device))
+            .collect::<Result<_, RuntimeError>>()?;
+        Self::new(models, options)
+    }
+
+    /// Serves the given models, one worker thread per model. The models should hold the same
+    /// module, as requests go to whichever worker is idle. Returns an error if a model does not
+    /// have the function of the options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no models or the queue capacity is zero.
+    pub fn new(models: Vec<Model>, options: ServeOptions) -> Result<Self, RuntimeError> {
+        assert!(
+            !models.is_empty(),
+            "an async model needs at least one worker"
+        );
+        assert!(
+            options.queue_capacity > 0,
+            "an async model needs room for at least one request"
+        );
+        for model in &models {
+            model.function(&options.function)?;
+        }
+        let shared = Arc::new(Shared {
+            queue: Mutex::default(),
+            available: Condvar::new(),
+            capacity: options.queue_capacity,
+            backpressure: options.backpressure,
+        });
+        let workers = models
+            .into_iter()
+            .map(|model| {
+                let shared = shared.clone();
+                let function = options.function.clone();
+                std::thread::spawn(move || run_worker(model, &function, &shared))
+            })
+            .collect();
+        Ok(Self { shared, workers })
+    }
+
+    /// Sends a request with the given inputs, and returns a future that resolves to the results
+    /// of the function, see [`Model::call_tensors`]. The request is queued when the future is
+    /// first polled.
+    pub fn infer(&self, inputs: Vec<Tensor>) -> Inference {
+        Inference {
+            shared: self.shared.clone(),
+            inputs: Some(inputs),
+            reply: Arc::default(),
+        }
+    }
+
+    /// Returns the number of requests waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.shared.lock().jobs.len()
+    }
+
+    /// Returns the number of worker threads.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for AsyncModel {
+    fn drop(&mut self) {
+        {
+            let mut queue = self.shared.lock();
+            queue.closed = true;
+            queue.wake_senders();
+        }
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            // A worker only panics if a model does, which already reported the panic.
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(model: Model, function: &str, shared: &Shared) {
+    loop {
+        let job = {
+            let mut queue = shared.lock();
+            loop {
+                if let Some(job) = queue.jobs.pop_front() {
+                    queue.wake_senders();
+                    break job;
+                }
+                if queue.closed {
+                    return;
+                }
+                queue = shared
+                    .available
+                    .wait(queue)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+        // A panicking request fails instead of leaving its future pending, and the worker keeps
+        // serving the queue.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            model.call_tensors(function, &job.inputs)
+        }))
+        .unwrap_or_else(|_| {
+            status_error(
+                StatusErrorKind::Internal,
+                "the model panicked while running the request",
+            )
+        });
+        let mut reply = job.reply.lock();
+        reply.result = Some(result);
+        if let Some(waker) = reply.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The future returned by [`AsyncModel::infer`], resolving to the results of the request.
+///
+/// Dropping the future before the request is queued withdraws it; once queued, the request runs
+/// and its results are discarded.
+pub struct Inference {
+    shared: Arc<Shared>,
+    // The inputs until the request is queued.
+    inputs: Option<Vec<Tensor>>,
+    reply: Arc<Reply>,
+}
+
+impl Future for Inference {
+    type Output = Result<Vec<Tensor>, RuntimeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(inputs) = this.inputs.take() {
+            let mut queue = this.shared.lock();
+            if queue.closed {
+                return Poll::Ready(status_error(
+                    StatusErrorKind::Unavailable,
+                    "the async model has been dropped",
+                ));
+            }
+            if queue.jobs.len() >= this.shared.capacity {
+                return match this.shared.backpressure {
+                    Backpressure::Reject => Poll::Ready(status_error(
+                        StatusErrorKind::ResourceExhausted,
+                        "the request queue is full",
+                    )),
+                    Backpressure::Wait => {
+                        queue.senders.push(cx.waker().clone());
+                        this.inputs = Some(inputs);
+                        Poll::Pending
+                    }
+                };
+            }
+            queue.jobs.push_back(Job {
+                inputs,
+                reply: this.reply.clone(),
+            });
+            drop(queue);
+            this.shared.available.notify_one();
+        }
+        let mut reply = this.reply.lock();
+        match reply.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                reply.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn status_error(kind: StatusErrorKind, message: &str) -> Result<Vec<Tensor>, RuntimeError> {
+    Err(base::Status::from_raw(base::allocate_status(kind, message))
+        .to_result()
+        .unwrap_err()
+        .into())
+}
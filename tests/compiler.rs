@@ -1,28 +1,15 @@
-// forking is necessary to avoid the compiler being initialized multiple times in the same process
-// TODO: make a global compiler object so we don't need this
 #![cfg(feature = "compiler")]
 mod test {
     use eerie::compiler::*;
     use log::{debug, info};
     use std::path::Path;
-    use std::sync::Mutex;
     use test_log::test;
 
-    static COMPILER: Mutex<Option<Compiler>> = Mutex::new(None);
-
-    fn init_compiler() {
-        let mut global_compiler = COMPILER.lock().unwrap();
-        if global_compiler.is_none() {
-            let compiler = Compiler::new().unwrap();
-            *global_compiler = Some(compiler);
-        }
-    }
-
     #[test]
     fn test_compiler() {
-        init_compiler();
-        let compiler = Compiler::new();
-        assert!(compiler.is_err());
+        let compiler = Compiler::global().unwrap();
+        assert!(std::ptr::eq(compiler, Compiler::global().unwrap()));
+        assert!(Compiler::new().is_err());
     }
 
     #[test]
@@ -35,24 +22,13 @@ mod test {
 
     #[test]
     fn get_revision() {
-        init_compiler();
-        let rev = COMPILER
-            .lock()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .get_revision()
-            .unwrap();
+        let rev = Compiler::global().unwrap().get_revision().unwrap();
         debug!("Revision: \"{}\"", rev);
     }
 
     #[test]
     fn setup_global_cl() {
-        init_compiler();
-        COMPILER
-            .lock()
-            .unwrap()
-            .as_mut()
+        Compiler::global()
             .unwrap()
             .setup_global_cl(vec!["--iree-example-flag=false".to_string()])
             .unwrap();
@@ -60,11 +36,7 @@ mod test {
 
     #[test]
     fn get_registered_hal_target_backends() {
-        init_compiler();
-        let backends = COMPILER
-            .lock()
-            .unwrap()
-            .as_ref()
+        let backends = Compiler::global()
             .unwrap()
             .get_registered_hal_target_backends();
         info!("Input Backends: {:?}", backends);
@@ -72,24 +44,18 @@ mod test {
 
     #[test]
     fn get_plugins() {
-        init_compiler();
-        let plugins = COMPILER.lock().unwrap().as_ref().unwrap().get_plugins();
+        let plugins = Compiler::global().unwrap().get_plugins();
         info!("Plugins: {:?}", plugins);
     }
 
     #[test]
     fn test_session() {
-        init_compiler();
-        COMPILER.lock().unwrap().as_ref().unwrap().create_session();
+        Compiler::global().unwrap().create_session();
     }
 
     #[test]
     fn session_set_get_flags() {
-        init_compiler();
-        let flags = COMPILER
-            .lock()
-            .unwrap()
-            .as_ref()
+        let flags = Compiler::global()
             .unwrap()
             .create_session()
             .set_flags(vec!["--iree-input-type=tosa".to_string()])
@@ -99,12 +65,118 @@ mod test {
     }
 
     #[test]
-    fn init_invocation() {
-        init_compiler();
-        COMPILER
-            .lock()
+    fn session_set_options() {
+        let options = CompilerOptions::new()
+            .target_backend(TargetBackend::LlvmCpu)
+            .target_backend(TargetBackend::Vmvx)
+            .input_type(InputType::Tosa)
+            .target_cpu("host")
+            .target_cpu_feature("+avx2")
+            .target_cpu_feature("+fma")
+            .flag("--iree-opt-const-eval=false");
+        assert_eq!(
+            options.to_flags(),
+            vec![
+                "--iree-hal-target-backends=llvm-cpu,vmvx",
+                "--iree-input-type=tosa",
+                "--iree-llvmcpu-target-cpu=host",
+                "--iree-llvmcpu-target-cpu-features=+avx2,+fma",
+                "--iree-opt-const-eval=false",
+            ]
+        );
+
+        let flags = Compiler::global()
             .unwrap()
-            .as_ref()
+            .create_session()
+            .set_options(&options)
+            .unwrap()
+            .get_flags(true);
+        info!("Flags: {:?}", flags);
+    }
+
+    #[test]
+    fn session_set_llvm_cpu_target() {
+        let target = LlvmCpuTarget::new("aarch64-none-elf")
+            .cpu("cortex-a53")
+            .cpu_feature("+neon")
+            .link_embedded(true);
+        let options = CompilerOptions::new()
+            .target_backend(TargetBackend::LlvmCpu)
+            .llvm_cpu_target(target);
+        assert_eq!(
+            options.to_flags(),
+            vec![
+                "--iree-hal-target-backends=llvm-cpu",
+                "--iree-llvmcpu-target-triple=aarch64-none-elf",
+                "--iree-llvmcpu-target-cpu=cortex-a53",
+                "--iree-llvmcpu-target-cpu-features=+neon",
+                "--iree-llvmcpu-link-embedded=true",
+            ]
+        );
+
+        let flags = Compiler::global()
+            .unwrap()
+            .create_session()
+            .set_options(&options)
+            .unwrap()
+            .get_flags(true);
+        info!("Flags: {:?}", flags);
+
+        let target = LlvmCpuTarget::new("thumbv7em-pc-none-eabihf")
+            .static_library_output(std::path::Path::new("simple_mul.o"));
+        assert_eq!(
+            target.to_flags(),
+            vec![
+                "--iree-llvmcpu-target-triple=thumbv7em-pc-none-eabihf",
+                "--iree-llvmcpu-link-static",
+                "--iree-llvmcpu-static-library-output-path=simple_mul.o",
+            ]
+        );
+    }
+
+    #[test]
+    fn session_activate_plugins() {
+        let compiler = Compiler::global().unwrap();
+        let plugins = compiler.get_plugins();
+        let plugins = plugins.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut session = compiler.create_session();
+        session.activate_plugins(&plugins).unwrap();
+        assert!(matches!(
+            session.activate_plugins(&["no_such_plugin"]),
+            Err(CompilerError::PluginNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn session_set_input_type() {
+        let compiler = Compiler::global().unwrap();
+        let plugins = compiler.get_plugins();
+        let mut session = compiler.create_session();
+        for input_type in [
+            InputType::StableHlo,
+            InputType::Tosa,
+            InputType::Torch,
+            InputType::Onnx,
+            InputType::TmTensor,
+        ] {
+            let available = plugins
+                .iter()
+                .any(|plugin| Some(plugin.as_str()) == input_type.required_plugin());
+            match session.set_input_type(input_type.clone()) {
+                Ok(_) => assert!(available),
+                Err(CompilerError::UnsupportedInputType(_)) => assert!(!available),
+                Err(err) => panic!("unexpected error for {}: {}", input_type, err),
+            }
+        }
+        session.set_input_type(InputType::None).unwrap();
+        assert!(session
+            .get_flags(true)
+            .contains(&"--iree-input-type=none".to_string()));
+    }
+
+    #[test]
+    fn init_invocation() {
+        Compiler::global()
             .unwrap()
             .create_session()
             .create_invocation();
@@ -112,9 +184,8 @@ mod test {
 
     #[test]
     fn source_from_file() {
-        init_compiler();
-        let compiler = COMPILER.lock().unwrap();
-        let session = compiler.as_ref().unwrap().create_session();
+        let compiler = Compiler::global().unwrap();
+        let session = compiler.create_session();
         let mut invocation = session.create_invocation();
         invocation.set_verify_ir(true);
         let source = Source::from_file(&session, Path::new("tests/mul.mlir")).unwrap();
@@ -123,7 +194,6 @@ mod test {
 
     #[test]
     fn source_from_cstr() {
-        init_compiler();
         let source_ir = r#"
         module @arithmetic {
             func.func @simple_add(%arg0: tensor<4xf32>, %arg1: tensor<4xf32>) -> tensor<4xf32> {
@@ -132,8 +202,8 @@ mod test {
             }
         }"#;
         let source_ir_cstr = std::ffi::CString::new(source_ir).unwrap();
-        let compiler = COMPILER.lock().unwrap();
-        let session = compiler.as_ref().unwrap().create_session();
+        let compiler = Compiler::global().unwrap();
+        let session = compiler.create_session();
         let mut invocation = session.create_invocation();
         invocation.set_verify_ir(true);
         let source = session.create_source_from_cstr(&source_ir_cstr).unwrap();
@@ -142,7 +212,6 @@ mod test {
 
     #[test]
     fn source_from_invalid_cstr() {
-        init_compiler();
         let source_ir = r#"
         module @arithmetic {
             func.func @simple_add(%arg0: tensor<4xf32>, %arg1: tensor<4xf32>) -> tensor<4xf32> {
@@ -152,8 +221,8 @@ mod test {
             }
         }"#;
         let source_ir_cstr = std::ffi::CString::new(source_ir).unwrap();
-        let compiler = COMPILER.lock().unwrap();
-        let session = compiler.as_ref().unwrap().create_session();
+        let compiler = Compiler::global().unwrap();
+        let session = compiler.create_session();
         let mut invocation = session.create_invocation();
         invocation.set_verify_ir(true);
         let source = session.create_source_from_cstr(&source_ir_cstr).unwrap();
@@ -162,16 +231,15 @@ mod test {
 
     #[test]
     fn output_byte_code() {
-        init_compiler();
-        let compiler = COMPILER.lock().unwrap();
-        let mut session = compiler.as_ref().unwrap().create_session();
+        let compiler = Compiler::global().unwrap();
+        let mut session = compiler.create_session();
         session
             .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
             .unwrap();
         let mut invocation = session.create_invocation();
         invocation.set_verify_ir(true);
         let source = Source::from_file(&session, Path::new("tests/mul.mlir")).unwrap();
-        let mut output = MemBufferOutput::new(compiler.as_ref().unwrap()).unwrap();
+        let mut output = MemBufferOutput::new(compiler).unwrap();
         invocation.set_compile_to_phase("end").unwrap();
         invocation.parse_source(source).unwrap();
         invocation.pipeline(Pipeline::Std).unwrap();
@@ -183,4 +251,159 @@ mod test {
                 .unwrap()
         });
     }
+
+    #[test]
+    fn compile_to_vmfb() {
+        let compiler = Compiler::global().unwrap();
+        let options = CompilerOptions::new().target_backend(TargetBackend::LlvmCpu);
+        let source = std::fs::read("tests/mul.mlir").unwrap();
+        let vmfb = compile_mlir_to_vmfb(compiler, &source, &options).unwrap();
+        assert!(!vmfb.is_empty());
+
+        let invalid = compile_mlir_to_vmfb(compiler, "INVALID!!!!", &options);
+        match invalid {
+            Err(CompilerError::IREECompilerDiagnosticsError(diagnostics)) => {
+                info!("Diagnostics: {}", diagnostics)
+            }
+            other => panic!(
+                "expected diagnostics, got {:?}",
+                other.map(|vmfb| vmfb.len())
+            ),
+        }
+    }
+
+    #[test]
+    fn compile_to_c_module() {
+        let compiler = Compiler::global().unwrap();
+        let options = CompilerOptions::new().target_backend(TargetBackend::LlvmCpu);
+        let source = std::fs::read("tests/mul.mlir").unwrap();
+        let c_source = compile_mlir_to_c_module(compiler, &source, &options).unwrap();
+        assert!(c_source.contains("EMITC_IMPLEMENTATION"));
+        assert!(c_source.contains("arithmetic_create"));
+    }
+
+    #[test]
+    fn ir_dumps() {
+        let compiler = Compiler::global().unwrap();
+        let mut session = compiler.create_session();
+        session
+            .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
+            .unwrap();
+        let mut invocation = session.create_invocation();
+        invocation
+            .parse_source_from_file(Path::new("tests/mul.mlir"))
+            .unwrap();
+        let mut phases = vec![];
+        invocation
+            .compile_with_ir_dumps(|phase, ir| {
+                assert!(!ir.is_empty());
+                phases.push(phase.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(phases, COMPILE_PHASES[1..]);
+        let mut output = MemBufferOutput::new(compiler).unwrap();
+        invocation.output_vm_byte_code(&mut output).unwrap();
+        assert!(!output.map_memory().unwrap().is_empty());
+    }
+
+    #[test]
+    fn compile_job() {
+        let compiler = Compiler::global().unwrap();
+        let options = CompilerOptions::new().target_backend(TargetBackend::LlvmCpu);
+        let source = std::fs::read("tests/mul.mlir").unwrap();
+        let finished_phases = |events: &[CompileEvent]| {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    CompileEvent::PhaseFinished(phase) => Some(*phase),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let job = CompileJob::spawn(compiler, source.clone(), options.clone()).unwrap();
+        let (vmfb, events) = job.join_with_events();
+        assert!(!vmfb.unwrap().is_empty());
+        // Phases are reported as they finish, from the one after `input` on.
+        assert_eq!(finished_phases(&events), COMPILE_PHASES[1..]);
+
+        // A cancelled job stops after the phase that was running, so it reports at least one
+        // phase and never all of them.
+        let job = CompileJob::spawn(compiler, source, options).unwrap();
+        job.cancel();
+        let (result, events) = job.join_with_events();
+        assert!(matches!(result, Err(CompilerError::Cancelled)));
+        let phases = finished_phases(&events);
+        assert!(!phases.is_empty() && phases.len() < COMPILE_PHASES.len() - 1);
+        assert_eq!(phases, COMPILE_PHASES[1..=phases.len()]);
+    }
+
+    #[test]
+    fn import_onnx() {
+        let compiler = Compiler::global().unwrap();
+        let mut session = compiler.create_session();
+        session
+            .set_options(&CompilerOptions::new().target_backend(TargetBackend::LlvmCpu))
+            .unwrap();
+        let mut invocation = session
+            .import_onnx(Path::new("tests/onnx_mul.mlir"))
+            .unwrap();
+        invocation.pipeline(Pipeline::Std).unwrap();
+        let mut output = MemBufferOutput::new(compiler).unwrap();
+        invocation.output_vm_byte_code(&mut output).unwrap();
+        assert!(!output.map_memory().unwrap().is_empty());
+    }
+
+    #[test]
+    fn output_stream() {
+        let compiler = Compiler::global().unwrap();
+        let mut session = compiler.create_session();
+        session
+            .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
+            .unwrap();
+        let mut invocation = session.create_invocation();
+        let source = Source::from_file(&session, Path::new("tests/mul.mlir")).unwrap();
+        invocation.parse_source(source).unwrap();
+        invocation.pipeline(Pipeline::Std).unwrap();
+
+        let mut output = MemBufferOutput::new(compiler).unwrap();
+        invocation.output_vm_byte_code(&mut output).unwrap();
+        let mut stream = StreamOutput::new(compiler, Vec::new()).unwrap();
+        invocation.output_vm_byte_code(&mut stream).unwrap();
+        assert_eq!(stream.finish().unwrap(), output.map_memory().unwrap());
+    }
+
+    #[test]
+    fn output_write_bytes() {
+        let compiler = Compiler::global().unwrap();
+        let mut output = MemBufferOutput::new(compiler).unwrap();
+        output
+            .write_bytes(b"metadata;")
+            .unwrap()
+            .write_bytes(b"payload")
+            .unwrap();
+        assert_eq!(output.map_memory().unwrap(), b"metadata;payload");
+    }
+
+    #[test]
+    fn parameter_options() {
+        let flags = CompilerOptions::new()
+            .import_parameters(None, Path::new("base.irpa"))
+            .import_parameters(Some("lora"), Path::new("lora.safetensors"))
+            .export_parameters(Some("model"), Path::new("model.irpa"))
+            .export_parameter_minimum_size(1024)
+            .splat_parameters(Path::new("splat.irpa"))
+            .to_flags();
+        assert_eq!(
+            flags,
+            vec![
+                "--iree-opt-import-parameters=base.irpa",
+                "--iree-opt-import-parameters=lora=lora.safetensors",
+                "--iree-opt-export-parameters=model=model.irpa",
+                "--iree-opt-export-parameter-minimum-size=1024",
+                "--iree-opt-splat-parameters=splat.irpa",
+            ]
+        );
+    }
 }
@@ -0,0 +1,22 @@
+module @decoder {
+  // Predicts token (t + 1) mod 4 after token t, and counts the predictions in the cache.
+  func.func @decode(%tokens: tensor<1x1xi64>, %position: i64, %cache: tensor<4xf32>) -> (tensor<4xf32>, tensor<4xf32>) {
+    %c0 = arith.constant 0 : index
+    %c1_i64 = arith.constant 1 : i64
+    %c4_i64 = arith.constant 4 : i64
+    %one = arith.constant 1.0 : f32
+    %zero = arith.constant 0.0 : f32
+    %token = tensor.extract %tokens[%c0, %c0] : tensor<1x1xi64>
+    %next_token = arith.addi %token, %c1_i64 : i64
+    %predicted = arith.remsi %next_token, %c4_i64 : i64
+    %logits = tensor.generate {
+    ^bb0(%i: index):
+      %idx = arith.index_cast %i : index to i64
+      %is_predicted = arith.cmpi eq, %idx, %predicted : i64
+      %logit = arith.select %is_predicted, %one, %zero : f32
+      tensor.yield %logit : f32
+    } : tensor<4xf32>
+    %updated = arith.addf %cache, %logits : tensor<4xf32>
+    return %logits, %updated : tensor<4xf32>, tensor<4xf32>
+  }
+}
@@ -0,0 +1,26 @@
+#pipeline_layout = #hal.pipeline.layout<bindings = [
+  #hal.pipeline.binding<storage_buffer, ReadOnly>,
+  #hal.pipeline.binding<storage_buffer>
+]>
+
+hal.executable.source public @executable {
+  hal.executable.export public @double ordinal(0) layout(#pipeline_layout) count(%device: !hal.device) -> (index, index, index) {
+    %c1 = arith.constant 1 : index
+    hal.return %c1, %c1, %c1 : index, index, index
+  } attributes {workgroup_size = [1 : index, 1 : index, 1 : index]}
+  builtin.module {
+    func.func @double() {
+      %c0 = arith.constant 0 : index
+      %c1 = arith.constant 1 : index
+      %c4 = arith.constant 4 : index
+      %input = hal.interface.binding.subspan layout(#pipeline_layout) binding(0) : memref<4xf32>
+      %output = hal.interface.binding.subspan layout(#pipeline_layout) binding(1) : memref<4xf32>
+      scf.for %i = %c0 to %c4 step %c1 {
+        %x = memref.load %input[%i] : memref<4xf32>
+        %y = arith.addf %x, %x : f32
+        memref.store %y, %output[%i] : memref<4xf32>
+      }
+      return
+    }
+  }
+}
@@ -0,0 +1,6 @@
+module @onnx_arithmetic {
+  func.func @simple_mul(%arg0: !torch.vtensor<[100],f32>, %arg1: !torch.vtensor<[100],f32>) -> !torch.vtensor<[100],f32> attributes {torch.onnx_meta.ir_version = 8 : si64, torch.onnx_meta.opset_version = 17 : si64, torch.onnx_meta.producer_name = "eerie", torch.onnx_meta.producer_version = ""} {
+    %0 = torch.operator "onnx.Mul"(%arg0, %arg1) : (!torch.vtensor<[100],f32>, !torch.vtensor<[100],f32>) -> !torch.vtensor<[100],f32>
+    return %0 : !torch.vtensor<[100],f32>
+  }
+}
@@ -37,6 +37,114 @@ fn test_session() {
     )
     .expect("Failed to create session");
     session.trim().expect("Failed to trim session");
+
+    let options = runtime::api::SessionOptions::default()
+        .context_flags(runtime::vm::ContextFlags::TRACE_EXECUTION);
+    runtime::api::Session::create_with_device(&instance, &options, &device)
+        .expect("Failed to create session with context flags");
+
+    use runtime::api::SessionBuiltins;
+    assert!(SessionBuiltins::default().contains(SessionBuiltins::HAL));
+    let options = runtime::api::SessionOptions::default().builtin_modules(SessionBuiltins::NONE);
+    let session = runtime::api::Session::create_with_device(&instance, &options, &device)
+        .expect("Failed to create session without builtins");
+    assert!(session.modules().is_empty());
+    let hal_module = runtime::hal::create_hal_module(&instance, &[&device]).unwrap();
+    session.append_module(&hal_module).unwrap();
+    assert_eq!(session.modules()[0].name(), "hal");
+}
+
+#[test]
+fn driver_devices() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let driver = instance
+        .lookup_driver(runtime::hal::drivers::LOCAL_SYNC)
+        .unwrap();
+    assert_eq!(driver.name(), "local-sync");
+    let devices = driver.query_available_devices().unwrap();
+    info!("local-sync devices: {:?}", devices);
+    let device = match devices.first() {
+        Some(info) => {
+            instance.create_device_by_id("local-sync", info.id).unwrap();
+            driver.create_device(info).unwrap()
+        }
+        None => driver.create_default_device().unwrap(),
+    };
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    assert!(instance.lookup_driver("no-such-driver").is_err());
+}
+
+#[test]
+fn hal_module() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let module = runtime::hal::create_hal_module(&instance, &[&device]).unwrap();
+    assert_eq!(module.name(), "hal");
+    assert!(runtime::hal::create_hal_module(&instance, &[]).is_err());
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let session_module = session
+        .modules()
+        .into_iter()
+        .find(|module| module.name() == "hal")
+        .unwrap();
+    assert_eq!(module.version(), session_module.version());
+}
+
+#[test]
+fn available_drivers_and_device_uri() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let drivers = instance.available_drivers().unwrap();
+    info!("Drivers: {:?}", drivers);
+    assert!(drivers.iter().any(|driver| driver.name == "local-sync"));
+    assert_eq!(
+        runtime::hal::DriverRegistry::new()
+            .enumerate_drivers()
+            .unwrap(),
+        drivers
+    );
+    // Drivers are registered even if their backing libraries are unavailable on this machine.
+    for driver in drivers.iter() {
+        let Ok(driver) = instance.lookup_driver(&driver.name) else {
+            continue;
+        };
+        info!(
+            "{} devices: {:?}",
+            driver.name(),
+            driver.query_available_devices()
+        );
+    }
+    let device = instance.create_device("local-sync://").unwrap();
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    assert!(instance.create_device("no-such-driver://0").is_err());
 }
 
 #[test]
@@ -56,6 +164,57 @@ fn dynamic_list() {
     assert_eq!(val.from_value(), 1);
 }
 
+#[test]
+fn static_list() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let mut storage = runtime::vm::ListStorage::<512>::new();
+    let size = runtime::vm::StaticList::<Value<i32>>::storage_size(4, &instance);
+    assert!(size <= 512);
+    let list =
+        runtime::vm::StaticList::<Value<i32>>::new(storage.as_byte_span(), 4, &instance).unwrap();
+    for i in 0..4 {
+        list.push_value(i.to_value()).unwrap();
+    }
+    assert_eq!(list.size(), 4);
+    assert_eq!(list.get_value::<i32>(3).unwrap().from_value(), 3);
+    drop(list);
+
+    let mut small_storage = runtime::vm::ListStorage::<8>::new();
+    assert!(matches!(
+        runtime::vm::StaticList::<Value<i32>>::new(small_storage.as_byte_span(), 4, &instance),
+        Err(runtime::error::RuntimeError::ShapeMismatch(_))
+    ));
+}
+
+#[test]
+fn list_iter() {
+    use runtime::vm::Variant;
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let list = runtime::vm::DynamicList::<Value<i32>>::new(3, &instance).unwrap();
+    for i in 1..=3 {
+        list.push_value(i.to_value()).unwrap();
+    }
+    let values = list
+        .values::<i32>()
+        .map(|value| value.map(|value| value.from_value()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(values, vec![1, 2, 3]);
+    assert_eq!(list.iter().len(), 3);
+    assert!(matches!(list.iter().next_back(), Some(Ok(Variant::I32(3)))));
+    for element in &list {
+        assert!(matches!(element.unwrap(), Variant::I32(_)));
+    }
+}
+
 #[test]
 fn ref_list() {
     let instance = runtime::api::Instance::new(
@@ -92,31 +251,2961 @@ fn ref_list() {
     info!("mapping: {:?}", mapping.data());
 }
 
-#[cfg(feature = "compiler")]
-mod integration_tests {
-    use eerie::compiler;
-    use eerie::runtime;
-    use eerie::runtime::hal::{BufferMapping, BufferView, EncodingType};
-    use eerie::runtime::vm::{List, ToRef};
-    use log::{debug, info};
-    use std::path::Path;
-    use std::sync::Mutex;
+#[test]
+fn variant_list() {
+    use runtime::vm::{Undefined, Variant, VariantType};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let buffer = BufferView::<f32>::new(
+        &session,
+        &[2],
+        runtime::hal::EncodingType::DenseRowMajor,
+        &[1.0, 2.0],
+    )
+    .unwrap();
 
-    static COMPILER: Mutex<Option<compiler::Compiler>> = Mutex::new(None);
+    let list = runtime::vm::DynamicList::<Undefined>::new(4, &instance).unwrap();
+    list.push_value(7i32.to_value()).unwrap();
+    list.push_ref_move(buffer.to_ref(&instance).unwrap())
+        .unwrap();
+    list.push_value(0.5f32.to_value()).unwrap();
+    assert_eq!(list.element_type(0).unwrap(), VariantType::I32);
+    assert_eq!(list.element_type(1).unwrap(), VariantType::Ref);
+    assert_eq!(list.element_type(2).unwrap(), VariantType::F32);
 
-    fn init_compiler() {
-        let mut global_compiler = COMPILER.lock().unwrap();
-        if global_compiler.is_none() {
-            let compiler = compiler::Compiler::new().unwrap();
-            *global_compiler = Some(compiler);
-        }
+    let Variant::Ref(element) = list.get_variant(1).unwrap() else {
+        panic!("expected a ref");
+    };
+    assert!(element.is::<BufferView<f32>>(&instance));
+    assert!(!element.is::<runtime::hal::Fence>(&instance));
+    assert!(matches!(list.get_variant(0).unwrap(), Variant::I32(7)));
+    assert_eq!(
+        list.get_variant(2).unwrap().variant_type(),
+        VariantType::F32
+    );
+
+    let refs =
+        runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(1, &instance).unwrap();
+    refs.push_ref(&element.to_ref::<BufferView<f32>>(&instance).unwrap())
+        .unwrap();
+    refs.set_ref(0, &buffer.to_ref(&instance).unwrap()).unwrap();
+    let assigned = unsafe { refs.get_ref_assign::<BufferView<f32>>(0) }.unwrap();
+    assert!(runtime::vm::NativeRef::new(&assigned).is::<BufferView<f32>>(&instance));
+}
+
+#[test]
+fn ref_helpers() {
+    use runtime::error::RuntimeError;
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let buffer = BufferView::<i32>::new(
+        &session,
+        &[2],
+        runtime::hal::EncodingType::DenseRowMajor,
+        &[1, 2],
+    )
+    .unwrap();
+    let mut buffer_ref = buffer.to_ref(&instance).unwrap();
+    let cloned = buffer_ref.clone();
+    assert!(buffer_ref == cloned);
+    assert_eq!(
+        buffer_ref
+            .try_to_buffer_view(&session)
+            .unwrap()
+            .to_vec()
+            .unwrap(),
+        vec![1, 2]
+    );
+
+    let taken = buffer_ref.take();
+    assert!(buffer_ref.is_null() && !taken.is_null());
+    assert!(matches!(
+        buffer_ref.try_to_buffer_view(&session),
+        Err(RuntimeError::SignatureMismatch(_))
+    ));
+
+    // A list element read with the wrong element type is rejected.
+    let list = runtime::vm::DynamicList::<runtime::vm::Undefined>::new(1, &instance).unwrap();
+    list.push_ref(&taken).unwrap();
+    let wrong: runtime::vm::Ref<BufferView<f32>> = list.get_ref(0).unwrap();
+    assert!(matches!(
+        wrong.try_to_buffer_view(&session),
+        Err(RuntimeError::SignatureMismatch(_))
+    ));
+
+    // Untyped reads only check that the ref holds a buffer view.
+    let bytes: runtime::vm::Ref<BufferView<u8>> = list.get_ref(0).unwrap();
+    assert_eq!(
+        bytes
+            .try_to_untyped_buffer_view(&session)
+            .unwrap()
+            .element_type(),
+        runtime::hal::ElementType::Sint32
+    );
+    assert!(matches!(
+        buffer_ref.try_to_untyped_buffer_view(&session),
+        Err(RuntimeError::SignatureMismatch(_))
+    ));
+}
+
+#[test]
+fn buffer_and_list_refs() {
+    use runtime::vm::{DynamicList, Ref, Undefined};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let allocated = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    let buffer = device
+        .queue_alloca(runtime::hal::QUEUE_AFFINITY_ANY, None, &allocated, 16)
+        .unwrap();
+    allocated.wait(runtime::hal::Timeout::Infinite).unwrap();
+
+    let inner = DynamicList::<Undefined>::new(2, &instance).unwrap();
+    inner.push_ref(&buffer.to_ref(&instance).unwrap()).unwrap();
+    inner
+        .push_ref(&allocated.to_ref(&instance).unwrap())
+        .unwrap();
+    let outer = DynamicList::<Ref<DynamicList<Undefined>>>::new(1, &instance).unwrap();
+    outer.push_ref(&inner.to_ref(&instance).unwrap()).unwrap();
+
+    let list_ref: Ref<DynamicList<Undefined>> = outer.get_ref(0).unwrap();
+    let nested = list_ref.to_list().unwrap();
+    assert_eq!(nested.size(), 2);
+    // Elements that are not lists are rejected instead of being read as one.
+    let not_a_list: Ref<DynamicList<Undefined>> = nested.get_ref(0).unwrap();
+    assert!(not_a_list.to_list().is_err());
+    let mut null_ref = list_ref.clone();
+    let _taken = null_ref.take();
+    assert!(null_ref.to_list().is_err());
+    let buffer_ref: Ref<runtime::hal::Buffer> = nested.get_ref(0).unwrap();
+    assert_eq!(buffer_ref.to_buffer().unwrap().byte_length(), 16);
+    let fence_ref: Ref<runtime::hal::Fence> = nested.get_ref(1).unwrap();
+    fence_ref
+        .to_fence()
+        .unwrap()
+        .wait(runtime::hal::Timeout::Immediate)
+        .unwrap();
+    // Refs holding another type are rejected instead of being read as one.
+    let wrong: Ref<runtime::hal::Buffer> = nested.get_ref(1).unwrap();
+    assert!(wrong.to_buffer().is_err());
+    let wrong: Ref<runtime::hal::Fence> = nested.get_ref(0).unwrap();
+    assert!(wrong.to_fence().is_err());
+    let wrong: Ref<runtime::hal::Semaphore> = nested.get_ref(1).unwrap();
+    assert!(wrong.to_semaphore().is_err());
+    let wrong: Ref<runtime::hal::Channel> = nested.get_ref(0).unwrap();
+    assert!(wrong.to_channel().is_err());
+    let wrong: Ref<runtime::vm::Buffer> = nested.get_ref(0).unwrap();
+    assert!(wrong.to_buffer().is_err());
+}
+
+#[test]
+fn vm_buffer() {
+    use runtime::vm::{Buffer, DynamicList, Ref, Undefined, Variant};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let buffer = Buffer::from_str(&instance, "hello").unwrap();
+    assert_eq!(buffer.len(), 5);
+    assert_eq!(buffer.to_vec().unwrap(), b"hello");
+
+    let list = DynamicList::<Undefined>::new(1, &instance).unwrap();
+    list.push_ref(&buffer.to_ref(&instance).unwrap()).unwrap();
+    let Variant::Ref(element) = list.get_variant(0).unwrap() else {
+        panic!("expected a ref");
+    };
+    assert!(element.is::<Buffer>(&instance));
+    let buffer_ref: Ref<Buffer> = list.get_ref(0).unwrap();
+    assert_eq!(
+        buffer_ref.to_buffer().unwrap().to_string_lossy().unwrap(),
+        "hello"
+    );
+    let mut null_ref = buffer_ref.clone();
+    let _taken = null_ref.take();
+    assert!(null_ref.to_buffer().is_err());
+    assert!(Buffer::from_bytes(&instance, &[]).unwrap().is_empty());
+}
+
+#[test]
+fn buffer_view_introspection() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let data = (0..24).collect::<Vec<i32>>();
+    let buffer = BufferView::<i32>::new(
+        &session,
+        &[2, 3, 4],
+        runtime::hal::EncodingType::DenseRowMajor,
+        &data,
+    )
+    .unwrap();
+    assert_eq!(buffer.shape(), vec![2, 3, 4]);
+    assert_eq!(buffer.rank(), 3);
+    assert_eq!(buffer.element_count(), 24);
+    assert_eq!(buffer.element_type(), runtime::hal::ElementType::Sint32);
+    assert_eq!(buffer.to_vec().unwrap(), data);
+}
+
+#[test]
+fn buffer_view_transfer() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let encoding = runtime::hal::EncodingType::DenseRowMajor;
+    let source = BufferView::<f32>::new(&session, &[4], encoding, &[0.0; 4]).unwrap();
+    source.copy_from_host(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+    let target = BufferView::<f32>::new(&session, &[4], encoding, &[0.0; 4]).unwrap();
+    target.copy_from_device(&source).unwrap();
+    let mut data = [0.0; 4];
+    target.copy_to_host(&mut data).unwrap();
+    assert_eq!(data, [1.0, 2.0, 3.0, 4.0]);
+    assert!(matches!(
+        target.copy_to_host(&mut [0.0; 3]),
+        Err(runtime::error::RuntimeError::ShapeMismatch(_))
+    ));
+}
+
+#[test]
+fn allocator_statistics() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let buffer = BufferView::<f32>::new(
+        &session,
+        &[1024],
+        runtime::hal::EncodingType::DenseRowMajor,
+        &[0.0; 1024],
+    )
+    .unwrap();
+    let statistics = device.allocator_statistics();
+    info!("Allocator statistics: {:?}", statistics);
+    assert!(statistics.host_bytes_in_use() <= statistics.host_bytes_allocated);
+    info!(
+        "Allocator statistics report:\n{}",
+        device.allocator_statistics_report().unwrap()
+    );
+    drop(buffer);
+    device.trim().unwrap();
+}
+
+#[test]
+fn device_profiling() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let mut mode = runtime::hal::DeviceProfilingMode::NONE;
+    mode |= runtime::hal::DeviceProfilingMode::QUEUE_OPERATIONS;
+    assert!(mode.contains(runtime::hal::DeviceProfilingMode::QUEUE_OPERATIONS));
+    assert!(!mode.contains(runtime::hal::DeviceProfilingMode::DISPATCH_COUNTERS));
+    device.profiling_begin(mode, None).unwrap();
+    device.profiling_flush().unwrap();
+    device.profiling_end().unwrap();
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn profiling_capture() {
+    runtime::profiling::start_capture();
+    runtime::profiling::frame_mark();
+    runtime::profiling::frame_mark_named(c"inference");
+    runtime::profiling::stop_capture();
+}
+
+#[test]
+fn external_buffer_view() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let mut data = [1.0f32, 2.0, 3.0, 4.0];
+    let data_ptr = data.as_ptr();
+    let buffer = unsafe {
+        BufferView::<f32>::from_slice_external(
+            &session,
+            &[2, 2],
+            runtime::hal::EncodingType::DenseRowMajor,
+            &mut data,
+        )
+    }
+    .unwrap();
+    assert_eq!(buffer.byte_length(), 16);
+    let mapping = runtime::hal::BufferMapping::new(buffer).unwrap();
+    assert_eq!(mapping.data(), &[1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(mapping.data().as_ptr(), data_ptr);
+    drop(mapping);
+
+    let data = vec![5i32; 64];
+    let data_ptr = data.as_ptr();
+    let buffer = BufferView::<i32>::wrap_host_buffer(
+        &session,
+        &[8, 8],
+        runtime::hal::EncodingType::DenseRowMajor,
+        data,
+    )
+    .unwrap();
+    assert_eq!(buffer.shape(), vec![8, 8]);
+    let mapping = runtime::hal::BufferMapping::new(buffer).unwrap();
+    assert_eq!(mapping.data().as_ptr(), data_ptr);
+    assert!(mapping.data().iter().all(|&x| x == 5));
+
+    assert!(matches!(
+        BufferView::<i32>::wrap_host_buffer(
+            &session,
+            &[8, 9],
+            runtime::hal::EncodingType::DenseRowMajor,
+            vec![5i32; 64],
+        ),
+        Err(runtime::error::RuntimeError::ShapeMismatch(_))
+    ));
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn ndarray_buffer_view() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let array = ndarray::ArrayD::from_shape_vec(
+        ndarray::IxDyn(&[2, 3]),
+        vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0],
+    )
+    .unwrap();
+    // Transposed arrays are not in standard layout and must be copied on upload.
+    let transposed = array.t().to_owned();
+    let buffer = BufferView::from_array(&session, &transposed).unwrap();
+    assert_eq!(buffer.shape(), vec![3, 2]);
+    let output: ndarray::ArrayD<f32> = (&buffer).try_into().unwrap();
+    assert_eq!(output, transposed);
+
+    // Views and fixed dimensionality arrays are accepted as well.
+    let view = array.slice(ndarray::s![.., 1..]);
+    let buffer = BufferView::try_from((&session, view.clone())).unwrap();
+    assert_eq!(buffer.shape(), vec![2, 2]);
+    assert_eq!(buffer.into_array().unwrap(), view.to_owned());
+    let array2 = ndarray::arr2(&[[1i32, 2], [3, 4]]);
+    let buffer = BufferView::from_array(&session, &array2).unwrap();
+    let output: ndarray::ArrayD<i32> = buffer.try_into().unwrap();
+    assert_eq!(output, array2.into_dyn());
+}
+
+#[test]
+fn file_handle_rejects_invalid_module() {
+    let file_handle = runtime::io::FileHandle::from_vec(b"not a module".to_vec()).unwrap();
+    assert_eq!(file_handle.contents(), b"not a module");
+    assert!(matches!(
+        file_handle.verify_bytecode_header(),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    // Identifier present, but the root table points past the end of the data.
+    assert!(runtime::io::verify_bytecode_header(b"\xff\0\0\0IREE").is_err());
+    assert!(runtime::io::verify_bytecode_header(b"\x08\0\0\0IREE").is_err());
+    assert!(runtime::io::verify_bytecode_header(b"\x04\0\0\0IREE\0\0\0\0").is_ok());
+}
+
+#[test]
+fn append_module_from_memory_checked_rejects_invalid_module() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let modules = session.modules().len();
+    assert!(matches!(
+        session.append_module_from_memory_checked(b"not a module"),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    // The header is well formed, but the flatbuffer is not.
+    assert!(matches!(
+        session.append_module_from_memory_checked(b"\x08\0\0\0IREE\xff\xff\xff\xff\0\0\0\0"),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    assert_eq!(session.modules().len(), modules);
+}
+
+#[test]
+fn builtin_module_versions() {
+    let versions = runtime::version::builtin_module_versions().unwrap();
+    info!("Builtin modules: {:?}", versions);
+    assert!(versions.iter().any(|module| module.name == "hal"));
+    assert!(matches!(
+        eerie::check_artifact_compatibility(b"not a module"),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    assert!(matches!(
+        eerie::check_artifact_compatibility(b"\x08\0\0\0IREE\xff\xff\xff\xff\0\0\0\0"),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+}
+
+#[test]
+fn status_error_accessors() {
+    use runtime::base::StatusErrorKind;
+    use runtime::error::RuntimeError;
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let Err(RuntimeError::StatusError(err)) = instance.lookup_driver("no-such-driver") else {
+        panic!("expected a status error");
+    };
+    info!("{} at {:?}", err, err.source_location());
+    assert_eq!(err.code(), StatusErrorKind::NotFound);
+    assert!(err.message().contains("no-such-driver"));
+    let mut buf = [0u8; 512];
+    assert!(err
+        .format_into(&mut buf)
+        .unwrap()
+        .contains("no-such-driver"));
+    assert!(err.format_into(&mut [0u8; 4]).is_none());
+
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let Err(RuntimeError::StatusError(err)) = runtime::hal::Fence::at(&semaphore, 1)
+        .unwrap()
+        .wait(runtime::hal::Timeout::Immediate)
+    else {
+        panic!("expected a status error");
+    };
+    assert_eq!(err.code(), StatusErrorKind::DeadlineExceeded);
+}
+
+#[test]
+fn fence_at_semaphore() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let semaphore = runtime::hal::Semaphore::new(&device, 1).unwrap();
+    let reached = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    assert!(reached.is_signaled().unwrap());
+    reached.wait(runtime::hal::Timeout::Immediate).unwrap();
+    let pending = runtime::hal::Fence::at(&semaphore, 2).unwrap();
+    assert!(!pending.is_signaled().unwrap());
+    assert!(pending.wait(runtime::hal::Timeout::Immediate).is_err());
+}
+
+#[test]
+fn fence_timepoints_and_device_wait() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let first = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let second = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let fence = runtime::hal::Fence::from_timepoints(&[(&first, 1), (&second, 2)]).unwrap();
+
+    let timepoints = fence.timepoints();
+    assert_eq!(timepoints.len(), 2);
+    assert_eq!(
+        timepoints
+            .iter()
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    let imported = runtime::hal::Fence::from_timepoints(
+        &timepoints
+            .iter()
+            .map(|(semaphore, value)| (semaphore, *value))
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+
+    first.signal(1).unwrap();
+    device
+        .wait_semaphores(
+            runtime::hal::WaitMode::Any,
+            &[(&first, 1), (&second, 2)],
+            runtime::hal::Timeout::Immediate,
+        )
+        .unwrap();
+    assert!(device
+        .wait_semaphores(
+            runtime::hal::WaitMode::All,
+            &[(&first, 1), (&second, 2)],
+            runtime::hal::Timeout::Immediate,
+        )
+        .is_err());
+    assert!(!imported.is_signaled().unwrap());
+    second.signal(2).unwrap();
+    assert!(imported.is_signaled().unwrap());
+    assert!(fence.is_signaled().unwrap());
+}
+
+#[test]
+fn queue_barrier() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-task")
+        .expect("Failed to create device");
+    let upstream = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let downstream = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let wait_fence = runtime::hal::Fence::at(&upstream, 1).unwrap();
+    let signal_fence = runtime::hal::Fence::at(&downstream, 1).unwrap();
+    device
+        .queue_barrier(
+            runtime::hal::QUEUE_AFFINITY_ANY,
+            Some(&wait_fence),
+            &signal_fence,
+        )
+        .unwrap();
+    assert_eq!(downstream.query().unwrap(), 0);
+    upstream.signal(1).unwrap();
+    signal_fence.wait(runtime::hal::Timeout::Infinite).unwrap();
+    assert_eq!(downstream.query().unwrap(), 1);
+}
+
+#[test]
+fn queue_alloca() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let allocated = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    let buffer = device
+        .queue_alloca(
+            runtime::hal::QUEUE_AFFINITY_ANY,
+            None,
+            &allocated,
+            4 * std::mem::size_of::<f32>(),
+        )
+        .unwrap();
+    allocated.wait(runtime::hal::Timeout::Infinite).unwrap();
+    assert_eq!(buffer.byte_length(), 16);
+
+    let buffer_view = BufferView::<f32>::from_buffer(
+        &session,
+        &buffer,
+        &[4],
+        runtime::hal::EncodingType::DenseRowMajor,
+    )
+    .unwrap();
+    buffer_view.copy_from_host(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+    assert_eq!(buffer_view.to_vec().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+    assert!(matches!(
+        BufferView::<f32>::from_buffer(
+            &session,
+            &buffer,
+            &[3],
+            runtime::hal::EncodingType::DenseRowMajor,
+        ),
+        Err(runtime::error::RuntimeError::ShapeMismatch(_))
+    ));
+    drop(buffer_view);
+
+    let deallocated = runtime::hal::Fence::at(&semaphore, 2).unwrap();
+    device
+        .queue_dealloca(
+            runtime::hal::QUEUE_AFFINITY_ANY,
+            Some(&allocated),
+            &deallocated,
+            &buffer,
+        )
+        .unwrap();
+    deallocated.wait(runtime::hal::Timeout::Infinite).unwrap();
+}
+
+#[test]
+fn command_buffer() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let encoding = runtime::hal::EncodingType::DenseRowMajor;
+    let source = BufferView::<u32>::new(&session, &[4], encoding, &[0; 4]).unwrap();
+    let target = BufferView::<u32>::new(&session, &[4], encoding, &[0; 4]).unwrap();
+
+    let mut command_buffer =
+        runtime::hal::CommandBuffer::new(&device, runtime::hal::QUEUE_AFFINITY_ANY).unwrap();
+    command_buffer
+        .fill_buffer(&source.buffer(), 0, 16, &7u32.to_ne_bytes())
+        .unwrap()
+        .execution_barrier()
+        .unwrap()
+        .copy_buffer(&source.buffer(), 0, &target.buffer(), 8, 8)
+        .unwrap();
+    command_buffer.end().unwrap();
+
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let signal_fence = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    device
+        .queue_execute(
+            runtime::hal::QUEUE_AFFINITY_ANY,
+            None,
+            &signal_fence,
+            &[&command_buffer],
+        )
+        .unwrap();
+    signal_fence.wait(runtime::hal::Timeout::Infinite).unwrap();
+    assert_eq!(source.to_vec().unwrap(), vec![7; 4]);
+    assert_eq!(target.to_vec().unwrap(), vec![0, 0, 7, 7]);
+}
+
+#[test]
+fn channel() {
+    use runtime::base::StatusErrorKind;
+    use runtime::error::RuntimeError;
+    use runtime::vm::ToRef;
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-task")
+        .expect("Failed to create device");
+    let params = runtime::hal::ChannelParams {
+        rank: Some(0),
+        count: Some(1),
+        ..Default::default()
+    };
+    match runtime::hal::Channel::new(&device, runtime::hal::QUEUE_AFFINITY_ANY, &params) {
+        Ok(channel) => {
+            assert_eq!(channel.rank_and_count(), (0, 1));
+            let channel_ref = channel.to_ref(&instance).unwrap();
+            assert_eq!(channel_ref.to_channel().unwrap().rank_and_count(), (0, 1));
+        }
+        Err(RuntimeError::StatusError(err)) => {
+            info!("Collectives are not supported: {}", err);
+            assert_eq!(err.code(), StatusErrorKind::Unimplemented);
+        }
+        Err(err) => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn fence_future() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let fence = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    let signaler = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        semaphore.signal(1).unwrap();
+    });
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(fence.clone()).unwrap();
+    assert!(fence.is_signaled().unwrap());
+    signaler.join().unwrap();
+}
+
+#[test]
+fn semaphore_signal_and_fence() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let producer = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let consumer = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let mut fence = runtime::hal::Fence::new(2).unwrap();
+    fence.insert(&producer, 1).unwrap();
+    fence.insert(&consumer, 2).unwrap();
+    assert_eq!(fence.timepoint_count(), 2);
+
+    producer.signal(1).unwrap();
+    assert_eq!(producer.query().unwrap(), 1);
+    assert!(!fence.is_signaled().unwrap());
+    consumer.signal(2).unwrap();
+    consumer.wait(2, runtime::hal::Timeout::Immediate).unwrap();
+    assert!(fence.is_signaled().unwrap());
+
+    let other = runtime::hal::Fence::at(&producer, 3).unwrap();
+    let joined = runtime::hal::Fence::join(&[&fence, &other]).unwrap();
+    assert!(!joined.is_signaled().unwrap());
+    other.signal().unwrap();
+    joined.wait(runtime::hal::Timeout::Infinite).unwrap();
+
+    let fence_ref = joined.to_ref(&instance).unwrap();
+    assert!(fence_ref.to_fence().unwrap().is_signaled().unwrap());
+    let semaphore_ref = producer.to_ref(&instance).unwrap();
+    assert_eq!(semaphore_ref.to_semaphore().unwrap().query().unwrap(), 3);
+
+    producer.fail(runtime::base::StatusErrorKind::Aborted);
+    assert!(producer.query().is_err());
+}
+
+#[test]
+fn instrument_data_chunks() {
+    use runtime::api::{InstrumentChunkKind, InstrumentData};
+    use runtime::error::RuntimeError;
+    let chunk = |kind: u16, content: &[u8]| {
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(b"DBTS");
+        chunk.extend_from_slice(&kind.to_le_bytes());
+        chunk.extend_from_slice(&1u16.to_le_bytes());
+        chunk.extend_from_slice(&2u16.to_le_bytes());
+        chunk.extend_from_slice(&[0; 6]);
+        chunk.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        chunk.extend_from_slice(content);
+        chunk
+    };
+    let mut bytes = chunk(0, b"metadata");
+    bytes.extend(chunk(1, &[7; 32]));
+    bytes.extend(chunk(9, &[]));
+    let data = InstrumentData::from_bytes(bytes.clone());
+    let chunks = data.chunks().unwrap();
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].kind, InstrumentChunkKind::DispatchMetadata);
+    assert_eq!(chunks[0].version, (1, 2));
+    assert_eq!(chunks[0].content, b"metadata");
+    assert_eq!(chunks[1].kind, InstrumentChunkKind::DispatchRingbuffer);
+    assert_eq!(chunks[1].content, &[7; 32]);
+    assert_eq!(chunks[2].kind, InstrumentChunkKind::Other(9));
+
+    bytes.truncate(bytes.len() - 1);
+    assert!(matches!(
+        InstrumentData::from_bytes(bytes).chunks(),
+        Err(RuntimeError::InvalidArchive(_))
+    ));
+    assert!(matches!(
+        InstrumentData::from_bytes(vec![0; 24]).chunks(),
+        Err(RuntimeError::InvalidArchive(_))
+    ));
+}
+
+#[test]
+fn executable_cache() {
+    use runtime::hal::{ExecutableCache, ExecutableCachingMode};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let cache = ExecutableCache::new(&device, "eerie-test").unwrap();
+    let mode =
+        ExecutableCachingMode::ALLOW_PERSISTENT_CACHING | ExecutableCachingMode::ALLOW_OPTIMIZATION;
+    assert!(mode.contains(ExecutableCachingMode::ALLOW_OPTIMIZATION));
+    assert!(!mode.contains(ExecutableCachingMode::ENABLE_DEBUGGING));
+    assert!(!cache.can_prepare_format("not-a-format", mode));
+    assert!(cache
+        .prepare_executable("not-a-format", b"not an executable", mode)
+        .is_err());
+}
+
+#[test]
+fn local_task_device() {
+    use runtime::hal::LocalTaskDeviceParams;
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let params = LocalTaskDeviceParams::new()
+        .worker_count(2)
+        .worker_stack_size(256 * 1024)
+        .cpu_affinity(&[0]);
+    let device = instance.create_local_task_device(&params).unwrap();
+    let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+    let fence = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+    device
+        .queue_barrier(runtime::hal::QUEUE_AFFINITY_ANY, None, &fence)
+        .unwrap();
+    fence.wait(runtime::hal::Timeout::Infinite).unwrap();
+    assert_eq!(semaphore.query().unwrap(), 1);
+}
+
+#[test]
+fn native_module() {
+    use runtime::vm::{NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("add", "0ii_i", |args| match args {
+            [NativeValue::I32(a), NativeValue::I32(b)] => Ok(vec![NativeValue::I32(a + b)]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .export("scale", "0f_f", |args| match args {
+            [NativeValue::F32(x)] => Ok(vec![NativeValue::F32(x * 2.0)]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(module.name(), "host");
+    session.append_module(&module).unwrap();
+
+    let function = session.lookup_function("host.add").unwrap();
+    let inputs = runtime::vm::DynamicList::<Value<i32>>::new(2, &instance).unwrap();
+    inputs.push_value(2.to_value()).unwrap();
+    inputs.push_value(3.to_value()).unwrap();
+    let outputs = runtime::vm::DynamicList::<Value<i32>>::new(1, &instance).unwrap();
+    function.invoke(&inputs, &outputs).unwrap();
+    assert_eq!(outputs.get_value::<i32>(0).unwrap().from_value(), 5);
+}
+
+#[test]
+fn context() {
+    use runtime::vm::{Context, ContextFlags, ContextOptions, NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let hal_module = runtime::hal::create_hal_module(&instance, &[&device]).unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("add", "0ii_i", |args| match args {
+            [NativeValue::I32(a), NativeValue::I32(b)] => Ok(vec![NativeValue::I32(a + b)]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    let context = Context::create_with_modules(&instance, &[&hal_module]).unwrap();
+    context.register_modules(&[&module]).unwrap();
+    context.freeze().unwrap();
+    assert!(context.register_modules(&[&module]).is_err());
+
+    let function = context.resolve_function("host.add").unwrap();
+    assert_eq!(function.name(), "add");
+    let inputs = runtime::vm::DynamicList::<Value<i32>>::new(2, &instance).unwrap();
+    inputs.push_value(2.to_value()).unwrap();
+    inputs.push_value(3.to_value()).unwrap();
+    let outputs = runtime::vm::DynamicList::<Value<i32>>::new(1, &instance).unwrap();
+    function.invoke(&inputs, &outputs).unwrap();
+    assert_eq!(outputs.get_value::<i32>(0).unwrap().from_value(), 5);
+    assert!(context.resolve_function("host.sub").is_err());
+
+    let options = ContextOptions::new().flags(ContextFlags::CONCURRENT | ContextFlags::NONE);
+    let context =
+        Context::create_with_modules_and_options(&instance, &[&hal_module, &module], &options)
+            .unwrap();
+    let function = context.resolve_function("host.add").unwrap();
+    function.invoke(&inputs, &outputs).unwrap();
+    Context::with_options(&instance, &ContextOptions::new()).unwrap();
+}
+
+#[test]
+fn module_function_lookup() {
+    use runtime::vm::{FunctionLinkage, NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("add", "0ii_i", |args| match args {
+            [NativeValue::I32(a), NativeValue::I32(b)] => Ok(vec![NativeValue::I32(a + b)]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+
+    assert_eq!(module.export_count(), 1);
+    let exports = module.exports().unwrap();
+    assert_eq!(exports[0].name(), "add");
+    let function = module
+        .lookup_function_by_name(FunctionLinkage::Export, "add")
+        .unwrap();
+    assert_eq!(function.ordinal(), 0);
+    assert_eq!(function.calling_convention(), "0ii_i");
+    assert!(module
+        .lookup_function_by_ordinal(FunctionLinkage::Export, 1)
+        .is_err());
+    assert!(module
+        .lookup_function_by_name(FunctionLinkage::Export, "missing")
+        .is_err());
+
+    assert!(matches!(
+        function.bind(&session),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    session.append_module(&module).unwrap();
+    let (sum,): (i32,) = function.bind(&session).unwrap().call((2, 3)).unwrap();
+    assert_eq!(sum, 5);
+
+    let context = runtime::vm::Context::new(&instance).unwrap();
+    assert!(function.bind_context(&context).is_err());
+    context.register_modules(&[&module]).unwrap();
+    assert_eq!(function.bind_context(&context).unwrap().name(), "add");
+}
+
+#[test]
+fn stack_frames_of_failed_call() {
+    use runtime::error::RuntimeError;
+    use runtime::vm::{FunctionLinkage, NativeModuleBuilder};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("fail", "0i_i", |_| {
+            Err(RuntimeError::ShapeMismatch("bad input".to_string()))
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    session.append_module(&module).unwrap();
+
+    let function = session.lookup_function("host.fail").unwrap();
+    let result: Result<(i32,), _> = function.call((1,));
+    let Err(RuntimeError::StatusError(err)) = result else {
+        panic!("expected a status error");
+    };
+    info!("{}", err);
+    for frame in err.stack_frames() {
+        info!("{} {:?} {:?}", frame.function, frame.pc, frame.location);
+        assert!(!frame.function.is_empty());
+    }
+
+    // Native functions carry no source locations.
+    let function = module
+        .lookup_function_by_name(FunctionLinkage::Export, "fail")
+        .unwrap();
+    assert!(!matches!(function.resolve_source_location(0), Ok(Some(_))));
+}
+
+#[test]
+fn typed_call() {
+    use runtime::vm::{NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("mul_add", "0iif_fi", |args| match args {
+            [NativeValue::I32(a), NativeValue::I32(b), NativeValue::F32(c)] => Ok(vec![
+                NativeValue::F32(*a as f32 * *b as f32 + c),
+                NativeValue::I32(a + b),
+            ]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    session.append_module(&module).unwrap();
+
+    let function = session.lookup_function("host.mul_add").unwrap();
+    let (product, sum): (f32, i32) = function.call((2, 3, 0.5f32)).unwrap();
+    assert_eq!(product, 6.5);
+    assert_eq!(sum, 5);
+    assert!(matches!(
+        function.call::<_, (f32, i32)>((2, 3)),
+        Err(runtime::error::RuntimeError::SignatureMismatch(_))
+    ));
+    assert!(matches!(
+        function.call::<_, (f32,)>((2, 3, 0.5f32)),
+        Err(runtime::error::RuntimeError::SignatureMismatch(_))
+    ));
+
+    let mut call = runtime::api::Call::new(&session, &function).unwrap();
+    let (product, sum): (f32, i32) = call.invoke_typed((4, 5, 0.25f32)).unwrap();
+    assert_eq!(product, 20.25);
+    assert_eq!(sum, 9);
+    // The lists are reset, so the call can be reused.
+    let (product, _): (f32, i32) = call.invoke_typed((1, 1, 0.0f32)).unwrap();
+    assert_eq!(product, 1.0);
+    assert!(matches!(
+        call.invoke_typed::<_, (f32, i32)>((4, 5)),
+        Err(runtime::error::RuntimeError::SignatureMismatch(_))
+    ));
+
+    let results: Vec<(f32, i32)> = call
+        .invoke_batch([(1, 2, 0.0f32), (2, 2, 0.5f32), (3, 2, 1.0f32)])
+        .unwrap();
+    assert_eq!(results, vec![(2.0, 3), (4.5, 4), (7.0, 5)]);
+    assert!(matches!(
+        call.invoke_batch::<_, (f32,)>([(1, 2, 0.0f32)]),
+        Err(runtime::error::RuntimeError::SignatureMismatch(_))
+    ));
+
+    call.inputs_push_back_value(2i32).unwrap();
+    call.inputs_push_back_value(3i32).unwrap();
+    call.inputs_push_back_value(0.5f32).unwrap();
+    call.invoke().unwrap();
+    assert!(matches!(
+        call.outputs_pop_front_value::<i32>(),
+        Err(runtime::error::RuntimeError::SignatureMismatch(_))
+    ));
+    assert_eq!(
+        call.outputs_pop_front_value::<f32>().unwrap().from_value(),
+        6.5
+    );
+    assert_eq!(
+        call.outputs_pop_front_value::<i32>().unwrap().from_value(),
+        5
+    );
+    assert!(call.outputs_pop_front_value::<i32>().is_err());
+
+    use runtime::api::CallFlags;
+    let flags = CallFlags::NONE | CallFlags::TRACE_EXECUTION;
+    assert!(flags.contains(CallFlags::TRACE_EXECUTION));
+    assert!(!CallFlags::default().contains(CallFlags::TRACE_EXECUTION));
+    call.inputs_push_back_value(1i32).unwrap();
+    call.inputs_push_back_value(2i32).unwrap();
+    call.inputs_push_back_value(0.0f32).unwrap();
+    call.invoke_with_flags(flags).unwrap();
+    assert_eq!(
+        call.outputs_pop_front_value::<f32>().unwrap().from_value(),
+        2.0
+    );
+}
+
+#[test]
+fn native_module_refs() {
+    use runtime::hal::{Fence, Semaphore};
+    use runtime::vm::{NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("signal", "0rI_r", |args| match args {
+            [NativeValue::Semaphore(semaphore), NativeValue::I64(value)] => {
+                semaphore.signal(*value as u64)?;
+                Ok(vec![NativeValue::Fence(Fence::at(
+                    semaphore,
+                    *value as u64,
+                )?)])
+            }
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    session.append_module(&module).unwrap();
+
+    let semaphore = Semaphore::new(&device, 0).unwrap();
+    let function = session.lookup_function("host.signal").unwrap();
+    let (fence,): (Fence,) = function.call((&semaphore, 2i64)).unwrap();
+    assert_eq!(semaphore.query().unwrap(), 2);
+    assert!(fence.is_signaled().unwrap());
+}
+
+#[test]
+fn invocation_policy_cancellation() {
+    use runtime::api::{CancellationToken, InvocationPolicy};
+    use runtime::base::StatusErrorKind;
+    use runtime::error::RuntimeError;
+    use runtime::hal::{Fence, Semaphore, Timeout};
+    use runtime::vm::{NativeModuleBuilder, NativeValue};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    // Never signals its signal fence, like a model that takes forever.
+    let module = NativeModuleBuilder::new(&instance, "host")
+        .export("hang", "0rr_v", |_| Ok(vec![]))
+        .unwrap()
+        .export("add", "0ii_i", |args| match args {
+            [NativeValue::I32(a), NativeValue::I32(b)] => Ok(vec![NativeValue::I32(a + b)]),
+            _ => unreachable!(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    session.append_module(&module).unwrap();
+    let is_cancelled = |err: RuntimeError| matches!(err, RuntimeError::StatusError(err) if err.code() == StatusErrorKind::Cancelled);
+
+    let token = CancellationToken::new(&device).unwrap();
+    assert!(!token.is_cancelled());
+    let policy = InvocationPolicy::new().cancellation(&token);
+    let add = session.lookup_function("host.add").unwrap();
+    let mut call = runtime::api::Call::new(&session, &add).unwrap();
+    call.inputs_push_back_value(2i32).unwrap();
+    call.inputs_push_back_value(3i32).unwrap();
+    call.invoke_with_policy(&policy).unwrap();
+    assert_eq!(
+        call.outputs_pop_front_value::<i32>().unwrap().from_value(),
+        5
+    );
+
+    let hang = session.lookup_function("host.hang").unwrap();
+    let mut call = runtime::api::Call::new(&session, &hang).unwrap();
+    let wait_semaphore = Semaphore::new(&device, 0).unwrap();
+    let wait_fence = Fence::at(&wait_semaphore, 0).unwrap();
+    call.begin_with_policy(Some(&wait_fence), &policy).unwrap();
+    assert!(call.poll().unwrap().is_pending());
+    std::thread::scope(|scope| {
+        let token = token.clone();
+        scope.spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        assert!(is_cancelled(call.wait(Timeout::Infinite).unwrap_err()));
+    });
+    assert!(token.is_cancelled());
+    // The pending invocation was dropped, and new invocations are refused.
+    assert!(call.poll().unwrap().is_ready());
+    assert!(is_cancelled(call.invoke_with_policy(&policy).unwrap_err()));
+    let inputs = runtime::vm::DynamicList::<Value<i32>>::new(2, &instance).unwrap();
+    let outputs = runtime::vm::DynamicList::<Value<i32>>::new(1, &instance).unwrap();
+    assert!(is_cancelled(
+        add.invoke_with_policy(&inputs, &outputs, &policy)
+            .unwrap_err()
+    ));
+}
+
+#[cfg(feature = "parameters")]
+#[test]
+fn parameter_provider() {
+    use runtime::io::{FileHandle, ParameterIndex, ParameterProvider};
+
+    let index = ParameterIndex::new().unwrap();
+    assert!(index.is_empty());
+    let invalid = FileHandle::from_vec(vec![0u8; 64]).unwrap();
+    assert!(index.add_archive("invalid.irpa", &invalid).is_err());
+    assert_eq!(index.len(), 0);
+
+    let mut driver_registry = runtime::hal::DriverRegistry::new();
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut driver_registry).use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let provider = ParameterProvider::from_index("model", &index).unwrap();
+    session.append_parameter_providers(&[&provider]).unwrap();
+}
+
+#[cfg(feature = "vision")]
+#[test]
+fn vision_helpers() {
+    use runtime::vision::{self, DetectionFormat, ImageOptions, Layout};
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    // A 2x1 image with a red and a blue pixel.
+    let pixels = [255, 0, 0, 0, 0, 255];
+    let nchw =
+        vision::rgb8_to_buffer_view(&session, &pixels, 2, 1, &ImageOptions::default()).unwrap();
+    assert_eq!(nchw.shape(), [1, 3, 1, 2]);
+    assert_eq!(nchw.to_vec().unwrap(), [1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    let options = ImageOptions {
+        layout: Layout::Nhwc,
+        mean: [0.5; 3],
+        std: [0.5; 3],
+        ..Default::default()
+    };
+    let nhwc = vision::rgb8_to_buffer_view(&session, &pixels, 2, 1, &options).unwrap();
+    assert_eq!(nhwc.shape(), [1, 1, 2, 3]);
+    assert_eq!(nhwc.to_vec().unwrap(), [1.0, -1.0, -1.0, -1.0, -1.0, 1.0]);
+    let resized =
+        vision::rgb8_to_buffer_view(&session, &pixels, 2, 1, &ImageOptions::imagenet(4, 4))
+            .unwrap();
+    assert_eq!(resized.shape(), [1, 3, 4, 4]);
+    assert!(vision::rgb8_to_buffer_view(&session, &pixels, 3, 1, &options).is_err());
+
+    // Three YOLOv5 boxes with two classes, the second overlapping the first.
+    #[rustfmt::skip]
+    let output = [
+        10.0, 10.0, 4.0, 4.0, 0.9, 0.1, 0.9,
+        10.5, 10.0, 4.0, 4.0, 0.8, 0.2, 0.8,
+        30.0, 30.0, 2.0, 2.0, 0.9, 0.9, 0.1,
+    ];
+    let detections =
+        vision::decode_detections(&output, &[1, 3, 7], DetectionFormat::YoloV5, 0.5, 0.5).unwrap();
+    assert_eq!(detections.len(), 2);
+    assert_eq!(detections[0].class, 1);
+    assert_eq!(detections[0].bbox, [8.0, 8.0, 12.0, 12.0]);
+    assert_eq!(detections[1].class, 0);
+    assert_eq!(
+        detections[1].scaled(2.0, 0.5).bbox,
+        [58.0, 14.5, 62.0, 15.5]
+    );
+    assert!(
+        vision::decode_detections(&output, &[1, 7, 3], DetectionFormat::YoloV5, 0.5, 0.5).is_err()
+    );
+}
+
+#[cfg(feature = "safetensors")]
+#[test]
+fn safetensors_buffer_view() {
+    use runtime::{
+        hal::{ElementType, SafeTensors},
+        io::FileHandle,
+    };
+
+    let mut header = br#"{"weight":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#.to_vec();
+    while header.len() % 8 != 0 {
+        header.push(b' ');
+    }
+    let mut archive = (header.len() as u64).to_le_bytes().to_vec();
+    archive.extend_from_slice(&header);
+    for value in [1.0f32, 2.0, 3.0, 4.0] {
+        archive.extend_from_slice(&value.to_le_bytes());
+    }
+    let tensors =
+        SafeTensors::from_file_handle("test.safetensors", FileHandle::from_vec(archive).unwrap())
+            .unwrap();
+    assert_eq!(tensors.tensors().len(), 1);
+    let weight = tensors.get("weight").unwrap();
+    assert_eq!(weight.element_type, ElementType::Float32);
+    assert_eq!(weight.shape, vec![2, 2]);
+    assert_eq!(tensors.data("weight").unwrap().len(), 16);
+    assert!(SafeTensors::from_file_handle(
+        "invalid.safetensors",
+        FileHandle::from_vec(vec![0xff; 16]).unwrap()
+    )
+    .is_err());
+
+    let mut driver_registry = runtime::hal::DriverRegistry::new();
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut driver_registry).use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    assert!(tensors.buffer_view::<i32>(&session, "weight").is_err());
+    assert!(tensors.buffer_view::<f32>(&session, "bias").is_err());
+    let buffer_view = tensors.buffer_view::<f32>(&session, "weight").unwrap();
+    assert_eq!(buffer_view.shape(), vec![2, 2]);
+    assert_eq!(buffer_view.to_vec().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+    // The buffer keeps the archive alive.
+    drop(tensors);
+    assert_eq!(buffer_view.to_vec().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn abi_signature() {
+    use runtime::{
+        hal::ElementType,
+        vm::{AbiSignature, AbiType, TensorType},
+    };
+
+    let signature = AbiSignature::parse(
+        "sync func @main(%input0: tensor<?x4xf32>, %input1: i32, %input2: !hal.fence) -> \
+         (%output0: tensor<2x3xcomplex<f32>> {iree.abi.name = \"out\"})",
+    )
+    .unwrap();
+    assert_eq!(signature.name, "main");
+    assert!(!signature.is_async);
+    assert_eq!(signature.arguments.len(), 3);
+    assert_eq!(signature.arguments[0].name, "input0");
+    let input0 = TensorType {
+        shape: vec![None, Some(4)],
+        element_type: ElementType::Float32,
+    };
+    assert_eq!(
+        signature.arguments[0].value_type,
+        AbiType::Tensor(input0.clone())
+    );
+    assert!(input0.accepts_shape(&[7, 4]));
+    assert!(!input0.accepts_shape(&[7, 5]));
+    assert!(!input0.accepts_shape(&[4]));
+    assert_eq!(input0.static_shape(), None);
+    assert_eq!(
+        signature.arguments[1].value_type,
+        AbiType::Scalar(ElementType::Int32)
+    );
+    assert_eq!(
+        signature.arguments[2].value_type,
+        AbiType::Other("!hal.fence".to_string())
+    );
+    assert_eq!(
+        signature.results[0].value_type,
+        AbiType::Tensor(TensorType {
+            shape: vec![Some(2), Some(3)],
+            element_type: ElementType::ComplexFloat64,
+        })
+    );
+
+    let signature = AbiSignature::parse("async func @empty()").unwrap();
+    assert!(signature.is_async);
+    assert!(signature.arguments.is_empty() && signature.results.is_empty());
+    assert!(AbiSignature::parse("func @main(").is_err());
+}
+
+struct CountingAllocator(std::sync::atomic::AtomicUsize);
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+static COUNTING_ALLOCATOR: CountingAllocator =
+    CountingAllocator(std::sync::atomic::AtomicUsize::new(0));
+
+#[test]
+fn instance_with_specific_drivers() {
+    let mut driver_registry = runtime::hal::DriverRegistry::empty().unwrap();
+    driver_registry
+        .register_driver(runtime::hal::drivers::LOCAL_SYNC)
+        .unwrap();
+    assert!(driver_registry
+        .register_driver(runtime::hal::drivers::LOCAL_SYNC)
+        .is_err());
+    assert!(driver_registry.register_driver("not-a-driver").is_err());
+    let drivers = driver_registry.enumerate_drivers().unwrap();
+    assert_eq!(drivers.len(), 1);
+    assert_eq!(drivers[0].name, runtime::hal::drivers::LOCAL_SYNC);
+
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut driver_registry)
+            .host_allocator(&COUNTING_ALLOCATOR),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device(runtime::hal::drivers::LOCAL_SYNC)
+        .unwrap();
+    // Drivers outside of the registry are not available to the instance.
+    assert!(instance.try_create_default_device("local-task").is_err());
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    assert!(
+        COUNTING_ALLOCATOR
+            .0
+            .load(std::sync::atomic::Ordering::Relaxed)
+            > 0
+    );
+}
+
+// A bump allocator over a fixed, 16 byte aligned buffer, which never frees.
+#[repr(C, align(16))]
+struct BumpArena {
+    memory: std::cell::UnsafeCell<[u8; 16 << 20]>,
+    offset: std::sync::atomic::AtomicUsize,
+}
+
+unsafe impl Sync for BumpArena {}
+
+unsafe impl runtime::base::HostAllocator for BumpArena {
+    fn allocate(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let size = (layout.size() + layout.align() - 1) & !(layout.align() - 1);
+        let offset = self
+            .offset
+            .fetch_add(size, std::sync::atomic::Ordering::Relaxed);
+        if offset + size > std::mem::size_of_val(&self.memory) {
+            return std::ptr::null_mut();
+        }
+        unsafe { (self.memory.get() as *mut u8).add(offset) }
+    }
+
+    unsafe fn deallocate(&self, _: *mut u8, _: std::alloc::Layout) {}
+}
+
+static ARENA: BumpArena = BumpArena {
+    memory: std::cell::UnsafeCell::new([0; 16 << 20]),
+    offset: std::sync::atomic::AtomicUsize::new(0),
+};
+
+#[test]
+fn instance_with_arena_allocator() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers()
+            .host_allocator(&ARENA),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    assert!(ARENA.offset.load(std::sync::atomic::Ordering::Relaxed) > 0);
+}
+
+// An allocator that is always out of memory.
+struct ExhaustedAllocator;
+
+unsafe impl runtime::base::HostAllocator for ExhaustedAllocator {
+    fn allocate(&self, _: std::alloc::Layout) -> *mut u8 {
+        std::ptr::null_mut()
+    }
+
+    unsafe fn deallocate(&self, _: *mut u8, _: std::alloc::Layout) {}
+}
+
+static EXHAUSTED_ALLOCATOR: ExhaustedAllocator = ExhaustedAllocator;
+
+#[test]
+fn instance_with_exhausted_allocator() {
+    use runtime::base::StatusErrorKind;
+    use runtime::error::RuntimeError;
+    // Failed allocations are reported to the runtime with a code-only status.
+    match runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers()
+            .host_allocator(&EXHAUSTED_ALLOCATOR),
+    ) {
+        Err(RuntimeError::StatusError(err)) => {
+            assert_eq!(err.code(), StatusErrorKind::ResourceExhausted)
+        }
+        Err(err) => panic!("unexpected error: {}", err),
+        Ok(_) => panic!("the instance was allocated without memory"),
+    }
+}
+
+static TRACKING_ALLOCATOR: runtime::base::TrackingAllocator<
+    runtime::base::AlignedAllocator<std::alloc::System, 64>,
+> = runtime::base::TrackingAllocator::new(runtime::base::AlignedAllocator(std::alloc::System));
+
+#[test]
+fn tracking_allocator() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers()
+            .host_allocator(&TRACKING_ALLOCATOR),
+    )
+    .unwrap();
+    let buffer = runtime::vm::Buffer::from_bytes(&instance, &[1, 2, 3]).unwrap();
+    let statistics = TRACKING_ALLOCATOR.statistics();
+    assert!(statistics.allocation_count > 0);
+    assert!(statistics.current_bytes > 0);
+    assert!(statistics.peak_bytes >= statistics.current_bytes);
+    assert!(statistics.total_bytes >= statistics.peak_bytes);
+
+    drop(buffer);
+    drop(instance);
+    assert!(TRACKING_ALLOCATOR.statistics().current_bytes < statistics.current_bytes);
+}
+
+#[test]
+fn owned_buffer_view() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let owned = {
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        BufferView::new(
+            &session,
+            &[2, 2],
+            runtime::hal::EncodingType::DenseRowMajor,
+            &[1.0f32, 2.0, 3.0, 4.0],
+        )
+        .unwrap()
+        .to_owned_view()
+    };
+
+    // The owned buffer view outlives its session and can be moved to another thread.
+    let owned = std::thread::spawn(move || {
+        assert_eq!(owned.to_vec().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+        owned
+    })
+    .join()
+    .unwrap();
+    assert_eq!(owned.shape(), vec![2, 2]);
+
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let buffer_view = owned.to_buffer_view(&session).unwrap();
+    let mut data = [0.0f32; 4];
+    buffer_view.copy_to_host(&mut data).unwrap();
+    assert_eq!(data, [1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn session_pool() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let mut setup_calls = 0;
+    let pool = runtime::api::SessionPool::new(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+        2,
+        |_| {
+            setup_calls += 1;
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(pool.len(), 2);
+    assert_eq!(setup_calls, 2);
+
+    let results = std::thread::scope(|scope| {
+        let handles = (0..4)
+            .map(|i| {
+                let pool = &pool;
+                scope.spawn(move || {
+                    pool.with_session(|session| {
+                        BufferView::new(
+                            session,
+                            &[2],
+                            runtime::hal::EncodingType::DenseRowMajor,
+                            &[i, i + 1],
+                        )
+                        .unwrap()
+                        .to_vec()
+                        .unwrap()
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(
+        results,
+        vec![vec![0, 1], vec![1, 2], vec![2, 3], vec![3, 4]]
+    );
+}
+
+#[test]
+fn model_rejects_invalid_module() {
+    assert!(matches!(
+        eerie::Model::from_bytes(
+            b"not a module",
+            runtime::api::DeviceSpec::Driver("local-sync")
+        ),
+        Err(runtime::error::RuntimeError::InvalidModule(_))
+    ));
+    assert!(
+        eerie::Model::load("does/not/exist.vmfb", runtime::api::DeviceSpec::default()).is_err()
+    );
+}
+
+#[test]
+fn tensor_round_trip() {
+    let tensor = eerie::Tensor::new(&[2, 2], &[1.0f32, 2.0, 3.0, 4.0]).unwrap();
+    assert_eq!(tensor.shape(), &[2, 2]);
+    assert_eq!(tensor.element_type(), runtime::hal::ElementType::Float32);
+    assert_eq!(tensor.as_bytes().len(), 16);
+    assert!(tensor.as_slice::<i32>().is_err());
+    assert!(eerie::Tensor::new(&[3], &[1u8, 2]).is_err());
+
+    // Tensors are owned, so they can be moved to another thread.
+    let tensor = std::thread::spawn(move || tensor).join().unwrap();
+
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let buffer_view = tensor.to_buffer_view::<f32>(&session).unwrap();
+    assert_eq!(buffer_view.shape(), vec![2, 2]);
+    assert!(tensor.to_buffer_view::<f64>(&session).is_err());
+    assert_eq!(
+        eerie::Tensor::from_buffer_view(&buffer_view).unwrap(),
+        tensor
+    );
+}
+
+#[cfg(feature = "static-library")]
+#[test]
+fn static_library_device() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let loader = runtime::hal::StaticLibraryLoader::new(&instance, &[]).unwrap();
+    let device = instance.create_static_library_device(&loader).unwrap();
+    drop(loader);
+    assert_eq!(device.id(), runtime::hal::drivers::LOCAL_SYNC);
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+}
+
+#[cfg(feature = "hal-inline")]
+#[test]
+fn inline_hal_session() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let device = instance
+        .try_create_default_device("local-sync")
+        .expect("Failed to create device");
+    let session = runtime::api::Session::create_with_inline_hal(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+    let names = session
+        .modules()
+        .iter()
+        .map(|module| module.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["hal_inline", "hal_loader"]);
+}
+
+#[cfg(feature = "hip")]
+#[test]
+fn hip_device() {
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    assert!(instance
+        .available_drivers()
+        .unwrap()
+        .iter()
+        .any(|driver| driver.name == runtime::hal::drivers::HIP));
+    // Machines without ROCm cannot create the driver.
+    let driver = match instance.lookup_driver(runtime::hal::drivers::HIP) {
+        Ok(driver) => driver,
+        Err(err) => {
+            info!("HIP is unavailable: {}", err);
+            return;
+        }
+    };
+    if driver.query_available_devices().unwrap().is_empty() {
+        return;
+    }
+    let device = instance.create_device("hip://0").unwrap();
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+}
+
+#[cfg(feature = "vulkan")]
+#[test]
+fn vulkan_devices() {
+    use runtime::hal::{enumerate_vulkan_devices, VulkanDeviceType};
+
+    // Machines without a Vulkan loader report the driver as unavailable.
+    let physical_devices = match enumerate_vulkan_devices() {
+        Ok(physical_devices) => physical_devices,
+        Err(err) => {
+            info!("Vulkan is unavailable: {}", err);
+            return;
+        }
+    };
+    info!("Vulkan devices: {:?}", physical_devices);
+    let instance = runtime::api::Instance::new(
+        &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+            .use_all_available_drivers(),
+    )
+    .unwrap();
+    let Some(physical_device) = physical_devices
+        .iter()
+        .filter(|physical_device| physical_device.supports_compute())
+        .max_by_key(|physical_device| physical_device.device_type == VulkanDeviceType::DiscreteGpu)
+    else {
+        return;
+    };
+    assert_eq!(
+        physical_device.uri(),
+        format!("vulkan://{}", physical_device.index)
+    );
+    let device = instance.create_vulkan_device(physical_device).unwrap();
+    runtime::api::Session::create_with_device(
+        &instance,
+        &runtime::api::SessionOptions::default(),
+        &device,
+    )
+    .unwrap();
+}
+
+#[cfg(feature = "compiler")]
+mod integration_tests {
+    use eerie::compiler;
+    use eerie::runtime;
+    use eerie::runtime::hal::{BufferMapping, BufferView, EncodingType, Timeout};
+    use eerie::runtime::vm::{CallingConventionType, List, ToRef};
+    use log::{debug, info};
+    use std::path::Path;
+
+    fn compile_mul_module(extra_flags: &[&str]) -> Vec<u8> {
+        compile_module(Path::new("tests/mul.mlir"), extra_flags)
+    }
+
+    fn compile_module(path: &Path, extra_flags: &[&str]) -> Vec<u8> {
+        let compiler = compiler::Compiler::global().unwrap();
+        let mut compiler_session = compiler.create_session();
+        let mut flags = vec!["--iree-hal-target-backends=llvm-cpu".to_string()];
+        flags.extend(extra_flags.iter().map(|flag| flag.to_string()));
+        compiler_session.set_flags(flags).unwrap();
+        let source = compiler_session.create_source_from_file(path).unwrap();
+        let mut invocation = compiler_session.create_invocation();
+        let mut output = compiler::MemBufferOutput::new(compiler).unwrap();
+        invocation
+            .parse_source(source)
+            .unwrap()
+            .pipeline(compiler::Pipeline::Std)
+            .unwrap()
+            .output_vm_byte_code(&mut output)
+            .unwrap();
+        Vec::from(output.map_memory().unwrap())
+    }
+
+    #[test]
+    fn model_call() {
+        let vmfb = compile_mul_module(&[]);
+        let path = std::env::temp_dir().join("eerie_model_call.vmfb");
+        std::fs::write(&path, &vmfb).unwrap();
+        let model =
+            eerie::Model::load(&path, runtime::api::DeviceSpec::Driver("local-sync")).unwrap();
+        assert_eq!(model.module_name(), "arithmetic");
+        let input = BufferView::<f32>::new(
+            model.session(),
+            &[100],
+            EncodingType::DenseRowMajor,
+            &[3.0; 100],
+        )
+        .unwrap();
+        let (output,): (BufferView<f32>,) = model.call("simple_mul", (&input, &input)).unwrap();
+        assert_eq!(output.to_vec().unwrap(), vec![9.0; 100]);
+        assert!(model.function("arithmetic.simple_mul").is_ok());
+        assert!(model.function("missing").is_err());
+    }
+
+    #[test]
+    fn model_call_tensors() {
+        use runtime::error::RuntimeError;
+        let vmfb = compile_mul_module(&[]);
+        let model = eerie::Model::from_bytes(&vmfb, runtime::api::DeviceSpec::Driver("local-sync"))
+            .unwrap();
+        let input = eerie::Tensor::new(&[100], &[3.0f32; 100]).unwrap();
+        let outputs = model
+            .call_tensors("simple_mul", &[input.clone(), input.clone()])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].shape(), &[100]);
+        assert_eq!(outputs[0].as_slice::<f32>().unwrap(), &[9.0; 100]);
+        assert!(matches!(
+            model.call_tensors("simple_mul", &[input]),
+            Err(RuntimeError::SignatureMismatch(_))
+        ));
+    }
+
+    #[cfg(feature = "serve")]
+    #[test]
+    fn async_model_serve() {
+        use eerie::serve::{AsyncModel, Backpressure, ServeOptions};
+        use runtime::{base::StatusErrorKind, error::RuntimeError};
+        use std::sync::Arc;
+        let vmfb = compile_mul_module(&[]);
+        let device = runtime::api::DeviceSpec::Driver("local-sync");
+        let rt = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+
+        let model = Arc::new(
+            AsyncModel::from_bytes(
+                &vmfb,
+                device,
+                ServeOptions::new("simple_mul").workers(2).queue_capacity(2),
+            )
+            .unwrap(),
+        );
+        assert_eq!(model.workers(), 2);
+        let results = rt.block_on(async {
+            let handles = (0..32)
+                .map(|i| {
+                    let model = model.clone();
+                    tokio::spawn(async move {
+                        let input = eerie::Tensor::new(&[100], &[i as f32; 100]).unwrap();
+                        model.infer(vec![input.clone(), input]).await
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut results = Vec::new();
+            for handle in handles {
+                results.push(handle.await.unwrap());
+            }
+            results
+        });
+        // Waiting requests are never dropped, however small the queue.
+        for (i, outputs) in results.into_iter().enumerate() {
+            assert_eq!(
+                outputs.unwrap()[0].as_slice::<f32>().unwrap(),
+                &[(i * i) as f32; 100]
+            );
+        }
+        assert_eq!(model.queued(), 0);
+
+        // A failing request does not stall the requests queued behind it on the same worker.
+        let model = AsyncModel::from_bytes(&vmfb, device, ServeOptions::new("simple_mul")).unwrap();
+        let input = eerie::Tensor::new(&[100], &[3.0f32; 100]).unwrap();
+        let results = rt.block_on(async {
+            let failing = model.infer(vec![input.clone()]);
+            let requests = (0..4)
+                .map(|_| model.infer(vec![input.clone(), input.clone()]))
+                .collect::<Vec<_>>();
+            let failing = tokio::spawn(failing);
+            let handles = requests.into_iter().map(tokio::spawn).collect::<Vec<_>>();
+            let mut results = vec![failing.await.unwrap()];
+            for handle in handles {
+                results.push(handle.await.unwrap());
+            }
+            results
+        });
+        assert!(results[0].is_err());
+        for outputs in &results[1..] {
+            assert_eq!(
+                outputs.as_ref().unwrap()[0].as_slice::<f32>().unwrap(),
+                &[9.0; 100]
+            );
+        }
+        assert_eq!(model.queued(), 0);
+
+        let model = AsyncModel::from_bytes(
+            &vmfb,
+            device,
+            ServeOptions::new("simple_mul")
+                .queue_capacity(1)
+                .backpressure(Backpressure::Reject),
+        )
+        .unwrap();
+        let input = eerie::Tensor::new(&[100], &[2.0f32; 100]).unwrap();
+        let results = rt.block_on(async {
+            let requests = (0..32)
+                .map(|_| model.infer(vec![input.clone(), input.clone()]))
+                .collect::<Vec<_>>();
+            let mut results = Vec::new();
+            for request in requests {
+                results.push(tokio::spawn(request));
+            }
+            let mut outputs = Vec::new();
+            for result in results {
+                outputs.push(result.await.unwrap());
+            }
+            outputs
+        });
+        // Requests either run or are rejected because the queue is full, never anything else.
+        let mut rejected = 0;
+        for result in results {
+            match result {
+                Ok(outputs) => assert_eq!(outputs[0].as_slice::<f32>().unwrap(), &[4.0; 100]),
+                Err(RuntimeError::StatusError(err)) => {
+                    assert_eq!(err.code(), StatusErrorKind::ResourceExhausted);
+                    rejected += 1;
+                }
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+        assert!(rejected < 32);
+
+        // Requests sent to a dropped model fail instead of hanging.
+        let request = model.infer(vec![input.clone(), input]);
+        drop(model);
+        assert!(matches!(
+            rt.block_on(request),
+            Err(RuntimeError::StatusError(err)) if err.code() == StatusErrorKind::Unavailable
+        ));
+
+        assert!(AsyncModel::from_bytes(&vmfb, device, ServeOptions::new("missing")).is_err());
+    }
+
+    #[test]
+    fn instrumented_module() {
+        use runtime::api::InstrumentChunkKind;
+        let vmfb = compile_mul_module(&["--iree-hal-instrument-dispatches=16mib"]);
+        let model = eerie::Model::from_bytes(&vmfb, runtime::api::DeviceSpec::Driver("local-sync"))
+            .unwrap();
+        let input = eerie::Tensor::new(&[100], &[3.0f32; 100]).unwrap();
+        model
+            .call_tensors("simple_mul", &[input.clone(), input])
+            .unwrap();
+        let data = model.session().query_instruments().unwrap().unwrap();
+        assert!(!data.as_bytes().is_empty());
+        let chunks = data.chunks().unwrap();
+        assert!(chunks
+            .iter()
+            .any(|chunk| chunk.kind == InstrumentChunkKind::DispatchRingbuffer));
+
+        let vmfb = compile_mul_module(&[]);
+        let model = eerie::Model::from_bytes(&vmfb, runtime::api::DeviceSpec::Driver("local-sync"))
+            .unwrap();
+        assert!(model.session().query_instruments().unwrap().is_none());
+    }
+
+    #[test]
+    fn text_generation() {
+        use runtime::pipelines::text_generation::{GenerationConfig, Sampling, TextGenerator};
+        let vmfb = compile_module(Path::new("tests/decoder.mlir"), &[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+        let empty_cache =
+            || BufferView::new(&session, &[4], EncodingType::DenseRowMajor, &[0.0f32; 4]).unwrap();
+        assert!(matches!(
+            TextGenerator::new(
+                &session,
+                "decoder.decode",
+                vec![empty_cache(), empty_cache()]
+            ),
+            Err(runtime::error::RuntimeError::SignatureMismatch(_))
+        ));
+
+        let mut generator =
+            TextGenerator::new(&session, "decoder.decode", vec![empty_cache()]).unwrap();
+        let mut streamed = Vec::new();
+        let config = GenerationConfig {
+            max_new_tokens: 5,
+            ..Default::default()
+        };
+        let tokens = generator
+            .generate(&[2, 3], &config, |token| {
+                streamed.push(token);
+                true
+            })
+            .unwrap();
+        assert_eq!(tokens, [0, 1, 2, 3, 0]);
+        assert_eq!(streamed, tokens);
+        // The last generated token is not fed back.
+        assert_eq!(generator.position(), 6);
+        assert_eq!(
+            generator.kv_cache()[0].to_vec().unwrap(),
+            [2.0, 1.0, 1.0, 2.0]
+        );
+
+        // Consecutive steps reuse the call and its output list.
+        generator.reset();
+        generator.set_kv_cache(vec![empty_cache()]);
+        let first = generator.step(2).unwrap().to_vec();
+        let second = generator.step(3).unwrap().to_vec();
+        assert_eq!(first.len(), second.len());
+        assert_eq!(generator.position(), 2);
+
+        generator.reset();
+        generator.set_kv_cache(vec![empty_cache()]);
+        let config = GenerationConfig {
+            max_new_tokens: 8,
+            stop_tokens: vec![3],
+            sampling: Sampling::TopK {
+                temperature: 0.1,
+                top_k: 2,
+                seed: 42,
+            },
+        };
+        let tokens = generator.generate(&[0], &config, |_| true).unwrap();
+        assert_eq!(tokens.last(), Some(&3));
+        assert!(tokens.len() <= 8);
+        assert!(generator.generate(&[], &config, |_| true).is_err());
+    }
+
+    #[test]
+    fn session_state_snapshot() {
+        use runtime::api::{StateAccessor, StateSnapshot};
+        let vmfb = compile_module(Path::new("tests/stateful.mlir"), &[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let new_session = || {
+            let session = runtime::api::Session::create_with_device(
+                &instance,
+                &runtime::api::SessionOptions::default(),
+                &device,
+            )
+            .unwrap();
+            unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+            session
+        };
+        let accessors = [StateAccessor {
+            getter: "stateful.get_cache",
+            setter: "stateful.set_cache",
+        }];
+
+        let session = new_session();
+        let module = session.modules().pop().unwrap();
+        assert!(!session
+            .resolve_module_state(&module)
+            .unwrap()
+            .as_ptr()
+            .is_null());
+        let cache = BufferView::<f32>::new(
+            &session,
+            &[4],
+            EncodingType::DenseRowMajor,
+            &[1.0, 2.0, 3.0, 4.0],
+        )
+        .unwrap();
+        let () = session
+            .lookup_function("stateful.set_cache")
+            .unwrap()
+            .call((&cache,))
+            .unwrap();
+        let snapshot = session.snapshot_state(&accessors).unwrap();
+        let (setter, tensors) = snapshot.entries().next().unwrap();
+        assert_eq!(setter, "stateful.set_cache");
+        assert_eq!(tensors[0].as_slice::<f32>().unwrap(), [1.0, 2.0, 3.0, 4.0]);
+
+        // Restore the state into a fresh session, as after a process restart.
+        let bytes = snapshot.to_bytes();
+        assert!(StateSnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        // A tensor whose data is shorter than its shape, with the lengths patched to match.
+        let mut truncated = bytes[..bytes.len() - 4].to_vec();
+        let length_offset = truncated.len() - 12 - 8;
+        truncated[length_offset..length_offset + 8].copy_from_slice(&12u64.to_le_bytes());
+        assert!(matches!(
+            StateSnapshot::from_bytes(&truncated),
+            Err(runtime::error::RuntimeError::InvalidArchive(_))
+        ));
+        let snapshot = StateSnapshot::from_bytes(&bytes).unwrap();
+        let restored = new_session();
+        let getter = restored.lookup_function("stateful.get_cache").unwrap();
+        let (cache,): (BufferView<f32>,) = getter.call(()).unwrap();
+        assert_eq!(cache.to_vec().unwrap(), vec![0.0; 4]);
+        restored.restore_state(&snapshot).unwrap();
+        let (cache,): (BufferView<f32>,) = getter.call(()).unwrap();
+        assert_eq!(cache.to_vec().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+
+        assert!(matches!(
+            session.snapshot_state(&[StateAccessor {
+                getter: "stateful.set_cache",
+                setter: "stateful.get_cache",
+            }]),
+            Err(runtime::error::RuntimeError::SignatureMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn function_signature() {
+        let vmfb = compile_mul_module(&[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+
+        let function = session.lookup_function("arithmetic.simple_mul").unwrap();
+        assert_eq!(function.name(), "simple_mul");
+        assert_eq!(function.qualified_name(), "arithmetic.simple_mul");
+        let functions = session.list_functions().unwrap();
+        info!(
+            "Functions: {:?}",
+            functions
+                .iter()
+                .map(|function| function.qualified_name())
+                .collect::<Vec<_>>()
+        );
+        let listed = functions
+            .iter()
+            .find(|function| function.qualified_name() == "arithmetic.simple_mul")
+            .unwrap();
+        assert_eq!(
+            listed.signature().calling_convention(),
+            function.signature().calling_convention()
+        );
+        let signature = function.signature();
+        info!("Calling convention: {}", signature.calling_convention());
+        assert_eq!(signature.count_arguments_and_results().unwrap(), (2, 1));
+        assert_eq!(
+            signature.argument_types().unwrap(),
+            vec![CallingConventionType::Ref, CallingConventionType::Ref]
+        );
+        assert_eq!(
+            signature.result_types().unwrap(),
+            vec![CallingConventionType::Ref]
+        );
+        info!("Reflection attrs: {:?}", function.reflection_attrs());
+        assert!(function
+            .abi_declaration()
+            .is_some_and(|declaration| declaration.contains("simple_mul")));
+        let abi_signature = function.abi_signature().unwrap();
+        assert_eq!(abi_signature.name, "simple_mul");
+        assert_eq!(abi_signature.arguments.len(), 2);
+        assert_eq!(abi_signature.results.len(), 1);
+
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[1.0; 100])
+                .unwrap();
+        let input_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(2, &instance)
+                .unwrap();
+        let output_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(1, &instance)
+                .unwrap();
+        input_list
+            .push_ref(&input.to_ref(&instance).unwrap())
+            .unwrap();
+        assert!(matches!(
+            function.invoke_typed(&input_list, &output_list),
+            Err(runtime::error::RuntimeError::SignatureMismatch(_))
+        ));
+        input_list
+            .push_ref(&input.to_ref(&instance).unwrap())
+            .unwrap();
+        function.invoke_typed(&input_list, &output_list).unwrap();
+        assert_eq!(output_list.size(), 1);
+    }
+
+    #[test]
+    fn typed_call_buffer_view() {
+        let vmfb = compile_mul_module(&[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        session.append_module_from_memory_checked(&vmfb).unwrap();
+
+        let function = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let lhs =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[2.0; 100])
+                .unwrap();
+        let rhs =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[3.0; 100])
+                .unwrap();
+        let (output,): (BufferView<f32>,) = function.call((&lhs, &rhs)).unwrap();
+        let mapping = BufferMapping::new(output).unwrap();
+        assert_eq!(mapping.data(), &[6.0; 100]);
+    }
+
+    #[test]
+    fn module_compatibility() {
+        let vmfb = compile_mul_module(&[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+
+        let module = unsafe { runtime::vm::Module::from_bytecode(&instance, &vmfb) }.unwrap();
+        assert_eq!(module.name(), "arithmetic");
+        let dependencies = module.dependencies().unwrap();
+        info!("Dependencies: {:?}", dependencies);
+        assert!(dependencies
+            .iter()
+            .any(|dependency| dependency.name == "hal" && !dependency.optional));
+        assert!(session
+            .modules()
+            .iter()
+            .any(|module| module.name() == "hal"));
+        unsafe { session.check_module_compatibility(&vmfb) }.unwrap();
+        session.check_compatibility(&module).unwrap();
+        session.append_module(&module).unwrap();
+
+        let report = runtime::vm::IncompatibilityReport {
+            module_name: "arithmetic".to_string(),
+            unsatisfied: vec![runtime::vm::UnsatisfiedDependency {
+                name: "hal".to_string(),
+                required_version: 5,
+                provided_version: Some(4),
+            }],
+        };
+        assert_eq!(
+            report.to_string(),
+            "module arithmetic cannot be loaded: requires hal version >= 5, but version 4 is \
+             provided;"
+        );
+    }
+
+    #[test]
+    fn artifact_compatibility() {
+        let vmfb = compile_mul_module(&[]);
+        eerie::check_artifact_compatibility(&vmfb).unwrap();
+        let version = compiler::Compiler::global().unwrap().version().unwrap();
+        info!("Compiler version: {:?}", version);
+        assert_eq!(version.api_version, compiler::get_api_version());
+    }
+
+    #[test]
+    fn load_module_cached() {
+        let vmfb = compile_mul_module(&[]);
+        let mut instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        {
+            let device = instance
+                .try_create_default_device("local-sync")
+                .expect("Failed to create device");
+            let module = instance.load_module_cached(&vmfb).unwrap();
+            let cached = instance.load_module_cached(&vmfb.clone()).unwrap();
+            assert_eq!(instance.cached_module_count(), 1);
+            assert_eq!(cached.name(), "arithmetic");
+            for module in [&module, &cached] {
+                let session = runtime::api::Session::create_with_device(
+                    &instance,
+                    &runtime::api::SessionOptions::default(),
+                    &device,
+                )
+                .unwrap();
+                session.append_module(module).unwrap();
+                session.lookup_function("arithmetic.simple_mul").unwrap();
+            }
+            assert!(matches!(
+                instance.load_module_cached(b"not a module"),
+                Err(runtime::error::RuntimeError::InvalidModule(_))
+            ));
+            assert_eq!(instance.cached_module_count(), 1);
+        }
+        instance.clear_module_cache();
+        assert_eq!(instance.cached_module_count(), 0);
+    }
+
+    #[test]
+    fn mapped_module() {
+        let vmfb = compile_mul_module(&[]);
+        let path = std::env::temp_dir().join("eerie_mapped_module.vmfb");
+        std::fs::write(&path, &vmfb).unwrap();
+
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let mapped = unsafe {
+            runtime::vm::MappedModule::open(&instance, &path, runtime::io::MmapAdvice::Sequential)
+        }
+        .unwrap();
+        assert_eq!(mapped.contents(), &vmfb[..]);
+        mapped.advise(runtime::io::MmapAdvice::WillNeed).unwrap();
+        for _ in 0..2 {
+            let session = runtime::api::Session::create_with_device(
+                &instance,
+                &runtime::api::SessionOptions::default(),
+                &device,
+            )
+            .unwrap();
+            session.append_module(mapped.module()).unwrap();
+            session.lookup_function("arithmetic.simple_mul").unwrap();
+        }
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn append_module_from_file_handle() {
+        let vmfb = compile_mul_module(&[]);
+        runtime::io::verify_bytecode_header(&vmfb).unwrap();
+        let path = std::env::temp_dir().join("eerie_append_module_from_file_handle.vmfb");
+        std::fs::write(&path, &vmfb).unwrap();
+
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        let module = unsafe { runtime::vm::Module::from_file(&instance, &path) }.unwrap();
+        assert_eq!(module.name(), "arithmetic");
+        let file_handle = runtime::io::FileHandle::open_mapped(&path).unwrap();
+        assert_eq!(file_handle.contents(), &vmfb[..]);
+        unsafe { session.append_module_from_file_handle(&file_handle) }.unwrap();
+        // The session keeps the mapping alive.
+        drop(file_handle);
+
+        let function = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[3.0; 100])
+                .unwrap();
+        let (output,): (BufferView<f32>,) = function.call((&input, &input)).unwrap();
+        let mapping = BufferMapping::new(output).unwrap();
+        assert_eq!(mapping.data(), &[9.0; 100]);
+    }
+
+    #[test]
+    fn share_module_across_sessions() {
+        let vmfb = compile_mul_module(&[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let module = unsafe { runtime::vm::Module::from_bytecode(&instance, &vmfb) }.unwrap();
+        let sessions: Vec<_> = (0..2)
+            .map(|_| {
+                let session = runtime::api::Session::create_with_device(
+                    &instance,
+                    &runtime::api::SessionOptions::default(),
+                    &device,
+                )
+                .unwrap();
+                session.append_module(&module).unwrap();
+                session
+            })
+            .collect();
+        // The sessions retain the module.
+        drop(module);
+
+        for (i, session) in sessions.iter().enumerate() {
+            let function = session.lookup_function("arithmetic.simple_mul").unwrap();
+            let input = BufferView::<f32>::new(
+                session,
+                &[100],
+                EncodingType::DenseRowMajor,
+                &[i as f32; 100],
+            )
+            .unwrap();
+            let (output,): (BufferView<f32>,) = function.call((&input, &input)).unwrap();
+            let mapping = BufferMapping::new(output).unwrap();
+            assert_eq!(mapping.data(), &[(i * i) as f32; 100]);
+        }
+    }
+
+    #[test]
+    fn call_statistics() {
+        let vmfb = compile_mul_module(&[]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+
+        let func = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let mut call = runtime::api::Call::new(&session, &func).unwrap();
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[2.0; 100])
+                .unwrap();
+        let (_,): (BufferView<f32>,) = call.invoke_typed((&input, &input)).unwrap();
+        assert!(call.statistics().is_none());
+        assert!(call.last_invoke_duration().is_none());
+
+        call.enable_statistics();
+        for _ in 0..3 {
+            let (_,): (BufferView<f32>,) = call.invoke_typed((&input, &input)).unwrap();
+        }
+        let statistics = call.statistics().unwrap();
+        info!("Call statistics: {:?}", statistics);
+        assert_eq!(statistics.invocation_count, 3);
+        assert_eq!(
+            call.last_invoke_duration(),
+            Some(statistics.last_invoke_duration)
+        );
+        assert!(statistics.total_invoke_duration >= statistics.last_invoke_duration);
+        assert!(statistics.mean_invoke_duration().is_some());
+
+        call.disable_statistics();
+        assert!(call.statistics().is_none());
+    }
+
+    #[test]
+    fn invoke_async() {
+        let vmfb = compile_mul_module(&["--iree-execution-model=async-external"]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-task")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+
+        let func = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let mut call = runtime::api::Call::new(&session, &func).unwrap();
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[2.0; 100])
+                .unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        let fence = call.invoke_async(None).unwrap();
+        fence.wait(Timeout::Infinite).unwrap();
+        assert!(fence.is_signaled().unwrap());
+
+        let output = call.outputs_pop_front_buffer_view::<f32>().unwrap();
+        let mapping = BufferMapping::new(output).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+
+        // Chain a second invocation on the fence of the first one.
+        let input_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(2, &instance)
+                .unwrap();
+        let output_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(1, &instance)
+                .unwrap();
+        let input_ref = input.to_ref(&instance).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        let fence = func
+            .invoke_async(&input_list, &output_list, Some(&fence))
+            .unwrap();
+        fence.wait(Timeout::Infinite).unwrap();
+        let output_ref: runtime::vm::Ref<BufferView<f32>> = output_list.get_ref(0).unwrap();
+        let mapping = BufferMapping::new(output_ref.to_buffer_view(&session)).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+
+        // Drive the call by polling instead of waiting on the fence.
+        let mut call = runtime::api::Call::new(&session, &func).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        call.begin(None).unwrap();
+        assert!(call.begin(None).is_err());
+        while call.poll().unwrap().is_pending() {
+            std::thread::yield_now();
+        }
+        let output = call.outputs_pop_front_buffer_view::<f32>().unwrap();
+        let mapping = BufferMapping::new(output).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+
+        // Fences can also be passed to typed calls directly.
+        let semaphore = runtime::hal::Semaphore::new(&session.device(), 0).unwrap();
+        let signal_fence = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+        let (output,): (BufferView<f32>,) = func
+            .call((&input, &input, None::<&runtime::hal::Fence>, &signal_fence))
+            .unwrap();
+        signal_fence.wait(Timeout::Infinite).unwrap();
+        assert_eq!(semaphore.query().unwrap(), 1);
+        let mapping = BufferMapping::new(output).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+    }
+
+    #[test]
+    fn cancel_running_call() {
+        use runtime::api::{CancellationToken, InvocationPolicy};
+        use runtime::base::StatusErrorKind;
+        use runtime::error::RuntimeError;
+        use runtime::hal::{Fence, Semaphore};
+        let vmfb = compile_mul_module(&["--iree-execution-model=async-external"]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-task")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+        let func = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[2.0; 100])
+                .unwrap();
+        let token = CancellationToken::new(&device).unwrap();
+        let policy = InvocationPolicy::new().cancellation(&token);
+
+        // Without fences in the inputs, the invocation waits for its own signal fence.
+        let input_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(2, &instance)
+                .unwrap();
+        let output_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(1, &instance)
+                .unwrap();
+        let input_ref = input.to_ref(&instance).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        func.invoke_with_policy(&input_list, &output_list, &policy)
+            .unwrap();
+        let output_ref: runtime::vm::Ref<BufferView<f32>> = output_list.get_ref(0).unwrap();
+        let mapping = BufferMapping::new(output_ref.to_buffer_view(&session)).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+
+        // The device work waits on a fence that is never signaled, so the call only returns once
+        // the token is cancelled from another thread.
+        let mut call = runtime::api::Call::new(&session, &func).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        let wait_semaphore = Semaphore::new(&device, 0).unwrap();
+        let wait_fence = Fence::at(&wait_semaphore, 1).unwrap();
+        let signal_fence = Fence::at(&Semaphore::new(&device, 0).unwrap(), 1).unwrap();
+        {
+            let inputs = call.input_list();
+            inputs
+                .push_ref(&wait_fence.to_ref(&instance).unwrap())
+                .unwrap();
+            inputs
+                .push_ref(&signal_fence.to_ref(&instance).unwrap())
+                .unwrap();
+        }
+        std::thread::scope(|scope| {
+            let token = token.clone();
+            scope.spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                token.cancel();
+            });
+            let err = call.invoke_with_policy(&policy).unwrap_err();
+            assert!(
+                matches!(err, RuntimeError::StatusError(err) if err.code() == StatusErrorKind::Cancelled)
+            );
+        });
+        assert!(signal_fence.is_signaled().is_err());
+        // Abort the device work that is still waiting.
+        wait_semaphore.fail(StatusErrorKind::Aborted);
+    }
+
+    #[test]
+    fn command_buffer_dispatch() {
+        use runtime::hal::{CommandBuffer, ExecutableCache, ExecutableCachingMode};
+        let compiler = compiler::Compiler::global().unwrap();
+        let mut compiler_session = compiler.create_session();
+        compiler_session
+            .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
+            .unwrap();
+        let source = compiler_session
+            .create_source_from_file(Path::new("tests/dispatch.mlir"))
+            .unwrap();
+        let mut invocation = compiler_session.create_invocation();
+        let mut output = compiler::MemBufferOutput::new(compiler).unwrap();
+        invocation
+            .parse_source(source)
+            .unwrap()
+            .pipeline(compiler::Pipeline::HalExecutable)
+            .unwrap()
+            .output_hal_executable(&mut output)
+            .unwrap();
+        let binary = output.map_memory().unwrap();
+
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let device = instance
+            .try_create_default_device("local-sync")
+            .expect("Failed to create device");
+        let session = runtime::api::Session::create_with_device(
+            &instance,
+            &runtime::api::SessionOptions::default(),
+            &device,
+        )
+        .unwrap();
+        let cache = ExecutableCache::new(&device, "eerie-test").unwrap();
+        let mode = ExecutableCachingMode::DEFAULT;
+        let format = ["embedded-elf-x86_64", "embedded-elf-arm_64"]
+            .into_iter()
+            .find(|format| cache.can_prepare_format(format, mode))
+            .expect("No embedded ELF loader for this architecture");
+        let executable = cache.prepare_executable(format, binary, mode).unwrap();
+
+        let input = BufferView::<f32>::new(
+            &session,
+            &[4],
+            EncodingType::DenseRowMajor,
+            &[1., 2., 3., 4.],
+        )
+        .unwrap();
+        let output =
+            BufferView::<f32>::new(&session, &[4], EncodingType::DenseRowMajor, &[0.; 4]).unwrap();
+        let mut command_buffer =
+            CommandBuffer::new(&device, runtime::hal::QUEUE_AFFINITY_ANY).unwrap();
+        command_buffer
+            .dispatch(
+                &executable,
+                0,
+                [1, 1, 1],
+                &[],
+                &[&input.buffer(), &output.buffer()],
+            )
+            .unwrap();
+        command_buffer.end().unwrap();
+
+        let semaphore = runtime::hal::Semaphore::new(&device, 0).unwrap();
+        let signal_fence = runtime::hal::Fence::at(&semaphore, 1).unwrap();
+        device
+            .queue_execute(
+                runtime::hal::QUEUE_AFFINITY_ANY,
+                None,
+                &signal_fence,
+                &[&command_buffer],
+            )
+            .unwrap();
+        signal_fence.wait(Timeout::Infinite).unwrap();
+        assert_eq!(output.to_vec().unwrap(), vec![2., 4., 6., 8.]);
+    }
+
+    #[test]
+    fn multi_device_session() {
+        let vmfb = compile_mul_module(&["--iree-execution-model=async-external"]);
+        let instance = runtime::api::Instance::new(
+            &runtime::api::InstanceOptions::new(&mut runtime::hal::DriverRegistry::new())
+                .use_all_available_drivers(),
+        )
+        .unwrap();
+        let cpu = instance
+            .try_create_default_device("local-task")
+            .expect("Failed to create device");
+        let other = instance
+            .try_create_default_device("local-task")
+            .expect("Failed to create device");
+        let options = runtime::api::SessionOptions::default();
+        assert!(runtime::api::Session::create_with_devices(&instance, &options, &[]).is_err());
+        let session =
+            runtime::api::Session::create_with_devices(&instance, &options, &[&cpu, &other])
+                .unwrap();
+        assert_eq!(session.device_count(), 2);
+        assert!(session.device_at(2).is_none());
+        assert!(session
+            .modules()
+            .iter()
+            .any(|module| module.name() == "hal"));
+        unsafe { session.append_module_from_memory(&vmfb) }.unwrap();
+
+        let func = session.lookup_function("arithmetic.simple_mul").unwrap();
+        let mut call = runtime::api::Call::new(&session, &func).unwrap();
+        let input =
+            BufferView::<f32>::new(&session, &[100], EncodingType::DenseRowMajor, &[2.0; 100])
+                .unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        call.inputs_push_back_buffer_view(&input).unwrap();
+        let policy = runtime::api::InvocationPolicy::new()
+            .device(1)
+            .queue_affinity(runtime::hal::QUEUE_AFFINITY_ANY);
+        let fence = call.invoke_async_with_policy(None, &policy).unwrap();
+        fence.wait(Timeout::Infinite).unwrap();
+        let output = call.outputs_pop_front_buffer_view::<f32>().unwrap();
+        let mapping = BufferMapping::new(output).unwrap();
+        assert!(mapping.data().iter().all(|&x| x == 4.0));
+
+        // A device ordinal the session does not have is rejected before invoking.
+        let input_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(2, &instance)
+                .unwrap();
+        let output_list =
+            runtime::vm::DynamicList::<runtime::vm::Ref<BufferView<f32>>>::new(1, &instance)
+                .unwrap();
+        let input_ref = input.to_ref(&instance).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        input_list.push_ref(&input_ref).unwrap();
+        let policy = runtime::api::InvocationPolicy::new().device(2);
+        assert!(func
+            .invoke_async_with_policy(&input_list, &output_list, None, &policy)
+            .is_err());
+        assert_eq!(output_list.size(), 0);
     }
 
     #[test]
     fn append_module() {
-        init_compiler();
-        let compiler = COMPILER.lock().unwrap();
-        let mut compiler_session = compiler.as_ref().unwrap().create_session();
+        let compiler = compiler::Compiler::global().unwrap();
+        let mut compiler_session = compiler.create_session();
         compiler_session
             .set_flags(vec!["--iree-hal-target-backends=llvm-cpu".to_string()])
             .unwrap();
@@ -124,7 +3213,7 @@ mod integration_tests {
             .create_source_from_file(Path::new("tests/mul.mlir"))
             .unwrap();
         let mut invocation = compiler_session.create_invocation();
-        let mut output = compiler::MemBufferOutput::new(compiler.as_ref().unwrap()).unwrap();
+        let mut output = compiler::MemBufferOutput::new(compiler).unwrap();
         invocation
             .parse_source(source)
             .unwrap()
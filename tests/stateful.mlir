@@ -0,0 +1,11 @@
+module @stateful {
+  util.global private mutable @cache = dense<0.0> : tensor<4xf32>
+  func.func @get_cache() -> tensor<4xf32> {
+    %0 = util.global.load @cache : tensor<4xf32>
+    return %0 : tensor<4xf32>
+  }
+  func.func @set_cache(%arg0: tensor<4xf32>) {
+    util.global.store %arg0, @cache : tensor<4xf32>
+    return
+  }
+}